@@ -1,7 +1,7 @@
 //! OSINT Platform Command Line Interface
 
 use clap::{Parser, Subcommand};
-use osint_core::{OSINTPlatform, PlatformConfig, intelligence::*, Result};
+use osint_core::{OSINTPlatform, PlatformConfig, intelligence::*, models::IntelReport, Result};
 use tracing::{info, warn, error};
 use uuid::Uuid;
 
@@ -43,6 +43,10 @@ enum Commands {
         /// Source name
         #[arg(short, long, default_value = "manual")]
         source: String,
+
+        /// Public suffix list to validate domains with instead of the bundled snapshot
+        #[arg(long, value_name = "FILE")]
+        public_suffix_list: Option<String>,
     },
     /// Search entities
     Search {
@@ -66,6 +70,16 @@ enum Commands {
         #[arg(short, long)]
         analyst: String,
     },
+    /// Render an intelligence report (JSON) as Markdown with indicators defanged
+    Report {
+        /// Report JSON file
+        #[arg(short, long)]
+        input: String,
+
+        /// Markdown output file; printed when omitted
+        #[arg(short, long)]
+        output: Option<String>,
+    },
 }
 
 #[tokio::main]
@@ -109,7 +123,7 @@ async fn main() -> Result<()> {
             println!("✅ Platform ready for intelligence processing!");
         }
 
-        Commands::Process { input, data_type, source } => {
+        Commands::Process { input, data_type, source, public_suffix_list } => {
             info!("Processing intelligence data from: {}", input);
             
             // Read input file
@@ -123,7 +137,15 @@ async fn main() -> Result<()> {
 
             // Create intelligence engine
             let mut engine = IntelligenceEngine::new();
-            engine.add_processor(Box::new(TextProcessor));
+            let processor = match public_suffix_list {
+                Some(path) => {
+                    let suffixes = PublicSuffixList::load_file(&path)?;
+                    info!("Loaded {} public suffix rules from: {}", suffixes.len(), path);
+                    TextProcessor::with_validator(IocValidator::default().with_public_suffixes(suffixes))
+                }
+                None => TextProcessor::new(),
+            };
+            engine.add_processor(Box::new(processor));
 
             // Create intelligence data
             let data = IntelligenceData {
@@ -152,7 +174,7 @@ async fn main() -> Result<()> {
                     
                     for entity in &result.entities {
                         println!("  • {} ({}): {:.2} confidence", 
-                            entity.name, 
+                            defang(&entity.name), 
                             format!("{:?}", entity.entity_type),
                             entity.confidence
                         );
@@ -187,7 +209,7 @@ async fn main() -> Result<()> {
                     } else {
                         for entity in results {
                             println!("  • {} ({}): {:.2} confidence", 
-                                entity.name,
+                                defang(&entity.name),
                                 format!("{:?}", entity.entity_type),
                                 entity.confidence
                             );
@@ -256,6 +278,31 @@ async fn main() -> Result<()> {
                 }
             }
         }
+
+        Commands::Report { input, output } => {
+            info!("Rendering intelligence report from: {}", input);
+
+            let report: IntelReport = match std::fs::read_to_string(&input).map(|json| serde_json::from_str(&json)) {
+                Ok(Ok(report)) => report,
+                Ok(Err(e)) => {
+                    error!("Invalid report file '{}': {}", input, e);
+                    return Err(osint_core::Error::Parsing(e.to_string()));
+                }
+                Err(e) => {
+                    error!("Failed to read report file '{}': {}", input, e);
+                    return Err(e.into());
+                }
+            };
+
+            let markdown = report.to_markdown();
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, markdown)?;
+                    println!("📝 Report written to: {}", path);
+                }
+                None => print!("{}", markdown),
+            }
+        }
     };
 
     Ok(())
@@ -1,12 +1,16 @@
 //! Data fusion engine for combining intelligence from multiple sources
 
 use crate::{Result, Error, models::*};
+use crate::geo_intel::uncertainty::{UncertaintyCircle, colocation_probability};
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc, Duration};
 use uuid::Uuid;
 use std::collections::{HashMap, HashSet, BTreeMap};
 use tokio::sync::RwLock;
 
+/// Least co-location probability of two entities' locations counted as geographic evidence
+const MIN_COLOCATION: f64 = 0.05;
+
 /// Data fusion engine for correlating intelligence from multiple sources
 #[derive(Debug)]
 pub struct DataFusionEngine {
@@ -263,14 +267,14 @@ impl DataFusionEngine {
             evidence_count += 1;
         }
 
-        // Check geographic proximity if both have locations
-        if let (Some(loc1), Some(loc2)) = (&entity1.location, &entity2.location) {
-            let distance = self.calculate_distance(loc1, loc2);
-            if distance < 1000.0 { // Within 1km
-                let geo_confidence = (1000.0 - distance) / 1000.0;
+        // Check geographic proximity if both have locations, allowing for their uncertainty circles
+        if let (Some(loc1), Some(loc2)) = (UncertaintyCircle::from_entity(entity1), UncertaintyCircle::from_entity(entity2)) {
+            let distance = self.calculate_distance(&loc1.center, &loc2.center);
+            let geo_confidence = colocation_probability(distance, loc1.accuracy_m, loc2.accuracy_m);
+            if geo_confidence >= MIN_COLOCATION {
                 evidence.push(CorrelationEvidence {
                     evidence_type: EvidenceType::GeographicProximity,
-                    value: format!("{:.1}m apart", distance),
+                    value: format!("{:.1}m apart, {:.0}% co-location", distance, geo_confidence * 100.0),
                     confidence: geo_confidence as f32,
                     source: "geographic_analysis".to_string(),
                 });
@@ -593,6 +597,31 @@ mod tests {
         assert_eq!(correlation.correlation_type, CorrelationType::SameEntity);
     }
 
+    #[tokio::test]
+    async fn test_location_evidence_uses_accuracy() {
+        let engine = DataFusionEngine::new();
+        let located = |value: &str, lon: f64, accuracy_m: f64| {
+            let mut entity = IntelEntity::new(EntityType::Location, value, "source1");
+            entity.location = Some(geo::Point::new(lon, 0.0));
+            entity.attributes.insert("accuracy_m".to_string(), serde_json::json!(accuracy_m));
+            entity
+        };
+        let geo_evidence = |correlation: &CorrelationMatch| {
+            correlation.evidence.iter().find(|e| e.evidence_type == EvidenceType::GeographicProximity).map(|e| e.confidence)
+        };
+
+        // About 330 m apart: distinct for GPS fixes, plausibly the same place for coarse ones
+        let precise = engine.calculate_entity_correlation(&located("a", 0.0, 10.0), &located("b", 0.003, 10.0)).await.unwrap();
+        assert_eq!(geo_evidence(&precise), None);
+
+        let coarse = engine.calculate_entity_correlation(&located("a", 0.0, 2000.0), &located("b", 0.003, 2000.0)).await.unwrap();
+        assert!(geo_evidence(&coarse).unwrap() > 0.9);
+
+        // A GPS fix inside a city-level location supports it
+        let nested = engine.calculate_entity_correlation(&located("a", 0.0, 10.0), &located("b", 0.0, 1000.0)).await.unwrap();
+        assert!(geo_evidence(&nested).unwrap() > 0.99);
+    }
+
     #[tokio::test]
     async fn test_entity_fusion() {
         let mut engine = DataFusionEngine::new();
@@ -0,0 +1,122 @@
+//! Enrichment of indicators and entities from external reputation services
+
+use crate::{Result, models::*};
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+
+pub mod virustotal;
+pub mod geoip;
+
+pub use virustotal::VirusTotalEnricher;
+pub use geoip::{GeoIpEnricher, GeoIpRecord};
+
+/// Trait for enrichment providers
+#[async_trait::async_trait]
+pub trait Enricher: Send + Sync {
+    /// Get provider name
+    fn name(&self) -> &str;
+
+    /// Check if provider can enrich this indicator
+    fn supports_indicator(&self, indicator: &ThreatIndicator) -> bool;
+
+    /// Check if provider can enrich this entity
+    fn supports_entity(&self, entity: &IntelEntity) -> bool;
+
+    /// Enrich indicator in place
+    async fn enrich_indicator(&self, indicator: &mut ThreatIndicator) -> Result<EnrichmentOutcome>;
+
+    /// Enrich entity in place
+    async fn enrich_entity(&self, entity: &mut IntelEntity) -> Result<EnrichmentOutcome>;
+}
+
+/// Result of a single enrichment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnrichmentOutcome {
+    pub provider: String,
+    /// Whether the provider had any data for the lookup
+    pub found: bool,
+    /// Attribute keys written to the indicator or entity
+    pub attributes: Vec<String>,
+    pub previous_severity: Option<ThreatSeverity>,
+    pub confidence_delta: f32,
+    /// Whether the result was served from the provider's local cache
+    pub from_cache: bool,
+    /// Geo intel derived from the lookup, such as a GeoIP location
+    #[serde(default)]
+    pub geo_intel: Vec<GeoIntel>,
+    pub enriched_at: DateTime<Utc>,
+}
+
+impl EnrichmentOutcome {
+    pub fn new(provider: impl Into<String>) -> Self {
+        Self {
+            provider: provider.into(),
+            found: false,
+            attributes: Vec::new(),
+            previous_severity: None,
+            confidence_delta: 0.0,
+            from_cache: false,
+            geo_intel: Vec::new(),
+            enriched_at: Utc::now(),
+        }
+    }
+}
+
+/// Runs all registered enrichers that support a given indicator or entity
+pub struct EnrichmentEngine {
+    enrichers: Vec<Box<dyn Enricher>>,
+}
+
+impl EnrichmentEngine {
+    /// Create new enrichment engine
+    pub fn new() -> Self {
+        Self {
+            enrichers: Vec::new(),
+        }
+    }
+
+    /// Add enrichment provider
+    pub fn add_enricher(&mut self, enricher: Box<dyn Enricher>) {
+        self.enrichers.push(enricher);
+    }
+
+    /// Enrich indicator with every supporting provider; provider failures are logged and skipped
+    pub async fn enrich_indicator(&self, indicator: &mut ThreatIndicator) -> Vec<EnrichmentOutcome> {
+        let mut outcomes = Vec::new();
+
+        for enricher in &self.enrichers {
+            if !enricher.supports_indicator(indicator) {
+                continue;
+            }
+            match enricher.enrich_indicator(indicator).await {
+                Ok(outcome) => outcomes.push(outcome),
+                Err(e) => tracing::warn!("{} failed to enrich {}: {}", enricher.name(), indicator.value, e),
+            }
+        }
+
+        outcomes
+    }
+
+    /// Enrich entity with every supporting provider; provider failures are logged and skipped
+    pub async fn enrich_entity(&self, entity: &mut IntelEntity) -> Vec<EnrichmentOutcome> {
+        let mut outcomes = Vec::new();
+
+        for enricher in &self.enrichers {
+            if !enricher.supports_entity(entity) {
+                continue;
+            }
+            match enricher.enrich_entity(entity).await {
+                Ok(outcome) => outcomes.push(outcome),
+                Err(e) => tracing::warn!("{} failed to enrich {}: {}", enricher.name(), entity.name, e),
+            }
+        }
+
+        outcomes
+    }
+}
+
+impl Default for EnrichmentEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,443 @@
+//! Offline IP geolocation from MaxMind DB (GeoLite2/GeoIP2) files
+
+use super::{Enricher, EnrichmentOutcome};
+use crate::{Result, Error, models::*};
+use crate::geo_intel::{ENTITY_ID_PROPERTY, link_indicator};
+use serde::{Deserialize, Serialize};
+use chrono::Utc;
+use geo::{Geometry, Point};
+use maxminddb::{geoip2, MaxMindDBError, Reader};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::{Mutex, RwLock};
+use uuid::Uuid;
+
+const PROVIDER: &str = "GeoIP";
+
+/// Accuracy assumed when the database has no radius, by the finest place it names
+const CITY_ACCURACY_KM: f32 = 50.0;
+const REGION_ACCURACY_KM: f32 = 250.0;
+const COUNTRY_ACCURACY_KM: f32 = 1000.0;
+
+/// Geolocation of a single IP address
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GeoIpRecord {
+    pub location: Option<Point>,
+    pub accuracy_radius_km: Option<u16>,
+    pub country: Option<String>,
+    pub country_code: Option<String>,
+    pub region: Option<String>,
+    pub city: Option<String>,
+    pub time_zone: Option<String>,
+    pub asn: Option<u32>,
+    pub as_organization: Option<String>,
+}
+
+impl GeoIpRecord {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Accuracy radius in meters; Country databases give none, so their centroids get a coarse default
+    pub fn accuracy_m(&self) -> f32 {
+        let km = match self.accuracy_radius_km {
+            Some(km) => km as f32,
+            None if self.city.is_some() => CITY_ACCURACY_KM,
+            None if self.region.is_some() => REGION_ACCURACY_KM,
+            None => COUNTRY_ACCURACY_KM,
+        };
+        km * 1000.0
+    }
+
+    /// Geo intel record for the located address, with the accuracy radius in meters
+    pub fn to_geo_intel(&self, ip: IpAddr, source: &str) -> Option<GeoIntel> {
+        let location = self.location?;
+        let mut properties = HashMap::new();
+        properties.insert("ip".to_string(), serde_json::json!(ip.to_string()));
+        if let Some(code) = &self.country_code {
+            properties.insert("country_code".to_string(), serde_json::json!(code));
+        }
+        if let Some(asn) = self.asn {
+            properties.insert("asn".to_string(), serde_json::json!(asn));
+        }
+        if let Some(organization) = &self.as_organization {
+            properties.insert("as_organization".to_string(), serde_json::json!(organization));
+        }
+
+        Some(GeoIntel {
+            id: Uuid::new_v4(),
+            geometry: Geometry::Point(location),
+            country: self.country.clone(),
+            region: self.region.clone(),
+            city: self.city.clone(),
+            accuracy: self.accuracy_m(),
+            source: source.to_string(),
+            collected_at: Utc::now(),
+            properties,
+        })
+    }
+}
+
+/// Loaded database file and the modification time it was read at
+struct MmdbFile {
+    path: PathBuf,
+    reader: Arc<Reader<Vec<u8>>>,
+    modified: Option<SystemTime>,
+}
+
+impl MmdbFile {
+    async fn open(path: &Path) -> Result<Self> {
+        let modified = tokio::fs::metadata(path).await?.modified().ok();
+        let bytes = tokio::fs::read(path).await?;
+        let reader = Reader::from_source(bytes)
+            .map_err(|e| Error::Database(format!("Invalid MaxMind database {}: {}", path.display(), e)))?;
+        Ok(Self { path: path.to_path_buf(), reader: Arc::new(reader), modified })
+    }
+
+    async fn is_stale(&self) -> bool {
+        match tokio::fs::metadata(&self.path).await.and_then(|m| m.modified()) {
+            Ok(modified) => Some(modified) != self.modified,
+            Err(_) => false,
+        }
+    }
+}
+
+/// Look up a record, treating addresses outside the database as absent
+fn lookup<'de, T: Deserialize<'de>>(reader: &'de Reader<Vec<u8>>, ip: IpAddr) -> Result<Option<T>> {
+    match reader.lookup::<T>(ip) {
+        Ok(record) => Ok(Some(record)),
+        Err(MaxMindDBError::AddressNotFoundError(_)) => Ok(None),
+        Err(e) => Err(Error::Database(format!("GeoIP lookup for {} failed: {}", ip, e))),
+    }
+}
+
+fn english(names: &Option<std::collections::BTreeMap<&str, &str>>) -> Option<String> {
+    names.as_ref()?.get("en").map(|name| name.to_string())
+}
+
+/// Enricher geolocating IP addresses from local City and ASN databases
+///
+/// Databases can be swapped at runtime with [`GeoIpEnricher::reload`], or checked for a
+/// newer file on disk at most once per `with_reload_interval`.
+pub struct GeoIpEnricher {
+    city: RwLock<MmdbFile>,
+    asn: Option<RwLock<MmdbFile>>,
+    reload_interval: Option<Duration>,
+    last_check: Mutex<Instant>,
+}
+
+impl GeoIpEnricher {
+    /// Open a City (or Country) database
+    pub async fn open(city_database: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self {
+            city: RwLock::new(MmdbFile::open(city_database.as_ref()).await?),
+            asn: None,
+            reload_interval: None,
+            last_check: Mutex::new(Instant::now()),
+        })
+    }
+
+    /// Add an ASN database for AS number and organisation lookups
+    pub async fn with_asn_database(mut self, asn_database: impl AsRef<Path>) -> Result<Self> {
+        self.asn = Some(RwLock::new(MmdbFile::open(asn_database.as_ref()).await?));
+        Ok(self)
+    }
+
+    pub fn with_reload_interval(mut self, interval: Duration) -> Self {
+        self.reload_interval = Some(interval);
+        self
+    }
+
+    /// Re-read the database files, optionally switching the City database to a new path
+    pub async fn reload(&self, city_database: Option<&Path>) -> Result<()> {
+        let city_path = match city_database {
+            Some(path) => path.to_path_buf(),
+            None => self.city.read().await.path.clone(),
+        };
+        // Open both before taking the write locks so lookups continue during the read and a
+        // broken file leaves both databases in place
+        let city = MmdbFile::open(&city_path).await?;
+        let asn = match &self.asn {
+            Some(asn) => Some(MmdbFile::open(&asn.read().await.path.clone()).await?),
+            None => None,
+        };
+        self.swap(Some(city), asn).await;
+        Ok(())
+    }
+
+    /// Replace the given databases while holding both write locks, so no lookup mixes versions
+    async fn swap(&self, city: Option<MmdbFile>, asn: Option<MmdbFile>) {
+        let mut city_guard = self.city.write().await;
+        if let (Some(lock), Some(asn)) = (&self.asn, asn) {
+            *lock.write().await = asn;
+        }
+        if let Some(city) = city {
+            *city_guard = city;
+        }
+    }
+
+    /// Reload any database whose file changed on disk, returning whether one did
+    pub async fn reload_if_changed(&self) -> Result<bool> {
+        let mut files = Vec::new();
+        for database in std::iter::once(&self.city).chain(self.asn.as_ref()) {
+            let current = database.read().await;
+            let file = if current.is_stale().await { Some(MmdbFile::open(&current.path).await?) } else { None };
+            files.push(file);
+        }
+
+        let mut files = files.into_iter();
+        let (city, asn) = (files.next().flatten(), files.next().flatten());
+        let reloaded: Vec<String> = city.iter().chain(&asn).map(|file| file.path.display().to_string()).collect();
+        if reloaded.is_empty() {
+            return Ok(false);
+        }
+        self.swap(city, asn).await;
+        tracing::info!("Reloaded GeoIP databases {}", reloaded.join(", "));
+        Ok(true)
+    }
+
+    async fn maybe_reload(&self) {
+        let Some(interval) = self.reload_interval else {
+            return;
+        };
+        {
+            let mut last_check = self.last_check.lock().await;
+            if last_check.elapsed() < interval {
+                return;
+            }
+            *last_check = Instant::now();
+        }
+        if let Err(e) = self.reload_if_changed().await {
+            tracing::warn!("Keeping current GeoIP database after failed reload: {}", e);
+        }
+    }
+
+    /// Geolocate an IP address
+    pub async fn lookup(&self, ip: IpAddr) -> Result<GeoIpRecord> {
+        self.maybe_reload().await;
+        let mut record = GeoIpRecord::default();
+
+        // The ASN reader is taken under the City lock so both come from the same reload
+        let (city_reader, asn_reader) = {
+            let city = self.city.read().await;
+            let asn = match &self.asn {
+                Some(asn) => Some(asn.read().await.reader.clone()),
+                None => None,
+            };
+            (city.reader.clone(), asn)
+        };
+        if let Some(city) = lookup::<geoip2::City>(&city_reader, ip)? {
+            if let Some(location) = &city.location {
+                if let (Some(lat), Some(lon)) = (location.latitude, location.longitude) {
+                    record.location = Some(Point::new(lon, lat));
+                }
+                record.accuracy_radius_km = location.accuracy_radius;
+                record.time_zone = location.time_zone.map(str::to_string);
+            }
+            if let Some(country) = &city.country {
+                record.country = english(&country.names);
+                record.country_code = country.iso_code.map(str::to_string);
+            }
+            record.region = city.subdivisions.as_ref()
+                .and_then(|subdivisions| subdivisions.first())
+                .and_then(|subdivision| english(&subdivision.names));
+            record.city = city.city.as_ref().and_then(|c| english(&c.names));
+        }
+
+        if let Some(asn_reader) = asn_reader {
+            if let Some(asn) = lookup::<geoip2::Asn>(&asn_reader, ip)? {
+                record.asn = asn.autonomous_system_number;
+                record.as_organization = asn.autonomous_system_organization.map(str::to_string);
+            }
+        }
+
+        Ok(record)
+    }
+}
+
+fn parse_ip(value: &str) -> Result<IpAddr> {
+    value.trim().parse()
+        .map_err(|_| Error::InvalidInput(format!("{} is not an IP address", value)))
+}
+
+/// Write geolocation attributes, returning the keys written
+fn apply_attributes(attributes: &mut HashMap<String, serde_json::Value>, record: &GeoIpRecord) -> Vec<String> {
+    let values = [
+        ("country", record.country.clone().map(serde_json::Value::from)),
+        ("country_code", record.country_code.clone().map(serde_json::Value::from)),
+        ("region", record.region.clone().map(serde_json::Value::from)),
+        ("city", record.city.clone().map(serde_json::Value::from)),
+        ("time_zone", record.time_zone.clone().map(serde_json::Value::from)),
+        ("asn", record.asn.map(serde_json::Value::from)),
+        ("as_organization", record.as_organization.clone().map(serde_json::Value::from)),
+        ("geoip_accuracy_km", record.accuracy_radius_km.map(serde_json::Value::from)),
+    ];
+
+    let mut written = Vec::new();
+    for (key, value) in values {
+        if let Some(value) = value {
+            attributes.insert(key.to_string(), value);
+            written.push(key.to_string());
+        }
+    }
+    written
+}
+
+#[async_trait::async_trait]
+impl Enricher for GeoIpEnricher {
+    fn name(&self) -> &str {
+        PROVIDER
+    }
+
+    fn supports_indicator(&self, indicator: &ThreatIndicator) -> bool {
+        indicator.indicator_type == IndicatorType::IpAddress
+    }
+
+    fn supports_entity(&self, entity: &IntelEntity) -> bool {
+        entity.entity_type == EntityType::IpAddress
+    }
+
+    async fn enrich_indicator(&self, indicator: &mut ThreatIndicator) -> Result<EnrichmentOutcome> {
+        let ip = parse_ip(&indicator.value)?;
+        let record = self.lookup(ip).await?;
+        let mut outcome = EnrichmentOutcome::new(PROVIDER);
+        outcome.found = !record.is_empty();
+        outcome.attributes = apply_attributes(&mut indicator.attributes, &record);
+
+        if let Some(mut geo_intel) = record.to_geo_intel(ip, PROVIDER) {
+            link_indicator(&mut geo_intel, indicator);
+            indicator.attributes.insert("geo_intel_id".to_string(), serde_json::json!(geo_intel.id));
+            outcome.attributes.push("geo_intel_id".to_string());
+            outcome.geo_intel.push(geo_intel);
+        }
+        Ok(outcome)
+    }
+
+    async fn enrich_entity(&self, entity: &mut IntelEntity) -> Result<EnrichmentOutcome> {
+        let ip = parse_ip(&entity.name)?;
+        let record = self.lookup(ip).await?;
+        let mut outcome = EnrichmentOutcome::new(PROVIDER);
+        outcome.found = !record.is_empty();
+        outcome.attributes = apply_attributes(&mut entity.attributes, &record);
+
+        if let Some(mut geo_intel) = record.to_geo_intel(ip, PROVIDER) {
+            geo_intel.properties.insert(ENTITY_ID_PROPERTY.to_string(), serde_json::json!(entity.id));
+            geo_intel.properties.insert("entity_type".to_string(), serde_json::json!(entity.entity_type));
+            entity.location = record.location;
+            entity.attributes.insert("accuracy_m".to_string(), serde_json::json!(geo_intel.accuracy));
+            entity.attributes.insert("geo_intel_id".to_string(), serde_json::json!(geo_intel.id));
+            outcome.attributes.extend(["accuracy_m".to_string(), "geo_intel_id".to_string()]);
+            entity.updated_at = Utc::now();
+            outcome.geo_intel.push(geo_intel);
+        }
+
+        Ok(outcome)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo_intel::StyleBy;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/geo").join(name)
+    }
+
+    #[tokio::test]
+    async fn test_enrich_ip_entity() {
+        let enricher = GeoIpEnricher::open(fixture("GeoLite2-City-Test.mmdb")).await.unwrap()
+            .with_asn_database(fixture("GeoLite2-ASN-Test.mmdb")).await.unwrap();
+
+        let mut entity = IntelEntity::new(EntityType::IpAddress, "81.2.69.142", "test");
+        let outcome = enricher.enrich_entity(&mut entity).await.unwrap();
+
+        assert!(outcome.found);
+        assert_eq!(entity.location, Some(Point::new(-0.0931, 51.5142)));
+        assert_eq!(entity.attributes["city"], "London");
+        assert_eq!(entity.attributes["region"], "England");
+        assert_eq!(entity.attributes["country_code"], "GB");
+        assert_eq!(entity.attributes["asn"], 20712);
+        assert_eq!(entity.attributes["as_organization"], "Andrews & Arnold Ltd");
+
+        let geo_intel = outcome.geo_intel;
+        assert_eq!(geo_intel.len(), 1);
+        assert_eq!(geo_intel[0].accuracy, 10_000.0);
+        assert_eq!(geo_intel[0].city.as_deref(), Some("London"));
+        assert_eq!(entity.attributes["geo_intel_id"], serde_json::json!(geo_intel[0].id));
+
+        assert_eq!(entity.attributes["accuracy_m"], 10_000.0);
+
+        // Addresses outside the database are not an error
+        let mut unknown = IntelEntity::new(EntityType::IpAddress, "192.0.2.1", "test");
+        assert!(!enricher.enrich_entity(&mut unknown).await.unwrap().found);
+        assert!(unknown.location.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_enrich_ip_indicator_carries_severity() {
+        let enricher = GeoIpEnricher::open(fixture("GeoLite2-City-Test.mmdb")).await.unwrap();
+        let mut indicator = ThreatIndicator::new(IndicatorType::IpAddress, "81.2.69.142", "test");
+        indicator.severity = ThreatSeverity::Critical;
+        let outcome = enricher.enrich_indicator(&mut indicator).await.unwrap();
+
+        let record = &outcome.geo_intel[0];
+        assert_eq!(crate::geo_intel::record_severity(record), Some(ThreatSeverity::Critical));
+        assert_eq!(record.properties["indicator_id"], serde_json::json!(indicator.id));
+        assert_eq!(indicator.attributes["geo_intel_id"], serde_json::json!(record.id));
+        assert_eq!(StyleBy::Severity.color(record), "#b2182b");
+
+        let range = ThreatIndicator::new(IndicatorType::NetworkRange, "10.0.0.0/8", "test");
+        assert!(!enricher.supports_indicator(&range));
+    }
+
+    #[test]
+    fn test_missing_accuracy_radius_is_coarse() {
+        let country_level = GeoIpRecord {
+            location: Some(Point::new(10.0, 51.0)),
+            country_code: Some("DE".to_string()),
+            ..GeoIpRecord::default()
+        };
+        let geo_intel = country_level.to_geo_intel("203.0.113.1".parse().unwrap(), PROVIDER).unwrap();
+        assert_eq!(geo_intel.accuracy, 1_000_000.0);
+
+        let city_level = GeoIpRecord { city: Some("Berlin".to_string()), ..country_level };
+        assert_eq!(city_level.accuracy_m(), 50_000.0);
+    }
+
+    #[tokio::test]
+    async fn test_hot_reload() {
+        let enricher = GeoIpEnricher::open(fixture("GeoLite2-City-Test.mmdb")).await.unwrap();
+        let ip: IpAddr = "81.2.69.142".parse().unwrap();
+        assert_eq!(enricher.lookup(ip).await.unwrap().city.as_deref(), Some("London"));
+
+        enricher.reload(Some(&fixture("GeoLite2-City-Test-Updated.mmdb"))).await.unwrap();
+        let record = enricher.lookup(ip).await.unwrap();
+        assert_eq!(record.city.as_deref(), Some("Manchester"));
+        assert_eq!(record.accuracy_radius_km, Some(20));
+
+        // A corrupt replacement keeps the current database
+        assert!(enricher.reload(Some(&fixture("countries.geojson"))).await.is_err());
+        assert_eq!(enricher.lookup(ip).await.unwrap().city.as_deref(), Some("Manchester"));
+        assert!(!enricher.reload_if_changed().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_failed_reload_keeps_both_databases() {
+        let asn_path = std::env::temp_dir().join(format!("osint-asn-{}.mmdb", uuid::Uuid::new_v4()));
+        std::fs::copy(fixture("GeoLite2-ASN-Test.mmdb"), &asn_path).unwrap();
+        let enricher = GeoIpEnricher::open(fixture("GeoLite2-City-Test.mmdb")).await.unwrap()
+            .with_asn_database(&asn_path).await.unwrap();
+        let ip: IpAddr = "81.2.69.142".parse().unwrap();
+
+        std::fs::write(&asn_path, b"not a database").unwrap();
+        let result = enricher.reload(Some(&fixture("GeoLite2-City-Test-Updated.mmdb"))).await;
+        std::fs::remove_file(&asn_path).unwrap();
+
+        assert!(result.is_err());
+        assert_eq!(enricher.lookup(ip).await.unwrap().city.as_deref(), Some("London"));
+    }
+}
@@ -0,0 +1,718 @@
+//! VirusTotal v3 enrichment for hashes, domains, IP addresses and URLs
+
+use super::{Enricher, EnrichmentOutcome};
+use crate::{Result, Error, models::*};
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, TimeZone, Utc};
+use base64::Engine as _;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use reqwest::{Client, StatusCode};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+const DEFAULT_BASE_URL: &str = "https://www.virustotal.com";
+const PROVIDER: &str = "VirusTotal";
+/// Cached lookups kept before the least recently used one is evicted
+const DEFAULT_CACHE_CAPACITY: usize = 10_000;
+
+/// VirusTotal enrichment provider
+pub struct VirusTotalEnricher {
+    base_url: String,
+    api_key: String,
+    client: Client,
+    quota: VtQuota,
+    quota_state: Mutex<QuotaState>,
+    cache: Mutex<HashMap<String, CacheEntry>>,
+    cache_ttl: Duration,
+    cache_capacity: usize,
+}
+
+/// Request quota of a VirusTotal API key
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct VtQuota {
+    pub requests_per_minute: u32,
+    pub requests_per_day: u32,
+}
+
+impl VtQuota {
+    /// Limits of the free public API
+    pub fn public_api() -> Self {
+        Self {
+            requests_per_minute: 4,
+            requests_per_day: 500,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct QuotaState {
+    recent: VecDeque<Instant>,
+    day_started: Instant,
+    day_count: u32,
+}
+
+#[derive(Debug, Clone)]
+struct CacheEntry {
+    fetched_at: Instant,
+    last_used: Instant,
+    /// `None` when VirusTotal has no record of the object
+    report: Option<VtReport>,
+}
+
+/// Summary of a VirusTotal object report
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VtReport {
+    pub malicious: u32,
+    pub suspicious: u32,
+    pub harmless: u32,
+    pub undetected: u32,
+    pub first_submission: Option<DateTime<Utc>>,
+    pub categories: Vec<String>,
+    pub related_infrastructure: Vec<String>,
+    pub reputation: Option<i64>,
+    pub asn: Option<u64>,
+    pub as_owner: Option<String>,
+    pub country: Option<String>,
+}
+
+impl VtReport {
+    /// Engines that returned a verdict
+    pub fn total_engines(&self) -> u32 {
+        self.malicious + self.suspicious + self.harmless + self.undetected
+    }
+
+    /// Fraction of engines flagging the object as malicious
+    pub fn detection_ratio(&self) -> f32 {
+        match self.total_engines() {
+            0 => 0.0,
+            total => self.malicious as f32 / total as f32,
+        }
+    }
+
+    /// Severity implied by the detection results
+    pub fn severity(&self) -> ThreatSeverity {
+        let ratio = self.detection_ratio();
+        if ratio >= 0.5 {
+            ThreatSeverity::Critical
+        } else if ratio >= 0.2 {
+            ThreatSeverity::High
+        } else if self.malicious >= 3 {
+            ThreatSeverity::Medium
+        } else if self.malicious >= 1 || self.suspicious >= 1 {
+            ThreatSeverity::Low
+        } else {
+            ThreatSeverity::Info
+        }
+    }
+}
+
+/// Object collections of the VirusTotal v3 API
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LookupKind {
+    File,
+    Domain,
+    IpAddress,
+    Url,
+}
+
+impl LookupKind {
+    fn from_indicator(indicator_type: &IndicatorType) -> Option<Self> {
+        match indicator_type {
+            IndicatorType::Hash => Some(LookupKind::File),
+            IndicatorType::Domain => Some(LookupKind::Domain),
+            IndicatorType::IpAddress => Some(LookupKind::IpAddress),
+            IndicatorType::Url => Some(LookupKind::Url),
+            _ => None,
+        }
+    }
+
+    fn from_entity(entity_type: &EntityType) -> Option<Self> {
+        match entity_type {
+            EntityType::Domain => Some(LookupKind::Domain),
+            EntityType::IpAddress => Some(LookupKind::IpAddress),
+            EntityType::Url => Some(LookupKind::Url),
+            _ => None,
+        }
+    }
+
+    /// API path for an object, with relationships that reveal related infrastructure
+    fn path(&self, value: &str) -> String {
+        match self {
+            LookupKind::File => format!(
+                "/api/v3/files/{}?relationships=contacted_domains,contacted_ips",
+                value.to_lowercase()
+            ),
+            LookupKind::Domain => format!("/api/v3/domains/{}", value.to_lowercase()),
+            LookupKind::IpAddress => format!("/api/v3/ip_addresses/{}", value),
+            // URL identifiers are the unpadded URL-safe base64 of the URL
+            LookupKind::Url => format!("/api/v3/urls/{}", URL_SAFE_NO_PAD.encode(value)),
+        }
+    }
+
+    /// Only the scheme and host of a URL are case-insensitive; other values are compared in lowercase
+    fn cache_key(&self, value: &str) -> String {
+        let value = match (self, value.split_once("://")) {
+            (LookupKind::Url, Some((scheme, rest))) => {
+                let authority_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+                let (authority, path) = rest.split_at(authority_end);
+                let (userinfo, host) = match authority.rsplit_once('@') {
+                    Some((userinfo, host)) => (format!("{}@", userinfo), host),
+                    None => (String::new(), authority),
+                };
+                format!("{}://{}{}{}", scheme.to_lowercase(), userinfo, host.to_lowercase(), path)
+            }
+            (LookupKind::Url, None) => value.to_string(),
+            _ => value.to_lowercase(),
+        };
+        format!("{:?}:{}", self, value)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ObjectResponse {
+    data: ObjectData,
+}
+
+#[derive(Debug, Deserialize)]
+struct ObjectData {
+    #[serde(default)]
+    attributes: serde_json::Value,
+    #[serde(default)]
+    relationships: BTreeMap<String, Relationship>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Relationship {
+    #[serde(default)]
+    data: Vec<RelatedObject>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RelatedObject {
+    id: String,
+}
+
+impl VirusTotalEnricher {
+    /// Create enricher for the public VirusTotal API
+    pub fn new(api_key: String) -> Self {
+        Self::with_base_url(DEFAULT_BASE_URL.to_string(), api_key)
+    }
+
+    /// Create enricher for a VirusTotal-compatible API at a custom location
+    pub fn with_base_url(base_url: String, api_key: String) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(30))
+            .user_agent("OSINT-Platform/1.0")
+            .build()
+            .expect("Failed to create HTTP client");
+
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            client,
+            quota: VtQuota::public_api(),
+            quota_state: Mutex::new(QuotaState {
+                recent: VecDeque::new(),
+                day_started: Instant::now(),
+                day_count: 0,
+            }),
+            cache: Mutex::new(HashMap::new()),
+            cache_ttl: Duration::from_secs(24 * 3600),
+            cache_capacity: DEFAULT_CACHE_CAPACITY,
+        }
+    }
+
+    /// Set request quota, e.g. for premium API keys
+    pub fn with_quota(mut self, quota: VtQuota) -> Self {
+        self.quota = quota;
+        self
+    }
+
+    /// Set how long lookups are served from the local cache
+    pub fn with_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = ttl;
+        self
+    }
+
+    /// Set how many lookups the local cache holds
+    pub fn with_cache_capacity(mut self, capacity: usize) -> Self {
+        self.cache_capacity = capacity.max(1);
+        self
+    }
+
+    /// Look up an object, using the local cache when fresh
+    async fn lookup(&self, kind: LookupKind, value: &str) -> Result<(Option<VtReport>, bool)> {
+        let key = kind.cache_key(value);
+
+        if let Some(entry) = self.cache.lock().await.get_mut(&key) {
+            if entry.fetched_at.elapsed() < self.cache_ttl {
+                entry.last_used = Instant::now();
+                return Ok((entry.report.clone(), true));
+            }
+        }
+
+        self.acquire_quota().await?;
+        let report = self.fetch_report(kind, value).await?;
+
+        let mut cache = self.cache.lock().await;
+        cache.retain(|_, entry| entry.fetched_at.elapsed() < self.cache_ttl);
+        while cache.len() >= self.cache_capacity {
+            let Some(oldest) = cache.iter().min_by_key(|(_, entry)| entry.last_used).map(|(key, _)| key.clone()) else {
+                break;
+            };
+            cache.remove(&oldest);
+        }
+        let now = Instant::now();
+        cache.insert(key, CacheEntry {
+            fetched_at: now,
+            last_used: now,
+            report: report.clone(),
+        });
+
+        Ok((report, false))
+    }
+
+    /// Wait for a free slot in the per-minute window, failing once the daily quota is spent
+    async fn acquire_quota(&self) -> Result<()> {
+        let minute = Duration::from_secs(60);
+        let mut state = self.quota_state.lock().await;
+
+        if state.day_started.elapsed() >= Duration::from_secs(24 * 3600) {
+            state.day_started = Instant::now();
+            state.day_count = 0;
+        }
+        if state.day_count >= self.quota.requests_per_day {
+            return Err(Error::RateLimited(format!(
+                "VirusTotal daily quota of {} requests exhausted", self.quota.requests_per_day
+            )));
+        }
+
+        loop {
+            while state.recent.front().is_some_and(|t| t.elapsed() >= minute) {
+                state.recent.pop_front();
+            }
+            if (state.recent.len() as u32) < self.quota.requests_per_minute {
+                break;
+            }
+            if let Some(oldest) = state.recent.front() {
+                let wait = minute.saturating_sub(oldest.elapsed());
+                tracing::debug!("VirusTotal per-minute quota reached, waiting {:?}", wait);
+                tokio::time::sleep(wait).await;
+            }
+        }
+
+        state.recent.push_back(Instant::now());
+        state.day_count += 1;
+        Ok(())
+    }
+
+    async fn fetch_report(&self, kind: LookupKind, value: &str) -> Result<Option<VtReport>> {
+        let url = format!("{}{}", self.base_url, kind.path(value));
+        let response = self.client
+            .get(&url)
+            .header("x-apikey", &self.api_key)
+            .send()
+            .await?;
+
+        match response.status() {
+            StatusCode::NOT_FOUND => return Ok(None),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                return Err(Error::Authentication("VirusTotal rejected the API key".to_string()));
+            }
+            StatusCode::TOO_MANY_REQUESTS => {
+                return Err(Error::RateLimited("VirusTotal quota exceeded".to_string()));
+            }
+            _ => {}
+        }
+
+        let body = response.error_for_status()?.text().await?;
+        let object: ObjectResponse = serde_json::from_str(&body)
+            .map_err(|e| Error::Parsing(format!("Invalid VirusTotal response: {}", e)))?;
+
+        Ok(Some(parse_report(kind, object.data)))
+    }
+}
+
+/// Extract the fields of interest from an object report
+fn parse_report(kind: LookupKind, data: ObjectData) -> VtReport {
+    let attributes = &data.attributes;
+    let stats = &attributes["last_analysis_stats"];
+    let count = |field: &str| stats[field].as_u64().unwrap_or(0) as u32;
+
+    let mut report = VtReport {
+        malicious: count("malicious"),
+        suspicious: count("suspicious"),
+        harmless: count("harmless"),
+        undetected: count("undetected"),
+        first_submission: attributes["first_submission_date"].as_i64()
+            .or_else(|| attributes["creation_date"].as_i64())
+            .and_then(|ts| Utc.timestamp_opt(ts, 0).single()),
+        reputation: attributes["reputation"].as_i64(),
+        asn: attributes["asn"].as_u64(),
+        as_owner: attributes["as_owner"].as_str().map(str::to_string),
+        country: attributes["country"].as_str().map(str::to_string),
+        ..Default::default()
+    };
+
+    // Vendor categories for URLs and domains, popular threat categories for files
+    let mut categories: Vec<String> = attributes["categories"].as_object()
+        .map(|c| c.values().filter_map(|v| v.as_str()).map(str::to_lowercase).collect())
+        .unwrap_or_default();
+    if let Some(popular) = attributes["popular_threat_classification"]["popular_threat_category"].as_array() {
+        categories.extend(popular.iter().filter_map(|c| c["value"].as_str()).map(str::to_lowercase));
+    }
+    categories.sort();
+    categories.dedup();
+    report.categories = categories;
+
+    let mut related = Vec::new();
+    match kind {
+        LookupKind::Domain => {
+            if let Some(records) = attributes["last_dns_records"].as_array() {
+                related.extend(records.iter()
+                    .filter(|r| matches!(r["type"].as_str(), Some("A") | Some("AAAA") | Some("CNAME") | Some("NS") | Some("MX")))
+                    .filter_map(|r| r["value"].as_str())
+                    .map(str::to_string));
+            }
+        }
+        LookupKind::IpAddress => {
+            if let Some(network) = attributes["network"].as_str() {
+                related.push(network.to_string());
+            }
+        }
+        LookupKind::Url => {
+            if let Some(final_url) = attributes["last_final_url"].as_str() {
+                related.push(final_url.to_string());
+            }
+        }
+        LookupKind::File => {}
+    }
+    for relationship in data.relationships.values() {
+        for object in &relationship.data {
+            if !related.contains(&object.id) {
+                related.push(object.id.clone());
+            }
+        }
+    }
+    report.related_infrastructure = related;
+
+    report
+}
+
+/// Write report attributes, returning the keys that were set
+fn apply_attributes(attributes: &mut HashMap<String, serde_json::Value>, report: &VtReport) -> Vec<String> {
+    let mut values = vec![
+        ("vt_malicious", serde_json::json!(report.malicious)),
+        ("vt_suspicious", serde_json::json!(report.suspicious)),
+        ("vt_total_engines", serde_json::json!(report.total_engines())),
+        ("vt_detection_ratio", serde_json::json!(format!("{}/{}", report.malicious, report.total_engines()))),
+        ("vt_categories", serde_json::json!(report.categories)),
+        ("vt_related_infrastructure", serde_json::json!(report.related_infrastructure)),
+    ];
+    if let Some(first_submission) = report.first_submission {
+        values.push(("vt_first_submission", serde_json::json!(first_submission.to_rfc3339())));
+    }
+    if let Some(reputation) = report.reputation {
+        values.push(("vt_reputation", serde_json::json!(reputation)));
+    }
+    if let Some(asn) = report.asn {
+        values.push(("vt_asn", serde_json::json!(asn)));
+    }
+    if let Some(as_owner) = &report.as_owner {
+        values.push(("vt_as_owner", serde_json::json!(as_owner)));
+    }
+    if let Some(country) = &report.country {
+        values.push(("vt_country", serde_json::json!(country)));
+    }
+
+    values.into_iter()
+        .map(|(key, value)| {
+            attributes.insert(key.to_string(), value);
+            key.to_string()
+        })
+        .collect()
+}
+
+/// Attribute keeping the confidence the VirusTotal verdict is applied to
+pub const BASE_CONFIDENCE_ATTRIBUTE: &str = "vt_base_confidence";
+
+/// Apply the verdict stored in `attributes` to a base confidence, remembering the base
+///
+/// Recomputing from the base keeps repeated enrichment from compounding the adjustment.
+/// Without a stored verdict the base is returned unchanged.
+pub fn verdict_confidence(base: f32, attributes: &mut HashMap<String, serde_json::Value>) -> f32 {
+    let count = |key: &str| attributes.get(key).and_then(|v| v.as_u64()).map(|n| n as u32);
+    let (Some(malicious), Some(total)) = (count("vt_malicious"), count("vt_total_engines")) else {
+        return base;
+    };
+    attributes.insert(BASE_CONFIDENCE_ATTRIBUTE.to_string(), serde_json::json!(base));
+    let report = VtReport { malicious, undetected: total.saturating_sub(malicious), ..VtReport::default() };
+    adjusted_confidence(base, &report)
+}
+
+/// Confidence before any earlier verdict was applied
+fn base_confidence(current: f32, attributes: &HashMap<String, serde_json::Value>) -> f32 {
+    attributes.get(BASE_CONFIDENCE_ATTRIBUTE)
+        .and_then(|v| v.as_f64())
+        .map_or(current, |base| base as f32)
+}
+
+/// Confidence after weighing in the detection results
+fn adjusted_confidence(current: f32, report: &VtReport) -> f32 {
+    if report.malicious == 0 {
+        // Clean verdicts weaken, but do not refute, the reporting source
+        (current * 0.8).clamp(0.0, 1.0)
+    } else {
+        current.max((0.5 + report.detection_ratio()).min(0.99))
+    }
+}
+
+#[async_trait::async_trait]
+impl Enricher for VirusTotalEnricher {
+    fn name(&self) -> &str {
+        PROVIDER
+    }
+
+    fn supports_indicator(&self, indicator: &ThreatIndicator) -> bool {
+        LookupKind::from_indicator(&indicator.indicator_type).is_some()
+    }
+
+    fn supports_entity(&self, entity: &IntelEntity) -> bool {
+        LookupKind::from_entity(&entity.entity_type).is_some()
+    }
+
+    async fn enrich_indicator(&self, indicator: &mut ThreatIndicator) -> Result<EnrichmentOutcome> {
+        let kind = LookupKind::from_indicator(&indicator.indicator_type)
+            .ok_or_else(|| Error::InvalidInput(format!("{:?} indicators are not supported by VirusTotal", indicator.indicator_type)))?;
+
+        let (report, from_cache) = self.lookup(kind, indicator.value.trim()).await?;
+        let mut outcome = EnrichmentOutcome::new(PROVIDER);
+        outcome.from_cache = from_cache;
+
+        let Some(report) = report else {
+            indicator.attributes.insert("vt_found".to_string(), serde_json::json!(false));
+            outcome.attributes.push("vt_found".to_string());
+            return Ok(outcome);
+        };
+
+        outcome.found = true;
+        let base = base_confidence(indicator.confidence, &indicator.attributes);
+        outcome.attributes = apply_attributes(&mut indicator.attributes, &report);
+
+        // Lower ordinal means more severe; only escalate
+        let severity = report.severity();
+        if report.malicious > 0 && severity < indicator.severity {
+            outcome.previous_severity = Some(indicator.severity.clone());
+            indicator.severity = severity;
+        }
+
+        let confidence = verdict_confidence(base, &mut indicator.attributes);
+        outcome.attributes.push(BASE_CONFIDENCE_ATTRIBUTE.to_string());
+        outcome.confidence_delta = confidence - indicator.confidence;
+        indicator.confidence = confidence;
+
+        Ok(outcome)
+    }
+
+    async fn enrich_entity(&self, entity: &mut IntelEntity) -> Result<EnrichmentOutcome> {
+        let kind = LookupKind::from_entity(&entity.entity_type)
+            .ok_or_else(|| Error::InvalidInput(format!("{:?} entities are not supported by VirusTotal", entity.entity_type)))?;
+
+        let (report, from_cache) = self.lookup(kind, entity.name.trim()).await?;
+        let mut outcome = EnrichmentOutcome::new(PROVIDER);
+        outcome.from_cache = from_cache;
+
+        let Some(report) = report else {
+            entity.attributes.insert("vt_found".to_string(), serde_json::json!(false));
+            outcome.attributes.push("vt_found".to_string());
+            return Ok(outcome);
+        };
+
+        outcome.found = true;
+        let base = base_confidence(entity.confidence, &entity.attributes);
+        outcome.attributes = apply_attributes(&mut entity.attributes, &report);
+        entity.attributes.insert("vt_severity".to_string(), serde_json::to_value(report.severity()).unwrap_or_default());
+        outcome.attributes.push("vt_severity".to_string());
+
+        let previous = entity.confidence;
+        let confidence = verdict_confidence(base, &mut entity.attributes);
+        outcome.attributes.push(BASE_CONFIDENCE_ATTRIBUTE.to_string());
+        entity.update_confidence(confidence);
+        outcome.confidence_delta = entity.confidence - previous;
+
+        Ok(outcome)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    use wiremock::matchers::{header, method, path};
+
+    const FILE_REPORT: &str = include_str!("../../tests/fixtures/virustotal/file_report.json");
+    const DOMAIN_REPORT: &str = include_str!("../../tests/fixtures/virustotal/domain_report.json");
+    const SHA256: &str = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+
+    #[test]
+    fn test_url_identifier() {
+        assert_eq!(
+            LookupKind::Url.path("http://example.com/"),
+            "/api/v3/urls/aHR0cDovL2V4YW1wbGUuY29tLw"
+        );
+    }
+
+    #[test]
+    fn test_cache_key_keeps_url_path_case() {
+        assert_eq!(
+            LookupKind::Url.cache_key("HTTP://Evil.Example/Payload.EXE?Id=A"),
+            "Url:http://evil.example/Payload.EXE?Id=A"
+        );
+        assert_eq!(LookupKind::Url.cache_key("https://User@CDN.example"), "Url:https://User@cdn.example");
+        assert_eq!(LookupKind::Domain.cache_key("Evil.Example"), "Domain:evil.example");
+    }
+
+    #[tokio::test]
+    async fn test_cache_evicts_least_recently_used() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v3/domains/a.example"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(DOMAIN_REPORT))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v3/domains/b.example"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(DOMAIN_REPORT))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v3/domains/c.example"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(DOMAIN_REPORT))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let enricher = VirusTotalEnricher::with_base_url(server.uri(), "vt-key".to_string())
+            .with_quota(VtQuota { requests_per_minute: 10, requests_per_day: 10 })
+            .with_cache_capacity(2);
+        async fn from_cache(enricher: &VirusTotalEnricher, name: &str) -> bool {
+            let mut entity = IntelEntity::new(EntityType::Domain, name, "test");
+            enricher.enrich_entity(&mut entity).await.unwrap().from_cache
+        }
+
+        assert!(!from_cache(&enricher, "c.example").await);
+        assert!(!from_cache(&enricher, "a.example").await);
+        assert!(from_cache(&enricher, "a.example").await);
+        // c.example is the least recently used entry and makes room for b.example
+        assert!(!from_cache(&enricher, "b.example").await);
+        assert!(from_cache(&enricher, "a.example").await);
+        assert!(!from_cache(&enricher, "c.example").await);
+        assert_eq!(enricher.cache.lock().await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_enrich_hash_indicator_with_cache() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(format!("/api/v3/files/{}", SHA256)))
+            .and(header("x-apikey", "vt-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(FILE_REPORT))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let enricher = VirusTotalEnricher::with_base_url(server.uri(), "vt-key".to_string());
+        let mut indicator = ThreatIndicator {
+            severity: ThreatSeverity::Medium,
+            confidence: 0.6,
+            ..ThreatIndicator::new(IndicatorType::Hash, SHA256.to_uppercase(), "misp")
+        };
+
+        let outcome = enricher.enrich_indicator(&mut indicator).await.unwrap();
+        assert!(outcome.found);
+        assert!(!outcome.from_cache);
+        assert_eq!(indicator.severity, ThreatSeverity::Critical);
+        assert_eq!(outcome.previous_severity, Some(ThreatSeverity::Medium));
+        assert!(indicator.confidence > 0.9);
+        assert_eq!(indicator.attributes["vt_detection_ratio"], "52/70");
+        assert_eq!(indicator.attributes["vt_categories"], serde_json::json!(["ransomware", "trojan"]));
+        assert_eq!(indicator.attributes["vt_related_infrastructure"], serde_json::json!(["update-check.example", "203.0.113.50"]));
+
+        // Second lookup is served locally and consumes no quota
+        let mut again = ThreatIndicator::new(IndicatorType::Hash, SHA256, "otx");
+        let outcome = enricher.enrich_indicator(&mut again).await.unwrap();
+        assert!(outcome.from_cache);
+    }
+
+    #[tokio::test]
+    async fn test_enrich_domain_entity_and_daily_quota() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v3/domains/malware-command.example.com"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(DOMAIN_REPORT))
+            .mount(&server)
+            .await;
+
+        let enricher = VirusTotalEnricher::with_base_url(server.uri(), "vt-key".to_string())
+            .with_quota(VtQuota { requests_per_minute: 4, requests_per_day: 1 });
+
+        let mut entity = IntelEntity::new(EntityType::Domain, "malware-command.example.com", "test");
+        let outcome = enricher.enrich_entity(&mut entity).await.unwrap();
+        assert!(outcome.found);
+        assert_eq!(entity.attributes["vt_related_infrastructure"], serde_json::json!(["198.51.100.7", "ns1.bulletproof.example"]));
+        assert!(entity.attributes.contains_key("vt_first_submission"));
+
+        let mut other = IntelEntity::new(EntityType::IpAddress, "198.51.100.7", "test");
+        let err = enricher.enrich_entity(&mut other).await.unwrap_err();
+        assert!(matches!(err, Error::RateLimited(_)));
+    }
+
+    #[tokio::test]
+    async fn test_clean_verdict_does_not_compound() {
+        let server = MockServer::start().await;
+        let clean = DOMAIN_REPORT
+            .replace("\"malicious\": 9", "\"malicious\": 0")
+            .replace("\"suspicious\": 2", "\"suspicious\": 0");
+        Mock::given(method("GET"))
+            .and(path("/api/v3/domains/cdn.example.org"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(clean))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let enricher = VirusTotalEnricher::with_base_url(server.uri(), "vt-key".to_string());
+        let mut indicator = ThreatIndicator {
+            confidence: 0.6,
+            ..ThreatIndicator::new(IndicatorType::Domain, "cdn.example.org", "feed")
+        };
+
+        enricher.enrich_indicator(&mut indicator).await.unwrap();
+        assert!((indicator.confidence - 0.48).abs() < 1e-6);
+        // Re-running from the cache recomputes from the stored base
+        let outcome = enricher.enrich_indicator(&mut indicator).await.unwrap();
+        assert!(outcome.from_cache);
+        assert!((indicator.confidence - 0.48).abs() < 1e-6);
+        assert_eq!(indicator.attributes[BASE_CONFIDENCE_ATTRIBUTE], serde_json::json!(0.6f32));
+
+        // A new base, e.g. after corroboration by another source, keeps the verdict applied
+        assert!((verdict_confidence(0.9, &mut indicator.attributes) - 0.72).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_object() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+
+        let enricher = VirusTotalEnricher::with_base_url(server.uri(), "vt-key".to_string());
+        let mut indicator = ThreatIndicator::new(IndicatorType::IpAddress, "192.0.2.1", "test");
+        let outcome = enricher.enrich_indicator(&mut indicator).await.unwrap();
+        assert!(!outcome.found);
+        assert_eq!(indicator.attributes["vt_found"], false);
+    }
+}
@@ -17,6 +17,12 @@ pub enum Error {
     #[error("Network error: {0}")]
     Network(#[from] reqwest::Error),
 
+    #[error("Operation timed out: {0}")]
+    Timeout(String),
+
+    #[error("Rate limit exceeded: {0}")]
+    RateLimited(String),
+
     #[error("Database error: {0}")]
     Database(String),
 
@@ -62,7 +68,7 @@ impl Error {
     pub fn is_retriable(&self) -> bool {
         matches!(
             self,
-            Error::Network(_) | Error::Database(_) | Error::Internal(_)
+            Error::Network(_) | Error::Timeout(_) | Error::Database(_) | Error::Internal(_)
         )
     }
 
@@ -72,7 +78,7 @@ impl Error {
             Error::InitializationFailed(_) => ErrorSeverity::Critical,
             Error::Configuration(_) => ErrorSeverity::High,
             Error::Authentication(_) | Error::Authorization(_) => ErrorSeverity::High,
-            Error::Network(_) | Error::Database(_) => ErrorSeverity::Medium,
+            Error::Network(_) | Error::Timeout(_) | Error::Database(_) => ErrorSeverity::Medium,
             Error::Parsing(_) | Error::InvalidInput(_) => ErrorSeverity::Low,
             _ => ErrorSeverity::Medium,
         }
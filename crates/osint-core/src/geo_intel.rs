@@ -2,41 +2,59 @@
 
 use crate::{Result, Error, models::*};
 use serde::{Deserialize, Serialize};
-use geo::{Point, Geometry, Contains, HaversineDistance};
-use geojson::{GeoJson, Feature, FeatureCollection};
+use geo::{ChamberlainDuquetteArea, Point, Polygon, Geometry, Intersects, Relate};
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+
+pub mod spatial_index;
+pub mod clustering;
+pub mod geocoder;
+pub mod formats;
+pub mod trajectory;
+pub mod geofence;
+pub mod aggregation;
+pub mod uncertainty;
+
+pub use spatial_index::{GeoBounds, SpatialIndex, distance_to_geometry_km, geometry_distance_km};
+pub use geocoder::{AdminLevel, BoundaryFeature, ReverseGeocode, ReverseGeocoder};
+pub use clustering::{ClusterAlgorithm, ClusteringParams, ClusteringResult, cluster_points, cluster_uncertain_points};
+pub use formats::{ExportFormat, ShapefileLayer, StyleBy};
+pub use aggregation::{AggregateCell, AggregationGrid, AggregationParams, GridKind, TileId};
+pub use geofence::{Geofence, GeofenceArea, GeofenceEvent, GeofenceEventType, GeofenceMonitor, GeofenceTrigger};
+pub use trajectory::{ENTITY_ID_PROPERTY, Stop, Track, TrackLeg, TrackPoint, TrajectoryParams, build_tracks, find_co_travel, track_entity_id};
+pub use uncertainty::{UncertaintyCircle, colocation_probability, probability_within};
+
+/// Property holding a record's threat severity name
+pub const SEVERITY_PROPERTY: &str = "severity";
 
 /// Geospatial intelligence engine
 #[derive(Debug)]
 pub struct GeoIntelEngine {
     geometries: HashMap<Uuid, GeoIntel>,
     spatial_index: SpatialIndex,
-    country_boundaries: Option<FeatureCollection>,
-}
-
-/// Spatial indexing for efficient geographic queries
-#[derive(Debug)]
-pub struct SpatialIndex {
-    // Simplified spatial index - in production, use R-tree or similar
-    grid: HashMap<GridCell, Vec<Uuid>>,
-    cell_size: f64, // degrees
-}
-
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
-struct GridCell {
-    x: i32,
-    y: i32,
+    geocoder: ReverseGeocoder,
+    geofences: GeofenceMonitor,
 }
 
 /// Geographic analysis query
+///
+/// Point geometries search within `radius_km`, lines search a corridor of `radius_km`
+/// either side of the route, and polygons or rectangles match what they contain or
+/// intersect, buffered by `radius_km` when given. `bounds` handles boxes crossing the
+/// antimeridian, which a `Geometry::Rect` cannot express.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GeoQuery {
     pub geometry: Option<Geometry>,
+    #[serde(default)]
+    pub bounds: Option<GeoBounds>,
     pub radius_km: Option<f64>,
+    /// Clustering of the matches; DBSCAN with default parameters when unset
+    #[serde(default)]
+    pub clustering: Option<ClusteringParams>,
     pub countries: Option<Vec<String>>,
     pub date_range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    /// Largest accepted accuracy radius in meters
     pub accuracy_threshold: Option<f32>,
 }
 
@@ -46,6 +64,9 @@ pub struct GeoAnalysisResult {
     pub query_id: Uuid,
     pub matches: Vec<GeoMatch>,
     pub clusters: Vec<GeoCluster>,
+    /// Matches that belong to no cluster
+    #[serde(default)]
+    pub noise: Vec<Uuid>,
     pub statistics: GeoStatistics,
     pub created_at: DateTime<Utc>,
 }
@@ -66,6 +87,10 @@ pub enum GeoMatchType {
     SameCountry,
     SameRegion,
     SameCity,
+    WithinArea,
+    IntersectsArea,
+    WithinBounds,
+    WithinCorridor,
 }
 
 /// Geographic cluster of related points
@@ -75,7 +100,10 @@ pub struct GeoCluster {
     pub center: Point,
     pub radius_km: f64,
     pub members: Vec<Uuid>,
+    /// Members per square kilometre of hull area
     pub density: f32,
+    pub hull: Polygon,
+    pub area_km2: f64,
     pub created_at: DateTime<Utc>,
 }
 
@@ -93,29 +121,59 @@ impl GeoIntelEngine {
     pub fn new() -> Self {
         Self {
             geometries: HashMap::new(),
-            spatial_index: SpatialIndex::new(0.1), // 0.1 degree cells (~11km)
-            country_boundaries: None,
+            spatial_index: SpatialIndex::new(),
+            geocoder: ReverseGeocoder::new(),
+            geofences: GeofenceMonitor::new(),
         }
     }
 
-    /// Load country boundary data
+    /// Load country boundary data used for reverse geocoding
     pub async fn load_country_boundaries(&mut self, geojson_data: &str) -> Result<()> {
-        let geojson = geojson_data.parse::<GeoJson>()
-            .map_err(|e| Error::Geospatial(format!("Failed to parse GeoJSON: {}", e)))?;
+        self.load_boundaries(AdminLevel::Country, geojson_data)
+    }
 
-        match geojson {
-            GeoJson::FeatureCollection(fc) => {
-                self.country_boundaries = Some(fc);
-                Ok(())
-            }
-            _ => Err(Error::Geospatial("Expected FeatureCollection for country boundaries".to_string()))
+    /// Load admin-1 boundary data used for reverse geocoding
+    pub async fn load_region_boundaries(&mut self, geojson_data: &str) -> Result<()> {
+        self.load_boundaries(AdminLevel::Region, geojson_data)
+    }
+
+    /// Load city boundaries or populated places used for reverse geocoding
+    pub async fn load_city_boundaries(&mut self, geojson_data: &str) -> Result<()> {
+        self.load_boundaries(AdminLevel::City, geojson_data)
+    }
+
+    /// Add a boundary layer and fill in locations of records already loaded
+    fn load_boundaries(&mut self, level: AdminLevel, geojson_data: &str) -> Result<()> {
+        self.geocoder.load_geojson(level, geojson_data)?;
+
+        let geocoder = &self.geocoder;
+        for geo_intel in self.geometries.values_mut() {
+            fill_location(geocoder, geo_intel);
         }
+        Ok(())
     }
 
-    /// Add geospatial intelligence data
-    pub fn add_geo_intel(&mut self, geo_intel: GeoIntel) -> Result<()> {
+    /// Reverse geocoder built from the loaded boundaries
+    pub fn geocoder(&self) -> &ReverseGeocoder {
+        &self.geocoder
+    }
+
+    /// Reverse geocode an entity location into `country`, `country_code`, `region` and `city` attributes
+    ///
+    /// Existing attributes are kept. Returns whether any attribute was added.
+    pub fn geocode_entity(&self, entity: &mut IntelEntity) -> bool {
+        geocode_entity(&self.geocoder, entity)
+    }
+
+    /// Add geospatial intelligence data, filling in missing country, region and city
+    ///
+    /// The record is evaluated against the geofences; take the resulting events with
+    /// [`GeoIntelEngine::drain_geofence_events`].
+    pub fn add_geo_intel(&mut self, mut geo_intel: GeoIntel) -> Result<()> {
         // Add to spatial index
         self.spatial_index.insert(geo_intel.id, &geo_intel.geometry)?;
+        fill_location(&self.geocoder, &mut geo_intel);
+        self.geofences.observe_geo_intel(&geo_intel)?;
         
         // Store the geometry
         self.geometries.insert(geo_intel.id, geo_intel);
@@ -123,12 +181,151 @@ impl GeoIntelEngine {
         Ok(())
     }
 
+    /// Add or replace a geofence
+    pub fn add_geofence(&mut self, geofence: Geofence) -> Result<()> {
+        self.geofences.add(geofence)
+    }
+
+    pub fn remove_geofence(&mut self, id: &Uuid) -> Option<Geofence> {
+        self.geofences.remove(id)
+    }
+
+    pub fn geofences(&self) -> &GeofenceMonitor {
+        &self.geofences
+    }
+
+    /// Load geofence definitions from a JSON file, returning how many were added
+    pub fn load_geofences(&mut self, path: &std::path::Path) -> Result<usize> {
+        self.geofences.load_file(path)
+    }
+
+    pub fn save_geofences(&self, path: &std::path::Path) -> Result<()> {
+        self.geofences.save_file(path)
+    }
+
+    /// Evaluate a located entity against the geofences
+    pub fn observe_entity(&mut self, entity: &IntelEntity) -> Result<()> {
+        self.geofences.observe_entity(entity)
+    }
+
+    /// Take the geofence events raised since the last call
+    pub fn drain_geofence_events(&mut self) -> Vec<GeofenceEvent> {
+        self.geofences.drain_events()
+    }
+
+    /// Remove geospatial intelligence data
+    pub fn remove_geo_intel(&mut self, id: &Uuid) -> Option<GeoIntel> {
+        self.spatial_index.remove(id);
+        self.geometries.remove(id)
+    }
+
+    /// Replace existing geospatial intelligence data with the same ID
+    pub fn update_geo_intel(&mut self, mut geo_intel: GeoIntel) -> Result<()> {
+        if !self.geometries.contains_key(&geo_intel.id) {
+            return Err(Error::NotFound(format!("Geo intel {} not found", geo_intel.id)));
+        }
+        self.spatial_index.update(geo_intel.id, &geo_intel.geometry)?;
+        fill_location(&self.geocoder, &mut geo_intel);
+        self.geometries.insert(geo_intel.id, geo_intel);
+        Ok(())
+    }
+
+    /// The `k` records nearest to a point with their distances in km
+    pub fn nearest_geo_intel(&self, point: &Point, k: usize) -> Vec<(&GeoIntel, f64)> {
+        self.spatial_index.nearest(point, k)
+            .into_iter()
+            .filter_map(|(id, distance)| self.geometries.get(&id).map(|g| (g, distance)))
+            .collect()
+    }
+
+    /// Records intersecting longitude/latitude bounds
+    pub fn geo_intel_in_bounds(&self, bounds: &GeoBounds) -> Result<Vec<&GeoIntel>> {
+        Ok(self.spatial_index.query_bounds(bounds)?
+            .iter()
+            .filter_map(|id| self.geometries.get(id))
+            .collect())
+    }
+
+    /// Add imported records, returning how many were added
+    pub fn import_records(&mut self, records: Vec<GeoIntel>) -> Result<usize> {
+        let count = records.len();
+        for record in records {
+            self.add_geo_intel(record)?;
+        }
+        Ok(count)
+    }
+
+    /// Import a KML, KMZ, GPX, GeoJSON or Shapefile file
+    pub async fn import_file(&mut self, path: &std::path::Path, source: &str) -> Result<usize> {
+        let records = formats::import_file(path, source).await?;
+        self.import_records(records)
+    }
+
+    /// Export the matched records and clusters of an analysis
+    pub fn export_analysis(&self, result: &GeoAnalysisResult, format: ExportFormat, style: StyleBy) -> Result<Vec<u8>> {
+        formats::export(&self.analysis_records(result), &result.clusters, format, style)
+    }
+
+    /// Export the matched records of an analysis as shapefile layers
+    pub fn export_shapefiles(&self, result: &GeoAnalysisResult) -> Result<Vec<ShapefileLayer>> {
+        formats::shapefile::export_shapefiles(&self.analysis_records(result))
+    }
+
+    fn analysis_records(&self, result: &GeoAnalysisResult) -> Vec<&GeoIntel> {
+        result.matches.iter()
+            .filter_map(|m| self.geometries.get(&m.geo_intel_id))
+            .collect()
+    }
+
+    /// Tracks of every entity referenced by a record's `entity_id` property
+    pub fn entity_tracks(&self, params: &TrajectoryParams) -> Result<Vec<Track>> {
+        build_tracks(
+            self.geometries.values().filter_map(|g| trajectory::record_entity_id(g).map(|id| (id, g))),
+            params,
+        )
+    }
+
+    /// Entities whose tracks travelled together
+    pub fn co_travelling_entities(&self, params: &TrajectoryParams) -> Result<Vec<crate::data_fusion::CorrelationMatch>> {
+        find_co_travel(&self.entity_tracks(params)?, params)
+    }
+
+    /// Bin records into hexagon or geohash cells
+    pub fn aggregate(&self, params: &AggregationParams) -> Result<Vec<AggregateCell>> {
+        match &params.bounds {
+            Some(bounds) => aggregation::aggregate(self.geo_intel_in_bounds(bounds)?, params),
+            None => aggregation::aggregate(self.geometries.values(), params),
+        }
+    }
+
+    /// Aggregated cells as a GeoJSON map layer
+    pub fn aggregate_geojson(&self, params: &AggregationParams) -> Result<geojson::FeatureCollection> {
+        Ok(aggregation::to_feature_collection(&self.aggregate(params)?))
+    }
+
+    /// Aggregated cells centred in a z/x/y tile, as GeoJSON
+    pub fn aggregate_tile(&self, tile: TileId, params: &AggregationParams) -> Result<geojson::FeatureCollection> {
+        let cells = aggregation::aggregate_tile(tile, params, |bounds| self.geo_intel_in_bounds(bounds))?;
+        Ok(aggregation::to_feature_collection(&cells))
+    }
+
     /// Perform geographic analysis query
     pub async fn analyze_geography(&self, query: &GeoQuery) -> Result<GeoAnalysisResult> {
         let mut matches = Vec::new();
 
         if let Some(query_geometry) = &query.geometry {
-            matches = self.find_nearby_geometries(query_geometry, query.radius_km.unwrap_or(10.0))?;
+            matches = self.find_matching_geometries(query_geometry, query.radius_km)?;
+        }
+
+        if let Some(bounds) = &query.bounds {
+            bounds.validate()?;
+            let bounds_matches = self.find_area_matches(&bounds.to_geometry(), query.radius_km, true)?;
+            if query.geometry.is_some() {
+                // Both constraints apply; keep the geometry match details
+                matches.retain(|m| bounds_matches.iter().any(|b| b.geo_intel_id == m.geo_intel_id));
+            } else {
+                matches = bounds_matches;
+            }
         }
 
         // Filter by countries if specified
@@ -136,7 +333,7 @@ impl GeoIntelEngine {
             matches.retain(|m| {
                 if let Some(geo_intel) = self.geometries.get(&m.geo_intel_id) {
                     if let Some(country) = &geo_intel.country {
-                        countries.contains(country)
+                        countries.iter().any(|c| self.geocoder.country_matches(c, country))
                     } else {
                         false
                     }
@@ -157,8 +354,15 @@ impl GeoIntelEngine {
             });
         }
 
+        // Drop records less precise than the threshold, in meters
+        if let Some(threshold) = query.accuracy_threshold {
+            matches.retain(|m| {
+                self.geometries.get(&m.geo_intel_id).is_some_and(|geo_intel| geo_intel.accuracy <= threshold)
+            });
+        }
+
         // Generate clusters
-        let clusters = self.generate_clusters(&matches)?;
+        let clustering = self.generate_clusters(&matches, &query.clustering.clone().unwrap_or_default())?;
 
         // Calculate statistics
         let statistics = self.calculate_geo_statistics(&matches);
@@ -166,109 +370,121 @@ impl GeoIntelEngine {
         Ok(GeoAnalysisResult {
             query_id: Uuid::new_v4(),
             matches,
-            clusters,
+            clusters: clustering.clusters,
+            noise: clustering.noise,
             statistics,
             created_at: Utc::now(),
         })
     }
 
-    /// Find geometries near a given location
-    fn find_nearby_geometries(&self, query_geometry: &Geometry, radius_km: f64) -> Result<Vec<GeoMatch>> {
-        let mut matches = Vec::new();
-
-        let query_point = match query_geometry {
-            Geometry::Point(p) => *p,
-            _ => return Err(Error::Geospatial("Only point queries are currently supported".to_string())),
-        };
-
-        // Get candidate cells from spatial index
-        let candidate_ids = self.spatial_index.query_radius(&query_point, radius_km)?;
+    /// Dispatch a query geometry to the point, corridor or area search
+    fn find_matching_geometries(&self, query_geometry: &Geometry, radius_km: Option<f64>) -> Result<Vec<GeoMatch>> {
+        match query_geometry {
+            Geometry::Point(p) => Ok(self.find_nearby_geometries(p, radius_km.unwrap_or(10.0))),
+            Geometry::MultiPoint(_) => self.find_proximity_matches(query_geometry, radius_km.unwrap_or(10.0), GeoMatchType::WithinRadius),
+            Geometry::Line(_) | Geometry::LineString(_) | Geometry::MultiLineString(_) => {
+                let width_km = radius_km.ok_or_else(|| Error::Geospatial("Corridor queries require radius_km".to_string()))?;
+                self.find_proximity_matches(query_geometry, width_km, GeoMatchType::WithinCorridor)
+            }
+            Geometry::Polygon(_) | Geometry::MultiPolygon(_) | Geometry::Triangle(_) => {
+                self.find_area_matches(query_geometry, radius_km, false)
+            }
+            Geometry::Rect(_) => self.find_area_matches(query_geometry, radius_km, true),
+            Geometry::GeometryCollection(_) => {
+                Err(Error::Geospatial("Geometry collections are not supported as queries".to_string()))
+            }
+        }
+    }
 
-        for id in candidate_ids {
-            if let Some(geo_intel) = self.geometries.get(&id) {
-                let distance = match &geo_intel.geometry {
-                    Geometry::Point(p) => query_point.haversine_distance(p) / 1000.0, // Convert to km
-                    _ => continue, // Skip non-point geometries for now
+    /// Find geometries near a given location
+    fn find_nearby_geometries(&self, query_point: &Point, radius_km: f64) -> Vec<GeoMatch> {
+        let mut matches: Vec<GeoMatch> = self.spatial_index.within_distance(query_point, radius_km)
+            .into_iter()
+            .map(|(id, distance)| {
+                let match_type = if distance < 0.1 {
+                    GeoMatchType::ExactLocation
+                } else {
+                    GeoMatchType::WithinRadius
                 };
-
-                if distance <= radius_km {
-                    let match_type = if distance < 0.1 {
-                        GeoMatchType::ExactLocation
-                    } else {
-                        GeoMatchType::WithinRadius
-                    };
-
-                    let relevance_score = (1.0 - (distance / radius_km)).max(0.0) as f32;
-
-                    matches.push(GeoMatch {
-                        geo_intel_id: id,
-                        distance_km: Some(distance),
-                        relevance_score,
-                        match_type,
-                    });
+                GeoMatch {
+                    geo_intel_id: id,
+                    distance_km: Some(distance),
+                    relevance_score: proximity_relevance(distance, radius_km) * self.accuracy_weight(&id, distance, radius_km),
+                    match_type,
                 }
-            }
-        }
+            })
+            .collect();
 
-        // Sort by relevance
-        matches.sort_by(|a, b| b.relevance_score.partial_cmp(&a.relevance_score).unwrap_or(std::cmp::Ordering::Equal));
+        sort_by_relevance(&mut matches);
+        matches
+    }
+
+    /// Find geometries within a distance of any part of the query geometry
+    fn find_proximity_matches(&self, query_geometry: &Geometry, radius_km: f64, match_type: GeoMatchType) -> Result<Vec<GeoMatch>> {
+        let mut matches: Vec<GeoMatch> = self.spatial_index.within_distance_of(query_geometry, radius_km)?
+            .into_iter()
+            .map(|(id, distance)| GeoMatch {
+                geo_intel_id: id,
+                distance_km: Some(distance),
+                relevance_score: proximity_relevance(distance, radius_km) * self.accuracy_weight(&id, distance, radius_km),
+                match_type: match_type.clone(),
+            })
+            .collect();
 
+        sort_by_relevance(&mut matches);
         Ok(matches)
     }
 
-    /// Generate geographic clusters using DBSCAN-like algorithm
-    fn generate_clusters(&self, matches: &[GeoMatch]) -> Result<Vec<GeoCluster>> {
-        let mut clusters = Vec::new();
-        let mut visited = vec![false; matches.len()];
-        let min_points = 3;
-        let cluster_radius = 5.0; // km
-
-        for i in 0..matches.len() {
-            if visited[i] {
-                continue;
-            }
+    /// Find geometries inside or intersecting an area, optionally buffered
+    fn find_area_matches(&self, area: &Geometry, buffer_km: Option<f64>, is_bounds: bool) -> Result<Vec<GeoMatch>> {
+        let buffer_km = buffer_km.unwrap_or(0.0);
+        // Radius of a circle with the same area, the scale records must be precise to
+        let area_radius_km = (area.chamberlain_duquette_unsigned_area() / std::f64::consts::PI).sqrt() / 1000.0;
+        let mut matches: Vec<GeoMatch> = self.spatial_index.within_distance_of(area, buffer_km)?
+            .into_iter()
+            .filter_map(|(id, distance)| {
+                let geometry = self.spatial_index.geometry(&id)?;
+                let (match_type, relevance_score) = if distance > 0.0 {
+                    (GeoMatchType::WithinRadius, 0.5 * proximity_relevance(distance, buffer_km) * self.accuracy_weight(&id, distance, buffer_km))
+                } else if area.relate(geometry).is_contains() {
+                    (if is_bounds { GeoMatchType::WithinBounds } else { GeoMatchType::WithinArea }, self.accuracy_weight(&id, 0.0, area_radius_km))
+                } else {
+                    (GeoMatchType::IntersectsArea, 0.75 * self.accuracy_weight(&id, 0.0, area_radius_km))
+                };
+                Some(GeoMatch {
+                    geo_intel_id: id,
+                    distance_km: Some(distance),
+                    relevance_score,
+                    match_type,
+                })
+            })
+            .collect();
 
-            visited[i] = true;
-            
-            if let Some(geo_intel) = self.geometries.get(&matches[i].geo_intel_id) {
-                if let Geometry::Point(center) = geo_intel.geometry {
-                    let mut cluster_members = vec![matches[i].geo_intel_id];
-                    
-                    // Find all points within cluster radius
-                    for j in i + 1..matches.len() {
-                        if visited[j] {
-                            continue;
-                        }
-
-                        if let Some(other_geo) = self.geometries.get(&matches[j].geo_intel_id) {
-                            if let Geometry::Point(other_point) = other_geo.geometry {
-                                let distance = center.haversine_distance(&other_point) / 1000.0;
-                                
-                                if distance <= cluster_radius {
-                                    visited[j] = true;
-                                    cluster_members.push(matches[j].geo_intel_id);
-                                }
-                            }
-                        }
-                    }
+        sort_by_relevance(&mut matches);
+        Ok(matches)
+    }
 
-                    if cluster_members.len() >= min_points {
-                        let density = cluster_members.len() as f32 / (std::f32::consts::PI * cluster_radius as f32 * cluster_radius as f32);
-                        
-                        clusters.push(GeoCluster {
-                            id: Uuid::new_v4(),
-                            center,
-                            radius_km: cluster_radius,
-                            members: cluster_members,
-                            density,
-                            created_at: Utc::now(),
-                        });
-                    }
-                }
+    /// Probability that a record truly lies within `radius_km` of a point `distance_km` from
+    /// its reported location, given its accuracy
+    fn accuracy_weight(&self, id: &Uuid, distance_km: f64, radius_km: f64) -> f32 {
+        match self.geometries.get(id) {
+            Some(geo_intel) if radius_km > 0.0 => {
+                probability_within(distance_km * 1000.0, geo_intel.accuracy as f64, radius_km * 1000.0) as f32
             }
+            _ => 1.0,
         }
+    }
 
-        Ok(clusters)
+    /// Cluster matches by the representative point of their geometry and its accuracy
+    fn generate_clusters(&self, matches: &[GeoMatch], params: &ClusteringParams) -> Result<ClusteringResult> {
+        let points: Vec<(Uuid, Point, f64)> = matches.iter()
+            .filter_map(|m| {
+                let geo_intel = self.geometries.get(&m.geo_intel_id)?;
+                clustering::representative_point(&geo_intel.geometry).map(|p| (m.geo_intel_id, p, geo_intel.accuracy as f64))
+            })
+            .collect();
+
+        cluster_uncertain_points(&points, params)
     }
 
     /// Calculate geographic statistics
@@ -307,31 +523,118 @@ impl GeoIntelEngine {
         }
     }
 
-    /// Get geographic summary for a region
-    pub fn get_regional_summary(&self, bounds: &Geometry) -> Result<RegionalSummary> {
-        let mut entities_in_region = Vec::new();
-        let mut threat_indicators: Vec<ThreatIndicator> = Vec::new();
-
-        for geo_intel in self.geometries.values() {
-            if bounds.contains(&geo_intel.geometry) {
-                entities_in_region.push(geo_intel.id);
-                
-                // This would be extended to link with threat intelligence
-                // For now, it's a placeholder
-            }
+    /// Summarise threat activity in a region
+    ///
+    /// Indicators are located through the records in the region: a record's `indicator_id`
+    /// or `ip` property, an indicator's `geo_intel_id` attribute, or an entity in the region
+    /// named after the indicator value. The threat level comes from severity-weighted,
+    /// recency-decayed indicator counts over the last `period_days`, and the trend compares
+    /// that period with the one before it.
+    pub fn get_regional_summary(
+        &self,
+        bounds: &Geometry,
+        indicators: &[ThreatIndicator],
+        entities: &[IntelEntity],
+        options: &RegionalSummaryOptions,
+    ) -> Result<RegionalSummary> {
+        if options.period_days <= 0 || options.half_life_days <= 0.0 {
+            return Err(Error::InvalidInput("Summary period and half-life must be positive".to_string()));
         }
+        let now = Utc::now();
+        let period_start = now - chrono::Duration::days(options.period_days);
+        let baseline_start = period_start - chrono::Duration::days(options.period_days);
+
+        let records: Vec<&GeoIntel> = self.spatial_index.within_distance_of(bounds, 0.0)?
+            .into_iter()
+            .filter_map(|(id, _)| self.geometries.get(&id))
+            .collect();
+        let record_ids: HashSet<Uuid> = records.iter().map(|r| r.id).collect();
+
+        let regional_entities: Vec<&IntelEntity> = entities.iter()
+            .filter(|e| {
+                e.location.is_some_and(|p| bounds.intersects(&p))
+                    || attribute_uuid(&e.attributes, "geo_intel_id").is_some_and(|id| record_ids.contains(&id))
+            })
+            .collect();
+
+        let linked_ids: HashSet<Uuid> = records.iter()
+            .filter_map(|r| r.properties.get("indicator_id").and_then(|v| v.as_str()).and_then(|s| Uuid::parse_str(s).ok()))
+            .collect();
+        let linked_values: HashSet<&str> = records.iter()
+            .filter_map(|r| r.properties.get("ip").and_then(|v| v.as_str()))
+            .chain(regional_entities.iter().map(|e| e.name.as_str()))
+            .collect();
+        let regional_indicators: Vec<&ThreatIndicator> = indicators.iter()
+            .filter(|i| !matches!(i.status, IndicatorStatus::Revoked | IndicatorStatus::FalsePositive))
+            .filter(|i| {
+                linked_ids.contains(&i.id)
+                    || linked_values.contains(i.value.as_str())
+                    || attribute_uuid(&i.attributes, "geo_intel_id").is_some_and(|id| record_ids.contains(&id))
+            })
+            .collect();
+
+        let last_activity = records.iter().map(|r| r.collected_at)
+            .chain(regional_indicators.iter().map(|i| i.last_seen))
+            .chain(regional_entities.iter().map(|e| e.updated_at))
+            .filter(|t| *t <= now)
+            .max();
+
+        let current: Vec<&ThreatIndicator> = regional_indicators.iter().copied()
+            .filter(|i| i.last_seen >= period_start && i.last_seen <= now)
+            .collect();
+        let threat_score: f64 = current.iter()
+            .map(|i| {
+                let age_days = (now - i.last_seen).num_seconds() as f64 / 86_400.0;
+                indicator_weight(i) * 0.5f64.powf(age_days / options.half_life_days)
+            })
+            .sum();
+        let current_score: f64 = current.iter().map(|i| indicator_weight(i)).sum();
+        let baseline_score: f64 = regional_indicators.iter()
+            .filter(|i| i.last_seen >= baseline_start && i.last_seen < period_start)
+            .map(|i| indicator_weight(i))
+            .sum();
+
+        let mut sources: Vec<&str> = current.iter().map(|i| i.source.as_str()).collect();
+        sources.extend(records.iter().filter(|r| r.collected_at >= period_start).map(|r| r.source.as_str()));
 
         Ok(RegionalSummary {
             id: Uuid::new_v4(),
             bounds: bounds.clone(),
-            entity_count: entities_in_region.len(),
-            threat_level: ThreatLevel::Medium, // Would be calculated based on threats
-            last_activity: Utc::now(),
-            entities: entities_in_region,
+            entity_count: regional_entities.len(),
+            threat_level: ThreatLevel::from_score(threat_score),
+            threat_score,
+            last_activity,
+            entities: regional_entities.iter().map(|e| e.id).collect(),
+            geo_intel: records.iter().map(|r| r.id).collect(),
+            indicators: regional_indicators.iter().map(|i| i.id).collect(),
+            top_threat_types: top_counts(current.iter().map(|i| i.threat_type.clone()), options.top_n),
+            top_sources: top_counts(sources.into_iter().map(str::to_string), options.top_n),
+            trend: RegionalTrend::new(current_score, baseline_score),
         })
     }
 }
 
+/// Periods and limits for regional summaries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegionalSummaryOptions {
+    /// Length of the current period; the baseline is the period before it
+    pub period_days: i64,
+    /// Age at which an indicator counts half towards the threat score
+    pub half_life_days: f64,
+    /// Number of threat types and sources to report
+    pub top_n: usize,
+}
+
+impl Default for RegionalSummaryOptions {
+    fn default() -> Self {
+        Self {
+            period_days: 30,
+            half_life_days: 7.0,
+            top_n: 5,
+        }
+    }
+}
+
 /// Regional intelligence summary
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RegionalSummary {
@@ -339,8 +642,17 @@ pub struct RegionalSummary {
     pub bounds: Geometry,
     pub entity_count: usize,
     pub threat_level: ThreatLevel,
-    pub last_activity: DateTime<Utc>,
+    /// Severity-weighted, recency-decayed indicator count behind `threat_level`
+    pub threat_score: f64,
+    /// Latest record, indicator sighting or entity update in the region
+    pub last_activity: Option<DateTime<Utc>>,
     pub entities: Vec<Uuid>,
+    pub geo_intel: Vec<Uuid>,
+    pub indicators: Vec<Uuid>,
+    /// Most frequent threat types in the current period, with counts
+    pub top_threat_types: Vec<(ThreatType, usize)>,
+    pub top_sources: Vec<(String, usize)>,
+    pub trend: RegionalTrend,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -352,56 +664,182 @@ pub enum ThreatLevel {
     Minimal,
 }
 
-impl SpatialIndex {
-    fn new(cell_size: f64) -> Self {
-        Self {
-            grid: HashMap::new(),
-            cell_size,
+impl ThreatLevel {
+    /// Level for a threat score; one recent high-severity indicator makes a region Medium
+    pub fn from_score(score: f64) -> Self {
+        match score {
+            s if s >= 20.0 => ThreatLevel::Critical,
+            s if s >= 8.0 => ThreatLevel::High,
+            s if s >= 3.0 => ThreatLevel::Medium,
+            s if s >= 0.5 => ThreatLevel::Low,
+            _ => ThreatLevel::Minimal,
         }
     }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum TrendDirection {
+    Rising,
+    Stable,
+    Falling,
+}
+
+/// Severity-weighted indicator counts of the current and baseline periods
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegionalTrend {
+    pub current_score: f64,
+    pub baseline_score: f64,
+    /// Relative change from the baseline, unset when the baseline is empty
+    pub change: Option<f64>,
+    pub direction: TrendDirection,
+}
+
+impl RegionalTrend {
+    /// Changes within 25% of the baseline count as stable
+    fn new(current_score: f64, baseline_score: f64) -> Self {
+        let change = (baseline_score > 0.0).then(|| (current_score - baseline_score) / baseline_score);
+        let direction = match change {
+            Some(c) if c > 0.25 => TrendDirection::Rising,
+            Some(c) if c < -0.25 => TrendDirection::Falling,
+            Some(_) => TrendDirection::Stable,
+            None if current_score > 0.0 => TrendDirection::Rising,
+            None => TrendDirection::Stable,
+        };
+        Self { current_score, baseline_score, change, direction }
+    }
+}
+
+pub(crate) fn severity_weight(severity: &ThreatSeverity) -> f64 {
+    match severity {
+        ThreatSeverity::Critical => 10.0,
+        ThreatSeverity::High => 5.0,
+        ThreatSeverity::Medium => 2.0,
+        ThreatSeverity::Low => 1.0,
+        ThreatSeverity::Info => 0.2,
+    }
+}
+
+fn indicator_weight(indicator: &ThreatIndicator) -> f64 {
+    severity_weight(&indicator.severity) * indicator.confidence.clamp(0.0, 1.0) as f64
+}
 
-    fn insert(&mut self, id: Uuid, geometry: &Geometry) -> Result<()> {
-        match geometry {
-            Geometry::Point(point) => {
-                let cell = self.point_to_cell(point);
-                self.grid.entry(cell).or_insert_with(Vec::new).push(id);
-                Ok(())
+fn attribute_uuid(attributes: &HashMap<String, serde_json::Value>, key: &str) -> Option<Uuid> {
+    attributes.get(key)
+        .and_then(|v| v.as_str())
+        .and_then(|s| Uuid::parse_str(s).ok())
+}
+
+/// The `n` most frequent values, ties broken by first appearance
+fn top_counts<T: Eq + std::hash::Hash + Clone>(values: impl Iterator<Item = T>, n: usize) -> Vec<(T, usize)> {
+    let mut counts: Vec<(T, usize)> = Vec::new();
+    let mut positions: HashMap<T, usize> = HashMap::new();
+    for value in values {
+        match positions.get(&value) {
+            Some(&position) => counts[position].1 += 1,
+            None => {
+                positions.insert(value.clone(), counts.len());
+                counts.push((value, 1));
             }
-            _ => Err(Error::Geospatial("Only point geometries supported in spatial index".to_string()))
         }
     }
+    counts.sort_by_key(|c| std::cmp::Reverse(c.1));
+    counts.truncate(n);
+    counts
+}
+
+/// Fill missing location names of a record from its representative point
+fn fill_location(geocoder: &ReverseGeocoder, geo_intel: &mut GeoIntel) {
+    if geocoder.is_empty() || (geo_intel.country.is_some() && geo_intel.region.is_some() && geo_intel.city.is_some()) {
+        return;
+    }
+    let Some(point) = clustering::representative_point(&geo_intel.geometry) else {
+        return;
+    };
 
-    fn point_to_cell(&self, point: &Point) -> GridCell {
-        GridCell {
-            x: (point.x() / self.cell_size).floor() as i32,
-            y: (point.y() / self.cell_size).floor() as i32,
+    let location = geocoder.reverse_geocode(&point);
+    if geo_intel.country.is_none() {
+        geo_intel.country = location.country;
+        if let Some(code) = location.country_code {
+            geo_intel.properties.entry("country_code".to_string()).or_insert(serde_json::Value::String(code));
         }
     }
+    geo_intel.region = geo_intel.region.take().or(location.region);
+    geo_intel.city = geo_intel.city.take().or(location.city);
+}
 
-    fn query_radius(&self, center: &Point, radius_km: f64) -> Result<Vec<Uuid>> {
-        let mut results = Vec::new();
-        let center_cell = self.point_to_cell(center);
-        
-        // Calculate how many cells to search (approximate)
-        let cells_to_search = ((radius_km / 111.0) / self.cell_size).ceil() as i32 + 1;
-
-        for x_offset in -cells_to_search..=cells_to_search {
-            for y_offset in -cells_to_search..=cells_to_search {
-                let cell = GridCell {
-                    x: center_cell.x + x_offset,
-                    y: center_cell.y + y_offset,
-                };
+/// Add location name attributes to an entity with a location
+pub fn geocode_entity(geocoder: &ReverseGeocoder, entity: &mut IntelEntity) -> bool {
+    let Some(point) = entity.location else {
+        return false;
+    };
+    let location = geocoder.reverse_geocode(&point);
 
-                if let Some(ids) = self.grid.get(&cell) {
-                    results.extend(ids.iter().cloned());
-                }
+    let mut added = false;
+    for (key, value) in [
+        ("country", location.country),
+        ("country_code", location.country_code),
+        ("region", location.region),
+        ("city", location.city),
+    ] {
+        if let Some(value) = value {
+            if !entity.attributes.contains_key(key) {
+                entity.attributes.insert(key.to_string(), serde_json::Value::String(value));
+                added = true;
             }
         }
+    }
+    added
+}
+
+/// Severity of a record, from its `severity` property
+pub fn record_severity(record: &GeoIntel) -> Option<ThreatSeverity> {
+    record.properties.get(SEVERITY_PROPERTY).and_then(|v| v.as_str()).and_then(ThreatSeverity::from_name)
+}
+
+/// Mark a record as locating an indicator, carrying its ID and severity
+pub fn link_indicator(record: &mut GeoIntel, indicator: &ThreatIndicator) {
+    record.properties.insert("indicator_id".to_string(), serde_json::json!(indicator.id));
+    record.properties.insert(SEVERITY_PROPERTY.to_string(), serde_json::json!(indicator.severity.name()));
+}
 
-        Ok(results)
+/// Geo intel record locating an entity, with its accuracy and geocoded names
+pub fn entity_geo_intel(entity: &IntelEntity) -> Option<GeoIntel> {
+    let circle = UncertaintyCircle::from_entity(entity)?;
+    let attribute = |key: &str| entity.attributes.get(key).and_then(|v| v.as_str()).map(str::to_string);
+    let mut properties = HashMap::new();
+    properties.insert(ENTITY_ID_PROPERTY.to_string(), serde_json::json!(entity.id));
+    properties.insert("entity_type".to_string(), serde_json::json!(entity.entity_type));
+    properties.insert("name".to_string(), serde_json::json!(entity.name));
+    if let Some(code) = attribute("country_code") {
+        properties.insert("country_code".to_string(), serde_json::json!(code));
+    }
+
+    Some(GeoIntel {
+        id: Uuid::new_v4(),
+        geometry: Geometry::Point(circle.center),
+        country: attribute("country"),
+        region: attribute("region"),
+        city: attribute("city"),
+        accuracy: circle.accuracy_m as f32,
+        source: entity.source.clone(),
+        collected_at: entity.updated_at,
+        properties,
+    })
+}
+
+/// Relevance falling linearly from 1 at the query to 0 at the search radius
+fn proximity_relevance(distance_km: f64, radius_km: f64) -> f32 {
+    if radius_km > 0.0 {
+        (1.0 - (distance_km / radius_km)).max(0.0) as f32
+    } else {
+        1.0
     }
 }
 
+fn sort_by_relevance(matches: &mut [GeoMatch]) {
+    matches.sort_by(|a, b| b.relevance_score.partial_cmp(&a.relevance_score).unwrap_or(std::cmp::Ordering::Equal));
+}
+
 impl Default for GeoIntelEngine {
     fn default() -> Self {
         Self::new()
@@ -412,6 +850,20 @@ impl Default for GeoIntelEngine {
 mod tests {
     use super::*;
 
+    fn record(geometry: Geometry, accuracy: f32) -> GeoIntel {
+        GeoIntel {
+            id: Uuid::new_v4(),
+            geometry,
+            country: None,
+            region: None,
+            city: None,
+            accuracy,
+            source: "test".to_string(),
+            collected_at: Utc::now(),
+            properties: HashMap::new(),
+        }
+    }
+
     #[test]
     fn test_geo_intel_engine_creation() {
         let engine = GeoIntelEngine::new();
@@ -420,14 +872,14 @@ mod tests {
 
     #[test]
     fn test_spatial_index() {
-        let mut index = SpatialIndex::new(1.0);
+        let mut index = SpatialIndex::new();
         let point = Point::new(0.0, 0.0);
         let id = Uuid::new_v4();
         
         assert!(index.insert(id, &Geometry::Point(point)).is_ok());
         
-        let results = index.query_radius(&point, 1.0).unwrap();
-        assert!(results.contains(&id));
+        let results = index.within_distance(&point, 1.0);
+        assert_eq!(results, vec![(id, 0.0)]);
     }
 
     #[tokio::test]
@@ -450,7 +902,9 @@ mod tests {
 
         let query = GeoQuery {
             geometry: Some(Geometry::Point(Point::new(0.001, 0.001))),
+            bounds: None,
             radius_km: Some(1.0),
+            clustering: None,
             countries: None,
             date_range: None,
             accuracy_threshold: None,
@@ -459,4 +913,221 @@ mod tests {
         let result = engine.analyze_geography(&query).await.unwrap();
         assert!(!result.matches.is_empty());
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_accuracy_weights_relevance_and_filters() {
+        let mut engine = GeoIntelEngine::new();
+        let mut add = |accuracy: f32| {
+            let record = record(Geometry::Point(Point::new(0.001, 0.0)), accuracy);
+            let id = record.id;
+            engine.add_geo_intel(record).unwrap();
+            id
+        };
+        let precise = add(10.0);
+        let vague = add(20_000.0);
+
+        let mut query = GeoQuery {
+            geometry: Some(Geometry::Point(Point::new(0.0, 0.0))),
+            bounds: None,
+            radius_km: Some(1.0),
+            clustering: None,
+            countries: None,
+            date_range: None,
+            accuracy_threshold: None,
+        };
+        let result = engine.analyze_geography(&query).await.unwrap();
+        let ids: Vec<Uuid> = result.matches.iter().map(|m| m.geo_intel_id).collect();
+        assert_eq!(ids, vec![precise, vague]);
+        assert!(result.matches[0].relevance_score > 0.8);
+        assert!(result.matches[1].relevance_score < 0.1);
+
+        query.accuracy_threshold = Some(100.0);
+        let result = engine.analyze_geography(&query).await.unwrap();
+        let ids: Vec<Uuid> = result.matches.iter().map(|m| m.geo_intel_id).collect();
+        assert_eq!(ids, vec![precise]);
+    }
+
+    #[tokio::test]
+    async fn test_area_bounds_and_corridor_queries() {
+        use geo::{line_string, polygon};
+
+        let mut engine = GeoIntelEngine::new();
+        let mut add = |geometry: Geometry| {
+            let record = record(geometry, 10.0);
+            let id = record.id;
+            engine.add_geo_intel(record).unwrap();
+            id
+        };
+        let inside = add(Geometry::Point(Point::new(0.5, 0.5)));
+        let crossing = add(Geometry::LineString(line_string![(x: 0.5, y: 0.2), (x: 1.5, y: 0.2)]));
+        let outside = add(Geometry::Point(Point::new(1.01, 0.5)));
+        let query = |geometry: Geometry, radius_km: Option<f64>| GeoQuery {
+            geometry: Some(geometry),
+            bounds: None,
+            radius_km,
+            clustering: None,
+            countries: None,
+            date_range: None,
+            accuracy_threshold: None,
+        };
+        let kinds = |result: &GeoAnalysisResult| -> HashMap<Uuid, GeoMatchType> {
+            result.matches.iter().map(|m| (m.geo_intel_id, m.match_type.clone())).collect()
+        };
+
+        let area = Geometry::Polygon(polygon![(x: 0.0, y: 0.0), (x: 1.0, y: 0.0), (x: 1.0, y: 1.0), (x: 0.0, y: 1.0), (x: 0.0, y: 0.0)]);
+        let result = engine.analyze_geography(&query(area.clone(), None)).await.unwrap();
+        assert_eq!(kinds(&result), HashMap::from([(inside, GeoMatchType::WithinArea), (crossing, GeoMatchType::IntersectsArea)]));
+
+        // A 2 km buffer reaches the point ~1.1 km east of the area
+        let result = engine.analyze_geography(&query(area, Some(2.0))).await.unwrap();
+        assert_eq!(kinds(&result)[&outside], GeoMatchType::WithinRadius);
+
+        let bbox = Geometry::Rect(geo::Rect::new((0.4, 0.4), (0.6, 0.6)));
+        let result = engine.analyze_geography(&query(bbox, None)).await.unwrap();
+        assert_eq!(kinds(&result), HashMap::from([(inside, GeoMatchType::WithinBounds)]));
+
+        // Route along the 0.5 parallel east of the area: 2 km corridor catches only the nearby point
+        let route = Geometry::LineString(line_string![(x: 1.0, y: 0.5), (x: 2.0, y: 0.5)]);
+        let result = engine.analyze_geography(&query(route.clone(), Some(2.0))).await.unwrap();
+        assert_eq!(kinds(&result), HashMap::from([(outside, GeoMatchType::WithinCorridor)]));
+        assert!(result.matches[0].distance_km.unwrap() < 0.01);
+        assert!(engine.analyze_geography(&query(route, None)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reverse_geocoding_fills_locations() {
+        let mut engine = GeoIntelEngine::new();
+        // Ingested before boundaries exist, then backfilled on load
+        let early = record(Geometry::Point(Point::new(1.0, 1.0)), 10.0);
+        let early_id = early.id;
+        engine.add_geo_intel(early).unwrap();
+        engine.load_country_boundaries(include_str!("../tests/fixtures/geo/countries.geojson")).await.unwrap();
+        assert_eq!(engine.geometries[&early_id].country.as_deref(), Some("Alphaland"));
+
+        engine.add_geo_intel(record(Geometry::Point(Point::new(4.5, 0.5)), 10.0)).unwrap();
+        let query = GeoQuery {
+            geometry: Some(Geometry::Rect(geo::Rect::new((0.0, 0.0), (5.0, 2.0)))),
+            bounds: None,
+            radius_km: None,
+            clustering: None,
+            countries: Some(vec!["BL".to_string()]),
+            date_range: None,
+            accuracy_threshold: None,
+        };
+        let result = engine.analyze_geography(&query).await.unwrap();
+        assert_eq!(result.matches.len(), 1);
+        assert_eq!(engine.geometries[&result.matches[0].geo_intel_id].country.as_deref(), Some("Betaland"));
+
+        let mut entity = IntelEntity {
+            id: Uuid::new_v4(),
+            entity_type: EntityType::Location,
+            name: "site".to_string(),
+            description: None,
+            confidence: 1.0,
+            source: "test".to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            tags: Vec::new(),
+            attributes: HashMap::new(),
+            location: Some(Point::new(2.5, 1.0)),
+            relationships: Vec::new(),
+        };
+        assert!(engine.geocode_entity(&mut entity));
+        assert_eq!(entity.attributes["country_code"], "BL");
+    }
+
+    #[tokio::test]
+    async fn test_import_and_export_analysis() {
+        let mut engine = GeoIntelEngine::new();
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/geo/field_report.kml");
+        assert_eq!(engine.import_file(&path, "field-team").await.unwrap(), 3);
+
+        let query = GeoQuery {
+            geometry: Some(Geometry::Point(Point::new(30.5234, 50.4501))),
+            bounds: None,
+            radius_km: Some(5.0),
+            clustering: None,
+            countries: None,
+            date_range: None,
+            accuracy_threshold: None,
+        };
+        let result = engine.analyze_geography(&query).await.unwrap();
+        assert_eq!(result.matches.len(), 3);
+
+        let kml = engine.export_analysis(&result, ExportFormat::Kml, StyleBy::Source).unwrap();
+        let reimported = formats::kml::import_kml(std::str::from_utf8(&kml).unwrap(), "export").unwrap();
+        assert_eq!(reimported.len(), 3 + result.clusters.len());
+
+        let layers = engine.export_shapefiles(&result).unwrap();
+        assert_eq!(layers.iter().map(|l| l.name.as_str()).collect::<Vec<_>>(), ["points", "lines", "polygons"]);
+    }
+
+    #[test]
+    fn test_new_geo_intel_triggers_geofences() {
+        let mut engine = GeoIntelEngine::new();
+        engine.add_geofence(Geofence::new("site", GeofenceArea::Radius { center: Point::new(10.0, 10.0), radius_km: 1.0 })).unwrap();
+
+        let inside = record(Geometry::Point(Point::new(10.001, 10.0)), 10.0);
+        engine.add_geo_intel(record(Geometry::Point(Point::new(11.0, 10.0)), 10.0)).unwrap();
+        engine.add_geo_intel(inside.clone()).unwrap();
+
+        let events = engine.drain_geofence_events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event_type, GeofenceEventType::Enter);
+        assert!(matches!(&events[0].trigger, GeofenceTrigger::GeoIntel(record) if record.id == inside.id));
+    }
+
+    fn indicator(value: &str, threat_type: ThreatType, severity: ThreatSeverity, source: &str, days_ago: i64) -> ThreatIndicator {
+        let seen = Utc::now() - chrono::Duration::days(days_ago);
+        ThreatIndicator {
+            threat_type,
+            severity,
+            confidence: 1.0,
+            tlp: TrafficLightProtocol::Green,
+            first_seen: seen,
+            last_seen: seen,
+            ..ThreatIndicator::new(IndicatorType::IpAddress, value, source)
+        }
+    }
+
+    #[test]
+    fn test_regional_summary_from_indicators() {
+        let mut engine = GeoIntelEngine::new();
+        let mut located = record(Geometry::Point(Point::new(1.0, 1.0)), 1000.0);
+        located.source = "geoip".to_string();
+        located.collected_at = Utc::now() - chrono::Duration::days(1);
+        located.properties.insert("ip".to_string(), serde_json::json!("203.0.113.5"));
+        engine.add_geo_intel(located.clone()).unwrap();
+
+        let c2 = indicator("203.0.113.5", ThreatType::CommandControl, ThreatSeverity::Critical, "feed-a", 1);
+        let mut revoked = indicator("203.0.113.5", ThreatType::Malware, ThreatSeverity::Critical, "feed-b", 1);
+        revoked.status = IndicatorStatus::Revoked;
+        let older = indicator("203.0.113.5", ThreatType::Phishing, ThreatSeverity::Low, "feed-b", 40);
+        let elsewhere = indicator("198.51.100.7", ThreatType::Malware, ThreatSeverity::Critical, "feed-a", 1);
+
+        let region = Geometry::Rect(geo::Rect::new((0.0, 0.0), (2.0, 2.0)));
+        let summary = engine.get_regional_summary(
+            &region,
+            &[c2.clone(), revoked, older.clone(), elsewhere],
+            &[],
+            &RegionalSummaryOptions::default(),
+        ).unwrap();
+
+        assert_eq!(summary.geo_intel, vec![located.id]);
+        assert_eq!(summary.indicators, vec![c2.id, older.id]);
+        assert_eq!(summary.threat_level, ThreatLevel::High);
+        assert_eq!(summary.last_activity, Some(c2.last_seen));
+        assert_eq!(summary.top_threat_types, vec![(ThreatType::CommandControl, 1)]);
+        assert_eq!(summary.trend.direction, TrendDirection::Rising);
+        assert_eq!(summary.trend.baseline_score, 1.0);
+
+        let quiet = engine.get_regional_summary(
+            &Geometry::Rect(geo::Rect::new((10.0, 10.0), (12.0, 12.0))),
+            &[c2],
+            &[],
+            &RegionalSummaryOptions::default(),
+        ).unwrap();
+        assert_eq!(quiet.threat_level, ThreatLevel::Minimal);
+        assert_eq!(quiet.last_activity, None);
+    }
+}
@@ -0,0 +1,393 @@
+//! Binning of geo intel into hexagon or geohash cells for map layers
+
+use crate::{Result, Error, models::GeoIntel};
+use super::{GeoBounds, clustering::representative_point};
+use serde::{Deserialize, Serialize};
+use geo::{Coord, LineString, Point, Polygon};
+use geojson::{Feature, FeatureCollection, JsonObject};
+use chrono::{DateTime, TimeZone, Utc};
+use h3o::{CellIndex, LatLng, Resolution};
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+
+/// Deepest zoom accepted for tiles
+pub const MAX_TILE_ZOOM: u8 = 24;
+
+/// Web Mercator latitude limit, the edge of the tile pyramid
+const MERCATOR_MAX_LAT: f64 = 85.051_128_779_806_59;
+
+/// Ground size of a pixel at zoom 0 on the equator, for 256 px tiles
+const METERS_PER_PIXEL_Z0: f64 = 156_543.033_928;
+
+/// Cells are sized to roughly this many pixels across at the tile's zoom
+const TARGET_CELL_PIXELS: f64 = 32.0;
+
+const KM_PER_DEGREE: f64 = 111.32;
+
+/// Longest time slice, a century
+const MAX_TIME_SLICE_MINUTES: i64 = 100 * 366 * 24 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GridKind {
+    Hex,
+    Geohash,
+}
+
+/// Cell grid to aggregate into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AggregationGrid {
+    /// H3 hexagons at resolution 0-15
+    Hex { resolution: u8 },
+    /// Geohash cells of 1-12 characters
+    Geohash { precision: usize },
+}
+
+impl AggregationGrid {
+    /// Grid whose cells are about 32 px across at a map zoom level
+    pub fn for_zoom(kind: GridKind, zoom: u8) -> Self {
+        let target_m = TARGET_CELL_PIXELS * METERS_PER_PIXEL_Z0 / 2f64.powi(zoom as i32);
+        let closest = |size_m: &dyn Fn(u32) -> f64, range: std::ops::RangeInclusive<u32>| {
+            range.min_by(|a, b| {
+                (size_m(*a) / target_m).ln().abs().total_cmp(&(size_m(*b) / target_m).ln().abs())
+            }).expect("non-empty range")
+        };
+        match kind {
+            GridKind::Hex => {
+                let diameter = |r: u32| 2.0 * Resolution::try_from(r as u8).expect("valid resolution").edge_length_m();
+                AggregationGrid::Hex { resolution: closest(&diameter, 0..=15) as u8 }
+            }
+            GridKind::Geohash => {
+                let width = |p: u32| 40_075_016.686 / 2f64.powi(((5 * p).div_ceil(2)) as i32);
+                AggregationGrid::Geohash { precision: closest(&width, 1..=12) as usize }
+            }
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        match self {
+            AggregationGrid::Hex { resolution } if *resolution > 15 => {
+                Err(Error::InvalidInput(format!("H3 resolution {} is out of range 0-15", resolution)))
+            }
+            AggregationGrid::Geohash { precision } if !(1..=12).contains(precision) => {
+                Err(Error::InvalidInput(format!("Geohash precision {} is out of range 1-12", precision)))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Identifier of the cell containing a point
+    pub fn cell_id(&self, point: &Point) -> Result<String> {
+        match self {
+            AggregationGrid::Hex { resolution } => {
+                let resolution = Resolution::try_from(*resolution)
+                    .map_err(|e| Error::InvalidInput(format!("Invalid H3 resolution: {}", e)))?;
+                let latlng = LatLng::new(point.y(), point.x())
+                    .map_err(|e| Error::Geospatial(format!("Invalid coordinate: {}", e)))?;
+                Ok(latlng.to_cell(resolution).to_string())
+            }
+            AggregationGrid::Geohash { precision } => geohash::encode(point.0, *precision)
+                .map_err(|e| Error::Geospatial(format!("Cannot geohash {:?}: {}", point, e))),
+        }
+    }
+
+    /// Outline and centre of a cell
+    pub fn cell_shape(&self, cell_id: &str) -> Result<(Polygon, Point)> {
+        match self {
+            AggregationGrid::Hex { .. } => {
+                let cell: CellIndex = cell_id.parse()
+                    .map_err(|e| Error::InvalidInput(format!("Invalid H3 cell {}: {}", cell_id, e)))?;
+                let mut ring: Vec<Coord> = cell.boundary().iter().map(|ll| Coord { x: ll.lng(), y: ll.lat() }).collect();
+                // Keep cells straddling the antimeridian contiguous for map renderers
+                if let Some(first) = ring.first().copied() {
+                    for coord in &mut ring {
+                        if coord.x - first.x > 180.0 {
+                            coord.x -= 360.0;
+                        } else if first.x - coord.x > 180.0 {
+                            coord.x += 360.0;
+                        }
+                    }
+                }
+                let center = LatLng::from(cell);
+                Ok((Polygon::new(LineString::new(ring), vec![]), Point::new(center.lng(), center.lat())))
+            }
+            AggregationGrid::Geohash { .. } => {
+                let rect = geohash::decode_bbox(cell_id)
+                    .map_err(|e| Error::InvalidInput(format!("Invalid geohash {}: {}", cell_id, e)))?;
+                Ok((rect.to_polygon(), Point(rect.center())))
+            }
+        }
+    }
+
+    /// Largest cell width and height in degrees at a latitude, used to pad tile queries
+    fn cell_extent_deg(&self, lat: f64) -> (f64, f64) {
+        match self {
+            AggregationGrid::Hex { resolution } => {
+                let diameter_km = 2.0 * Resolution::try_from(*resolution).map_or(0.0, |r| r.edge_length_km());
+                let lat_deg = diameter_km / KM_PER_DEGREE;
+                let cos = lat.abs().min(MERCATOR_MAX_LAT).to_radians().cos();
+                (lat_deg / cos, lat_deg)
+            }
+            AggregationGrid::Geohash { precision } => {
+                let bits = 5 * *precision as i32;
+                (360.0 / 2f64.powi((bits + 1) / 2), 180.0 / 2f64.powi(bits / 2))
+            }
+        }
+    }
+}
+
+/// What to aggregate and how to slice it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AggregationParams {
+    pub grid: AggregationGrid,
+    /// Split counts into time slices of this many minutes
+    #[serde(default)]
+    pub time_slice_minutes: Option<i64>,
+    #[serde(default)]
+    pub bounds: Option<GeoBounds>,
+    #[serde(default)]
+    pub date_range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+}
+
+impl AggregationParams {
+    pub fn new(grid: AggregationGrid) -> Self {
+        Self { grid, time_slice_minutes: None, bounds: None, date_range: None }
+    }
+
+    fn validate(&self) -> Result<()> {
+        self.grid.validate()?;
+        if self.time_slice_minutes.is_some_and(|m| m <= 0) {
+            return Err(Error::InvalidInput("Time slices must be at least a minute".to_string()));
+        }
+        if self.time_slice_minutes.is_some_and(|m| m > MAX_TIME_SLICE_MINUTES) {
+            return Err(Error::InvalidInput(format!("Time slices must be at most {} minutes", MAX_TIME_SLICE_MINUTES)));
+        }
+        if let Some(bounds) = &self.bounds {
+            bounds.validate()?;
+        }
+        Ok(())
+    }
+}
+
+/// Records falling in one cell and time slice
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AggregateCell {
+    pub cell_id: String,
+    /// Start of the time slice, unset when not slicing
+    pub slice_start: Option<DateTime<Utc>>,
+    pub count: usize,
+    /// Sum of the records' severity weights
+    pub score: f64,
+    pub center: Point,
+    pub polygon: Polygon,
+}
+
+/// Weight of a record by its `severity` property; records without one count as 1
+pub fn severity_weight(record: &GeoIntel) -> f64 {
+    super::record_severity(record).map_or(1.0, |severity| super::severity_weight(&severity))
+}
+
+/// Time slice start and cell ID
+type BinKey = (Option<DateTime<Utc>>, String);
+
+fn slice_start(time: DateTime<Utc>, minutes: i64) -> Option<DateTime<Utc>> {
+    let seconds = minutes.checked_mul(60)?;
+    Utc.timestamp_opt(time.timestamp().div_euclid(seconds).checked_mul(seconds)?, 0).single()
+}
+
+/// Bin records into cells, ordered by time slice and cell
+///
+/// Records outside `bounds` or `date_range` are skipped; non-point records count at their
+/// representative point.
+pub fn aggregate<'a>(records: impl IntoIterator<Item = &'a GeoIntel>, params: &AggregationParams) -> Result<Vec<AggregateCell>> {
+    params.validate()?;
+    let mut bins: BTreeMap<BinKey, (usize, f64)> = BTreeMap::new();
+    for record in records {
+        if params.date_range.is_some_and(|(start, end)| record.collected_at < start || record.collected_at > end) {
+            continue;
+        }
+        let Some(point) = representative_point(&record.geometry) else {
+            continue;
+        };
+        if params.bounds.as_ref().is_some_and(|b| !bounds_contain(b, &point)) {
+            continue;
+        }
+        let slice = params.time_slice_minutes.and_then(|m| slice_start(record.collected_at, m));
+        let bin = bins.entry((slice, params.grid.cell_id(&point)?)).or_default();
+        bin.0 += 1;
+        bin.1 += severity_weight(record);
+    }
+
+    bins.into_iter()
+        .map(|((slice_start, cell_id), (count, score))| {
+            let (polygon, center) = params.grid.cell_shape(&cell_id)?;
+            Ok(AggregateCell { cell_id, slice_start, count, score, center, polygon })
+        })
+        .collect()
+}
+
+/// Half-open containment, so a point on a shared edge belongs to one tile only
+fn bounds_contain(bounds: &GeoBounds, point: &Point) -> bool {
+    let in_lon = if bounds.crosses_antimeridian() {
+        point.x() >= bounds.west || point.x() < bounds.east
+    } else {
+        point.x() >= bounds.west && point.x() < bounds.east
+    };
+    let in_lat = point.y() >= bounds.south && (point.y() < bounds.north || bounds.north >= 90.0);
+    in_lon && in_lat
+}
+
+/// Cells as polygon features with `count`, `score`, `weight` (score relative to the
+/// highest) and `slice_start` properties
+pub fn to_feature_collection(cells: &[AggregateCell]) -> FeatureCollection {
+    let max_score = cells.iter().map(|c| c.score).fold(0.0, f64::max);
+    let features = cells.iter()
+        .map(|cell| {
+            let mut properties = JsonObject::new();
+            properties.insert("cell".to_string(), serde_json::json!(cell.cell_id));
+            properties.insert("count".to_string(), serde_json::json!(cell.count));
+            properties.insert("score".to_string(), serde_json::json!(cell.score));
+            properties.insert("weight".to_string(), serde_json::json!(if max_score > 0.0 { cell.score / max_score } else { 0.0 }));
+            if let Some(slice) = cell.slice_start {
+                properties.insert("slice_start".to_string(), serde_json::json!(slice.to_rfc3339()));
+            }
+            Feature {
+                bbox: None,
+                geometry: Some(geojson::Geometry::from(&geo::Geometry::Polygon(cell.polygon.clone()))),
+                id: Some(geojson::feature::Id::String(cell.cell_id.clone())),
+                properties: Some(properties),
+                foreign_members: None,
+            }
+        })
+        .collect();
+    FeatureCollection { bbox: None, features, foreign_members: None }
+}
+
+/// Slippy-map tile address
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TileId {
+    pub z: u8,
+    pub x: u32,
+    pub y: u32,
+}
+
+impl TileId {
+    pub fn new(z: u8, x: u32, y: u32) -> Result<Self> {
+        let tiles = 1u64 << z.min(MAX_TILE_ZOOM);
+        if z > MAX_TILE_ZOOM || x as u64 >= tiles || y as u64 >= tiles {
+            return Err(Error::InvalidInput(format!("Tile {}/{}/{} does not exist", z, x, y)));
+        }
+        Ok(Self { z, x, y })
+    }
+
+    /// Longitude/latitude bounds of the tile
+    pub fn bounds(&self) -> GeoBounds {
+        let n = 2f64.powi(self.z as i32);
+        let lon = |x: f64| x / n * 360.0 - 180.0;
+        let lat = |y: f64| (PI * (1.0 - 2.0 * y / n)).sinh().atan().to_degrees();
+        GeoBounds::new(lon(self.x as f64), lat(self.y as f64 + 1.0), lon(self.x as f64 + 1.0), lat(self.y as f64))
+    }
+
+    /// Tile bounds grown by one cell on every side, wrapping across the antimeridian
+    fn padded_bounds(&self, grid: &AggregationGrid) -> GeoBounds {
+        let bounds = self.bounds();
+        let (dlon, dlat) = grid.cell_extent_deg(bounds.north.abs().max(bounds.south.abs()));
+        let south = (bounds.south - dlat).max(-90.0);
+        let north = (bounds.north + dlat).min(90.0);
+        if bounds.east - bounds.west + 2.0 * dlon >= 360.0 {
+            return GeoBounds::new(-180.0, south, 180.0, north);
+        }
+        let wrap = |lon: f64| if lon < -180.0 { lon + 360.0 } else if lon > 180.0 { lon - 360.0 } else { lon };
+        GeoBounds::new(wrap(bounds.west - dlon), south, wrap(bounds.east + dlon), north)
+    }
+}
+
+/// Aggregate the records around a tile, keeping the cells whose centre lies in it
+///
+/// Cells are counted over every record they contain, not just those inside the tile, so
+/// a cell has the same totals whichever tile serves it. `fetch` returns the records within
+/// the bounds it is given.
+pub fn aggregate_tile<'a, I>(tile: TileId, params: &AggregationParams, fetch: impl FnOnce(&GeoBounds) -> Result<I>) -> Result<Vec<AggregateCell>>
+where
+    I: IntoIterator<Item = &'a GeoIntel>,
+{
+    let tile_bounds = tile.bounds();
+    let mut padded = params.clone();
+    padded.bounds = Some(tile.padded_bounds(&params.grid));
+    let records = fetch(padded.bounds.as_ref().expect("bounds set"))?;
+
+    let mut cells = aggregate(records, &padded)?;
+    cells.retain(|cell| bounds_contain(&tile_bounds, &cell.center));
+    Ok(cells)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::Geometry;
+    use std::collections::HashMap;
+    use uuid::Uuid;
+
+    fn record(lon: f64, lat: f64, severity: Option<&str>, hour: u32) -> GeoIntel {
+        let mut properties = HashMap::new();
+        if let Some(severity) = severity {
+            properties.insert("severity".to_string(), serde_json::json!(severity));
+        }
+        GeoIntel {
+            id: Uuid::new_v4(),
+            geometry: Geometry::Point(Point::new(lon, lat)),
+            country: None,
+            region: None,
+            city: None,
+            accuracy: 10.0,
+            source: "test".to_string(),
+            collected_at: Utc.with_ymd_and_hms(2024, 3, 1, hour, 30, 0).unwrap(),
+            properties,
+        }
+    }
+
+    #[test]
+    fn test_hex_and_geohash_aggregation() {
+        let records = [
+            record(13.4050, 52.5200, Some("critical"), 9),
+            record(13.4051, 52.5201, None, 9),
+            record(13.4052, 52.5199, Some("low"), 11),
+            record(2.3522, 48.8566, Some("high"), 9),
+        ];
+
+        let cells = aggregate(&records, &AggregationParams::new(AggregationGrid::Hex { resolution: 7 })).unwrap();
+        assert_eq!(cells.len(), 2);
+        let berlin = cells.iter().find(|c| c.count == 3).unwrap();
+        assert_eq!(berlin.score, 12.0);
+        assert_eq!(berlin.polygon.exterior().0.len(), 7);
+
+        let mut params = AggregationParams::new(AggregationGrid::Geohash { precision: 5 });
+        params.time_slice_minutes = Some(60);
+        let sliced = aggregate(&records, &params).unwrap();
+        assert_eq!(sliced.iter().map(|c| (c.cell_id.as_str(), c.count)).collect::<Vec<_>>(), [("u09tv", 1), ("u33dc", 2), ("u33dc", 1)]);
+        assert_eq!(sliced[0].slice_start, Some(Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap()));
+        params.time_slice_minutes = Some(i64::MAX);
+        assert!(matches!(aggregate(&records, &params), Err(Error::InvalidInput(_))));
+        assert_eq!(slice_start(records[0].collected_at, i64::MAX), None);
+
+        let collection = to_feature_collection(&cells);
+        assert_eq!(collection.features[0].property("weight").and_then(|w| w.as_f64()).map(|w| w > 0.0), Some(true));
+    }
+
+    #[test]
+    fn test_tiles_partition_cells() {
+        let tile = TileId::new(1, 1, 0).unwrap();
+        let bounds = tile.bounds();
+        assert_eq!((bounds.west, bounds.east, bounds.south), (0.0, 180.0, 0.0));
+        assert!((bounds.north - MERCATOR_MAX_LAT).abs() < 1e-9);
+        assert!(TileId::new(2, 4, 0).is_err());
+
+        // Points either side of the prime meridian split across the z1 tiles
+        let records = [record(-0.001, 10.0, None, 9), record(0.001, 10.0, None, 9), record(0.002, 10.0, None, 9)];
+        let params = AggregationParams::new(AggregationGrid::for_zoom(GridKind::Geohash, 1));
+        let fetch = |_: &GeoBounds| Ok(records.iter());
+        let east = aggregate_tile(TileId::new(1, 1, 0).unwrap(), &params, fetch).unwrap();
+        let west = aggregate_tile(TileId::new(1, 0, 0).unwrap(), &params, |_: &GeoBounds| Ok(records.iter())).unwrap();
+        assert_eq!(east.iter().chain(&west).map(|c| c.count).sum::<usize>(), 3);
+        assert!(east.iter().all(|c| c.center.x() >= 0.0) && west.iter().all(|c| c.center.x() < 0.0));
+    }
+}
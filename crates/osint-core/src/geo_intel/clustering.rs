@@ -0,0 +1,480 @@
+//! Density-based clustering of geo intel locations
+
+use crate::{Result, Error};
+use super::{GeoCluster, SpatialIndex};
+use super::uncertainty::sigma_m;
+use serde::{Deserialize, Serialize};
+use geo::{ChamberlainDuquetteArea, ConvexHull, Coord, Geometry, HaversineDistance, MultiPoint, Point};
+use chrono::Utc;
+use uuid::Uuid;
+
+/// Clustering algorithm
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClusterAlgorithm {
+    Dbscan,
+    Hdbscan,
+}
+
+/// Clustering parameters for geographic analysis
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClusteringParams {
+    pub algorithm: ClusterAlgorithm,
+    /// DBSCAN neighbourhood radius
+    pub eps_km: f64,
+    /// Neighbours, including the point itself, needed for a core point
+    pub min_points: usize,
+    /// Smallest HDBSCAN cluster; defaults to `min_points`
+    pub min_cluster_size: Option<usize>,
+}
+
+impl Default for ClusteringParams {
+    fn default() -> Self {
+        Self {
+            algorithm: ClusterAlgorithm::Dbscan,
+            eps_km: 5.0,
+            min_points: 3,
+            min_cluster_size: None,
+        }
+    }
+}
+
+impl ClusteringParams {
+    pub fn dbscan(eps_km: f64, min_points: usize) -> Self {
+        Self { algorithm: ClusterAlgorithm::Dbscan, eps_km, min_points, min_cluster_size: None }
+    }
+
+    pub fn hdbscan(min_points: usize, min_cluster_size: usize) -> Self {
+        Self { algorithm: ClusterAlgorithm::Hdbscan, min_points, min_cluster_size: Some(min_cluster_size), ..Self::default() }
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.min_points == 0 {
+            return Err(Error::InvalidInput("min_points must be at least 1".to_string()));
+        }
+        match self.algorithm {
+            ClusterAlgorithm::Dbscan if !(self.eps_km.is_finite() && self.eps_km > 0.0) => {
+                Err(Error::InvalidInput(format!("eps_km must be positive, got {}", self.eps_km)))
+            }
+            ClusterAlgorithm::Hdbscan if self.min_cluster_size.unwrap_or(self.min_points) < 2 => {
+                Err(Error::InvalidInput("min_cluster_size must be at least 2".to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Clusters found among a set of locations plus the points belonging to none
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClusteringResult {
+    pub clusters: Vec<GeoCluster>,
+    pub noise: Vec<Uuid>,
+}
+
+/// Representative point used to cluster a geometry
+pub fn representative_point(geometry: &Geometry) -> Option<Point> {
+    use geo::Centroid;
+    match geometry {
+        Geometry::Point(p) => Some(*p),
+        other => other.centroid(),
+    }
+}
+
+/// Cluster locations with the configured algorithm
+pub fn cluster_points(points: &[(Uuid, Point)], params: &ClusteringParams) -> Result<ClusteringResult> {
+    let points: Vec<(Uuid, Point, f64)> = points.iter().map(|&(id, p)| (id, p, 0.0)).collect();
+    cluster_uncertain_points(&points, params)
+}
+
+/// Cluster locations carrying a 95% accuracy radius in meters
+///
+/// With DBSCAN, points whose accuracy exceeds `eps_km` cannot be placed in a neighbourhood
+/// and are reported as noise. Cluster centres weight members by inverse variance.
+pub fn cluster_uncertain_points(points: &[(Uuid, Point, f64)], params: &ClusteringParams) -> Result<ClusteringResult> {
+    params.validate()?;
+
+    let mut noise = Vec::new();
+    let mut candidates = Vec::with_capacity(points.len());
+    let mut accuracies = Vec::with_capacity(points.len());
+    for &(id, point, accuracy_m) in points {
+        if params.algorithm == ClusterAlgorithm::Dbscan && accuracy_m > params.eps_km * 1000.0 {
+            noise.push(id);
+        } else {
+            candidates.push((id, point));
+            accuracies.push(accuracy_m);
+        }
+    }
+
+    let labels = match params.algorithm {
+        ClusterAlgorithm::Dbscan => dbscan(&candidates, params.eps_km, params.min_points)?,
+        ClusterAlgorithm::Hdbscan => hdbscan(&candidates, params.min_points, params.min_cluster_size.unwrap_or(params.min_points)),
+    };
+
+    let cluster_count = labels.iter().flatten().max().map_or(0, |max| max + 1);
+    let mut members: Vec<Vec<(Uuid, Point, f64)>> = vec![Vec::new(); cluster_count];
+    for ((&(id, point), &accuracy_m), label) in candidates.iter().zip(&accuracies).zip(&labels) {
+        match label {
+            Some(cluster) => members[*cluster].push((id, point, accuracy_m)),
+            None => noise.push(id),
+        }
+    }
+
+    Ok(ClusteringResult {
+        clusters: members.into_iter().map(|m| build_cluster(&m, params.eps_km)).collect(),
+        noise,
+    })
+}
+
+/// DBSCAN using the R-tree index for neighbourhood queries
+fn dbscan(points: &[(Uuid, Point)], eps_km: f64, min_points: usize) -> Result<Vec<Option<usize>>> {
+    let mut index = SpatialIndex::new();
+    let mut position = std::collections::HashMap::new();
+    for (i, (_, point)) in points.iter().enumerate() {
+        // Index by position so duplicate IDs still cluster independently
+        let key = Uuid::from_u128(i as u128);
+        index.insert(key, &Geometry::Point(*point))?;
+        position.insert(key, i);
+    }
+    let neighbours = |i: usize| -> Vec<usize> {
+        index.within_distance(&points[i].1, eps_km).into_iter().map(|(key, _)| position[&key]).collect()
+    };
+
+    let mut labels: Vec<Option<usize>> = vec![None; points.len()];
+    let mut visited = vec![false; points.len()];
+    let mut next_cluster = 0;
+
+    for i in 0..points.len() {
+        if visited[i] {
+            continue;
+        }
+        visited[i] = true;
+        let seeds = neighbours(i);
+        if seeds.len() < min_points {
+            continue;
+        }
+
+        let cluster = next_cluster;
+        next_cluster += 1;
+        labels[i] = Some(cluster);
+
+        let mut queue = seeds;
+        while let Some(j) = queue.pop() {
+            if labels[j].is_none() {
+                labels[j] = Some(cluster);
+            }
+            if visited[j] {
+                continue;
+            }
+            visited[j] = true;
+            let expansion = neighbours(j);
+            if expansion.len() >= min_points {
+                queue.extend(expansion.into_iter().filter(|k| !visited[*k] || labels[*k].is_none()));
+            }
+        }
+    }
+
+    Ok(labels)
+}
+
+/// Cluster in the HDBSCAN condensed tree
+struct CondensedCluster {
+    parent: Option<usize>,
+    birth: f64,
+    stability: f64,
+    children: Vec<usize>,
+}
+
+/// HDBSCAN with excess-of-mass cluster selection
+///
+/// A lone root cluster is kept when the hierarchy never splits, so a single dense
+/// group is reported rather than treated entirely as noise.
+fn hdbscan(points: &[(Uuid, Point)], min_points: usize, min_cluster_size: usize) -> Vec<Option<usize>> {
+    let n = points.len();
+    if n < min_cluster_size {
+        return vec![None; n];
+    }
+
+    let distance = |a: usize, b: usize| points[a].1.haversine_distance(&points[b].1) / 1000.0;
+
+    // Core distance: distance to the min_points-th nearest point, counting the point itself
+    let core: Vec<f64> = (0..n)
+        .map(|i| {
+            let mut distances: Vec<f64> = (0..n).map(|j| distance(i, j)).collect();
+            distances.sort_by(f64::total_cmp);
+            distances[min_points.min(n) - 1]
+        })
+        .collect();
+    let reachability = |a: usize, b: usize| distance(a, b).max(core[a]).max(core[b]);
+
+    // Prim's minimum spanning tree over mutual reachability distance
+    let mut in_tree = vec![false; n];
+    let mut best = vec![(f64::INFINITY, 0usize); n];
+    let mut edges = Vec::with_capacity(n - 1);
+    let mut current = 0;
+    in_tree[0] = true;
+    for _ in 1..n {
+        for j in 0..n {
+            if !in_tree[j] {
+                let d = reachability(current, j);
+                if d < best[j].0 {
+                    best[j] = (d, current);
+                }
+            }
+        }
+        let next = (0..n)
+            .filter(|j| !in_tree[*j])
+            .min_by(|a, b| best[*a].0.total_cmp(&best[*b].0))
+            .expect("spanning tree has unvisited points");
+        edges.push((best[next].1, next, best[next].0));
+        in_tree[next] = true;
+        current = next;
+    }
+    edges.sort_by(|a, b| a.2.total_cmp(&b.2));
+
+    // Single-linkage hierarchy: node n + i is the i-th merge
+    let mut component: Vec<usize> = (0..n).collect();
+    let mut node_of: Vec<usize> = (0..n).collect();
+    let mut merges: Vec<(usize, usize, f64)> = Vec::with_capacity(n - 1);
+    let mut size = vec![1usize; 2 * n - 1];
+    fn find(component: &mut [usize], mut i: usize) -> usize {
+        while component[i] != i {
+            component[i] = component[component[i]];
+            i = component[i];
+        }
+        i
+    }
+    for (a, b, d) in edges {
+        let (ra, rb) = (find(&mut component, a), find(&mut component, b));
+        let node = n + merges.len();
+        size[node] = size[node_of[ra]] + size[node_of[rb]];
+        merges.push((node_of[ra], node_of[rb], d));
+        component[rb] = ra;
+        node_of[ra] = node;
+    }
+
+    let leaves = |node: usize| -> Vec<usize> {
+        let mut stack = vec![node];
+        let mut out = Vec::new();
+        while let Some(node) = stack.pop() {
+            if node < n {
+                out.push(node);
+            } else {
+                let (l, r, _) = merges[node - n];
+                stack.extend([l, r]);
+            }
+        }
+        out
+    };
+
+    // Condense the hierarchy, recording the cluster each point falls out of
+    let mut clusters = vec![CondensedCluster { parent: None, birth: 0.0, stability: 0.0, children: Vec::new() }];
+    let mut exit_cluster = vec![0usize; n];
+    let mut stack = vec![(2 * n - 2, 0usize)];
+    while let Some((node, cluster)) = stack.pop() {
+        if node < n {
+            exit_cluster[node] = cluster;
+            continue;
+        }
+        let (left, right, d) = merges[node - n];
+        let lambda = 1.0 / d.max(1e-9);
+        let birth = clusters[cluster].birth;
+        let (left_big, right_big) = (size[left] >= min_cluster_size, size[right] >= min_cluster_size);
+
+        if left_big && right_big {
+            clusters[cluster].stability += (lambda - birth) * (size[left] + size[right]) as f64;
+            for child in [left, right] {
+                let id = clusters.len();
+                clusters.push(CondensedCluster { parent: Some(cluster), birth: lambda, stability: 0.0, children: Vec::new() });
+                clusters[cluster].children.push(id);
+                stack.push((child, id));
+            }
+            continue;
+        }
+
+        for (child, big) in [(left, left_big), (right, right_big)] {
+            if big {
+                stack.push((child, cluster));
+            } else {
+                for point in leaves(child) {
+                    exit_cluster[point] = cluster;
+                    clusters[cluster].stability += lambda - birth;
+                }
+            }
+        }
+    }
+
+    // Bottom-up selection: children were always created after their parent
+    let mut selected = vec![false; clusters.len()];
+    let mut subtree = vec![0.0; clusters.len()];
+    for c in (0..clusters.len()).rev() {
+        let children: f64 = clusters[c].children.iter().map(|child| subtree[*child]).sum();
+        if clusters[c].children.is_empty() || clusters[c].stability >= children {
+            selected[c] = c != 0 || clusters[c].children.is_empty();
+            subtree[c] = clusters[c].stability;
+        } else {
+            subtree[c] = children;
+        }
+    }
+    for c in 0..clusters.len() {
+        if selected[c] {
+            let mut stack = clusters[c].children.clone();
+            while let Some(child) = stack.pop() {
+                selected[child] = false;
+                stack.extend(clusters[child].children.iter().copied());
+            }
+        }
+    }
+
+    let mut label_of = vec![None; clusters.len()];
+    let mut next_label = 0;
+    for c in 0..clusters.len() {
+        if selected[c] {
+            label_of[c] = Some(next_label);
+            next_label += 1;
+        }
+    }
+
+    exit_cluster.into_iter()
+        .map(|mut c| loop {
+            if let Some(label) = label_of[c] {
+                break Some(label);
+            }
+            match clusters[c].parent {
+                Some(parent) => c = parent,
+                None => break None,
+            }
+        })
+        .collect()
+}
+
+/// Cluster summary with a spherical centroid, convex hull and hull-area density
+///
+/// Longitudes are unwrapped around the centroid so clusters spanning the antimeridian get
+/// a compact hull; its coordinates may then fall outside ±180. Degenerate hulls (one point
+/// or a line) use a circle of radius `eps_km` as their area. Members are weighted by the
+/// inverse variance of their accuracy when placing the centroid.
+fn build_cluster(members: &[(Uuid, Point, f64)], eps_km: f64) -> GeoCluster {
+    let mut sum = [0.0; 3];
+    for (_, p, accuracy_m) in members {
+        let weight = sigma_m(*accuracy_m).powi(-2);
+        let (lon, lat) = (p.x().to_radians(), p.y().to_radians());
+        sum[0] += weight * lat.cos() * lon.cos();
+        sum[1] += weight * lat.cos() * lon.sin();
+        sum[2] += weight * lat.sin();
+    }
+    let center = Point::new(
+        sum[1].atan2(sum[0]).to_degrees(),
+        sum[2].atan2((sum[0] * sum[0] + sum[1] * sum[1]).sqrt()).to_degrees(),
+    );
+
+    let unwrapped: MultiPoint = members.iter()
+        .map(|(_, p, _)| {
+            let offset = (p.x() - center.x() + 540.0).rem_euclid(360.0) - 180.0;
+            Point::from(Coord { x: center.x() + offset, y: p.y() })
+        })
+        .collect();
+    let hull = unwrapped.convex_hull();
+
+    let radius_km = members.iter()
+        .map(|(_, p, _)| center.haversine_distance(p) / 1000.0)
+        .fold(0.0, f64::max);
+    let hull_area_km2 = hull.chamberlain_duquette_unsigned_area() / 1_000_000.0;
+    let area_km2 = if hull_area_km2 > 1e-9 {
+        hull_area_km2
+    } else {
+        std::f64::consts::PI * eps_km * eps_km
+    };
+
+    GeoCluster {
+        id: Uuid::new_v4(),
+        center,
+        radius_km,
+        members: members.iter().map(|(id, _, _)| *id).collect(),
+        density: (members.len() as f64 / area_km2) as f32,
+        hull,
+        area_km2,
+        created_at: Utc::now(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Three tight groups of decreasing density along the equator plus two isolated points
+    fn sample() -> Vec<(Uuid, Point)> {
+        let mut points = Vec::new();
+        for (center, step, count) in [(0.0, 0.005, 8), (1.0, 0.01, 6), (2.0, 0.005, 5)] {
+            for i in 0..count {
+                let x = center + step * (i % 3) as f64;
+                let y = step * (i / 3) as f64;
+                points.push((Uuid::new_v4(), Point::new(x, y)));
+            }
+        }
+        points.push((Uuid::new_v4(), Point::new(0.5, 1.5)));
+        points.push((Uuid::new_v4(), Point::new(3.5, -1.0)));
+        points
+    }
+
+    #[test]
+    fn test_dbscan_expands_chains_and_reports_noise() {
+        // A chain of points 2 km apart only forms one cluster if clusters are expanded
+        let mut points: Vec<(Uuid, Point)> = (0..10).map(|i| (Uuid::new_v4(), Point::new(i as f64 * 0.018, 0.0))).collect();
+        let outlier = Uuid::new_v4();
+        points.push((outlier, Point::new(5.0, 5.0)));
+
+        let result = cluster_points(&points, &ClusteringParams::dbscan(2.5, 3)).unwrap();
+        assert_eq!(result.clusters.len(), 1);
+        assert_eq!(result.clusters[0].members.len(), 10);
+        assert_eq!(result.noise, vec![outlier]);
+
+        // Centroid lies mid-chain rather than at the first point
+        assert!((result.clusters[0].center.x() - 0.081).abs() < 1e-3);
+        assert!(result.clusters[0].area_km2 > 0.0);
+    }
+
+    #[test]
+    fn test_hdbscan_finds_groups_of_varying_density() {
+        let points = sample();
+        let result = cluster_points(&points, &ClusteringParams::hdbscan(3, 4)).unwrap();
+
+        let mut sizes: Vec<usize> = result.clusters.iter().map(|c| c.members.len()).collect();
+        sizes.sort();
+        assert_eq!(sizes, vec![5, 6, 8]);
+        assert_eq!(result.noise.len(), 2);
+        for cluster in &result.clusters {
+            assert!(cluster.density > 0.0 && cluster.area_km2 > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_cluster_across_antimeridian() {
+        let points: Vec<(Uuid, Point)> = [179.99, 179.995, -179.99, -179.995]
+            .iter()
+            .map(|lon| (Uuid::new_v4(), Point::new(*lon, 0.0)))
+            .collect();
+        let result = cluster_points(&points, &ClusteringParams::dbscan(3.0, 2)).unwrap();
+
+        assert_eq!(result.clusters.len(), 1);
+        assert!(result.clusters[0].center.x().abs() > 179.9);
+        assert!(result.clusters[0].radius_km < 2.0);
+        assert!(cluster_points(&points, &ClusteringParams::dbscan(0.0, 2)).is_err());
+    }
+
+    #[test]
+    fn test_uncertain_points_weight_centre_and_drop_imprecise() {
+        let precise = Uuid::new_v4();
+        let vague = Uuid::new_v4();
+        let geoip = Uuid::new_v4();
+        let points = vec![
+            (precise, Point::new(0.0, 0.0), 10.0),
+            (vague, Point::new(0.01, 0.0), 1000.0),
+            (geoip, Point::new(0.005, 0.0), 50_000.0),
+        ];
+        let result = cluster_uncertain_points(&points, &ClusteringParams::dbscan(2.0, 2)).unwrap();
+
+        assert_eq!(result.clusters.len(), 1);
+        assert_eq!(result.noise, vec![geoip]);
+        // The centre sits on the precise fix rather than midway
+        assert!(result.clusters[0].center.x() < 0.001);
+    }
+}
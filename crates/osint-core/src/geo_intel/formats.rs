@@ -0,0 +1,382 @@
+//! Import and export of geo intel in exchange formats (GeoJSON, KML/KMZ, GPX, Shapefile)
+
+use crate::{Result, Error, models::{GeoIntel, ThreatSeverity}};
+use crate::intelligence::{defang_json, refang_json};
+use super::GeoCluster;
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use geo::Geometry;
+use geojson::{Feature, FeatureCollection, GeoJson, JsonObject};
+use quick_xml::events::{BytesStart, Event};
+use std::collections::HashMap;
+use std::path::Path;
+use uuid::Uuid;
+
+pub mod kml;
+pub mod gpx;
+pub mod shapefile;
+
+pub use shapefile::ShapefileLayer;
+
+/// Accuracy assigned to imported records that carry none, typical of consumer GPS
+pub const DEFAULT_IMPORT_ACCURACY_M: f32 = 10.0;
+/// Property marking records whose indicators were defanged by our own export
+pub const DEFANGED_PROPERTY: &str = "defanged";
+
+/// Export format for geo intel and analysis results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExportFormat {
+    GeoJson,
+    Kml,
+    Gpx,
+}
+
+/// Attribute used to colour exported features
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum StyleBy {
+    #[default]
+    None,
+    Source,
+    /// The `severity` property, as set on records derived from threat indicators
+    Severity,
+}
+
+const SOURCE_PALETTE: [&str; 8] = ["#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b", "#e377c2", "#17becf"];
+const DEFAULT_COLOR: &str = "#777777";
+const CLUSTER_COLOR: &str = "#444444";
+
+impl StyleBy {
+    /// `#rrggbb` colour for a record
+    pub fn color(&self, record: &GeoIntel) -> &'static str {
+        match self {
+            StyleBy::None => DEFAULT_COLOR,
+            StyleBy::Source => {
+                // FNV-1a so colours are stable across runs
+                let hash = record.source.bytes().fold(0xcbf2_9ce4_8422_2325u64, |h, b| (h ^ b as u64).wrapping_mul(0x100_0000_01b3));
+                SOURCE_PALETTE[(hash % SOURCE_PALETTE.len() as u64) as usize]
+            }
+            StyleBy::Severity => match super::record_severity(record) {
+                Some(ThreatSeverity::Critical) => "#b2182b",
+                Some(ThreatSeverity::High) => "#ef8a62",
+                Some(ThreatSeverity::Medium) => "#fdb863",
+                Some(ThreatSeverity::Low) => "#67a9cf",
+                Some(ThreatSeverity::Info) => "#999999",
+                None => DEFAULT_COLOR,
+            },
+        }
+    }
+}
+
+/// Display name of a record: its `name` property, else its ID
+pub fn record_name(record: &GeoIntel) -> String {
+    record.properties.get("name")
+        .and_then(|v| v.as_str())
+        .map(str::to_string)
+        .unwrap_or_else(|| record.id.to_string())
+}
+
+/// Attribute a named track to the entity derived from its name, unless it already names one
+pub(crate) fn attribute_track(properties: &mut HashMap<String, serde_json::Value>) {
+    let Some(name) = properties.get("name").and_then(|v| v.as_str()).filter(|n| !n.trim().is_empty()) else {
+        return;
+    };
+    let entity_id = super::track_entity_id(name);
+    properties.entry(super::ENTITY_ID_PROPERTY.to_string()).or_insert_with(|| serde_json::json!(entity_id));
+}
+
+/// Build a record from an imported geometry with no location names yet
+///
+/// Properties of records carrying the [`DEFANGED_PROPERTY`] marker are refanged, undoing the
+/// defanging applied on export; properties of other files are kept as written.
+pub(crate) fn imported_record(geometry: Geometry, source: &str, collected_at: Option<DateTime<Utc>>, mut properties: HashMap<String, serde_json::Value>) -> GeoIntel {
+    // Text formats carry the marker as a string
+    let defanged = properties.remove(DEFANGED_PROPERTY)
+        .is_some_and(|marker| marker == serde_json::json!(true) || marker == "true");
+    if defanged {
+        properties = properties.iter().map(|(key, value)| (key.clone(), refang_json(value))).collect();
+    }
+    GeoIntel {
+        id: Uuid::new_v4(),
+        geometry,
+        country: None,
+        region: None,
+        city: None,
+        accuracy: DEFAULT_IMPORT_ACCURACY_M,
+        source: source.to_string(),
+        collected_at: collected_at.unwrap_or_else(Utc::now),
+        properties,
+    }
+}
+
+pub(crate) fn parse_time(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value.trim()).ok().map(|t| t.with_timezone(&Utc))
+}
+
+/// Copy of a record with indicators in its properties defanged, so exports carry no live IOCs
+pub(crate) fn defanged_record(record: &GeoIntel) -> GeoIntel {
+    let mut properties: HashMap<String, serde_json::Value> = record.properties.iter()
+        .map(|(key, value)| (key.clone(), defang_json(value)))
+        .collect();
+    properties.insert(DEFANGED_PROPERTY.to_string(), serde_json::json!(true));
+    GeoIntel {
+        properties,
+        ..record.clone()
+    }
+}
+
+/// Export records and clusters in the requested format, with indicators defanged
+pub fn export(records: &[&GeoIntel], clusters: &[GeoCluster], format: ExportFormat, style: StyleBy) -> Result<Vec<u8>> {
+    let defanged: Vec<GeoIntel> = records.iter().map(|record| defanged_record(record)).collect();
+    let records: Vec<&GeoIntel> = defanged.iter().collect();
+    let records = records.as_slice();
+    match format {
+        ExportFormat::GeoJson => {
+            let collection = to_feature_collection(records, clusters, style)?;
+            Ok(GeoJson::FeatureCollection(collection).to_string().into_bytes())
+        }
+        ExportFormat::Kml => Ok(kml::write_kml(records, clusters, style).into_bytes()),
+        ExportFormat::Gpx => Ok(gpx::write_gpx(records, clusters).into_bytes()),
+    }
+}
+
+/// Records and clusters as GeoJSON features with simplestyle colour properties
+pub fn to_feature_collection(records: &[&GeoIntel], clusters: &[GeoCluster], style: StyleBy) -> Result<FeatureCollection> {
+    let mut features = Vec::with_capacity(records.len() + clusters.len());
+
+    for record in records {
+        let mut properties = JsonObject::new();
+        for (key, value) in &record.properties {
+            properties.insert(key.clone(), value.clone());
+        }
+        properties.insert("id".to_string(), serde_json::json!(record.id));
+        properties.insert("source".to_string(), serde_json::json!(record.source));
+        properties.insert("collected_at".to_string(), serde_json::json!(record.collected_at));
+        properties.insert("accuracy_m".to_string(), serde_json::json!(record.accuracy));
+        for (key, value) in [("country", &record.country), ("region", &record.region), ("city", &record.city)] {
+            if let Some(value) = value {
+                properties.insert(key.to_string(), serde_json::json!(value));
+            }
+        }
+        let color = style.color(record);
+        for key in ["marker-color", "stroke", "fill"] {
+            properties.insert(key.to_string(), serde_json::json!(color));
+        }
+
+        features.push(Feature {
+            bbox: None,
+            geometry: Some(geojson::Geometry::from(&record.geometry)),
+            id: None,
+            properties: Some(properties),
+            foreign_members: None,
+        });
+    }
+
+    for cluster in clusters {
+        let mut properties = JsonObject::new();
+        properties.insert("kind".to_string(), serde_json::json!("cluster"));
+        properties.insert("id".to_string(), serde_json::json!(cluster.id));
+        properties.insert("members".to_string(), serde_json::json!(cluster.members));
+        properties.insert("radius_km".to_string(), serde_json::json!(cluster.radius_km));
+        properties.insert("density".to_string(), serde_json::json!(cluster.density));
+        properties.insert("area_km2".to_string(), serde_json::json!(cluster.area_km2));
+        properties.insert("stroke".to_string(), serde_json::json!(CLUSTER_COLOR));
+        properties.insert("fill-opacity".to_string(), serde_json::json!(0.2));
+
+        features.push(Feature {
+            bbox: None,
+            geometry: Some(geojson::Geometry::from(&Geometry::Polygon(cluster.hull.clone()))),
+            id: None,
+            properties: Some(properties),
+            foreign_members: None,
+        });
+    }
+
+    Ok(FeatureCollection { bbox: None, features, foreign_members: None })
+}
+
+/// Import the features of a GeoJSON document, keeping their properties
+pub fn import_geojson(data: &str, source: &str) -> Result<Vec<GeoIntel>> {
+    let geojson = data.parse::<GeoJson>()
+        .map_err(|e| Error::Geospatial(format!("Failed to parse GeoJSON: {}", e)))?;
+    let features = match geojson {
+        GeoJson::FeatureCollection(collection) => collection.features,
+        GeoJson::Feature(feature) => vec![feature],
+        GeoJson::Geometry(geometry) => vec![Feature::from(geometry)],
+    };
+
+    let mut records = Vec::new();
+    for feature in features {
+        let Some(geometry) = feature.geometry else {
+            continue;
+        };
+        let geometry = Geometry::try_from(geometry)
+            .map_err(|e| Error::Geospatial(format!("Invalid GeoJSON geometry: {}", e)))?;
+        let properties: HashMap<String, serde_json::Value> = feature.properties.unwrap_or_default().into_iter().collect();
+        let collected_at = ["time", "timestamp", "collected_at"].iter()
+            .find_map(|key| properties.get(*key).and_then(|v| v.as_str()).and_then(parse_time));
+        records.push(imported_record(geometry, source, collected_at, properties));
+    }
+    Ok(records)
+}
+
+/// Import a file, choosing the format from its extension
+///
+/// Shapefiles read attributes from the `.dbf` next to the `.shp` when present.
+pub async fn import_file(path: &Path, source: &str) -> Result<Vec<GeoIntel>> {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_ascii_lowercase();
+    match extension.as_str() {
+        "kml" => kml::import_kml(&tokio::fs::read_to_string(path).await?, source),
+        "kmz" => kml::import_kmz(&tokio::fs::read(path).await?, source),
+        "gpx" => gpx::import_gpx(&tokio::fs::read_to_string(path).await?, source),
+        "geojson" | "json" => import_geojson(&tokio::fs::read_to_string(path).await?, source),
+        "shp" => {
+            let shp = tokio::fs::read(path).await?;
+            let dbf = tokio::fs::read(path.with_extension("dbf")).await.ok();
+            shapefile::import_shapefile(&shp, dbf.as_deref(), source)
+        }
+        other => Err(Error::InvalidInput(format!("Unsupported geo file type: .{}", other))),
+    }
+}
+
+/// Minimal XML element tree used by the KML and GPX readers
+#[derive(Debug, Default)]
+pub(crate) struct XmlElement {
+    /// Local name without namespace prefix
+    pub name: String,
+    pub attributes: HashMap<String, String>,
+    pub text: String,
+    pub children: Vec<XmlElement>,
+}
+
+impl XmlElement {
+    pub fn child(&self, name: &str) -> Option<&XmlElement> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    pub fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a XmlElement> + 'a {
+        self.children.iter().filter(move |c| c.name == name)
+    }
+
+    pub fn child_text(&self, name: &str) -> Option<&str> {
+        self.child(name).map(|c| c.text.trim()).filter(|t| !t.is_empty())
+    }
+
+    /// All descendants with the given name, depth first
+    pub fn descendants<'a>(&'a self, name: &'a str, out: &mut Vec<&'a XmlElement>) {
+        for child in &self.children {
+            if child.name == name {
+                out.push(child);
+            }
+            child.descendants(name, out);
+        }
+    }
+}
+
+fn xml_error(e: quick_xml::Error) -> Error {
+    Error::Parsing(format!("Invalid XML: {}", e))
+}
+
+fn xml_element(start: &BytesStart) -> Result<XmlElement> {
+    let local = |name: &[u8]| String::from_utf8_lossy(name).to_string();
+    let mut element = XmlElement { name: local(start.local_name().as_ref()), ..Default::default() };
+    for attribute in start.attributes().flatten() {
+        let value = attribute.unescape_value().map_err(xml_error)?.to_string();
+        element.attributes.insert(local(attribute.key.local_name().as_ref()), value);
+    }
+    Ok(element)
+}
+
+pub(crate) fn parse_xml(data: &str) -> Result<XmlElement> {
+    let mut reader = quick_xml::Reader::from_str(data);
+    reader.trim_text(true);
+
+    let mut stack = vec![XmlElement::default()];
+    loop {
+        match reader.read_event().map_err(xml_error)? {
+            Event::Start(e) => stack.push(xml_element(&e)?),
+            Event::Empty(e) => {
+                let element = xml_element(&e)?;
+                stack.last_mut().expect("root element").children.push(element);
+            }
+            Event::Text(e) => {
+                let text = e.unescape().map_err(xml_error)?;
+                stack.last_mut().expect("root element").text.push_str(&text);
+            }
+            Event::CData(e) => {
+                let text = String::from_utf8_lossy(&e.into_inner()).to_string();
+                stack.last_mut().expect("root element").text.push_str(&text);
+            }
+            Event::End(_) => {
+                let element = stack.pop().expect("balanced elements");
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => return Err(Error::Parsing("Unbalanced XML end tag".to_string())),
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if stack.len() != 1 {
+        return Err(Error::Parsing("XML ended inside an element".to_string()));
+    }
+    Ok(stack.pop().expect("root element"))
+}
+
+pub(crate) fn xml_escape(value: &str) -> String {
+    quick_xml::escape::escape(value).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::Point;
+
+    #[test]
+    fn test_geojson_round_trip_with_styles() {
+        let mut record = imported_record(Geometry::Point(Point::new(1.0, 2.0)), "patrol", None, HashMap::new());
+        record.properties.insert("severity".to_string(), serde_json::json!("critical"));
+        record.properties.insert("name".to_string(), serde_json::json!("Checkpoint"));
+
+        let bytes = export(&[&record], &[], ExportFormat::GeoJson, StyleBy::Severity).unwrap();
+        let imported = import_geojson(std::str::from_utf8(&bytes).unwrap(), "reimport").unwrap();
+
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].geometry, record.geometry);
+        assert_eq!(imported[0].properties["marker-color"], "#b2182b");
+        assert_eq!(imported[0].properties["name"], "Checkpoint");
+        assert_eq!(imported[0].collected_at, record.collected_at);
+    }
+
+    #[test]
+    fn test_exports_are_defanged() {
+        let mut record = imported_record(Geometry::Point(Point::new(1.0, 2.0)), "sensor", None, HashMap::new());
+        record.properties.insert("name".to_string(), serde_json::json!("C2 at 203.0.113.7"));
+        record.properties.insert("urls".to_string(), serde_json::json!(["http://evil.example/x"]));
+
+        let kml = String::from_utf8(export(&[&record], &[], ExportFormat::Kml, StyleBy::None).unwrap()).unwrap();
+        assert!(kml.contains("C2 at 203[.]0[.]113[.]7"));
+        assert!(!kml.contains("203.0.113.7"));
+
+        let bytes = export(&[&record], &[], ExportFormat::GeoJson, StyleBy::None).unwrap();
+        let json = std::str::from_utf8(&bytes).unwrap();
+        assert!(json.contains("hxxp://evil[.]example/x"));
+        // Importing restores the live values
+        let imported = import_geojson(json, "reimport").unwrap();
+        assert_eq!(imported[0].properties["name"], "C2 at 203.0.113.7");
+        assert_eq!(imported[0].properties["urls"][0], "http://evil.example/x");
+        assert!(!imported[0].properties.contains_key(DEFANGED_PROPERTY));
+    }
+
+    #[test]
+    fn test_foreign_imports_are_not_refanged() {
+        let json = r#"{"type": "FeatureCollection", "features": [{"type": "Feature",
+            "geometry": {"type": "Point", "coordinates": [1.0, 2.0]},
+            "properties": {"path": "C:\\Users\\.ssh", "note": "mail ops [at] example (dot) org"}}]}"#;
+
+        let imported = import_geojson(json, "partner").unwrap();
+        assert_eq!(imported[0].properties["path"], "C:\\Users\\.ssh");
+        assert_eq!(imported[0].properties["note"], "mail ops [at] example (dot) org");
+    }
+}
@@ -0,0 +1,231 @@
+//! GPX 1.1 import/export
+
+use crate::{Result, Error, models::GeoIntel};
+use super::{attribute_track, imported_record, parse_time, parse_xml, record_name, xml_escape, XmlElement};
+use crate::geo_intel::GeoCluster;
+use chrono::{DateTime, Utc};
+use geo::{Coord, Geometry, LineString, MultiLineString, Point};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+
+struct GpxPoint {
+    coord: Coord,
+    time: Option<DateTime<Utc>>,
+    elevation: Option<f64>,
+}
+
+fn gpx_point(element: &XmlElement) -> Result<GpxPoint> {
+    let attribute = |name: &str| -> Result<f64> {
+        element.attributes.get(name)
+            .and_then(|v| v.trim().parse().ok())
+            .ok_or_else(|| Error::Parsing(format!("GPX {} without valid {}", element.name, name)))
+    };
+    Ok(GpxPoint {
+        coord: Coord { x: attribute("lon")?, y: attribute("lat")? },
+        time: element.child_text("time").and_then(parse_time),
+        elevation: element.child_text("ele").and_then(|e| e.parse().ok()),
+    })
+}
+
+/// Name, description, comment and type of a wpt, trk or rte
+fn descriptive_properties(element: &XmlElement) -> HashMap<String, serde_json::Value> {
+    let mut properties = HashMap::new();
+    for (tag, key) in [("name", "name"), ("desc", "description"), ("cmt", "comment"), ("type", "type"), ("sym", "symbol")] {
+        if let Some(value) = element.child_text(tag) {
+            properties.insert(key.to_string(), serde_json::json!(value));
+        }
+    }
+    properties
+}
+
+/// Insert the time range of a track or route, returning its start
+fn time_range(points: &[GpxPoint], properties: &mut HashMap<String, serde_json::Value>) -> Option<DateTime<Utc>> {
+    let start = points.iter().filter_map(|p| p.time).min()?;
+    let end = points.iter().filter_map(|p| p.time).max()?;
+    properties.insert("start_time".to_string(), serde_json::json!(start.to_rfc3339()));
+    properties.insert("end_time".to_string(), serde_json::json!(end.to_rfc3339()));
+    Some(start)
+}
+
+/// Import waypoints, tracks and routes from a GPX document
+///
+/// Tracks with several segments become multi line strings; the first and last point
+/// times are kept as `start_time` and `end_time`. Named tracks get an `entity_id` derived
+/// from the name.
+pub fn import_gpx(data: &str, source: &str) -> Result<Vec<GeoIntel>> {
+    let root = parse_xml(data)?;
+    let gpx = root.child("gpx").ok_or_else(|| Error::Parsing("Missing <gpx> root element".to_string()))?;
+    let mut records = Vec::new();
+
+    for wpt in gpx.children_named("wpt") {
+        let point = gpx_point(wpt)?;
+        let mut properties = descriptive_properties(wpt);
+        if let Some(elevation) = point.elevation {
+            properties.insert("elevation_m".to_string(), serde_json::json!(elevation));
+        }
+        records.push(imported_record(Geometry::Point(Point(point.coord)), source, point.time, properties));
+    }
+
+    for trk in gpx.children_named("trk") {
+        let mut properties = descriptive_properties(trk);
+        let mut all_points = Vec::new();
+        let mut segments = Vec::new();
+        for trkseg in trk.children_named("trkseg") {
+            let points = trkseg.children_named("trkpt").map(gpx_point).collect::<Result<Vec<_>>>()?;
+            if points.len() >= 2 {
+                segments.push(LineString::new(points.iter().map(|p| p.coord).collect()));
+            }
+            all_points.extend(points);
+        }
+        let geometry = match segments.len() {
+            0 => continue,
+            1 => Geometry::LineString(segments.remove(0)),
+            _ => Geometry::MultiLineString(MultiLineString::new(segments)),
+        };
+        let collected_at = time_range(&all_points, &mut properties);
+        attribute_track(&mut properties);
+        records.push(imported_record(geometry, source, collected_at, properties));
+    }
+
+    for rte in gpx.children_named("rte") {
+        let mut properties = descriptive_properties(rte);
+        let points = rte.children_named("rtept").map(gpx_point).collect::<Result<Vec<_>>>()?;
+        if points.len() < 2 {
+            continue;
+        }
+        let collected_at = time_range(&points, &mut properties);
+        properties.insert("route".to_string(), serde_json::json!(true));
+        let line = LineString::new(points.iter().map(|p| p.coord).collect());
+        records.push(imported_record(Geometry::LineString(line), source, collected_at, properties));
+    }
+
+    Ok(records)
+}
+
+fn write_waypoint(out: &mut String, point: &Point, name: &str, description: Option<&str>, time: Option<DateTime<Utc>>) {
+    let _ = write!(out, "<wpt lat=\"{}\" lon=\"{}\">", point.y(), point.x());
+    if let Some(time) = time {
+        let _ = write!(out, "<time>{}</time>", time.to_rfc3339());
+    }
+    let _ = write!(out, "<name>{}</name>", xml_escape(name));
+    if let Some(description) = description {
+        let _ = write!(out, "<desc>{}</desc>", xml_escape(description));
+    }
+    out.push_str("</wpt>");
+}
+
+fn write_track(out: &mut String, name: &str, description: Option<&str>, lines: &[&LineString]) {
+    let _ = write!(out, "<trk><name>{}</name>", xml_escape(name));
+    if let Some(description) = description {
+        let _ = write!(out, "<desc>{}</desc>", xml_escape(description));
+    }
+    for line in lines {
+        out.push_str("<trkseg>");
+        for coord in line.coords() {
+            let _ = write!(out, "<trkpt lat=\"{}\" lon=\"{}\"/>", coord.y, coord.x);
+        }
+        out.push_str("</trkseg>");
+    }
+    out.push_str("</trk>");
+}
+
+fn write_record(waypoints: &mut String, tracks: &mut String, record: &GeoIntel, geometry: &Geometry) {
+    let name = record_name(record);
+    let description = record.properties.get("description").and_then(|d| d.as_str());
+    match geometry {
+        Geometry::Point(p) => write_waypoint(waypoints, p, &name, description, Some(record.collected_at)),
+        Geometry::MultiPoint(mp) => {
+            for p in mp {
+                write_waypoint(waypoints, p, &name, description, Some(record.collected_at));
+            }
+        }
+        Geometry::Line(line) => write_track(tracks, &name, description, &[&LineString::from(*line)]),
+        Geometry::LineString(ls) => write_track(tracks, &name, description, &[ls]),
+        Geometry::MultiLineString(mls) => write_track(tracks, &name, description, &mls.iter().collect::<Vec<_>>()),
+        // GPX has no areas, so polygon rings are written as closed tracks
+        Geometry::Polygon(polygon) => {
+            let rings: Vec<&LineString> = std::iter::once(polygon.exterior()).chain(polygon.interiors()).collect();
+            write_track(tracks, &name, description, &rings);
+        }
+        Geometry::MultiPolygon(mp) => {
+            let rings: Vec<&LineString> = mp.iter().flat_map(|p| std::iter::once(p.exterior()).chain(p.interiors())).collect();
+            write_track(tracks, &name, description, &rings);
+        }
+        Geometry::Rect(rect) => write_record(waypoints, tracks, record, &Geometry::Polygon(rect.to_polygon())),
+        Geometry::Triangle(triangle) => write_record(waypoints, tracks, record, &Geometry::Polygon(triangle.to_polygon())),
+        Geometry::GeometryCollection(gc) => {
+            for member in gc {
+                write_record(waypoints, tracks, record, member);
+            }
+        }
+    }
+}
+
+/// GPX document with points as waypoints, everything else as tracks and one waypoint per
+/// cluster centre
+pub fn write_gpx(records: &[&GeoIntel], clusters: &[GeoCluster]) -> String {
+    let mut waypoints = String::new();
+    let mut tracks = String::new();
+    for record in records {
+        write_record(&mut waypoints, &mut tracks, record, &record.geometry);
+    }
+    for (index, cluster) in clusters.iter().enumerate() {
+        let description = format!("{} members, radius {:.2} km", cluster.members.len(), cluster.radius_km);
+        write_waypoint(&mut waypoints, &cluster.center, &format!("Cluster {}", index + 1), Some(&description), Some(cluster.created_at));
+    }
+
+    // GPX requires waypoints before routes and tracks
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<gpx version=\"1.1\" creator=\"osint-core\" xmlns=\"http://www.topografix.com/GPX/1/1\">{}{}</gpx>\n",
+        waypoints, tracks
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRACK: &str = r#"<?xml version="1.0"?>
+<gpx version="1.1" creator="test" xmlns="http://www.topografix.com/GPX/1/1">
+  <wpt lat="50.4501" lon="30.5234"><ele>179</ele><time>2024-03-01T08:15:00Z</time><name>Cache</name><desc>Supply drop</desc></wpt>
+  <trk><name>Patrol</name>
+    <trkseg>
+      <trkpt lat="50.4500" lon="30.5200"><time>2024-03-01T09:00:00Z</time></trkpt>
+      <trkpt lat="50.4520" lon="30.5250"><time>2024-03-01T09:05:00Z</time></trkpt>
+    </trkseg>
+    <trkseg>
+      <trkpt lat="50.4600" lon="30.5300"><time>2024-03-01T09:30:00Z</time></trkpt>
+      <trkpt lat="50.4650" lon="30.5350"><time>2024-03-01T09:40:00Z</time></trkpt>
+    </trkseg>
+  </trk>
+</gpx>"#;
+
+    #[test]
+    fn test_import_gpx_waypoints_and_tracks() {
+        let records = import_gpx(TRACK, "gps").unwrap();
+        assert_eq!(records.len(), 2);
+
+        assert_eq!(records[0].geometry, Geometry::Point(Point::new(30.5234, 50.4501)));
+        assert_eq!(records[0].properties["description"], "Supply drop");
+        assert_eq!(records[0].properties["elevation_m"], 179.0);
+
+        assert!(matches!(records[1].geometry, Geometry::MultiLineString(ref mls) if mls.0.len() == 2));
+        assert_eq!(records[1].properties["end_time"], "2024-03-01T09:40:00+00:00");
+        assert_eq!(records[1].collected_at.to_rfc3339(), "2024-03-01T09:00:00+00:00");
+    }
+
+    #[test]
+    fn test_gpx_round_trip() {
+        let records = import_gpx(TRACK, "gps").unwrap();
+        let refs: Vec<&GeoIntel> = records.iter().collect();
+        let reimported = import_gpx(&write_gpx(&refs, &[]), "export").unwrap();
+
+        assert_eq!(reimported.len(), 2);
+        assert_eq!(reimported[0].geometry, records[0].geometry);
+        assert_eq!(reimported[0].properties["name"], "Cache");
+        assert_eq!(reimported[1].geometry, records[1].geometry);
+        // The track keeps its entity across the round trip
+        assert_eq!(reimported[1].properties["entity_id"], serde_json::json!(crate::geo_intel::track_entity_id("Patrol")));
+        assert_eq!(reimported[1].properties["entity_id"], records[1].properties["entity_id"]);
+    }
+}
@@ -0,0 +1,345 @@
+//! KML and KMZ import/export
+
+use crate::{Result, Error, models::GeoIntel};
+use super::{attribute_track, imported_record, parse_time, parse_xml, record_name, xml_escape, StyleBy, XmlElement};
+use crate::geo_intel::GeoCluster;
+use geo::{Coord, Geometry, LineString, MultiPoint, Point, Polygon};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write as _;
+use std::io::Read;
+
+/// Largest KML document read from a KMZ archive, guarding against zip bombs
+pub const MAX_KML_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Import placemarks from a KML document
+///
+/// Names, descriptions and `ExtendedData` go into `properties`; `TimeStamp` or the start of a
+/// `TimeSpan` becomes `collected_at`. `gx:Track` elements become line strings whose
+/// per-point times are kept in the `timestamps` property; named tracks get an `entity_id`
+/// derived from the name.
+pub fn import_kml(data: &str, source: &str) -> Result<Vec<GeoIntel>> {
+    let root = parse_xml(data)?;
+    let mut placemarks = Vec::new();
+    root.descendants("Placemark", &mut placemarks);
+
+    let mut records = Vec::new();
+    for placemark in placemarks {
+        let mut properties = HashMap::new();
+        let mut timestamps = Vec::new();
+        let Some(geometry) = placemark_geometry(placemark, &mut timestamps)? else {
+            continue;
+        };
+
+        for key in ["name", "description"] {
+            if let Some(value) = placemark.child_text(key) {
+                properties.insert(key.to_string(), serde_json::json!(value));
+            }
+        }
+        if let Some(extended) = placemark.child("ExtendedData") {
+            for data in extended.children_named("Data") {
+                if let (Some(name), Some(value)) = (data.attributes.get("name"), data.child_text("value")) {
+                    properties.insert(name.clone(), serde_json::json!(value));
+                }
+            }
+            let mut simple = Vec::new();
+            extended.descendants("SimpleData", &mut simple);
+            for data in simple {
+                if let Some(name) = data.attributes.get("name") {
+                    properties.insert(name.clone(), serde_json::json!(data.text.trim()));
+                }
+            }
+        }
+
+        let when = placemark.child("TimeStamp").and_then(|t| t.child_text("when"));
+        let span = placemark.child("TimeSpan");
+        let mut collected_at = when.or_else(|| span.and_then(|s| s.child_text("begin"))).and_then(parse_time);
+        if let Some(end) = span.and_then(|s| s.child_text("end")) {
+            properties.insert("end_time".to_string(), serde_json::json!(end));
+        }
+        if !timestamps.is_empty() {
+            collected_at = collected_at.or_else(|| timestamps.first().copied());
+            properties.insert("timestamps".to_string(), serde_json::json!(timestamps));
+            attribute_track(&mut properties);
+        }
+
+        records.push(imported_record(geometry, source, collected_at, properties));
+    }
+    Ok(records)
+}
+
+/// Import the main KML document of a KMZ archive
+pub fn import_kmz(data: &[u8], source: &str) -> Result<Vec<GeoIntel>> {
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(data))
+        .map_err(|e| Error::Parsing(format!("Invalid KMZ archive: {}", e)))?;
+
+    // The spec names doc.kml; otherwise the first .kml entry is the main document
+    let names: Vec<String> = archive.file_names().map(str::to_string).collect();
+    let name = names.iter()
+        .find(|n| n.eq_ignore_ascii_case("doc.kml"))
+        .or_else(|| names.iter().find(|n| n.to_ascii_lowercase().ends_with(".kml")))
+        .ok_or_else(|| Error::Parsing("KMZ archive contains no KML document".to_string()))?
+        .clone();
+
+    let entry = archive.by_name(&name)
+        .map_err(|e| Error::Parsing(format!("Unreadable KMZ entry {}: {}", name, e)))?;
+    // The declared size can lie, so the read itself is bounded as well
+    let mut kml = String::new();
+    entry.take(MAX_KML_BYTES + 1).read_to_string(&mut kml)?;
+    if kml.len() as u64 > MAX_KML_BYTES {
+        return Err(Error::InvalidInput(format!("KMZ entry {} exceeds {} bytes", name, MAX_KML_BYTES)));
+    }
+    import_kml(&kml, source)
+}
+
+fn parse_coordinates(text: &str) -> Result<Vec<Coord>> {
+    text.split_whitespace()
+        .map(|tuple| {
+            let mut parts = tuple.split(',').map(|p| p.trim().parse::<f64>());
+            match (parts.next(), parts.next()) {
+                (Some(Ok(x)), Some(Ok(y))) => Ok(Coord { x, y }),
+                _ => Err(Error::Parsing(format!("Invalid KML coordinate {}", tuple))),
+            }
+        })
+        .collect()
+}
+
+fn element_coordinates(element: &XmlElement) -> Result<Vec<Coord>> {
+    parse_coordinates(element.child_text("coordinates").unwrap_or_default())
+}
+
+fn ring(boundary: &XmlElement) -> Result<LineString> {
+    let ring = boundary.child("LinearRing")
+        .ok_or_else(|| Error::Parsing("KML boundary without LinearRing".to_string()))?;
+    Ok(LineString::new(element_coordinates(ring)?))
+}
+
+fn geometry_of(element: &XmlElement, timestamps: &mut Vec<chrono::DateTime<chrono::Utc>>) -> Result<Option<Geometry>> {
+    let geometry = match element.name.as_str() {
+        "Point" => {
+            let coords = element_coordinates(element)?;
+            let coord = coords.first().ok_or_else(|| Error::Parsing("KML Point without coordinates".to_string()))?;
+            Geometry::Point(Point(*coord))
+        }
+        "LineString" => Geometry::LineString(LineString::new(element_coordinates(element)?)),
+        "Polygon" => {
+            let exterior = element.child("outerBoundaryIs")
+                .ok_or_else(|| Error::Parsing("KML Polygon without outerBoundaryIs".to_string()))?;
+            let interiors = element.children_named("innerBoundaryIs").map(ring).collect::<Result<Vec<_>>>()?;
+            Geometry::Polygon(Polygon::new(ring(exterior)?, interiors))
+        }
+        "Track" => {
+            let coords = element.children_named("coord")
+                .map(|c| {
+                    let values: Vec<f64> = c.text.split_whitespace().filter_map(|v| v.parse().ok()).collect();
+                    match values.as_slice() {
+                        [x, y, ..] => Ok(Coord { x: *x, y: *y }),
+                        _ => Err(Error::Parsing(format!("Invalid gx:coord {}", c.text))),
+                    }
+                })
+                .collect::<Result<Vec<_>>>()?;
+            timestamps.extend(element.children_named("when").filter_map(|w| parse_time(&w.text)));
+            match coords.len() {
+                0 => return Ok(None),
+                1 => Geometry::Point(Point(coords[0])),
+                _ => Geometry::LineString(LineString::new(coords)),
+            }
+        }
+        "MultiGeometry" | "MultiTrack" => {
+            let members = element.children.iter()
+                .map(|child| geometry_of(child, timestamps))
+                .collect::<Result<Vec<_>>>()?;
+            let members: Vec<Geometry> = members.into_iter().flatten().collect();
+            match members.len() {
+                0 => return Ok(None),
+                1 => members.into_iter().next().expect("one member"),
+                _ => collapse(members),
+            }
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(geometry))
+}
+
+/// Use a homogeneous multi-geometry where possible, else a collection
+fn collapse(members: Vec<Geometry>) -> Geometry {
+    if members.iter().all(|g| matches!(g, Geometry::Point(_))) {
+        return Geometry::MultiPoint(MultiPoint::new(members.into_iter().filter_map(|g| Point::try_from(g).ok()).collect()));
+    }
+    if members.iter().all(|g| matches!(g, Geometry::LineString(_))) {
+        return Geometry::MultiLineString(members.into_iter().filter_map(|g| LineString::try_from(g).ok()).collect());
+    }
+    if members.iter().all(|g| matches!(g, Geometry::Polygon(_))) {
+        return Geometry::MultiPolygon(members.into_iter().filter_map(|g| Polygon::try_from(g).ok()).collect());
+    }
+    Geometry::GeometryCollection(members.into_iter().collect())
+}
+
+fn placemark_geometry(placemark: &XmlElement, timestamps: &mut Vec<chrono::DateTime<chrono::Utc>>) -> Result<Option<Geometry>> {
+    for child in &placemark.children {
+        if let Some(geometry) = geometry_of(child, timestamps)? {
+            return Ok(Some(geometry));
+        }
+    }
+    Ok(None)
+}
+
+/// KML colours are `aabbggrr`
+fn kml_color(hex: &str, alpha: &str) -> String {
+    let hex = hex.trim_start_matches('#');
+    format!("{}{}{}{}", alpha, &hex[4..6], &hex[2..4], &hex[0..2])
+}
+
+fn coordinates(coords: impl Iterator<Item = Coord>) -> String {
+    coords.map(|c| format!("{},{}", c.x, c.y)).collect::<Vec<_>>().join(" ")
+}
+
+fn write_polygon(out: &mut String, polygon: &Polygon) {
+    let _ = write!(out, "<Polygon><outerBoundaryIs><LinearRing><coordinates>{}</coordinates></LinearRing></outerBoundaryIs>",
+        coordinates(polygon.exterior().coords().copied()));
+    for interior in polygon.interiors() {
+        let _ = write!(out, "<innerBoundaryIs><LinearRing><coordinates>{}</coordinates></LinearRing></innerBoundaryIs>",
+            coordinates(interior.coords().copied()));
+    }
+    out.push_str("</Polygon>");
+}
+
+fn write_geometry(out: &mut String, geometry: &Geometry) {
+    match geometry {
+        Geometry::Point(p) => { let _ = write!(out, "<Point><coordinates>{},{}</coordinates></Point>", p.x(), p.y()); }
+        Geometry::Line(line) => { let _ = write!(out, "<LineString><coordinates>{}</coordinates></LineString>", coordinates([line.start, line.end].into_iter())); }
+        Geometry::LineString(ls) => { let _ = write!(out, "<LineString><coordinates>{}</coordinates></LineString>", coordinates(ls.coords().copied())); }
+        Geometry::Polygon(polygon) => write_polygon(out, polygon),
+        Geometry::Rect(rect) => write_polygon(out, &rect.to_polygon()),
+        Geometry::Triangle(triangle) => write_polygon(out, &triangle.to_polygon()),
+        Geometry::MultiPoint(mp) => write_multi(out, mp.iter().map(|p| Geometry::Point(*p))),
+        Geometry::MultiLineString(mls) => write_multi(out, mls.iter().map(|ls| Geometry::LineString(ls.clone()))),
+        Geometry::MultiPolygon(mp) => write_multi(out, mp.iter().map(|p| Geometry::Polygon(p.clone()))),
+        Geometry::GeometryCollection(gc) => write_multi(out, gc.iter().cloned()),
+    }
+}
+
+fn write_multi(out: &mut String, members: impl Iterator<Item = Geometry>) {
+    out.push_str("<MultiGeometry>");
+    for member in members {
+        write_geometry(out, &member);
+    }
+    out.push_str("</MultiGeometry>");
+}
+
+/// KML document with one shared style per colour, records first and then cluster hulls
+pub fn write_kml(records: &[&GeoIntel], clusters: &[GeoCluster], style: StyleBy) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<kml xmlns=\"http://www.opengis.net/kml/2.2\"><Document>");
+
+    let colors: BTreeSet<&str> = records.iter().map(|r| style.color(r)).collect();
+    for color in &colors {
+        let _ = write!(out,
+            "<Style id=\"c{id}\"><IconStyle><color>{icon}</color></IconStyle><LineStyle><color>{icon}</color><width>2</width></LineStyle><PolyStyle><color>{fill}</color></PolyStyle></Style>",
+            id = color.trim_start_matches('#'), icon = kml_color(color, "ff"), fill = kml_color(color, "66"));
+    }
+    let _ = write!(out, "<Style id=\"cluster\"><LineStyle><color>{}</color><width>2</width></LineStyle><PolyStyle><color>{}</color></PolyStyle></Style>",
+        kml_color(super::CLUSTER_COLOR, "ff"), kml_color(super::CLUSTER_COLOR, "33"));
+
+    for record in records {
+        let _ = write!(out, "<Placemark id=\"{}\"><name>{}</name>", record.id, xml_escape(&record_name(record)));
+        if let Some(description) = record.properties.get("description").and_then(|d| d.as_str()) {
+            let _ = write!(out, "<description>{}</description>", xml_escape(description));
+        }
+        let _ = write!(out, "<TimeStamp><when>{}</when></TimeStamp><styleUrl>#c{}</styleUrl><ExtendedData>",
+            record.collected_at.to_rfc3339(), style.color(record).trim_start_matches('#'));
+
+        let mut data: Vec<(String, String)> = record.properties.iter()
+            .filter(|(key, _)| !matches!(key.as_str(), "name" | "description"))
+            .map(|(key, value)| (key.clone(), value.as_str().map(str::to_string).unwrap_or_else(|| value.to_string())))
+            .collect();
+        data.push(("source".to_string(), record.source.clone()));
+        data.push(("accuracy_m".to_string(), record.accuracy.to_string()));
+        for (key, value) in [("country", &record.country), ("region", &record.region), ("city", &record.city)] {
+            if let Some(value) = value {
+                data.push((key.to_string(), value.clone()));
+            }
+        }
+        data.sort();
+        for (key, value) in data {
+            let _ = write!(out, "<Data name=\"{}\"><value>{}</value></Data>", xml_escape(&key), xml_escape(&value));
+        }
+        out.push_str("</ExtendedData>");
+        write_geometry(&mut out, &record.geometry);
+        out.push_str("</Placemark>");
+    }
+
+    for (index, cluster) in clusters.iter().enumerate() {
+        let _ = write!(out, "<Placemark id=\"{}\"><name>Cluster {} ({} members)</name><styleUrl>#cluster</styleUrl>",
+            cluster.id, index + 1, cluster.members.len());
+        write_polygon(&mut out, &cluster.hull);
+        out.push_str("</Placemark>");
+    }
+
+    out.push_str("</Document></kml>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIELD_REPORT: &str = include_str!("../../../tests/fixtures/geo/field_report.kml");
+
+    #[test]
+    fn test_import_kml_placemarks_and_tracks() {
+        let records = import_kml(FIELD_REPORT, "field-team").unwrap();
+        assert_eq!(records.len(), 3);
+
+        let checkpoint = &records[0];
+        assert_eq!(checkpoint.geometry, Geometry::Point(Point::new(30.5234, 50.4501)));
+        assert_eq!(checkpoint.properties["name"], "Checkpoint Alpha");
+        assert_eq!(checkpoint.properties["unit"], "Recon 2");
+        assert_eq!(checkpoint.collected_at.to_rfc3339(), "2024-03-01T08:15:00+00:00");
+
+        let track = &records[1];
+        assert!(matches!(track.geometry, Geometry::LineString(ref ls) if ls.0.len() == 3));
+        assert_eq!(track.properties["timestamps"].as_array().unwrap().len(), 3);
+        assert_eq!(track.properties["entity_id"], serde_json::json!(crate::geo_intel::track_entity_id("Patrol route")));
+        assert!(!checkpoint.properties.contains_key("entity_id"));
+
+        assert!(matches!(records[2].geometry, Geometry::Polygon(ref p) if p.interiors().len() == 1));
+        assert_eq!(records[2].properties["end_time"], "2024-03-02T00:00:00Z");
+    }
+
+    #[test]
+    fn test_kml_round_trip_and_kmz() {
+        let records = import_kml(FIELD_REPORT, "field-team").unwrap();
+        let refs: Vec<&GeoIntel> = records.iter().collect();
+        let kml = write_kml(&refs, &[], StyleBy::Source);
+
+        let reimported = import_kml(&kml, "export").unwrap();
+        assert_eq!(reimported.len(), 3);
+        assert_eq!(reimported[0].geometry, records[0].geometry);
+        assert_eq!(reimported[0].properties["unit"], "Recon 2");
+        assert_eq!(reimported[0].collected_at, records[0].collected_at);
+
+        let mut kmz = Vec::new();
+        {
+            let mut writer = zip::ZipWriter::new(std::io::Cursor::new(&mut kmz));
+            writer.start_file("doc.kml", zip::write::FileOptions::default()).unwrap();
+            std::io::Write::write_all(&mut writer, kml.as_bytes()).unwrap();
+            writer.finish().unwrap();
+        }
+        assert_eq!(import_kmz(&kmz, "kmz").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_oversized_kmz_entry_is_rejected() {
+        let mut kmz = Vec::new();
+        {
+            let mut writer = zip::ZipWriter::new(std::io::Cursor::new(&mut kmz));
+            writer.start_file("doc.kml", zip::write::FileOptions::default()).unwrap();
+            let padding = vec![b' '; 1024 * 1024];
+            for _ in 0..=MAX_KML_BYTES / padding.len() as u64 {
+                std::io::Write::write_all(&mut writer, &padding).unwrap();
+            }
+            writer.finish().unwrap();
+        }
+        assert!(kmz.len() < 1024 * 1024);
+        assert!(matches!(import_kmz(&kmz, "kmz"), Err(Error::InvalidInput(_))));
+    }
+}
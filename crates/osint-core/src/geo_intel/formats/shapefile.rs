@@ -0,0 +1,592 @@
+//! ESRI Shapefile import/export
+//!
+//! A shapefile holds a single shape type, so exports produce one layer each for points,
+//! multipoints, lines and polygons. Attributes go in a dBASE III table encoded as UTF-8.
+
+use crate::{Result, Error, models::GeoIntel};
+use super::imported_record;
+use chrono::{Datelike, Utc};
+use geo::orient::{Direction, Orient};
+use geo::{Contains, Coord, Geometry, LineString, MultiLineString, MultiPoint, MultiPolygon, Point, Polygon, Winding};
+use std::collections::HashMap;
+use std::path::Path;
+use uuid::Uuid;
+
+const FILE_CODE: i32 = 9994;
+const VERSION: i32 = 1000;
+const HEADER_LEN: usize = 100;
+
+const SHAPE_NULL: i32 = 0;
+const SHAPE_POINT: i32 = 1;
+const SHAPE_POLYLINE: i32 = 3;
+const SHAPE_POLYGON: i32 = 5;
+const SHAPE_MULTIPOINT: i32 = 8;
+
+const WGS84_PRJ: &str = "GEOGCS[\"GCS_WGS_1984\",DATUM[\"D_WGS_1984\",SPHEROID[\"WGS_1984\",6378137.0,298.257223563]],PRIMEM[\"Greenwich\",0.0],UNIT[\"Degree\",0.0174532925199433]]";
+
+/// Maximum width of a dBASE character field
+const DBF_CHAR_MAX: usize = 254;
+
+/// Attribute table columns: name, type, width, decimals
+const DBF_FIELDS: [(&str, u8, u8, u8); 9] = [
+    ("ID", b'C', 36, 0),
+    ("NAME", b'C', 254, 0),
+    ("SOURCE", b'C', 64, 0),
+    ("COUNTRY", b'C', 64, 0),
+    ("REGION", b'C', 64, 0),
+    ("CITY", b'C', 64, 0),
+    ("ACCURACY", b'N', 12, 2),
+    ("COLLECTED", b'C', 32, 0),
+    ("PROPS", b'C', 254, 0),
+];
+
+/// One shapefile layer as the contents of its component files
+#[derive(Debug, Clone)]
+pub struct ShapefileLayer {
+    /// Layer name, used as the file name suffix
+    pub name: String,
+    pub shape_type: i32,
+    pub shp: Vec<u8>,
+    pub shx: Vec<u8>,
+    pub dbf: Vec<u8>,
+    pub prj: String,
+    pub cpg: String,
+}
+
+impl ShapefileLayer {
+    /// Write the layer as `<stem>_<name>.{shp,shx,dbf,prj,cpg}` in a directory
+    pub async fn write(&self, dir: &Path, stem: &str) -> Result<()> {
+        let base = dir.join(format!("{}_{}", stem, self.name));
+        tokio::fs::write(base.with_extension("shp"), &self.shp).await?;
+        tokio::fs::write(base.with_extension("shx"), &self.shx).await?;
+        tokio::fs::write(base.with_extension("dbf"), &self.dbf).await?;
+        tokio::fs::write(base.with_extension("prj"), &self.prj).await?;
+        tokio::fs::write(base.with_extension("cpg"), &self.cpg).await?;
+        Ok(())
+    }
+}
+
+/// Shape record content, before the record header
+struct Shape {
+    content: Vec<u8>,
+    bounds: [f64; 4],
+}
+
+fn bounds_of(coords: impl Iterator<Item = Coord>) -> [f64; 4] {
+    coords.fold([f64::MAX, f64::MAX, f64::MIN, f64::MIN], |[min_x, min_y, max_x, max_y], c| {
+        [min_x.min(c.x), min_y.min(c.y), max_x.max(c.x), max_y.max(c.y)]
+    })
+}
+
+fn put_bounds(out: &mut Vec<u8>, bounds: &[f64; 4]) {
+    for value in bounds {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+fn point_shape(point: &Point) -> Shape {
+    let mut content = SHAPE_POINT.to_le_bytes().to_vec();
+    content.extend_from_slice(&point.x().to_le_bytes());
+    content.extend_from_slice(&point.y().to_le_bytes());
+    Shape { content, bounds: [point.x(), point.y(), point.x(), point.y()] }
+}
+
+fn multipoint_shape(points: &MultiPoint) -> Shape {
+    let bounds = bounds_of(points.iter().map(|p| p.0));
+    let mut content = SHAPE_MULTIPOINT.to_le_bytes().to_vec();
+    put_bounds(&mut content, &bounds);
+    content.extend_from_slice(&(points.0.len() as i32).to_le_bytes());
+    for point in points {
+        content.extend_from_slice(&point.x().to_le_bytes());
+        content.extend_from_slice(&point.y().to_le_bytes());
+    }
+    Shape { content, bounds }
+}
+
+fn parts_shape(shape_type: i32, parts: &[&LineString]) -> Shape {
+    let bounds = bounds_of(parts.iter().flat_map(|p| p.coords().copied()));
+    let point_count: usize = parts.iter().map(|p| p.0.len()).sum();
+    let mut content = shape_type.to_le_bytes().to_vec();
+    put_bounds(&mut content, &bounds);
+    content.extend_from_slice(&(parts.len() as i32).to_le_bytes());
+    content.extend_from_slice(&(point_count as i32).to_le_bytes());
+    let mut start = 0;
+    for part in parts {
+        content.extend_from_slice(&(start as i32).to_le_bytes());
+        start += part.0.len();
+    }
+    for coord in parts.iter().flat_map(|p| p.coords()) {
+        content.extend_from_slice(&coord.x.to_le_bytes());
+        content.extend_from_slice(&coord.y.to_le_bytes());
+    }
+    Shape { content, bounds }
+}
+
+/// Polygon rings with exteriors clockwise and holes counter-clockwise, as the format requires
+fn polygon_shape(polygons: &MultiPolygon) -> Shape {
+    let oriented = polygons.orient(Direction::Reversed);
+    let rings: Vec<&LineString> = oriented.iter()
+        .flat_map(|p| std::iter::once(p.exterior()).chain(p.interiors()))
+        .collect();
+    parts_shape(SHAPE_POLYGON, &rings)
+}
+
+/// Split a geometry into the shapes it contributes to each layer
+fn shapes(geometry: &Geometry, out: &mut Vec<Shape>) {
+    match geometry {
+        Geometry::Point(p) => out.push(point_shape(p)),
+        Geometry::MultiPoint(mp) if !mp.0.is_empty() => out.push(multipoint_shape(mp)),
+        Geometry::Line(line) => out.push(parts_shape(SHAPE_POLYLINE, &[&LineString::from(*line)])),
+        Geometry::LineString(ls) if !ls.0.is_empty() => out.push(parts_shape(SHAPE_POLYLINE, &[ls])),
+        Geometry::MultiLineString(mls) if !mls.0.is_empty() => out.push(parts_shape(SHAPE_POLYLINE, &mls.iter().collect::<Vec<_>>())),
+        Geometry::Polygon(p) => out.push(polygon_shape(&MultiPolygon::new(vec![p.clone()]))),
+        Geometry::MultiPolygon(mp) if !mp.0.is_empty() => out.push(polygon_shape(mp)),
+        Geometry::Rect(rect) => out.push(polygon_shape(&MultiPolygon::new(vec![rect.to_polygon()]))),
+        Geometry::Triangle(triangle) => out.push(polygon_shape(&MultiPolygon::new(vec![triangle.to_polygon()]))),
+        Geometry::GeometryCollection(gc) => gc.iter().for_each(|member| shapes(member, out)),
+        _ => {}
+    }
+}
+
+fn shape_type_of(shape: &Shape) -> i32 {
+    i32::from_le_bytes(shape.content[0..4].try_into().expect("shape type"))
+}
+
+fn file_header(shape_type: i32, file_len: usize, bounds: &[f64; 4]) -> Vec<u8> {
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    header.extend_from_slice(&FILE_CODE.to_be_bytes());
+    header.extend_from_slice(&[0u8; 20]);
+    header.extend_from_slice(&((file_len / 2) as i32).to_be_bytes());
+    header.extend_from_slice(&VERSION.to_le_bytes());
+    header.extend_from_slice(&shape_type.to_le_bytes());
+    put_bounds(&mut header, bounds);
+    header.extend_from_slice(&[0u8; 32]);
+    header
+}
+
+/// Truncate to at most `max` bytes without splitting a character
+fn truncate_utf8(value: &str, max: usize) -> &str {
+    let mut end = value.len().min(max);
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+    &value[..end]
+}
+
+fn write_dbf(rows: &[[String; 9]]) -> Vec<u8> {
+    let record_len: usize = 1 + DBF_FIELDS.iter().map(|f| f.2 as usize).sum::<usize>();
+    let header_len = 32 + 32 * DBF_FIELDS.len() + 1;
+    let today = Utc::now().date_naive();
+
+    let mut dbf = vec![0x03, (today.year() - 1900) as u8, today.month() as u8, today.day() as u8];
+    dbf.extend_from_slice(&(rows.len() as u32).to_le_bytes());
+    dbf.extend_from_slice(&(header_len as u16).to_le_bytes());
+    dbf.extend_from_slice(&(record_len as u16).to_le_bytes());
+    dbf.extend_from_slice(&[0u8; 20]);
+    for (name, kind, width, decimals) in DBF_FIELDS {
+        let mut descriptor = [0u8; 32];
+        descriptor[..name.len()].copy_from_slice(name.as_bytes());
+        descriptor[11] = kind;
+        descriptor[16] = width;
+        descriptor[17] = decimals;
+        dbf.extend_from_slice(&descriptor);
+    }
+    dbf.push(0x0D);
+
+    for row in rows {
+        dbf.push(b' ');
+        for ((_, kind, width, _), value) in DBF_FIELDS.iter().zip(row) {
+            let width = *width as usize;
+            let value = truncate_utf8(value, width);
+            let padding = " ".repeat(width - value.len());
+            // Numbers are right aligned, text left aligned
+            if *kind == b'N' {
+                dbf.extend_from_slice(padding.as_bytes());
+                dbf.extend_from_slice(value.as_bytes());
+            } else {
+                dbf.extend_from_slice(value.as_bytes());
+                dbf.extend_from_slice(padding.as_bytes());
+            }
+        }
+    }
+    dbf.push(0x1A);
+    dbf
+}
+
+fn attribute_row(record: &GeoIntel) -> [String; 9] {
+    let mut properties = serde_json::Map::new();
+    properties.extend(record.properties.iter().map(|(k, v)| (k.clone(), v.clone())));
+    let props = serde_json::Value::Object(properties).to_string();
+    [
+        record.id.to_string(),
+        super::record_name(record),
+        record.source.clone(),
+        record.country.clone().unwrap_or_default(),
+        record.region.clone().unwrap_or_default(),
+        record.city.clone().unwrap_or_default(),
+        format!("{:.2}", record.accuracy),
+        record.collected_at.to_rfc3339(),
+        // Properties that do not fit are dropped rather than written as broken JSON
+        if props.len() <= DBF_CHAR_MAX { props } else { String::new() },
+    ]
+}
+
+/// Export records as one shapefile layer per shape type present
+pub fn export_shapefiles(records: &[&GeoIntel]) -> Result<Vec<ShapefileLayer>> {
+    let defanged: Vec<GeoIntel> = records.iter().map(|record| super::defanged_record(record)).collect();
+    let layers = [
+        (SHAPE_POINT, "points"),
+        (SHAPE_MULTIPOINT, "multipoints"),
+        (SHAPE_POLYLINE, "lines"),
+        (SHAPE_POLYGON, "polygons"),
+    ];
+    let mut by_type: HashMap<i32, Vec<(Shape, [String; 9])>> = HashMap::new();
+    for record in &defanged {
+        let mut record_shapes = Vec::new();
+        shapes(&record.geometry, &mut record_shapes);
+        for shape in record_shapes {
+            by_type.entry(shape_type_of(&shape)).or_default().push((shape, attribute_row(record)));
+        }
+    }
+
+    let mut output = Vec::new();
+    for (shape_type, name) in layers {
+        let Some(entries) = by_type.remove(&shape_type) else {
+            continue;
+        };
+        let bounds = entries.iter().fold([f64::MAX, f64::MAX, f64::MIN, f64::MIN], |b, (shape, _)| {
+            [b[0].min(shape.bounds[0]), b[1].min(shape.bounds[1]), b[2].max(shape.bounds[2]), b[3].max(shape.bounds[3])]
+        });
+
+        let mut body = Vec::new();
+        let mut index = Vec::new();
+        for (number, (shape, _)) in entries.iter().enumerate() {
+            let offset = HEADER_LEN + body.len();
+            let words = (shape.content.len() / 2) as i32;
+            index.extend_from_slice(&((offset / 2) as i32).to_be_bytes());
+            index.extend_from_slice(&words.to_be_bytes());
+            body.extend_from_slice(&(number as i32 + 1).to_be_bytes());
+            body.extend_from_slice(&words.to_be_bytes());
+            body.extend_from_slice(&shape.content);
+        }
+
+        let mut shp = file_header(shape_type, HEADER_LEN + body.len(), &bounds);
+        shp.extend_from_slice(&body);
+        let mut shx = file_header(shape_type, HEADER_LEN + index.len(), &bounds);
+        shx.extend_from_slice(&index);
+        let rows: Vec<[String; 9]> = entries.into_iter().map(|(_, row)| row).collect();
+
+        output.push(ShapefileLayer {
+            name: name.to_string(),
+            shape_type,
+            shp,
+            shx,
+            dbf: write_dbf(&rows),
+            prj: WGS84_PRJ.to_string(),
+            cpg: "UTF-8".to_string(),
+        });
+    }
+    Ok(output)
+}
+
+/// Little-endian reader over a byte slice with bounds checking
+struct Cursor<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(data: &'a [u8], position: usize) -> Self {
+        Self { data, position }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let bytes = self.position.checked_add(len)
+            .and_then(|end| self.data.get(self.position..end))
+            .ok_or_else(|| Error::Parsing("Truncated shapefile".to_string()))?;
+        self.position += len;
+        Ok(bytes)
+    }
+
+    fn i32_be(&mut self) -> Result<i32> {
+        Ok(i32::from_be_bytes(self.take(4)?.try_into().expect("4 bytes")))
+    }
+
+    fn i32_le(&mut self) -> Result<i32> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into().expect("4 bytes")))
+    }
+
+    fn f64_le(&mut self) -> Result<f64> {
+        Ok(f64::from_le_bytes(self.take(8)?.try_into().expect("8 bytes")))
+    }
+
+    fn count(&mut self) -> Result<usize> {
+        usize::try_from(self.i32_le()?).map_err(|_| Error::Parsing("Negative count in shapefile".to_string()))
+    }
+
+    fn coord(&mut self) -> Result<Coord> {
+        Ok(Coord { x: self.f64_le()?, y: self.f64_le()? })
+    }
+}
+
+/// Split the parts of a PolyLine or Polygon record
+fn read_parts(cursor: &mut Cursor) -> Result<Vec<LineString>> {
+    cursor.take(32)?;
+    let part_count = cursor.count()?;
+    let point_count = cursor.count()?;
+    let mut starts = (0..part_count).map(|_| cursor.count()).collect::<Result<Vec<_>>>()?;
+    let coords = (0..point_count).map(|_| cursor.coord()).collect::<Result<Vec<_>>>()?;
+    starts.push(point_count);
+
+    starts.windows(2)
+        .map(|w| coords.get(w[0]..w[1])
+            .map(|c| LineString::new(c.to_vec()))
+            .ok_or_else(|| Error::Parsing("Invalid part index in shapefile".to_string())))
+        .collect()
+}
+
+/// Group rings into polygons: clockwise rings are exteriors, the rest holes of the
+/// exterior that contains them
+fn assemble_polygons(rings: Vec<LineString>) -> Geometry {
+    let (exteriors, holes): (Vec<LineString>, Vec<LineString>) = rings.into_iter().partition(|r| r.is_cw());
+    let mut polygons: Vec<(LineString, Vec<LineString>)> = exteriors.into_iter().map(|e| (e, Vec::new())).collect();
+    for hole in holes {
+        let Some(first) = hole.0.first().copied() else {
+            continue;
+        };
+        match polygons.iter_mut().find(|(exterior, _)| Polygon::new(exterior.clone(), vec![]).contains(&first)) {
+            Some((_, interiors)) => interiors.push(hole),
+            // An orphan hole is usually a wrongly wound exterior
+            None => polygons.push((hole, Vec::new())),
+        }
+    }
+
+    let mut polygons: Vec<Polygon> = polygons.into_iter().map(|(e, i)| Polygon::new(e, i)).collect();
+    if polygons.len() == 1 {
+        Geometry::Polygon(polygons.remove(0))
+    } else {
+        Geometry::MultiPolygon(MultiPolygon::new(polygons))
+    }
+}
+
+/// Read the XY geometry of a shape record, including the Z and M variants
+fn read_shape(content: &[u8]) -> Result<Option<Geometry>> {
+    let mut cursor = Cursor::new(content, 0);
+    let geometry = match cursor.i32_le()? {
+        SHAPE_NULL => return Ok(None),
+        1 | 11 | 21 => Geometry::Point(Point(cursor.coord()?)),
+        8 | 18 | 28 => {
+            cursor.take(32)?;
+            let count = cursor.count()?;
+            let points = (0..count).map(|_| cursor.coord().map(Point)).collect::<Result<Vec<_>>>()?;
+            Geometry::MultiPoint(MultiPoint::new(points))
+        }
+        3 | 13 | 23 => {
+            let mut parts = read_parts(&mut cursor)?;
+            if parts.len() == 1 {
+                Geometry::LineString(parts.remove(0))
+            } else {
+                Geometry::MultiLineString(MultiLineString::new(parts))
+            }
+        }
+        5 | 15 | 25 => assemble_polygons(read_parts(&mut cursor)?),
+        other => return Err(Error::Parsing(format!("Unsupported shape type {}", other))),
+    };
+    Ok(Some(geometry))
+}
+
+/// Rows of a dBASE table keyed by lowercased field name, skipping blank values
+fn read_dbf(data: &[u8]) -> Result<Vec<HashMap<String, serde_json::Value>>> {
+    let truncated = || Error::Parsing("Truncated dBASE table".to_string());
+    let header = data.get(..32).ok_or_else(truncated)?;
+    let record_count = u32::from_le_bytes(header[4..8].try_into().expect("4 bytes")) as usize;
+    let header_len = u16::from_le_bytes(header[8..10].try_into().expect("2 bytes")) as usize;
+    let record_len = u16::from_le_bytes(header[10..12].try_into().expect("2 bytes")) as usize;
+    if record_len == 0 {
+        return Err(Error::Parsing("Invalid dBASE record length 0".to_string()));
+    }
+
+    let mut fields = Vec::new();
+    let mut position = 32;
+    while position + 32 <= header_len && data.get(position) != Some(&0x0D) {
+        let descriptor = data.get(position..position + 32).ok_or_else(truncated)?;
+        let name_end = descriptor[..11].iter().position(|b| *b == 0).unwrap_or(11);
+        let name = String::from_utf8_lossy(&descriptor[..name_end]).to_lowercase();
+        fields.push((name, descriptor[11], descriptor[16] as usize));
+        position += 32;
+    }
+
+    // The count comes from the file, so only reserve what the data can hold
+    let mut rows = Vec::with_capacity(record_count.min(data.len().saturating_sub(header_len) / record_len));
+    for index in 0..record_count {
+        let record = index.checked_mul(record_len)
+            .and_then(|offset| offset.checked_add(header_len))
+            .and_then(|start| data.get(start..start.checked_add(record_len)?))
+            .ok_or_else(truncated)?;
+        if record[0] == b'*' {
+            // Deleted records keep their slot so rows stay aligned with shapes
+            rows.push(HashMap::new());
+            continue;
+        }
+        let mut row = HashMap::new();
+        let mut offset = 1;
+        for (name, kind, width) in &fields {
+            let raw = record.get(offset..offset + width).ok_or_else(truncated)?;
+            offset += width;
+            let text = String::from_utf8_lossy(raw).trim().to_string();
+            if text.is_empty() {
+                continue;
+            }
+            let value = match kind {
+                b'N' | b'F' => text.parse::<f64>().map(|n| serde_json::json!(n)).unwrap_or(serde_json::json!(text)),
+                b'L' => serde_json::json!(matches!(text.as_str(), "T" | "t" | "Y" | "y")),
+                _ => serde_json::json!(text),
+            };
+            row.insert(name.clone(), value);
+        }
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+/// Build a record from a shape and its attributes, restoring the columns written by
+/// [`export_shapefiles`] and keeping any other columns as properties
+fn shape_record(geometry: Geometry, mut attributes: HashMap<String, serde_json::Value>, source: &str) -> GeoIntel {
+    let take_string = |attributes: &mut HashMap<String, serde_json::Value>, key: &str| {
+        attributes.remove(key).and_then(|v| v.as_str().map(str::to_string))
+    };
+
+    let mut properties = HashMap::new();
+    if let Some(props) = take_string(&mut attributes, "props") {
+        if let Ok(serde_json::Value::Object(map)) = serde_json::from_str(&props) {
+            properties.extend(map);
+        }
+    }
+    let collected_at = take_string(&mut attributes, "collected").as_deref().and_then(super::parse_time);
+    let id = take_string(&mut attributes, "id").and_then(|id| Uuid::parse_str(&id).ok());
+    let country = take_string(&mut attributes, "country");
+    let region = take_string(&mut attributes, "region");
+    let city = take_string(&mut attributes, "city");
+    let accuracy = attributes.remove("accuracy").and_then(|v| v.as_f64());
+    attributes.remove("source");
+    properties.extend(attributes);
+
+    let mut record = imported_record(geometry, source, collected_at, properties);
+    record.id = id.unwrap_or(record.id);
+    record.country = country;
+    record.region = region;
+    record.city = city;
+    if let Some(accuracy) = accuracy {
+        record.accuracy = accuracy as f32;
+    }
+    record
+}
+
+/// Import the shapes of a `.shp` file, with attributes from its `.dbf` when given
+pub fn import_shapefile(shp: &[u8], dbf: Option<&[u8]>, source: &str) -> Result<Vec<GeoIntel>> {
+    let mut cursor = Cursor::new(shp, 0);
+    if cursor.i32_be()? != FILE_CODE {
+        return Err(Error::Parsing("Not a shapefile: bad file code".to_string()));
+    }
+    cursor.take(20)?;
+    let file_len = usize::try_from(cursor.i32_be()?).ok()
+        .and_then(|words| words.checked_mul(2))
+        .ok_or_else(|| Error::Parsing("Invalid file length in shapefile".to_string()))?
+        .min(shp.len());
+    let mut rows = dbf.map(read_dbf).transpose()?.unwrap_or_default().into_iter();
+
+    let mut records = Vec::new();
+    let mut cursor = Cursor::new(&shp[..file_len], HEADER_LEN);
+    while cursor.position < file_len {
+        let _number = cursor.i32_be()?;
+        let len = usize::try_from(cursor.i32_be()?).ok()
+            .and_then(|words| words.checked_mul(2))
+            .ok_or_else(|| Error::Parsing("Negative record length in shapefile".to_string()))?;
+        let content = cursor.take(len)?;
+        let attributes = rows.next().unwrap_or_default();
+        if let Some(geometry) = read_shape(content)? {
+            records.push(shape_record(geometry, attributes, source));
+        }
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::polygon;
+
+    fn record(geometry: Geometry) -> GeoIntel {
+        let mut properties = HashMap::new();
+        properties.insert("name".to_string(), serde_json::json!("Site"));
+        properties.insert("severity".to_string(), serde_json::json!("high"));
+        let mut record = imported_record(geometry, "survey", None, properties);
+        record.country = Some("Alphaland".to_string());
+        record
+    }
+
+    #[test]
+    fn test_shapefile_round_trip() {
+        let site = polygon![
+            exterior: [(x: 0.0, y: 0.0), (x: 4.0, y: 0.0), (x: 4.0, y: 4.0), (x: 0.0, y: 4.0), (x: 0.0, y: 0.0)],
+            interiors: [[(x: 1.0, y: 1.0), (x: 1.0, y: 2.0), (x: 2.0, y: 2.0), (x: 1.0, y: 1.0)]],
+        ];
+        let records = [
+            record(Geometry::Point(Point::new(30.5, 50.4))),
+            record(Geometry::Polygon(site)),
+            record(Geometry::LineString(LineString::from(vec![(0.0, 0.0), (1.0, 1.0), (2.0, 0.5)]))),
+        ];
+        let refs: Vec<&GeoIntel> = records.iter().collect();
+        let layers = export_shapefiles(&refs).unwrap();
+        assert_eq!(layers.iter().map(|l| l.name.as_str()).collect::<Vec<_>>(), ["points", "lines", "polygons"]);
+
+        for (layer, original) in layers.iter().zip([&records[0], &records[2], &records[1]]) {
+            assert_eq!(layer.shx.len(), HEADER_LEN + 8);
+            let imported = import_shapefile(&layer.shp, Some(&layer.dbf), "shp").unwrap();
+            assert_eq!(imported.len(), 1);
+            assert_eq!(imported[0].id, original.id);
+            assert_eq!(imported[0].country.as_deref(), Some("Alphaland"));
+            assert_eq!(imported[0].properties["severity"], "high");
+            assert_eq!(imported[0].collected_at.timestamp(), original.collected_at.timestamp());
+        }
+
+        // Orientation is normalised on export but the rings survive
+        let polygons = import_shapefile(&layers[2].shp, None, "shp").unwrap();
+        match &polygons[0].geometry {
+            Geometry::Polygon(p) => {
+                assert_eq!(p.exterior().0.len(), 5);
+                assert_eq!(p.interiors().len(), 1);
+            }
+            other => panic!("expected polygon, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_malformed_files_are_errors() {
+        let records = [record(Geometry::Point(Point::new(30.5, 50.4)))];
+        let refs: Vec<&GeoIntel> = records.iter().collect();
+        let layer = export_shapefiles(&refs).unwrap().remove(0);
+
+        // Negative file length
+        let mut shp = layer.shp.clone();
+        shp[24..28].copy_from_slice(&(-1i32).to_be_bytes());
+        assert!(import_shapefile(&shp, None, "shp").is_err());
+
+        // Negative record length
+        let mut shp = layer.shp.clone();
+        shp[HEADER_LEN + 4..HEADER_LEN + 8].copy_from_slice(&(-4i32).to_be_bytes());
+        assert!(import_shapefile(&shp, None, "shp").is_err());
+
+        // Zero-length dBASE records
+        let mut dbf = layer.dbf.clone();
+        dbf[10..12].copy_from_slice(&0u16.to_le_bytes());
+        assert!(import_shapefile(&layer.shp, Some(&dbf), "shp").is_err());
+
+        // A huge record count in a tiny table is truncated, not allocated
+        let mut dbf = layer.dbf.clone();
+        dbf[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(import_shapefile(&layer.shp, Some(&dbf), "shp").is_err());
+
+        assert!(import_shapefile(&layer.shp[..HEADER_LEN + 6], None, "shp").is_err());
+    }
+}
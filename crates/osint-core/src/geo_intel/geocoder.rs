@@ -0,0 +1,291 @@
+//! Offline reverse geocoding against loaded boundary layers
+
+use crate::{Result, Error};
+use super::SpatialIndex;
+use serde::{Deserialize, Serialize};
+use geo::{ChamberlainDuquetteArea, Geometry, Point};
+use geojson::{Feature, FeatureCollection, GeoJson};
+use uuid::Uuid;
+
+/// Property keys tried, in order, for a feature's display name
+const NAME_KEYS: [&str; 6] = ["name", "NAME", "ADMIN", "admin", "NAME_EN", "name_en"];
+
+/// Property keys tried, in order, for a feature's code
+const CODE_KEYS: [&str; 6] = ["ISO_A2", "iso_a2", "iso_3166_2", "ISO_3166_2", "code", "id"];
+
+/// Property keys tried, in order, for the country an admin-1 or city feature belongs to
+const COUNTRY_KEYS: [&str; 4] = ["ADM0_A3", "adm0_a3", "country", "iso_a2"];
+
+/// Administrative level of a boundary layer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AdminLevel {
+    Country,
+    Region,
+    City,
+}
+
+/// Named feature of a boundary layer
+#[derive(Debug, Clone)]
+pub struct BoundaryFeature {
+    pub name: String,
+    pub code: Option<String>,
+    /// Country the feature belongs to; for countries, often their own ISO alpha-3 code
+    pub country: Option<String>,
+    pub geometry: Geometry,
+}
+
+impl BoundaryFeature {
+    /// Whether a country name or code, as a region or city lists it, refers to this feature
+    fn is_named(&self, value: &str) -> bool {
+        [Some(&self.name), self.code.as_ref(), self.country.as_ref()].into_iter()
+            .flatten()
+            .any(|identifier| identifier.eq_ignore_ascii_case(value))
+    }
+}
+
+/// Result of reverse geocoding a point
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReverseGeocode {
+    pub country: Option<String>,
+    pub country_code: Option<String>,
+    pub region: Option<String>,
+    pub city: Option<String>,
+}
+
+impl ReverseGeocode {
+    pub fn is_empty(&self) -> bool {
+        self.country.is_none() && self.region.is_none() && self.city.is_none()
+    }
+}
+
+#[derive(Debug, Default)]
+struct BoundaryLayer {
+    features: Vec<BoundaryFeature>,
+    /// Area of each feature in square metres
+    areas: Vec<f64>,
+    index: SpatialIndex,
+}
+
+impl BoundaryLayer {
+    fn key(position: usize) -> Uuid {
+        Uuid::from_u128(position as u128)
+    }
+
+    fn add(&mut self, feature: BoundaryFeature) -> Result<()> {
+        self.index.insert(Self::key(self.features.len()), &feature.geometry)?;
+        self.areas.push(feature.geometry.chamberlain_duquette_unsigned_area());
+        self.features.push(feature);
+        Ok(())
+    }
+
+    /// Smallest accepted feature containing the point, so enclaves win over the area around
+    /// them, else the nearest accepted one within `tolerance_km`
+    fn locate(&self, point: &Point, tolerance_km: f64, accept: impl Fn(&BoundaryFeature) -> bool) -> Option<&BoundaryFeature> {
+        let candidates: Vec<(Uuid, f64)> = self.index.within_distance(point, tolerance_km)
+            .into_iter()
+            .filter(|(key, _)| accept(&self.features[key.as_u128() as usize]))
+            .collect();
+        let containing = candidates.iter()
+            .take_while(|(_, distance)| *distance == 0.0)
+            .min_by(|a, b| self.areas[a.0.as_u128() as usize].total_cmp(&self.areas[b.0.as_u128() as usize]));
+        let (key, _) = containing.or(candidates.first())?;
+        self.features.get(key.as_u128() as usize)
+    }
+}
+
+/// Reverse geocoder over country, admin-1 and city layers
+///
+/// Polygon layers match by containment, falling back to the nearest boundary within
+/// `boundary_tolerance_km` for points just offshore of simplified coastlines. City layers
+/// may also be populated-place points, matched within `city_radius_km`.
+#[derive(Debug)]
+pub struct ReverseGeocoder {
+    countries: BoundaryLayer,
+    regions: BoundaryLayer,
+    cities: BoundaryLayer,
+    boundary_tolerance_km: f64,
+    city_radius_km: f64,
+}
+
+impl Default for ReverseGeocoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReverseGeocoder {
+    pub fn new() -> Self {
+        Self {
+            countries: BoundaryLayer::default(),
+            regions: BoundaryLayer::default(),
+            cities: BoundaryLayer::default(),
+            boundary_tolerance_km: 5.0,
+            city_radius_km: 20.0,
+        }
+    }
+
+    pub fn with_boundary_tolerance(mut self, km: f64) -> Self {
+        self.boundary_tolerance_km = km;
+        self
+    }
+
+    pub fn with_city_radius(mut self, km: f64) -> Self {
+        self.city_radius_km = km;
+        self
+    }
+
+    /// Whether any layer has been loaded
+    pub fn is_empty(&self) -> bool {
+        self.countries.features.is_empty() && self.regions.features.is_empty() && self.cities.features.is_empty()
+    }
+
+    /// Parse a GeoJSON FeatureCollection and add it to a layer, returning the feature count
+    pub fn load_geojson(&mut self, level: AdminLevel, geojson_data: &str) -> Result<usize> {
+        let geojson = geojson_data.parse::<GeoJson>()
+            .map_err(|e| Error::Geospatial(format!("Failed to parse GeoJSON: {}", e)))?;
+        match geojson {
+            GeoJson::FeatureCollection(fc) => self.load_features(level, &fc),
+            _ => Err(Error::Geospatial(format!("Expected FeatureCollection for {:?} boundaries", level))),
+        }
+    }
+
+    /// Add the named features of a collection to a layer, returning how many were added
+    pub fn load_features(&mut self, level: AdminLevel, collection: &FeatureCollection) -> Result<usize> {
+        let mut added = 0;
+        for feature in &collection.features {
+            let Some(boundary) = boundary_feature(feature)? else {
+                continue;
+            };
+            self.add_feature(level, boundary)?;
+            added += 1;
+        }
+        Ok(added)
+    }
+
+    pub fn add_feature(&mut self, level: AdminLevel, feature: BoundaryFeature) -> Result<()> {
+        match level {
+            AdminLevel::Country => self.countries.add(feature),
+            AdminLevel::Region => self.regions.add(feature),
+            AdminLevel::City => self.cities.add(feature),
+        }
+    }
+
+    /// Country, region and city containing a point
+    ///
+    /// Regions and cities that name a different country than the matched one are skipped, so
+    /// a point near a border does not pick up the neighbour's city.
+    pub fn reverse_geocode(&self, point: &Point) -> ReverseGeocode {
+        let country = self.countries.locate(point, self.boundary_tolerance_km, |_| true);
+        let in_country = |feature: &BoundaryFeature| match (country, &feature.country) {
+            (Some(country), Some(owner)) => country.is_named(owner),
+            _ => true,
+        };
+        let region = self.regions.locate(point, self.boundary_tolerance_km, in_country);
+        let city = self.cities.locate(point, self.city_radius_km, in_country);
+
+        ReverseGeocode {
+            country: country.map(|c| c.name.clone()),
+            country_code: country.and_then(|c| c.code.clone()),
+            region: region.map(|r| r.name.clone()),
+            city: city.map(|c| c.name.clone()),
+        }
+    }
+
+    /// Whether a country name or code refers to the given country
+    pub fn country_matches(&self, query: &str, country: &str) -> bool {
+        if query.eq_ignore_ascii_case(country) {
+            return true;
+        }
+        self.countries.features.iter()
+            .filter(|f| f.name.eq_ignore_ascii_case(country))
+            .any(|f| f.code.as_deref().is_some_and(|code| code.eq_ignore_ascii_case(query)))
+    }
+}
+
+fn property_string(feature: &Feature, keys: &[&str]) -> Option<String> {
+    keys.iter()
+        .filter_map(|key| feature.property(key))
+        .find_map(|value| match value {
+            serde_json::Value::String(s) if !s.is_empty() && s != "-99" => Some(s.clone()),
+            _ => None,
+        })
+}
+
+/// Convert a GeoJSON feature, skipping those without geometry or name
+fn boundary_feature(feature: &Feature) -> Result<Option<BoundaryFeature>> {
+    let (Some(geometry), Some(name)) = (&feature.geometry, property_string(feature, &NAME_KEYS)) else {
+        return Ok(None);
+    };
+    let geometry = Geometry::try_from(geometry.clone())
+        .map_err(|e| Error::Geospatial(format!("Invalid geometry for boundary {}: {}", name, e)))?;
+
+    Ok(Some(BoundaryFeature {
+        name,
+        code: property_string(feature, &CODE_KEYS),
+        country: property_string(feature, &COUNTRY_KEYS),
+        geometry,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::polygon;
+
+    const COUNTRIES: &str = include_str!("../../tests/fixtures/geo/countries.geojson");
+
+    #[test]
+    fn test_reverse_geocode_layers() {
+        let mut geocoder = ReverseGeocoder::new();
+        assert_eq!(geocoder.load_geojson(AdminLevel::Country, COUNTRIES).unwrap(), 2);
+        geocoder.add_feature(AdminLevel::City, BoundaryFeature {
+            name: "Springfield".to_string(),
+            code: None,
+            country: Some("ALP".to_string()),
+            geometry: Geometry::Point(Point::new(1.0, 1.0)),
+        }).unwrap();
+
+        // Nearer, but across the border
+        geocoder.add_feature(AdminLevel::City, BoundaryFeature {
+            name: "Bordertown".to_string(),
+            code: None,
+            country: Some("BET".to_string()),
+            geometry: Geometry::Point(Point::new(1.06, 1.06)),
+        }).unwrap();
+
+        let inside = geocoder.reverse_geocode(&Point::new(1.05, 1.05));
+        assert_eq!(inside.country.as_deref(), Some("Alphaland"));
+        assert_eq!(inside.country_code.as_deref(), Some("AL"));
+        assert_eq!(inside.city.as_deref(), Some("Springfield"));
+
+        // Just offshore of the eastern coast, within the boundary tolerance
+        let offshore = geocoder.reverse_geocode(&Point::new(3.03, 1.0));
+        assert_eq!(offshore.country.as_deref(), Some("Betaland"));
+        assert!(geocoder.reverse_geocode(&Point::new(10.0, 10.0)).is_empty());
+
+        assert!(geocoder.country_matches("al", "Alphaland"));
+        assert!(!geocoder.country_matches("BL", "Alphaland"));
+    }
+
+    #[test]
+    fn test_enclave_wins_over_surrounding_country() {
+        let square = |min: f64, max: f64| Geometry::Polygon(polygon![
+            (x: min, y: min), (x: max, y: min), (x: max, y: max), (x: min, y: max), (x: min, y: min),
+        ]);
+        let country = |name: &str, geometry: Geometry| BoundaryFeature { name: name.to_string(), code: None, country: None, geometry };
+
+        // Added in both orders so the result does not depend on insertion
+        for enclave_first in [false, true] {
+            let mut geocoder = ReverseGeocoder::new();
+            let mut features = vec![country("Outerland", square(0.0, 10.0)), country("Enclavia", square(4.0, 5.0))];
+            if enclave_first {
+                features.reverse();
+            }
+            for feature in features {
+                geocoder.add_feature(AdminLevel::Country, feature).unwrap();
+            }
+            assert_eq!(geocoder.reverse_geocode(&Point::new(4.5, 4.5)).country.as_deref(), Some("Enclavia"));
+            assert_eq!(geocoder.reverse_geocode(&Point::new(8.0, 8.0)).country.as_deref(), Some("Outerland"));
+        }
+    }
+}
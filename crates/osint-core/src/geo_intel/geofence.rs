@@ -0,0 +1,436 @@
+//! Geofences with enter, exit and dwell events
+
+use crate::{Result, Error, models::{EntityType, GeoIntel, IntelEntity}};
+use super::{SpatialIndex, trajectory::record_entity_id};
+use serde::{Deserialize, Serialize};
+use geo::{Geometry, Point};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Area covered by a geofence
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum GeofenceArea {
+    /// Polygon, multipolygon or rectangle
+    Shape(Geometry),
+    Radius { center: Point, radius_km: f64 },
+}
+
+/// Area of interest that raises events when intel arrives inside it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Geofence {
+    pub id: Uuid,
+    pub name: String,
+    pub area: GeofenceArea,
+    /// Observations outside this window are ignored
+    #[serde(default)]
+    pub active_from: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub active_until: Option<DateTime<Utc>>,
+    /// Entity types the fence applies to; empty for all. Geo intel records match through
+    /// their `entity_type` property.
+    #[serde(default)]
+    pub entity_types: Vec<EntityType>,
+    /// Raise a dwell event once a subject has stayed inside this long
+    #[serde(default)]
+    pub dwell_minutes: Option<f64>,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Geofence {
+    pub fn new(name: &str, area: GeofenceArea) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            area,
+            active_from: None,
+            active_until: None,
+            entity_types: Vec::new(),
+            dwell_minutes: None,
+            enabled: true,
+            created_at: Utc::now(),
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        match &self.area {
+            GeofenceArea::Shape(Geometry::Polygon(_) | Geometry::MultiPolygon(_) | Geometry::Rect(_)) => {}
+            GeofenceArea::Shape(_) => {
+                return Err(Error::InvalidInput(format!("Geofence {} must be a polygon or rectangle", self.name)));
+            }
+            GeofenceArea::Radius { radius_km, .. } if !radius_km.is_finite() || *radius_km <= 0.0 => {
+                return Err(Error::InvalidInput(format!("Geofence {} radius must be positive", self.name)));
+            }
+            GeofenceArea::Radius { .. } => {}
+        }
+        if let (Some(from), Some(until)) = (self.active_from, self.active_until) {
+            if from > until {
+                return Err(Error::InvalidInput(format!("Geofence {} window ends before it starts", self.name)));
+            }
+        }
+        Ok(())
+    }
+
+    fn indexed_geometry(&self) -> Geometry {
+        match &self.area {
+            GeofenceArea::Shape(geometry) => geometry.clone(),
+            GeofenceArea::Radius { center, .. } => Geometry::Point(*center),
+        }
+    }
+
+    fn is_active(&self, time: DateTime<Utc>) -> bool {
+        self.enabled
+            && self.active_from.is_none_or(|from| time >= from)
+            && self.active_until.is_none_or(|until| time <= until)
+    }
+
+    fn applies_to(&self, entity_type: Option<&EntityType>) -> bool {
+        self.entity_types.is_empty() || entity_type.is_some_and(|t| self.entity_types.contains(t))
+    }
+
+    /// Whether a geometry at `distance_km` from the indexed geometry is inside
+    fn contains_at(&self, distance_km: f64) -> bool {
+        match &self.area {
+            GeofenceArea::Shape(_) => distance_km == 0.0,
+            GeofenceArea::Radius { radius_km, .. } => distance_km <= *radius_km,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GeofenceEventType {
+    Enter,
+    Exit,
+    Dwell,
+}
+
+/// Observation that raised a geofence event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum GeofenceTrigger {
+    GeoIntel(GeoIntel),
+    Entity(IntelEntity),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeofenceEvent {
+    pub id: Uuid,
+    pub geofence_id: Uuid,
+    pub geofence_name: String,
+    pub event_type: GeofenceEventType,
+    /// Entity whose presence changed; a record's own ID when it names no entity
+    pub subject_id: Uuid,
+    pub trigger: GeofenceTrigger,
+    pub occurred_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+struct Presence {
+    entered_at: DateTime<Utc>,
+    dwell_reported: bool,
+}
+
+/// Geofence definitions, the subjects currently inside them and events not yet taken
+#[derive(Debug, Default)]
+pub struct GeofenceMonitor {
+    fences: HashMap<Uuid, Geofence>,
+    index: SpatialIndex,
+    /// Largest radius fence, so one index query finds every candidate
+    max_radius_km: f64,
+    presence: HashMap<Uuid, HashMap<Uuid, Presence>>,
+    pending: Vec<GeofenceEvent>,
+}
+
+impl GeofenceMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.fences.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fences.is_empty()
+    }
+
+    pub fn get(&self, id: &Uuid) -> Option<&Geofence> {
+        self.fences.get(id)
+    }
+
+    pub fn geofences(&self) -> impl Iterator<Item = &Geofence> {
+        self.fences.values()
+    }
+
+    /// Add or replace a geofence
+    pub fn add(&mut self, geofence: Geofence) -> Result<()> {
+        geofence.validate()?;
+        self.index.insert(geofence.id, &geofence.indexed_geometry())?;
+        if let GeofenceArea::Radius { radius_km, .. } = &geofence.area {
+            self.max_radius_km = self.max_radius_km.max(*radius_km);
+        }
+        self.fences.insert(geofence.id, geofence);
+        Ok(())
+    }
+
+    /// Remove a geofence, forgetting who was inside it
+    pub fn remove(&mut self, id: &Uuid) -> Option<Geofence> {
+        self.index.remove(id);
+        for fences in self.presence.values_mut() {
+            fences.remove(id);
+        }
+        self.fences.remove(id)
+    }
+
+    /// Load definitions saved with [`GeofenceMonitor::save_file`], returning how many were added
+    pub fn load_file(&mut self, path: impl AsRef<Path>) -> Result<usize> {
+        let content = std::fs::read_to_string(path.as_ref())?;
+        let fences: Vec<Geofence> = serde_json::from_str(&content)
+            .map_err(|e| Error::Parsing(format!("Invalid geofence file: {}", e)))?;
+        let count = fences.len();
+        for fence in fences {
+            self.add(fence)?;
+        }
+        Ok(count)
+    }
+
+    /// Save definitions as JSON, ordered by creation time
+    pub fn save_file(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut fences: Vec<&Geofence> = self.fences.values().collect();
+        fences.sort_by_key(|f| (f.created_at, f.id));
+        let json = serde_json::to_string_pretty(&fences)
+            .map_err(|e| Error::Internal(format!("Failed to serialize geofences: {}", e)))?;
+        std::fs::write(path.as_ref(), json)?;
+        Ok(())
+    }
+
+    /// Evaluate a new geo intel record, attributed to its `entity_id` property when set;
+    /// anonymous records are one-off sightings that only raise Enter events
+    pub fn observe_geo_intel(&mut self, record: &GeoIntel) -> Result<()> {
+        let entity_type = record.properties.get("entity_type")
+            .and_then(|v| serde_json::from_value::<EntityType>(v.clone()).ok());
+        let Some(subject_id) = record_entity_id(record) else {
+            let inside = self.fences_containing(entity_type.as_ref(), &record.geometry, record.collected_at)?;
+            let events = inside.into_iter().map(|id| (id, GeofenceEventType::Enter)).collect();
+            self.raise(events, record.id, record.collected_at, || GeofenceTrigger::GeoIntel(record.clone()));
+            return Ok(());
+        };
+        self.observe(subject_id, entity_type.as_ref(), &record.geometry, record.collected_at, || GeofenceTrigger::GeoIntel(record.clone()))
+    }
+
+    /// Evaluate the location of an entity; entities without one are ignored
+    pub fn observe_entity(&mut self, entity: &IntelEntity) -> Result<()> {
+        let Some(location) = entity.location else {
+            return Ok(());
+        };
+        self.observe(entity.id, Some(&entity.entity_type), &Geometry::Point(location), entity.updated_at, || GeofenceTrigger::Entity(entity.clone()))
+    }
+
+    fn observe(
+        &mut self,
+        subject_id: Uuid,
+        entity_type: Option<&EntityType>,
+        geometry: &Geometry,
+        time: DateTime<Utc>,
+        trigger: impl Fn() -> GeofenceTrigger,
+    ) -> Result<()> {
+        if self.fences.is_empty() {
+            return Ok(());
+        }
+        let applies = |fence: &Geofence| fence.is_active(time) && fence.applies_to(entity_type);
+        let inside = self.fences_containing(entity_type, geometry, time)?;
+
+        let mut events = Vec::new();
+        let presence = self.presence.entry(subject_id).or_default();
+
+        // Exits only count for fences that still apply to this observation
+        let exited: Vec<Uuid> = presence.keys()
+            .filter(|id| !inside.contains(id) && self.fences.get(id).is_some_and(applies))
+            .copied()
+            .collect();
+        for id in exited {
+            presence.remove(&id);
+            events.push((id, GeofenceEventType::Exit));
+        }
+
+        for id in inside {
+            match presence.get_mut(&id) {
+                None => {
+                    presence.insert(id, Presence { entered_at: time, dwell_reported: false });
+                    events.push((id, GeofenceEventType::Enter));
+                }
+                Some(state) => {
+                    let dwell = self.fences[&id].dwell_minutes;
+                    let stayed_minutes = (time - state.entered_at).num_seconds() as f64 / 60.0;
+                    if !state.dwell_reported && dwell.is_some_and(|d| stayed_minutes >= d) {
+                        state.dwell_reported = true;
+                        events.push((id, GeofenceEventType::Dwell));
+                    }
+                }
+            }
+        }
+        if presence.is_empty() {
+            self.presence.remove(&subject_id);
+        }
+
+        self.raise(events, subject_id, time, trigger);
+        Ok(())
+    }
+
+    /// Fences active at `time` for this entity type that contain the geometry
+    fn fences_containing(&self, entity_type: Option<&EntityType>, geometry: &Geometry, time: DateTime<Utc>) -> Result<Vec<Uuid>> {
+        if self.fences.is_empty() {
+            return Ok(Vec::new());
+        }
+        Ok(self.index.within_distance_of(geometry, self.max_radius_km)?
+            .into_iter()
+            .filter(|(id, distance)| self.fences.get(id).is_some_and(|f| {
+                f.is_active(time) && f.applies_to(entity_type) && f.contains_at(*distance)
+            }))
+            .map(|(id, _)| id)
+            .collect())
+    }
+
+    fn raise(
+        &mut self,
+        events: Vec<(Uuid, GeofenceEventType)>,
+        subject_id: Uuid,
+        time: DateTime<Utc>,
+        trigger: impl Fn() -> GeofenceTrigger,
+    ) {
+        for (geofence_id, event_type) in events {
+            self.pending.push(GeofenceEvent {
+                id: Uuid::new_v4(),
+                geofence_id,
+                geofence_name: self.fences[&geofence_id].name.clone(),
+                event_type,
+                subject_id,
+                trigger: trigger(),
+                occurred_at: time,
+            });
+        }
+    }
+
+    /// Take the events raised since the last call
+    pub fn drain_events(&mut self) -> Vec<GeofenceEvent> {
+        std::mem::take(&mut self.pending)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use geo::polygon;
+
+    fn vehicle_at(id: Uuid, lon: f64, lat: f64, minutes: i64) -> IntelEntity {
+        let time = DateTime::parse_from_rfc3339("2024-03-01T09:00:00Z").unwrap().with_timezone(&Utc) + Duration::minutes(minutes);
+        IntelEntity {
+            id,
+            entity_type: EntityType::Vehicle,
+            name: "truck".to_string(),
+            description: None,
+            confidence: 0.9,
+            source: "gps".to_string(),
+            created_at: time,
+            updated_at: time,
+            tags: Vec::new(),
+            attributes: HashMap::new(),
+            location: Some(Point::new(lon, lat)),
+            relationships: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_enter_dwell_exit() {
+        let mut monitor = GeofenceMonitor::new();
+        let mut compound = Geofence::new("compound", GeofenceArea::Shape(Geometry::Polygon(polygon![
+            (x: 0.0, y: 0.0), (x: 1.0, y: 0.0), (x: 1.0, y: 1.0), (x: 0.0, y: 1.0), (x: 0.0, y: 0.0),
+        ])));
+        compound.dwell_minutes = Some(30.0);
+        compound.entity_types = vec![EntityType::Vehicle];
+        let compound_id = compound.id;
+        monitor.add(compound).unwrap();
+        monitor.add(Geofence::new("port", GeofenceArea::Radius { center: Point::new(5.0, 5.0), radius_km: 2.0 })).unwrap();
+
+        let truck = Uuid::new_v4();
+        for (lon, lat, minutes) in [(-1.0, 0.5, 0), (0.5, 0.5, 10), (0.6, 0.5, 20), (0.7, 0.5, 45), (0.8, 0.5, 50), (5.01, 5.0, 60)] {
+            monitor.observe_entity(&vehicle_at(truck, lon, lat, minutes)).unwrap();
+        }
+
+        let events: Vec<(String, GeofenceEventType)> = monitor.drain_events().into_iter().map(|e| (e.geofence_name, e.event_type)).collect();
+        assert_eq!(events, [
+            ("compound".to_string(), GeofenceEventType::Enter),
+            ("compound".to_string(), GeofenceEventType::Dwell),
+            ("compound".to_string(), GeofenceEventType::Exit),
+            ("port".to_string(), GeofenceEventType::Enter),
+        ]);
+        assert!(monitor.drain_events().is_empty());
+
+        // Persons are not tracked by the vehicle-only compound fence
+        let mut person = vehicle_at(Uuid::new_v4(), 0.5, 0.5, 0);
+        person.entity_type = EntityType::Person;
+        monitor.observe_entity(&person).unwrap();
+        assert!(monitor.drain_events().is_empty());
+        assert!(monitor.get(&compound_id).is_some());
+    }
+
+    #[test]
+    fn test_anonymous_geo_intel_only_enters() {
+        let mut monitor = GeofenceMonitor::new();
+        monitor.add(Geofence::new("port", GeofenceArea::Radius { center: Point::new(5.0, 5.0), radius_km: 2.0 })).unwrap();
+        let sighting = |lon: f64| GeoIntel {
+            id: Uuid::new_v4(),
+            geometry: Geometry::Point(Point::new(lon, 5.0)),
+            country: None,
+            region: None,
+            city: None,
+            accuracy: 10.0,
+            source: "camera".to_string(),
+            collected_at: Utc::now(),
+            properties: HashMap::new(),
+        };
+
+        for lon in [5.0, 5.001, 9.0] {
+            monitor.observe_geo_intel(&sighting(lon)).unwrap();
+        }
+        let events = monitor.drain_events();
+        assert_eq!(events.len(), 2);
+        assert!(events.iter().all(|e| e.event_type == GeofenceEventType::Enter));
+        assert!(monitor.presence.is_empty());
+
+        // Records naming their entity are tracked across observations
+        let truck = Uuid::new_v4();
+        for lon in [5.0, 9.0] {
+            let mut record = sighting(lon);
+            record.properties.insert("entity_id".to_string(), serde_json::json!(truck));
+            monitor.observe_geo_intel(&record).unwrap();
+        }
+        let events: Vec<GeofenceEventType> = monitor.drain_events().into_iter().map(|e| e.event_type).collect();
+        assert_eq!(events, [GeofenceEventType::Enter, GeofenceEventType::Exit]);
+    }
+
+    #[test]
+    fn test_time_window_and_persistence() {
+        let mut fence = Geofence::new("night", GeofenceArea::Radius { center: Point::new(0.0, 0.0), radius_km: 1.0 });
+        fence.active_from = Some(DateTime::parse_from_rfc3339("2024-03-01T20:00:00Z").unwrap().with_timezone(&Utc));
+        let mut monitor = GeofenceMonitor::new();
+        monitor.add(fence).unwrap();
+
+        monitor.observe_entity(&vehicle_at(Uuid::new_v4(), 0.001, 0.0, 0)).unwrap();
+        assert!(monitor.drain_events().is_empty());
+        monitor.observe_entity(&vehicle_at(Uuid::new_v4(), 0.001, 0.0, 12 * 60)).unwrap();
+        assert_eq!(monitor.drain_events().len(), 1);
+
+        let path = std::env::temp_dir().join(format!("geofences-{}.json", Uuid::new_v4()));
+        monitor.save_file(&path).unwrap();
+        let mut restored = GeofenceMonitor::new();
+        assert_eq!(restored.load_file(&path).unwrap(), 1);
+        std::fs::remove_file(&path).unwrap();
+        assert!(restored.geofences().all(|f| f.name == "night" && f.active_from.is_some()));
+
+        let landmark = Geofence::new("landmark", GeofenceArea::Shape(Geometry::Point(Point::new(0.0, 0.0))));
+        assert!(restored.add(landmark).is_err());
+    }
+}
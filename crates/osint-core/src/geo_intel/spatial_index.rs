@@ -0,0 +1,524 @@
+//! R-tree spatial index over arbitrary geometries
+//!
+//! Envelopes are built in 3D on the unit sphere rather than in longitude/latitude, so
+//! geometries near the poles or crossing the antimeridian get tight, correct bounds and
+//! distance pruning works in chord length, which is monotonic in great-circle distance.
+
+use crate::{Result, Error};
+use serde::{Deserialize, Serialize};
+use geo::{Coord, CoordsIter, Geometry, Intersects, LineString, Point, Polygon, Rect};
+use rstar::{PointDistance, RTree, RTreeObject, AABB};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Mean Earth radius used for all distance calculations
+pub const EARTH_RADIUS_KM: f64 = 6371.0088;
+
+/// Longest arc between envelope sample points, in radians
+const MAX_SEGMENT_RAD: f64 = 1.0 * std::f64::consts::PI / 180.0;
+
+type Vec3 = [f64; 3];
+
+/// Longitude/latitude bounds; `west > east` denotes a box crossing the antimeridian
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GeoBounds {
+    pub west: f64,
+    pub south: f64,
+    pub east: f64,
+    pub north: f64,
+}
+
+impl GeoBounds {
+    pub fn new(west: f64, south: f64, east: f64, north: f64) -> Self {
+        Self { west, south, east, north }
+    }
+
+    pub fn crosses_antimeridian(&self) -> bool {
+        self.west > self.east
+    }
+
+    /// Planar rectangles covering the bounds, split at the antimeridian if necessary
+    pub fn rects(&self) -> Vec<Rect> {
+        if self.crosses_antimeridian() {
+            vec![
+                Rect::new(Coord { x: self.west, y: self.south }, Coord { x: 180.0, y: self.north }),
+                Rect::new(Coord { x: -180.0, y: self.south }, Coord { x: self.east, y: self.north }),
+            ]
+        } else {
+            vec![Rect::new(Coord { x: self.west, y: self.south }, Coord { x: self.east, y: self.north })]
+        }
+    }
+
+    /// Check coordinates are in range and south lies below north
+    pub fn validate(&self) -> Result<()> {
+        for lon in [self.west, self.east] {
+            check_coord(Coord { x: lon, y: 0.0 })?;
+        }
+        for lat in [self.south, self.north] {
+            check_coord(Coord { x: 0.0, y: lat })?;
+        }
+        if self.south > self.north {
+            return Err(Error::Geospatial("Bounds south edge lies north of the north edge".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Bounds as a rectangle, or a pair of polygons when crossing the antimeridian
+    pub fn to_geometry(&self) -> Geometry {
+        let rects = self.rects();
+        if rects.len() == 1 {
+            Geometry::Rect(rects[0])
+        } else {
+            Geometry::MultiPolygon(rects.into_iter().map(|r| r.to_polygon()).collect())
+        }
+    }
+}
+
+/// Envelope of one indexed geometry
+#[derive(Debug, Clone, PartialEq)]
+struct IndexedEnvelope {
+    id: Uuid,
+    envelope: AABB<Vec3>,
+}
+
+impl RTreeObject for IndexedEnvelope {
+    type Envelope = AABB<Vec3>;
+
+    fn envelope(&self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+impl PointDistance for IndexedEnvelope {
+    /// Lower bound of the squared chord distance to the geometry
+    fn distance_2(&self, point: &Vec3) -> f64 {
+        self.envelope.distance_2(point)
+    }
+}
+
+/// R-tree index supporting points, lines, polygons and collections
+#[derive(Debug, Default)]
+pub struct SpatialIndex {
+    tree: RTree<IndexedEnvelope>,
+    entries: HashMap<Uuid, (Geometry, AABB<Vec3>)>,
+}
+
+impl SpatialIndex {
+    /// Create empty index
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains(&self, id: &Uuid) -> bool {
+        self.entries.contains_key(id)
+    }
+
+    /// Insert geometry, replacing any geometry already indexed under the same ID
+    pub fn insert(&mut self, id: Uuid, geometry: &Geometry) -> Result<()> {
+        let envelope = sphere_envelope(geometry)?;
+        self.remove(&id);
+        self.tree.insert(IndexedEnvelope { id, envelope });
+        self.entries.insert(id, (geometry.clone(), envelope));
+        Ok(())
+    }
+
+    /// Remove geometry, returning whether it was indexed
+    pub fn remove(&mut self, id: &Uuid) -> bool {
+        match self.entries.remove(id) {
+            Some((_, envelope)) => {
+                self.tree.remove(&IndexedEnvelope { id: *id, envelope });
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Replace the geometry of an indexed entry
+    pub fn update(&mut self, id: Uuid, geometry: &Geometry) -> Result<()> {
+        if !self.contains(&id) {
+            return Err(Error::NotFound(format!("Geometry {} is not indexed", id)));
+        }
+        self.insert(id, geometry)
+    }
+
+    /// Indexed geometry
+    pub fn geometry(&self, id: &Uuid) -> Option<&Geometry> {
+        self.entries.get(id).map(|(geometry, _)| geometry)
+    }
+
+    /// Great-circle distance in km from a point to an indexed geometry
+    pub fn distance_km(&self, id: &Uuid, point: &Point) -> Option<f64> {
+        self.geometry(id).map(|geometry| distance_to_geometry_km(point, geometry))
+    }
+
+    /// The `k` geometries closest to a point, with distances in km, nearest first
+    pub fn nearest(&self, point: &Point, k: usize) -> Vec<(Uuid, f64)> {
+        let query = to_unit_vector(point.0);
+        let mut best: Vec<(Uuid, f64)> = Vec::new();
+        if k == 0 {
+            return best;
+        }
+
+        for candidate in self.tree.nearest_neighbor_iter(&query) {
+            // Envelope distances only increase; stop once they exceed the k-th exact distance
+            let lower_bound = chord_to_km(candidate.envelope.distance_2(&query).sqrt());
+            if best.len() == k && lower_bound > best[k - 1].1 {
+                break;
+            }
+
+            let distance = distance_to_geometry_km(point, &self.entries[&candidate.id].0);
+            let position = best.partition_point(|(_, d)| *d <= distance);
+            if position < k {
+                best.insert(position, (candidate.id, distance));
+                best.truncate(k);
+            }
+        }
+
+        best
+    }
+
+    /// Geometries within a distance of a point, with distances in km, nearest first
+    pub fn within_distance(&self, point: &Point, radius_km: f64) -> Vec<(Uuid, f64)> {
+        let query = to_unit_vector(point.0);
+        let chord = km_to_chord(radius_km);
+
+        let mut results: Vec<(Uuid, f64)> = self.tree.locate_within_distance(query, chord * chord)
+            .filter_map(|candidate| {
+                let distance = distance_to_geometry_km(point, &self.entries[&candidate.id].0);
+                (distance <= radius_km).then_some((candidate.id, distance))
+            })
+            .collect();
+
+        results.sort_by(|a, b| a.1.total_cmp(&b.1));
+        results
+    }
+
+    /// Geometries intersecting longitude/latitude bounds
+    pub fn query_bounds(&self, bounds: &GeoBounds) -> Result<Vec<Uuid>> {
+        bounds.validate()?;
+        let query_geometry = bounds.to_geometry();
+        let envelope = sphere_envelope(&query_geometry)?;
+
+        Ok(self.tree.locate_in_envelope_intersecting(&envelope)
+            .filter(|candidate| self.entries[&candidate.id].0.intersects(&query_geometry))
+            .map(|candidate| candidate.id)
+            .collect())
+    }
+
+    /// Geometries within a distance of any part of a query geometry, nearest first
+    pub fn within_distance_of(&self, geometry: &Geometry, radius_km: f64) -> Result<Vec<(Uuid, f64)>> {
+        let envelope = sphere_envelope(geometry)?;
+        let pad = km_to_chord(radius_km.max(0.0));
+        let (lower, upper) = (envelope.lower(), envelope.upper());
+        let search = AABB::from_corners(
+            [lower[0] - pad, lower[1] - pad, lower[2] - pad],
+            [upper[0] + pad, upper[1] + pad, upper[2] + pad],
+        );
+
+        let mut results: Vec<(Uuid, f64)> = self.tree.locate_in_envelope_intersecting(&search)
+            .filter_map(|candidate| {
+                let distance = geometry_distance_km(geometry, &self.entries[&candidate.id].0);
+                (distance <= radius_km).then_some((candidate.id, distance))
+            })
+            .collect();
+
+        results.sort_by(|a, b| a.1.total_cmp(&b.1));
+        Ok(results)
+    }
+
+    /// Geometries whose envelope intersects that of the given geometry
+    pub fn candidates(&self, geometry: &Geometry) -> Result<Vec<Uuid>> {
+        let envelope = sphere_envelope(geometry)?;
+        Ok(self.tree.locate_in_envelope_intersecting(&envelope)
+            .map(|candidate| candidate.id)
+            .collect())
+    }
+}
+
+fn check_coord(coord: Coord) -> Result<()> {
+    if !coord.x.is_finite() || !coord.y.is_finite() || coord.x.abs() > 180.0 || coord.y.abs() > 90.0 {
+        return Err(Error::Geospatial(format!("Coordinate ({}, {}) is outside longitude/latitude range", coord.x, coord.y)));
+    }
+    Ok(())
+}
+
+/// Unit vector for a longitude/latitude coordinate in degrees
+fn to_unit_vector(coord: Coord) -> Vec3 {
+    let (lon, lat) = (coord.x.to_radians(), coord.y.to_radians());
+    [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+}
+
+fn dot(a: Vec3, b: Vec3) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: Vec3, b: Vec3) -> Vec3 {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+fn norm(a: Vec3) -> f64 {
+    dot(a, a).sqrt()
+}
+
+/// Angle between two unit vectors, stable for small and antipodal angles
+fn angle(a: Vec3, b: Vec3) -> f64 {
+    norm(cross(a, b)).atan2(dot(a, b))
+}
+
+fn km_to_chord(km: f64) -> f64 {
+    let central = (km / EARTH_RADIUS_KM).min(std::f64::consts::PI);
+    2.0 * (central / 2.0).sin()
+}
+
+fn chord_to_km(chord: f64) -> f64 {
+    2.0 * (chord / 2.0).min(1.0).asin() * EARTH_RADIUS_KM
+}
+
+/// Points along the great-circle arc from `a` to `b`, excluding `a`
+fn densify(a: Vec3, b: Vec3, out: &mut Vec<Vec3>) {
+    let theta = angle(a, b);
+    let steps = (theta / MAX_SEGMENT_RAD).ceil().max(1.0) as usize;
+    let sin_theta = theta.sin();
+
+    for step in 1..=steps {
+        let t = step as f64 / steps as f64;
+        if sin_theta.abs() < 1e-12 {
+            out.push(b);
+            continue;
+        }
+        let wa = ((1.0 - t) * theta).sin() / sin_theta;
+        let wb = (t * theta).sin() / sin_theta;
+        out.push([wa * a[0] + wb * b[0], wa * a[1] + wb * b[1], wa * a[2] + wb * b[2]]);
+    }
+}
+
+fn line_samples(line: &LineString, out: &mut Vec<Vec3>) -> Result<()> {
+    let mut previous: Option<Vec3> = None;
+    for coord in line.coords() {
+        check_coord(*coord)?;
+        let v = to_unit_vector(*coord);
+        match previous {
+            Some(p) => densify(p, v, out),
+            None => out.push(v),
+        }
+        previous = Some(v);
+    }
+    Ok(())
+}
+
+/// Axis extremes of the sphere lying inside a polygon also bound its surface
+fn polygon_samples(polygon: &Polygon, out: &mut Vec<Vec3>) -> Result<()> {
+    line_samples(polygon.exterior(), out)?;
+    for interior in polygon.interiors() {
+        line_samples(interior, out)?;
+    }
+
+    let axis_points = [(0.0, 0.0), (180.0, 0.0), (-180.0, 0.0), (90.0, 0.0), (-90.0, 0.0), (0.0, 90.0), (0.0, -90.0)];
+    for (lon, lat) in axis_points {
+        if polygon.intersects(&Point::new(lon, lat)) {
+            out.push(to_unit_vector(Coord { x: lon, y: lat }));
+        }
+    }
+    Ok(())
+}
+
+fn geometry_samples(geometry: &Geometry, out: &mut Vec<Vec3>) -> Result<()> {
+    match geometry {
+        Geometry::Point(p) => {
+            check_coord(p.0)?;
+            out.push(to_unit_vector(p.0));
+        }
+        Geometry::MultiPoint(mp) => {
+            for p in mp {
+                check_coord(p.0)?;
+                out.push(to_unit_vector(p.0));
+            }
+        }
+        Geometry::Line(line) => line_samples(&LineString::from(vec![line.start, line.end]), out)?,
+        Geometry::LineString(ls) => line_samples(ls, out)?,
+        Geometry::MultiLineString(mls) => {
+            for ls in mls {
+                line_samples(ls, out)?;
+            }
+        }
+        Geometry::Polygon(polygon) => polygon_samples(polygon, out)?,
+        Geometry::MultiPolygon(mp) => {
+            for polygon in mp {
+                polygon_samples(polygon, out)?;
+            }
+        }
+        Geometry::Rect(rect) => polygon_samples(&rect.to_polygon(), out)?,
+        Geometry::Triangle(triangle) => polygon_samples(&triangle.to_polygon(), out)?,
+        Geometry::GeometryCollection(collection) => {
+            for member in collection {
+                geometry_samples(member, out)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Bounding box on the unit sphere, padded for the bulge of arcs between samples
+fn sphere_envelope(geometry: &Geometry) -> Result<AABB<Vec3>> {
+    let mut samples = Vec::new();
+    geometry_samples(geometry, &mut samples)?;
+    if samples.is_empty() {
+        return Err(Error::Geospatial("Cannot index an empty geometry".to_string()));
+    }
+
+    let envelope = AABB::from_points(samples.iter());
+    let pad = 1.0 - (MAX_SEGMENT_RAD / 2.0).cos() + 1e-9;
+    let (lower, upper) = (envelope.lower(), envelope.upper());
+    Ok(AABB::from_corners(
+        [lower[0] - pad, lower[1] - pad, lower[2] - pad],
+        [upper[0] + pad, upper[1] + pad, upper[2] + pad],
+    ))
+}
+
+/// Angular distance from `p` to the great-circle arc `a`-`b`
+fn arc_distance(p: Vec3, a: Vec3, b: Vec3) -> f64 {
+    let n = cross(a, b);
+    let n_len = norm(n);
+    if n_len < 1e-12 {
+        return angle(p, a).min(angle(p, b));
+    }
+    let n = [n[0] / n_len, n[1] / n_len, n[2] / n_len];
+
+    // Closest point on the full great circle, checked for lying between a and b
+    let along = dot(p, n);
+    let c = [p[0] - along * n[0], p[1] - along * n[1], p[2] - along * n[2]];
+    if norm(c) > 1e-12 && dot(cross(a, c), n) >= 0.0 && dot(cross(c, b), n) >= 0.0 {
+        along.abs().min(1.0).asin()
+    } else {
+        angle(p, a).min(angle(p, b))
+    }
+}
+
+fn line_distance(p: Vec3, line: &LineString) -> f64 {
+    let vectors: Vec<Vec3> = line.coords().map(|c| to_unit_vector(*c)).collect();
+    match vectors.len() {
+        0 => f64::INFINITY,
+        1 => angle(p, vectors[0]),
+        _ => vectors.windows(2).map(|w| arc_distance(p, w[0], w[1])).fold(f64::INFINITY, f64::min),
+    }
+}
+
+fn polygon_distance(point: &Point, p: Vec3, polygon: &Polygon) -> f64 {
+    if polygon.intersects(point) {
+        return 0.0;
+    }
+    std::iter::once(polygon.exterior())
+        .chain(polygon.interiors())
+        .map(|ring| line_distance(p, ring))
+        .fold(f64::INFINITY, f64::min)
+}
+
+/// Great-circle distance in km from a point to the nearest part of a geometry
+pub fn distance_to_geometry_km(point: &Point, geometry: &Geometry) -> f64 {
+    let p = to_unit_vector(point.0);
+    let radians = match geometry {
+        Geometry::Point(q) => angle(p, to_unit_vector(q.0)),
+        Geometry::MultiPoint(mp) => mp.iter().map(|q| angle(p, to_unit_vector(q.0))).fold(f64::INFINITY, f64::min),
+        Geometry::Line(line) => arc_distance(p, to_unit_vector(line.start), to_unit_vector(line.end)),
+        Geometry::LineString(ls) => line_distance(p, ls),
+        Geometry::MultiLineString(mls) => mls.iter().map(|ls| line_distance(p, ls)).fold(f64::INFINITY, f64::min),
+        Geometry::Polygon(polygon) => polygon_distance(point, p, polygon),
+        Geometry::MultiPolygon(mp) => mp.iter().map(|polygon| polygon_distance(point, p, polygon)).fold(f64::INFINITY, f64::min),
+        Geometry::Rect(rect) => polygon_distance(point, p, &rect.to_polygon()),
+        Geometry::Triangle(triangle) => polygon_distance(point, p, &triangle.to_polygon()),
+        Geometry::GeometryCollection(collection) => {
+            return collection.iter().map(|member| distance_to_geometry_km(point, member)).fold(f64::INFINITY, f64::min);
+        }
+    };
+    radians * EARTH_RADIUS_KM
+}
+
+/// Great-circle distance in km between the nearest parts of two geometries
+///
+/// Non-crossing arcs are closest at an endpoint of one of them, so checking every
+/// vertex of each geometry against the other is exact.
+pub fn geometry_distance_km(a: &Geometry, b: &Geometry) -> f64 {
+    if a.intersects(b) {
+        return 0.0;
+    }
+    let from_a = a.coords_iter().map(|c| distance_to_geometry_km(&Point(c), b));
+    let from_b = b.coords_iter().map(|c| distance_to_geometry_km(&Point(c), a));
+    from_a.chain(from_b).fold(f64::INFINITY, f64::min)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::{line_string, polygon};
+
+    #[test]
+    fn test_mixed_geometries_and_removal() {
+        let mut index = SpatialIndex::new();
+        let point = Uuid::new_v4();
+        let road = Uuid::new_v4();
+        let area = Uuid::new_v4();
+
+        index.insert(point, &Geometry::Point(Point::new(13.40, 52.52))).unwrap();
+        index.insert(road, &Geometry::LineString(line_string![(x: 13.0, y: 52.0), (x: 14.0, y: 52.0)])).unwrap();
+        index.insert(area, &Geometry::Polygon(polygon![
+            (x: 2.2, y: 48.8), (x: 2.5, y: 48.8), (x: 2.5, y: 48.9), (x: 2.2, y: 48.9), (x: 2.2, y: 48.8),
+        ])).unwrap();
+
+        // Query inside the polygon is at distance zero
+        let paris = Point::new(2.35, 48.85);
+        assert_eq!(index.nearest(&paris, 1), vec![(area, 0.0)]);
+
+        // The road passes 1 degree south of the query point, ~111 km
+        let nearby = index.within_distance(&Point::new(13.5, 53.0), 120.0);
+        assert_eq!(nearby.iter().map(|(id, _)| *id).collect::<Vec<_>>(), vec![point, road]);
+        assert!((nearby[1].1 - 111.2).abs() < 1.0);
+
+        let in_bounds = index.query_bounds(&GeoBounds::new(12.0, 51.0, 15.0, 53.0)).unwrap();
+        assert_eq!(in_bounds.len(), 2);
+
+        assert!(index.remove(&road));
+        assert!(!index.remove(&road));
+        index.update(point, &Geometry::Point(Point::new(2.36, 48.86))).unwrap();
+        assert_eq!(index.query_bounds(&GeoBounds::new(12.0, 51.0, 15.0, 53.0)).unwrap(), Vec::<Uuid>::new());
+        assert_eq!(index.len(), 2);
+    }
+
+    #[test]
+    fn test_antimeridian_and_poles() {
+        let mut index = SpatialIndex::new();
+        let fiji = Uuid::new_v4();
+        let samoa = Uuid::new_v4();
+        let arctic = Uuid::new_v4();
+
+        index.insert(fiji, &Geometry::Point(Point::new(179.9, -17.0))).unwrap();
+        index.insert(samoa, &Geometry::Point(Point::new(-179.9, -17.0))).unwrap();
+        index.insert(arctic, &Geometry::Point(Point::new(-120.0, 89.95))).unwrap();
+
+        // Points 0.2 degrees apart across the antimeridian are ~21 km apart
+        let across = index.within_distance(&Point::new(179.9, -17.0), 25.0);
+        assert_eq!(across.len(), 2);
+
+        // Near the pole longitudes converge: 180 degrees of longitude is ~11 km
+        let polar = index.nearest(&Point::new(60.0, 89.95), 1);
+        assert_eq!(polar[0].0, arctic);
+        assert!(polar[0].1 < 12.0);
+
+        let wrapped = index.query_bounds(&GeoBounds::new(179.0, -18.0, -179.0, -16.0)).unwrap();
+        assert_eq!(wrapped.len(), 2);
+        assert!(index.insert(Uuid::new_v4(), &Geometry::Point(Point::new(200.0, 0.0))).is_err());
+
+        // A route hugging the antimeridian passes within 11 km of both islands
+        let route = Geometry::LineString(line_string![(x: 180.0, y: -20.0), (x: 180.0, y: -10.0)]);
+        let corridor = index.within_distance_of(&route, 11.0).unwrap();
+        assert_eq!(corridor.len(), 2);
+        assert!(corridor.iter().all(|(_, d)| (d - 10.6).abs() < 0.5));
+    }
+}
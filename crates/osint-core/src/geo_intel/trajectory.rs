@@ -0,0 +1,394 @@
+//! Movement tracks assembled from time-ordered geo intel
+
+use crate::{Result, Error, models::GeoIntel};
+use crate::data_fusion::{CorrelationEvidence, CorrelationMatch, CorrelationType, EvidenceType};
+use super::clustering::representative_point;
+use serde::{Deserialize, Serialize};
+use geo::{HaversineDistance, HaversineIntermediate, Point};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use std::collections::BTreeMap;
+
+/// Property linking a geo intel record to the entity it locates
+pub const ENTITY_ID_PROPERTY: &str = "entity_id";
+
+/// Thresholds for track analysis
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrajectoryParams {
+    /// Speeds above this, after allowing for position accuracy, are physically impossible
+    pub max_speed_kmh: f64,
+    /// Positions are not interpolated across longer gaps between fixes
+    pub max_gap_minutes: f64,
+    /// A stop is a run of fixes within this distance of its first fix...
+    pub stop_radius_m: f64,
+    /// ...lasting at least this long
+    pub min_dwell_minutes: f64,
+    /// Tracks closer than this are travelling together...
+    pub co_travel_distance_m: f64,
+    /// ...when they stay close for at least this long
+    pub co_travel_minutes: f64,
+}
+
+impl Default for TrajectoryParams {
+    fn default() -> Self {
+        Self {
+            max_speed_kmh: 300.0,
+            max_gap_minutes: 30.0,
+            stop_radius_m: 100.0,
+            min_dwell_minutes: 10.0,
+            co_travel_distance_m: 200.0,
+            co_travel_minutes: 10.0,
+        }
+    }
+}
+
+impl TrajectoryParams {
+    fn validate(&self) -> Result<()> {
+        let values = [
+            self.max_speed_kmh, self.max_gap_minutes, self.stop_radius_m,
+            self.min_dwell_minutes, self.co_travel_distance_m, self.co_travel_minutes,
+        ];
+        if values.iter().any(|v| !v.is_finite() || *v <= 0.0) {
+            return Err(Error::InvalidInput("Trajectory thresholds must be positive".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Timestamped position of an entity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackPoint {
+    pub geo_intel_id: Uuid,
+    pub point: Point,
+    pub timestamp: DateTime<Utc>,
+    pub accuracy_m: f64,
+}
+
+/// Movement between consecutive track points
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackLeg {
+    pub from: usize,
+    pub to: usize,
+    pub distance_m: f64,
+    pub duration_s: f64,
+    /// Unset for simultaneous fixes
+    pub speed_kmh: Option<f64>,
+    /// Initial bearing in degrees from north, unset when the entity did not move
+    pub heading_deg: Option<f64>,
+    /// The move cannot be explained by position accuracy at `max_speed_kmh`
+    pub impossible: bool,
+}
+
+/// Time-ordered positions of one entity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Track {
+    pub entity_id: Uuid,
+    pub points: Vec<TrackPoint>,
+    pub legs: Vec<TrackLeg>,
+}
+
+/// Place an entity stayed for at least the minimum dwell time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Stop {
+    pub entity_id: Uuid,
+    pub center: Point,
+    pub arrived: DateTime<Utc>,
+    pub departed: DateTime<Utc>,
+    pub dwell_minutes: f64,
+    pub geo_intel_ids: Vec<Uuid>,
+}
+
+/// Initial great-circle bearing from `a` to `b`
+fn heading(a: &Point, b: &Point) -> f64 {
+    let (lat1, lat2) = (a.y().to_radians(), b.y().to_radians());
+    let delta_lon = (b.x() - a.x()).to_radians();
+    let y = delta_lon.sin() * lat2.cos();
+    let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * delta_lon.cos();
+    (y.atan2(x).to_degrees() + 360.0) % 360.0
+}
+
+fn seconds_between(a: DateTime<Utc>, b: DateTime<Utc>) -> f64 {
+    (b - a).num_milliseconds() as f64 / 1000.0
+}
+
+impl Track {
+    /// Sort points by time and compute the legs between them
+    pub fn new(entity_id: Uuid, mut points: Vec<TrackPoint>, params: &TrajectoryParams) -> Self {
+        points.sort_by_key(|p| p.timestamp);
+        let legs = points.windows(2)
+            .enumerate()
+            .map(|(i, pair)| {
+                let (a, b) = (&pair[0], &pair[1]);
+                let distance_m = a.point.haversine_distance(&b.point);
+                let duration_s = seconds_between(a.timestamp, b.timestamp);
+                // Only the movement beyond both fixes' error circles has to be explained
+                let unexplained_m = (distance_m - a.accuracy_m - b.accuracy_m).max(0.0);
+                let impossible = if duration_s > 0.0 {
+                    unexplained_m / duration_s * 3.6 > params.max_speed_kmh
+                } else {
+                    unexplained_m > 0.0
+                };
+                TrackLeg {
+                    from: i,
+                    to: i + 1,
+                    distance_m,
+                    duration_s,
+                    speed_kmh: (duration_s > 0.0).then(|| distance_m / duration_s * 3.6),
+                    heading_deg: (distance_m > 0.0).then(|| heading(&a.point, &b.point)),
+                    impossible,
+                }
+            })
+            .collect();
+        Self { entity_id, points, legs }
+    }
+
+    pub fn start(&self) -> Option<DateTime<Utc>> {
+        self.points.first().map(|p| p.timestamp)
+    }
+
+    pub fn end(&self) -> Option<DateTime<Utc>> {
+        self.points.last().map(|p| p.timestamp)
+    }
+
+    pub fn total_distance_m(&self) -> f64 {
+        self.legs.iter().map(|l| l.distance_m).sum()
+    }
+
+    /// Legs faster than the entity could have travelled
+    pub fn impossible_jumps(&self) -> impl Iterator<Item = &TrackLeg> {
+        self.legs.iter().filter(|l| l.impossible)
+    }
+
+    /// Interpolated position at a time, unless it falls in a long gap or an impossible leg
+    pub fn position_at(&self, time: DateTime<Utc>, params: &TrajectoryParams) -> Option<Point> {
+        let after = self.points.partition_point(|p| p.timestamp < time);
+        let next = self.points.get(after)?;
+        if next.timestamp == time {
+            return Some(next.point);
+        }
+        let previous = self.points.get(after.checked_sub(1)?)?;
+        let leg = &self.legs[after - 1];
+        if leg.impossible || leg.duration_s > params.max_gap_minutes * 60.0 {
+            return None;
+        }
+        let fraction = seconds_between(previous.timestamp, time) / leg.duration_s;
+        Some(previous.point.haversine_intermediate(&next.point, fraction))
+    }
+
+    /// Stay points: runs of fixes within `stop_radius_m` of the run's first fix that last
+    /// at least `min_dwell_minutes`
+    pub fn stops(&self, params: &TrajectoryParams) -> Vec<Stop> {
+        let mut stops = Vec::new();
+        let mut i = 0;
+        while i < self.points.len() {
+            let anchor = &self.points[i];
+            let mut j = i + 1;
+            while j < self.points.len() && anchor.point.haversine_distance(&self.points[j].point) <= params.stop_radius_m {
+                j += 1;
+            }
+
+            let run = &self.points[i..j];
+            let last = &run[run.len() - 1];
+            let dwell_minutes = seconds_between(anchor.timestamp, last.timestamp) / 60.0;
+            if run.len() > 1 && dwell_minutes >= params.min_dwell_minutes {
+                let n = run.len() as f64;
+                stops.push(Stop {
+                    entity_id: self.entity_id,
+                    center: Point::new(run.iter().map(|p| p.point.x()).sum::<f64>() / n, run.iter().map(|p| p.point.y()).sum::<f64>() / n),
+                    arrived: anchor.timestamp,
+                    departed: last.timestamp,
+                    dwell_minutes,
+                    geo_intel_ids: run.iter().map(|p| p.geo_intel_id).collect(),
+                });
+                i = j;
+            } else {
+                i += 1;
+            }
+        }
+        stops
+    }
+}
+
+/// Assemble per-entity tracks from `(entity_id, record)` observations
+///
+/// Non-point records are placed at their representative point.
+pub fn build_tracks<'a>(observations: impl IntoIterator<Item = (Uuid, &'a GeoIntel)>, params: &TrajectoryParams) -> Result<Vec<Track>> {
+    params.validate()?;
+    let mut by_entity: BTreeMap<Uuid, Vec<TrackPoint>> = BTreeMap::new();
+    for (entity_id, record) in observations {
+        let Some(point) = representative_point(&record.geometry) else {
+            continue;
+        };
+        by_entity.entry(entity_id).or_default().push(TrackPoint {
+            geo_intel_id: record.id,
+            point,
+            timestamp: record.collected_at,
+            accuracy_m: record.accuracy as f64,
+        });
+    }
+    Ok(by_entity.into_iter().map(|(entity_id, points)| Track::new(entity_id, points, params)).collect())
+}
+
+/// Stable entity ID for a named track, so fixes imported under the same name join one track
+pub fn track_entity_id(name: &str) -> Uuid {
+    Uuid::new_v5(&Uuid::NAMESPACE_OID, format!("track:{}", name.trim()).as_bytes())
+}
+
+/// Entity a record locates, from its `entity_id` property
+pub fn record_entity_id(record: &GeoIntel) -> Option<Uuid> {
+    record.properties.get(ENTITY_ID_PROPERTY)
+        .and_then(|v| v.as_str())
+        .and_then(|s| Uuid::parse_str(s).ok())
+}
+
+/// Longest span two tracks stay within `co_travel_distance_m`, with the time they overlap
+///
+/// Both tracks are sampled at every fix either has during their common period.
+fn longest_co_travel(a: &Track, b: &Track, params: &TrajectoryParams) -> Option<(f64, f64)> {
+    let start = a.start()?.max(b.start()?);
+    let end = a.end()?.min(b.end()?);
+    if end <= start {
+        return None;
+    }
+
+    let mut times: Vec<DateTime<Utc>> = a.points.iter().chain(&b.points)
+        .map(|p| p.timestamp)
+        .filter(|t| *t >= start && *t <= end)
+        .collect();
+    times.sort();
+    times.dedup();
+
+    let mut longest_s: f64 = 0.0;
+    let mut run_start = None;
+    for time in times {
+        let together = match (a.position_at(time, params), b.position_at(time, params)) {
+            (Some(pa), Some(pb)) => pa.haversine_distance(&pb) <= params.co_travel_distance_m,
+            _ => false,
+        };
+        match (together, run_start) {
+            (true, None) => run_start = Some(time),
+            (true, Some(since)) => longest_s = longest_s.max(seconds_between(since, time)),
+            (false, _) => run_start = None,
+        }
+    }
+    Some((longest_s, seconds_between(start, end)))
+}
+
+/// Pairs of tracks that stayed within `co_travel_distance_m` for `co_travel_minutes`
+///
+/// Confidence grows with the share of the common period spent together.
+pub fn find_co_travel(tracks: &[Track], params: &TrajectoryParams) -> Result<Vec<CorrelationMatch>> {
+    params.validate()?;
+    let mut matches = Vec::new();
+    for (i, a) in tracks.iter().enumerate() {
+        for b in &tracks[i + 1..] {
+            let Some((together_s, overlap_s)) = longest_co_travel(a, b, params) else {
+                continue;
+            };
+            if together_s < params.co_travel_minutes * 60.0 {
+                continue;
+            }
+            let confidence = (0.5 + 0.5 * together_s / overlap_s) as f32;
+            matches.push(CorrelationMatch {
+                entity1_id: a.entity_id,
+                entity2_id: b.entity_id,
+                correlation_type: CorrelationType::SpatialProximity,
+                confidence: confidence.min(1.0),
+                evidence: vec![
+                    CorrelationEvidence {
+                        evidence_type: EvidenceType::GeographicProximity,
+                        value: format!("within {:.0} m for {:.1} min", params.co_travel_distance_m, together_s / 60.0),
+                        confidence,
+                        source: "trajectory".to_string(),
+                    },
+                    CorrelationEvidence {
+                        evidence_type: EvidenceType::TemporalOverlap,
+                        value: format!("tracks overlap for {:.1} min", overlap_s / 60.0),
+                        confidence,
+                        source: "trajectory".to_string(),
+                    },
+                ],
+                created_at: Utc::now(),
+            });
+        }
+    }
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+    use geo::Geometry;
+    use std::collections::HashMap;
+
+    fn fix(lon: f64, lat: f64, minutes: i64) -> GeoIntel {
+        GeoIntel {
+            id: Uuid::new_v4(),
+            geometry: Geometry::Point(Point::new(lon, lat)),
+            country: None,
+            region: None,
+            city: None,
+            accuracy: 10.0,
+            source: "gps".to_string(),
+            collected_at: Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap() + Duration::minutes(minutes),
+            properties: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_track_kinematics_and_impossible_jumps() {
+        // Heading east at about 0.01 degrees (~1.1 km) per minute, then a jump of ~1000 km
+        let records = [fix(0.02, 0.0, 2), fix(0.0, 0.0, 0), fix(0.01, 0.0, 1), fix(9.0, 0.0, 3)];
+        let entity = Uuid::new_v4();
+        let tracks = build_tracks(records.iter().map(|r| (entity, r)), &TrajectoryParams::default()).unwrap();
+        let track = &tracks[0];
+
+        assert_eq!(track.points[0].timestamp, records[1].collected_at);
+        let leg = &track.legs[0];
+        assert!((leg.speed_kmh.unwrap() - 66.7).abs() < 0.5);
+        assert!((leg.heading_deg.unwrap() - 90.0).abs() < 1e-6);
+        assert_eq!(track.impossible_jumps().map(|l| l.from).collect::<Vec<_>>(), [2]);
+    }
+
+    #[test]
+    fn test_stop_detection() {
+        let records = [
+            fix(0.0, 0.0, 0),
+            fix(0.01, 0.0, 5),
+            fix(0.0102, 0.0003, 10),
+            fix(0.0101, 0.0001, 20),
+            fix(0.0099, 0.0002, 30),
+            fix(0.03, 0.0, 35),
+        ];
+        let entity = Uuid::new_v4();
+        let tracks = build_tracks(records.iter().map(|r| (entity, r)), &TrajectoryParams::default()).unwrap();
+        let stops = tracks[0].stops(&TrajectoryParams::default());
+
+        assert_eq!(stops.len(), 1);
+        assert_eq!(stops[0].geo_intel_ids.len(), 4);
+        assert_eq!(stops[0].dwell_minutes, 25.0);
+    }
+
+    #[test]
+    fn test_co_travel_detection() {
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let mut observations = Vec::new();
+        for minute in (0..=30).step_by(5) {
+            let lon = minute as f64 * 0.005;
+            // b follows about 50 m north of a, reporting two minutes later; c is 5 km away
+            observations.push((a, fix(lon, 0.0, minute)));
+            observations.push((b, fix(lon + 0.01, 0.00045, minute + 2)));
+            observations.push((c, fix(lon, 0.045, minute)));
+        }
+        let params = TrajectoryParams::default();
+        let tracks = build_tracks(observations.iter().map(|(id, r)| (*id, r)), &params).unwrap();
+        let matches = find_co_travel(&tracks, &params).unwrap();
+
+        assert_eq!(matches.len(), 1);
+        let pair = [matches[0].entity1_id, matches[0].entity2_id];
+        assert!(pair.contains(&a) && pair.contains(&b));
+        assert_eq!(matches[0].correlation_type, CorrelationType::SpatialProximity);
+        assert!(matches[0].confidence > 0.9);
+    }
+}
@@ -0,0 +1,154 @@
+//! Positional uncertainty of geo intel
+//!
+//! A record's `accuracy` is read as the radius containing its true location with 95%
+//! probability, modelled as an isotropic normal distribution around the reported point.
+
+use crate::models::{GeoIntel, IntelEntity};
+use super::clustering::representative_point;
+use geo::{HaversineDistance, Point};
+
+/// Accuracies below this are treated as this, so exact coordinates still have some spread
+pub const MIN_ACCURACY_M: f64 = 1.0;
+
+/// Accuracy assumed for entity locations that do not state one
+pub const DEFAULT_ENTITY_ACCURACY_M: f64 = 500.0;
+
+/// Radius of the 95% circle of a 2D normal distribution in standard deviations, sqrt(-2 ln 0.05)
+const SIGMA_PER_95_RADIUS: f64 = 2.447_746_830_680_816;
+
+/// Standard deviation per axis for a 95% accuracy radius
+pub fn sigma_m(accuracy_m: f64) -> f64 {
+    accuracy_m.max(MIN_ACCURACY_M) / SIGMA_PER_95_RADIUS
+}
+
+/// Reported point with its 95% accuracy radius
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UncertaintyCircle {
+    pub center: Point,
+    pub accuracy_m: f64,
+}
+
+impl UncertaintyCircle {
+    pub fn new(center: Point, accuracy_m: f64) -> Self {
+        Self { center, accuracy_m }
+    }
+
+    /// Circle of a record at its representative point
+    pub fn from_geo_intel(record: &GeoIntel) -> Option<Self> {
+        representative_point(&record.geometry).map(|p| Self::new(p, record.accuracy as f64))
+    }
+
+    /// Circle of an entity location, with accuracy from its `accuracy_m`, `precision_m`
+    /// or `geoip_accuracy_km` attribute
+    pub fn from_entity(entity: &IntelEntity) -> Option<Self> {
+        let attribute = |key: &str| entity.attributes.get(key).and_then(|v| v.as_f64());
+        let accuracy_m = attribute("accuracy_m")
+            .or_else(|| attribute("precision_m"))
+            .or_else(|| attribute("geoip_accuracy_km").map(|km| km * 1000.0))
+            .unwrap_or(DEFAULT_ENTITY_ACCURACY_M);
+        entity.location.map(|p| Self::new(p, accuracy_m))
+    }
+
+    pub fn distance_m(&self, other: &Self) -> f64 {
+        self.center.haversine_distance(&other.center)
+    }
+
+    /// Likelihood both circles report the same place, see [`colocation_probability`]
+    pub fn colocation(&self, other: &Self) -> f64 {
+        colocation_probability(self.distance_m(other), self.accuracy_m, other.accuracy_m)
+    }
+
+    /// Probability the true location lies within `radius_m` of a point
+    pub fn probability_within(&self, point: &Point, radius_m: f64) -> f64 {
+        probability_within(self.center.haversine_distance(point), self.accuracy_m, radius_m)
+    }
+}
+
+/// Probability that two reports of the same place lie at least `distance_m` apart
+///
+/// 1 for coincident reports whatever their spread, so a precise fix inside a coarse circle
+/// stays consistent with it; falls towards 0 as the distance exceeds the combined uncertainty.
+pub fn colocation_probability(distance_m: f64, accuracy1_m: f64, accuracy2_m: f64) -> f64 {
+    let (s1, s2) = (sigma_m(accuracy1_m), sigma_m(accuracy2_m));
+    let variance_sum = s1 * s1 + s2 * s2;
+    (-distance_m * distance_m / (2.0 * variance_sum)).exp()
+}
+
+/// Probability that a location reported `distance_m` from a point, with the given
+/// accuracy, truly lies within `radius_m` of it (the Rice distribution CDF)
+pub fn probability_within(distance_m: f64, accuracy_m: f64, radius_m: f64) -> f64 {
+    if radius_m <= 0.0 {
+        return 0.0;
+    }
+    let sigma = sigma_m(accuracy_m);
+    let variance = sigma * sigma;
+
+    // The density is negligible more than 8 sigma from the reported distance
+    let low = (distance_m - 8.0 * sigma).max(0.0);
+    let high = (distance_m + 8.0 * sigma).min(radius_m);
+    if high <= low {
+        return if low >= radius_m { 0.0 } else { 1.0 };
+    }
+
+    let density = |r: f64| {
+        let x = r * distance_m / variance;
+        r / variance * (-(r - distance_m).powi(2) / (2.0 * variance)).exp() * bessel_i0_scaled(x)
+    };
+
+    // Composite Simpson's rule
+    const STEPS: usize = 256;
+    let h = (high - low) / STEPS as f64;
+    let mut sum = density(low) + density(high);
+    for i in 1..STEPS {
+        sum += density(low + i as f64 * h) * if i.is_multiple_of(2) { 2.0 } else { 4.0 };
+    }
+    (sum * h / 3.0).clamp(0.0, 1.0)
+}
+
+/// `exp(-x) * I0(x)` for `x >= 0`, from Abramowitz and Stegun 9.8.1 and 9.8.2
+fn bessel_i0_scaled(x: f64) -> f64 {
+    if x <= 3.75 {
+        let t = (x / 3.75).powi(2);
+        let i0 = 1.0 + t * (3.515_622_9 + t * (3.089_942_4 + t * (1.206_749_2 + t * (0.265_973_2 + t * (0.036_076_8 + t * 0.004_581_3)))));
+        i0 * (-x).exp()
+    } else {
+        let t = 3.75 / x;
+        let poly = 0.398_942_28 + t * (0.013_285_92 + t * (0.002_253_19 + t * (-0.001_575_65 + t * (0.009_162_81
+            + t * (-0.020_577_06 + t * (0.026_355_37 + t * (-0.016_476_33 + t * 0.003_923_77)))))));
+        poly / x.sqrt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_colocation_probability() {
+        assert!((colocation_probability(0.0, 50.0, 50.0) - 1.0).abs() < 1e-12);
+        // Imprecise records are consistent at distances where precise ones are not
+        assert!(colocation_probability(500.0, 10.0, 10.0) < 1e-6);
+        assert!(colocation_probability(500.0, 1000.0, 1000.0) > 0.6);
+    }
+
+    #[test]
+    fn test_precise_fix_inside_coarse_circle() {
+        // A GPS fix at the centre of a 1 km circle is fully consistent with it
+        assert!((colocation_probability(0.0, 10.0, 1000.0) - 1.0).abs() < 1e-12);
+        // and still likely anywhere well inside the circle
+        assert!(colocation_probability(300.0, 10.0, 1000.0) > 0.7);
+        assert!(colocation_probability(3000.0, 10.0, 1000.0) < 0.01);
+    }
+
+    #[test]
+    fn test_probability_within() {
+        // The accuracy radius holds 95% of the distribution
+        assert!((probability_within(0.0, 100.0, 100.0) - 0.95).abs() < 1e-3);
+        assert!(probability_within(0.0, 10.0, 1000.0) > 0.999);
+        assert!(probability_within(5000.0, 10.0, 1000.0) < 1e-9);
+        // Half of a tight distribution centred on the boundary lies inside
+        assert!((probability_within(1000.0, 5.0, 1000.0) - 0.5).abs() < 0.01);
+        // Imprecise records are unlikely to be inside a small radius even when reported there
+        assert!(probability_within(0.0, 10_000.0, 1000.0) < 0.05);
+    }
+}
@@ -1,6 +1,10 @@
 //! Intelligence processing and analysis engine
 
 use crate::{Result, Error, models::*};
+use crate::mitre::{AttackCoverage, AttackKnowledgeBase};
+use crate::enrichment::EnrichmentEngine;
+use crate::geo_intel::{self, GeoIntelEngine, ReverseGeocoder};
+use crate::threat_intel::{AttributionCandidate, AttributionEngine, KillChainAnalysis, KillChainAnalyzer};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use chrono::{DateTime, Utc};
@@ -8,12 +12,25 @@ use uuid::Uuid;
 use tokio::sync::RwLock;
 use std::sync::Arc;
 
+pub mod coordinates;
+pub mod ioc;
+pub mod refang;
+pub mod validation;
+
+pub use coordinates::{CoordinateFormat, ExtractedCoordinate, extract_coordinates};
+pub use ioc::{ExtractedIoc, IocKind, extract_iocs};
+pub use refang::{RefangedText, defang, defang_ioc, defang_json, defang_report, refang, refang_json};
+pub use validation::{IocValidator, IpScope, PublicSuffixList, Validation, ValidationConfig, ip_scope};
+
 /// Intelligence processing engine
 pub struct IntelligenceEngine {
     entities: Arc<RwLock<HashMap<Uuid, IntelEntity>>>,
     indicators: Arc<RwLock<HashMap<Uuid, ThreatIndicator>>>,
     sessions: Arc<RwLock<HashMap<Uuid, AnalysisSession>>>,
     processors: Vec<Box<dyn IntelProcessor + Send + Sync>>,
+    geocoder: Option<Arc<ReverseGeocoder>>,
+    enrichment: Option<Arc<EnrichmentEngine>>,
+    geo_intel: Option<Arc<RwLock<GeoIntelEngine>>>,
 }
 
 /// Trait for intelligence processors
@@ -72,6 +89,9 @@ impl IntelligenceEngine {
             indicators: Arc::new(RwLock::new(HashMap::new())),
             sessions: Arc::new(RwLock::new(HashMap::new())),
             processors: Vec::new(),
+            geocoder: None,
+            enrichment: None,
+            geo_intel: None,
         }
     }
 
@@ -80,6 +100,21 @@ impl IntelligenceEngine {
         self.processors.push(processor);
     }
 
+    /// Reverse geocode the location of every processed entity
+    pub fn set_geocoder(&mut self, geocoder: Arc<ReverseGeocoder>) {
+        self.geocoder = Some(geocoder);
+    }
+
+    /// Enrich every processed entity
+    pub fn set_enrichment(&mut self, enrichment: Arc<EnrichmentEngine>) {
+        self.enrichment = Some(enrichment);
+    }
+
+    /// Store geo intel derived while processing, such as GeoIP locations
+    pub fn set_geo_intel_engine(&mut self, geo_intel: Arc<RwLock<GeoIntelEngine>>) {
+        self.geo_intel = Some(geo_intel);
+    }
+
     /// Process intelligence data
     pub async fn process_intelligence(&self, data: IntelligenceData) -> Result<ProcessingResult> {
         // Find suitable processor
@@ -88,7 +123,45 @@ impl IntelligenceEngine {
             .ok_or_else(|| Error::DataProcessing("No suitable processor found".to_string()))?;
 
         // Process the data
-        let result = processor.process(&data).await?;
+        let mut result = processor.process(&data).await?;
+
+        let mut geo_records = Vec::new();
+        if let Some(enrichment) = &self.enrichment {
+            for entity in &mut result.entities {
+                for outcome in enrichment.enrich_entity(entity).await {
+                    geo_records.extend(outcome.geo_intel);
+                }
+            }
+        }
+
+        if let Some(geocoder) = &self.geocoder {
+            for entity in &mut result.entities {
+                geo_intel::geocode_entity(geocoder, entity);
+            }
+        }
+
+        if let Some(geo_intel) = &self.geo_intel {
+            let mut geo_intel = geo_intel.write().await;
+            for entity in &mut result.entities {
+                geo_intel.observe_entity(entity)?;
+                // Located entities without a record yet, e.g. extracted coordinates
+                if !entity.attributes.contains_key("geo_intel_id") {
+                    if let Some(record) = geo_intel::entity_geo_intel(entity) {
+                        entity.attributes.insert("geo_intel_id".to_string(), serde_json::json!(record.id));
+                        geo_records.push(record);
+                    }
+                }
+            }
+            for mut record in geo_records {
+                // Records locating an entity that is also an indicator carry its severity
+                let entity = geo_intel::trajectory::record_entity_id(&record)
+                    .and_then(|id| result.entities.iter().find(|e| e.id == id));
+                if let Some(indicator) = entity.and_then(|e| result.indicators.iter().find(|i| i.value == e.name)) {
+                    geo_intel::link_indicator(&mut record, indicator);
+                }
+                geo_intel.add_geo_intel(record)?;
+            }
+        }
 
         // Store entities and indicators
         {
@@ -171,6 +244,36 @@ impl IntelligenceEngine {
         }
     }
 
+    /// ATT&CK techniques observed across the indicators of a session
+    pub async fn session_attack_coverage(&self, session_id: &Uuid, attack: &AttackKnowledgeBase) -> Result<AttackCoverage> {
+        let sessions = self.sessions.read().await;
+        let session = sessions.get(session_id)
+            .ok_or_else(|| Error::NotFound("Session not found".to_string()))?;
+        let indicators = self.indicators.read().await;
+
+        Ok(attack.coverage(session.indicators.iter().filter_map(|id| indicators.get(id))))
+    }
+
+    /// Reconstruct the kill chain timeline of a session's indicators
+    pub async fn session_kill_chain(&self, session_id: &Uuid) -> Result<KillChainAnalysis> {
+        let sessions = self.sessions.read().await;
+        let session = sessions.get(session_id)
+            .ok_or_else(|| Error::NotFound("Session not found".to_string()))?;
+        let indicators = self.indicators.read().await;
+
+        Ok(KillChainAnalyzer::new().analyze(session.indicators.iter().filter_map(|id| indicators.get(id))))
+    }
+
+    /// Rank known threat actors against a session's indicators
+    pub async fn session_attribution(&self, session_id: &Uuid, attribution: &AttributionEngine) -> Result<Vec<AttributionCandidate>> {
+        let sessions = self.sessions.read().await;
+        let session = sessions.get(session_id)
+            .ok_or_else(|| Error::NotFound("Session not found".to_string()))?;
+        let indicators = self.indicators.read().await;
+
+        Ok(attribution.attribute(session.indicators.iter().filter_map(|id| indicators.get(id))))
+    }
+
     /// Get intelligence statistics
     pub async fn get_statistics(&self) -> IntelligenceStats {
         let entities = self.entities.read().await;
@@ -269,44 +372,99 @@ pub struct IntelligenceStats {
 }
 
 /// Default text processor implementation
-pub struct TextProcessor;
+#[derive(Default)]
+pub struct TextProcessor {
+    validator: IocValidator,
+}
+
+impl TextProcessor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Process with custom allow-lists or a loaded public suffix list
+    pub fn with_validator(validator: IocValidator) -> Self {
+        Self { validator }
+    }
+}
 
 #[async_trait::async_trait]
 impl IntelProcessor for TextProcessor {
     async fn process(&self, data: &IntelligenceData) -> Result<ProcessingResult> {
         let mut entities = Vec::new();
-        let indicators = Vec::new();
+        let mut indicators: Vec<ThreatIndicator> = Vec::new();
 
-        // Simple entity extraction (IP addresses, domains, emails)
-        let ip_regex = regex::Regex::new(r"\b(?:[0-9]{1,3}\.){3}[0-9]{1,3}\b")
-            .map_err(|e| Error::Parsing(e.to_string()))?;
-        
-        let domain_regex = regex::Regex::new(r"\b[a-zA-Z0-9][a-zA-Z0-9-]{0,61}[a-zA-Z0-9]?\.[a-zA-Z]{2,}\b")
-            .map_err(|e| Error::Parsing(e.to_string()))?;
+        // Byte offset of each entity's mention, used to link co-located entities
+        let mut offsets = Vec::new();
 
-        let email_regex = regex::Regex::new(r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Z|a-z]{2,}\b")
-            .map_err(|e| Error::Parsing(e.to_string()))?;
+        let iocs = extract_iocs(&data.content);
+        let attack_ids = |kind: IocKind| -> Vec<String> {
+            let mut ids: Vec<String> = iocs.iter().filter(|ioc| ioc.kind == kind).map(|ioc| ioc.value.clone()).collect();
+            ids.sort();
+            ids.dedup();
+            ids
+        };
+        let (techniques, tactics) = (attack_ids(IocKind::AttackTechnique), attack_ids(IocKind::AttackTactic));
 
-        // Extract IP addresses
-        for ip_match in ip_regex.find_iter(&data.content) {
-            let ip = ip_match.as_str();
-            let entity = IntelEntity::new(EntityType::IpAddress, ip, &data.source);
-            entities.push(entity);
-        }
+        for ioc in &iocs {
+            let validation = self.validator.validate(ioc);
+            if !validation.keep {
+                continue;
+            }
 
-        // Extract domains
-        for domain_match in domain_regex.find_iter(&data.content) {
-            let domain = domain_match.as_str();
-            let entity = IntelEntity::new(EntityType::Domain, domain, &data.source);
+            let mut entity = IntelEntity::new(ioc.kind.entity_type(), &ioc.value, &data.source);
+            entity.confidence *= validation.weight;
+            entity.tags.extend(validation.tags.iter().cloned());
+            entity.attributes.insert("ioc_type".to_string(), serde_json::json!(ioc.kind));
+            if ioc.defanged {
+                entity.attributes.insert("original_text".to_string(), serde_json::json!(&data.content[ioc.start..ioc.end]));
+            }
             entities.push(entity);
+            offsets.push(ioc.start);
+
+            // Indicators found alongside ATT&CK IDs are attributed to them
+            if let Some(indicator_type) = ioc.kind.indicator_type() {
+                let mut indicator = ThreatIndicator::new(indicator_type, &ioc.value, &data.source);
+                indicator.confidence = data.confidence * 0.8 * validation.weight;
+                indicator.tags.extend(validation.tags.iter().cloned());
+                indicator.first_seen = data.collected_at;
+                indicator.last_seen = data.collected_at;
+                indicator.mitre_techniques = techniques.clone();
+                indicator.mitre_tactics = tactics.clone();
+                indicator.attributes.insert("ioc_type".to_string(), serde_json::json!(ioc.kind));
+                if !indicators.iter().any(|existing| existing.dedup_key() == indicator.dedup_key()) {
+                    indicators.push(indicator);
+                }
+            }
         }
 
-        // Extract emails
-        for email_match in email_regex.find_iter(&data.content) {
-            let email = email_match.as_str();
-            let entity = IntelEntity::new(EntityType::Email, email, &data.source);
-            entities.push(entity);
+        // Extract coordinates and link them to the other entities
+        let mut locations = Vec::new();
+        for coordinate in extract_coordinates(&data.content) {
+            let mut entity = IntelEntity::new(EntityType::Location, coordinate.text.trim(), &data.source);
+            entity.location = Some(coordinate.point);
+            entity.attributes.insert("coordinate_format".to_string(), serde_json::json!(coordinate.format));
+            entity.attributes.insert("precision_m".to_string(), serde_json::json!(coordinate.precision_m));
+            locations.push((entity, coordinate.start));
+        }
+        for (entity, offset) in entities.iter_mut().zip(&offsets) {
+            let candidates: Vec<(&IntelEntity, usize, f32)> = locations.iter()
+                .filter_map(|(location, location_offset)| {
+                    located_confidence(&data.content, *offset, *location_offset).map(|c| (location, *location_offset, c))
+                })
+                .collect();
+            // Locations on the same line all apply; otherwise only the closest in the paragraph
+            let same_line: Vec<_> = candidates.iter().filter(|(_, _, c)| *c >= SAME_LINE_CONFIDENCE).collect();
+            let linked = if same_line.is_empty() {
+                candidates.iter().min_by_key(|(_, location_offset, _)| location_offset.abs_diff(*offset)).into_iter().collect()
+            } else {
+                same_line
+            };
+            for (location, _, confidence) in linked {
+                entity.add_relationship(location.id, RelationshipType::Located, *confidence, data.source.clone());
+            }
         }
+        entities.extend(locations.into_iter().map(|(entity, _)| entity));
 
         Ok(ProcessingResult {
             entities,
@@ -326,6 +484,21 @@ impl IntelProcessor for TextProcessor {
     }
 }
 
+const SAME_LINE_CONFIDENCE: f32 = 0.6;
+const SAME_PARAGRAPH_CONFIDENCE: f32 = 0.3;
+
+/// Mentions on the same line are more likely to describe the same place;
+/// mentions in different paragraphs are not linked
+fn located_confidence(content: &str, a: usize, b: usize) -> Option<f32> {
+    let (start, end) = (a.min(b), a.max(b));
+    let lines: Vec<&str> = content[start..end].split('\n').collect();
+    match lines.len() {
+        1 => Some(SAME_LINE_CONFIDENCE),
+        n if lines[1..n - 1].iter().any(|line| line.trim().is_empty()) => None,
+        _ => Some(SAME_PARAGRAPH_CONFIDENCE),
+    }
+}
+
 impl Default for IntelligenceEngine {
     fn default() -> Self {
         Self::new()
@@ -339,7 +512,7 @@ mod tests {
     #[tokio::test]
     async fn test_intelligence_engine() {
         let mut engine = IntelligenceEngine::new();
-        engine.add_processor(Box::new(TextProcessor));
+        engine.add_processor(Box::new(TextProcessor::new()));
 
         let data = IntelligenceData {
             id: Uuid::new_v4(),
@@ -355,6 +528,77 @@ mod tests {
         assert!(!result.entities.is_empty());
     }
 
+    #[tokio::test]
+    async fn test_enrichment_geo_intel_reaches_geo_engine() {
+        let fixture = |name: &str| std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/geo").join(name);
+        let mut enrichment = EnrichmentEngine::new();
+        enrichment.add_enricher(Box::new(crate::enrichment::GeoIpEnricher::open(fixture("GeoLite2-City-Test.mmdb")).await.unwrap()));
+        let geo_engine = Arc::new(RwLock::new(GeoIntelEngine::new()));
+        geo_engine.write().await.add_geofence(geo_intel::Geofence::new(
+            "london",
+            geo_intel::GeofenceArea::Radius { center: geo::Point::new(-0.12, 51.5), radius_km: 50.0 },
+        )).unwrap();
+
+        let mut engine = IntelligenceEngine::new();
+        engine.add_processor(Box::new(TextProcessor::new()));
+        engine.set_enrichment(Arc::new(enrichment));
+        engine.set_geo_intel_engine(geo_engine.clone());
+
+        let data = IntelligenceData {
+            id: Uuid::new_v4(),
+            data_type: DataType::Text,
+            content: "Beacon to 81.2.69.142 observed".to_string(),
+            source: "test".to_string(),
+            confidence: 0.9,
+            collected_at: Utc::now(),
+            metadata: HashMap::new(),
+        };
+        let result = engine.process_intelligence(data).await.unwrap();
+
+        let ip = result.entities.iter().find(|e| e.name == "81.2.69.142").unwrap();
+        assert!(ip.location.is_some());
+        let mut geo_engine = geo_engine.write().await;
+        let events = geo_engine.drain_geofence_events();
+        assert_eq!(events.len(), 1);
+        assert!(events[0].subject_id == ip.id && matches!(events[0].trigger, geo_intel::GeofenceTrigger::Entity(_)));
+        let nearest = geo_engine.nearest_geo_intel(&ip.location.unwrap(), 5);
+        assert_eq!(nearest.len(), 1);
+        assert_eq!(nearest[0].0.city.as_deref(), Some("London"));
+        assert_eq!(geo_intel::trajectory::record_entity_id(nearest[0].0), Some(ip.id));
+        let indicator = result.indicators.iter().find(|i| i.value == "81.2.69.142").unwrap();
+        assert_eq!(geo_intel::record_severity(nearest[0].0), Some(indicator.severity.clone()));
+
+        let tracks = geo_engine.entity_tracks(&geo_intel::TrajectoryParams::default()).unwrap();
+        assert_eq!(tracks.len(), 1);
+        assert_eq!(tracks[0].entity_id, ip.id);
+    }
+
+    #[tokio::test]
+    async fn test_located_entities_become_geo_intel() {
+        let geo_engine = Arc::new(RwLock::new(GeoIntelEngine::new()));
+        let mut engine = IntelligenceEngine::new();
+        engine.add_processor(Box::new(TextProcessor::new()));
+        engine.set_geo_intel_engine(geo_engine.clone());
+
+        let data = IntelligenceData {
+            id: Uuid::new_v4(),
+            data_type: DataType::Text,
+            content: "Meeting point at 48.8584, 2.2945".to_string(),
+            source: "test".to_string(),
+            confidence: 0.9,
+            collected_at: Utc::now(),
+            metadata: HashMap::new(),
+        };
+        let result = engine.process_intelligence(data).await.unwrap();
+
+        let location = result.entities.iter().find(|e| e.location.is_some()).unwrap();
+        let record_id: Uuid = serde_json::from_value(location.attributes["geo_intel_id"].clone()).unwrap();
+        let geo_engine = geo_engine.read().await;
+        let (record, _) = geo_engine.nearest_geo_intel(&location.location.unwrap(), 1)[0];
+        assert_eq!(record.id, record_id);
+        assert_eq!(geo_intel::trajectory::record_entity_id(record), Some(location.id));
+    }
+
     #[tokio::test]
     async fn test_session_management() {
         let engine = IntelligenceEngine::new();
@@ -367,4 +611,126 @@ mod tests {
         let retrieved = engine.get_session(&session.id).await.unwrap();
         assert!(retrieved.is_some());
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_text_processor_extracts_locations() {
+        let data = IntelligenceData {
+            id: Uuid::new_v4(),
+            data_type: DataType::Text,
+            content: include_str!("../../../test_data.txt").to_string(),
+            source: "test".to_string(),
+            confidence: 0.9,
+            collected_at: Utc::now(),
+            metadata: HashMap::new(),
+        };
+
+        let result = TextProcessor::new().process(&data).await.unwrap();
+        let locations: Vec<&IntelEntity> = result.entities.iter()
+            .filter(|e| e.entity_type == EntityType::Location)
+            .collect();
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].location, Some(geo::Point::new(-74.0060, 40.7128)));
+
+        let ip = result.entities.iter().find(|e| e.name == "192.168.1.100").unwrap();
+        assert!(ip.relationships.iter().any(|r| r.target_entity_id == locations[0].id && r.relationship_type == RelationshipType::Located));
+    }
+
+    #[tokio::test]
+    async fn test_located_links_stay_local() {
+        let data = IntelligenceData {
+            id: Uuid::new_v4(),
+            data_type: DataType::Text,
+            content: "Relay 45.77.1.2 seen at 48.8584, 2.2945 and 51.5007, -0.1246.\n\
+                      Operator mail ops@evil-example.com was active.\n\
+                      \n\
+                      Second site 52.5200, 13.4050 hosts 45.77.9.9.\n\
+                      Backup 45.77.9.10 nearby.\n".to_string(),
+            source: "test".to_string(),
+            confidence: 0.9,
+            collected_at: Utc::now(),
+            metadata: HashMap::new(),
+        };
+
+        let result = TextProcessor::new().process(&data).await.unwrap();
+        let location = |lat: f64| result.entities.iter()
+            .find(|e| e.location.is_some_and(|p| (p.y() - lat).abs() < 1e-6))
+            .unwrap().id;
+        let links = |name: &str| -> Vec<(Uuid, f32)> {
+            let entity = result.entities.iter().find(|e| e.name == name).unwrap();
+            entity.relationships.iter()
+                .filter(|r| r.relationship_type == RelationshipType::Located)
+                .map(|r| (r.target_entity_id, r.confidence))
+                .collect()
+        };
+
+        // Both locations on its line, nothing from the next paragraph
+        assert_eq!(links("45.77.1.2"), vec![(location(48.8584), 0.6), (location(51.5007), 0.6)]);
+        // The closest location in the paragraph
+        assert_eq!(links("ops@evil-example.com"), vec![(location(51.5007), 0.3)]);
+        assert_eq!(links("45.77.9.9"), vec![(location(52.52), 0.6)]);
+        assert_eq!(links("45.77.9.10"), vec![(location(52.52), 0.3)]);
+    }
+
+    #[tokio::test]
+    async fn test_text_processor_creates_indicators() {
+        let data = IntelligenceData {
+            id: Uuid::new_v4(),
+            data_type: DataType::Text,
+            content: include_str!("../../../test_data.txt").to_string(),
+            source: "test".to_string(),
+            confidence: 0.9,
+            collected_at: Utc::now(),
+            metadata: HashMap::new(),
+        };
+
+        let result = TextProcessor::new().process(&data).await.unwrap();
+        let hash = result.entities.iter().find(|e| e.entity_type == EntityType::FileHash).unwrap();
+        assert_eq!(hash.name, "d2".repeat(32));
+
+        let indicator = |indicator_type: IndicatorType| result.indicators.iter().find(|i| i.indicator_type == indicator_type).map(|i| i.value.as_str());
+        assert_eq!(indicator(IndicatorType::Hash), Some(hash.name.as_str()));
+        assert_eq!(indicator(IndicatorType::IpAddress), Some("192.168.1.100"));
+        assert_eq!(indicator(IndicatorType::Domain), Some("malware-command.example.com"));
+        assert_eq!(indicator(IndicatorType::Email), Some("admin@suspicious-domain.net"));
+
+        // ATT&CK IDs in the text carry over to the indicators
+        let data = IntelligenceData {
+            content: "Loader at https://evil.example.net/a.ps1 runs via T1059.001".to_string(),
+            ..data
+        };
+        let result = TextProcessor::new().process(&data).await.unwrap();
+        assert_eq!(result.indicators.len(), 1);
+        assert_eq!(result.indicators[0].mitre_techniques, vec!["T1059.001"]);
+        assert!(result.entities.iter().any(|e| e.entity_type == EntityType::AttackPattern));
+    }
+
+    #[tokio::test]
+    async fn test_text_processor_validates_iocs() {
+        let data = IntelligenceData {
+            id: Uuid::new_v4(),
+            data_type: DataType::Text,
+            content: "See report.pdf: 10.0.0.5 beaconed to 45.77.1.2 and checked www.google.com".to_string(),
+            source: "test".to_string(),
+            confidence: 1.0,
+            collected_at: Utc::now(),
+            metadata: HashMap::new(),
+        };
+
+        let result = TextProcessor::new().process(&data).await.unwrap();
+        assert!(!result.entities.iter().any(|e| e.name == "report.pdf"));
+
+        let indicator = |value: &str| result.indicators.iter().find(|i| i.value == value).unwrap();
+        assert_eq!(indicator("10.0.0.5").tags, vec!["ip:private"]);
+        assert!(indicator("10.0.0.5").confidence < indicator("45.77.1.2").confidence);
+        assert_eq!(indicator("www.google.com").tags, vec!["allowlisted"]);
+
+        let validator = IocValidator::new(ValidationConfig {
+            allow_networks: vec!["45.77.0.0/16".to_string()],
+            drop_allowed: true,
+            drop_non_public_ips: true,
+            ..ValidationConfig::default()
+        }).unwrap();
+        let result = TextProcessor::with_validator(validator).process(&data).await.unwrap();
+        assert!(result.indicators.is_empty());
+    }
+}
@@ -0,0 +1,481 @@
+//! Extraction of geographic coordinates from free text
+
+use serde::{Deserialize, Serialize};
+use geo::Point;
+use regex::{Captures, Regex};
+use std::sync::OnceLock;
+
+/// WGS84 ellipsoid and UTM scale factor
+const WGS84_A: f64 = 6_378_137.0;
+const WGS84_F: f64 = 1.0 / 298.257_223_563;
+const UTM_K0: f64 = 0.9996;
+
+/// MGRS/UTM latitude bands, 8 degrees each from 80°S
+const LATITUDE_BANDS: &str = "CDEFGHJKLMNPQRSTUVWX";
+
+const GEOHASH_ALPHABET: &str = "0123456789bcdefghjkmnpqrstuvwxyz";
+const PLUS_CODE_ALPHABET: &str = "23456789CFGHJMPQRVWX";
+
+/// Metres per degree of latitude, used for precision estimates
+const METRES_PER_DEGREE: f64 = 111_320.0;
+
+/// Notation a coordinate was written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CoordinateFormat {
+    DecimalDegrees,
+    DegreesMinutesSeconds,
+    Utm,
+    Mgrs,
+    Geohash,
+    PlusCode,
+}
+
+/// Coordinate found in text
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExtractedCoordinate {
+    pub point: Point,
+    pub format: CoordinateFormat,
+    /// Matched text
+    pub text: String,
+    /// Byte range of the match
+    pub start: usize,
+    pub end: usize,
+    /// Approximate precision implied by the notation, in metres
+    pub precision_m: f64,
+}
+
+fn regex(cell: &'static OnceLock<Regex>, pattern: &str) -> &'static Regex {
+    cell.get_or_init(|| Regex::new(pattern).expect("coordinate pattern is valid"))
+}
+
+/// Find every coordinate in `text`, in order of appearance and without overlaps
+///
+/// More specific notations win over decimal degrees where matches overlap. Decimal pairs
+/// need three decimal places (or hemisphere letters) and must not be part of a longer
+/// dotted or alphanumeric token, which rules out version numbers and IP addresses.
+pub fn extract_coordinates(text: &str) -> Vec<ExtractedCoordinate> {
+    let mut found: Vec<ExtractedCoordinate> = Vec::new();
+    let extractors: [fn(&str) -> Vec<ExtractedCoordinate>; 6] = [
+        extract_mgrs,
+        extract_utm,
+        extract_dms,
+        extract_plus_codes,
+        extract_geohashes,
+        extract_decimal,
+    ];
+
+    for extractor in extractors {
+        for candidate in extractor(text) {
+            if !found.iter().any(|f| candidate.start < f.end && f.start < candidate.end) {
+                found.push(candidate);
+            }
+        }
+    }
+
+    found.sort_by_key(|c| c.start);
+    found
+}
+
+fn coordinate(point: Point, format: CoordinateFormat, m: regex::Match, precision_m: f64) -> Option<ExtractedCoordinate> {
+    let valid = point.y().abs() <= 90.0 && point.x().abs() <= 180.0 && point.x().is_finite() && point.y().is_finite();
+    valid.then(|| ExtractedCoordinate {
+        point,
+        format,
+        text: m.as_str().to_string(),
+        start: m.start(),
+        end: m.end(),
+        precision_m,
+    })
+}
+
+/// Whether the match is a standalone token rather than part of a version, IP or identifier
+fn is_standalone(text: &str, start: usize, end: usize) -> bool {
+    let before = text[..start].chars().next_back();
+    let after = text[end..].chars().next();
+    let after_next = text[end..].chars().nth(1);
+
+    let glued_before = before.is_some_and(|c| c.is_alphanumeric() || c == '.' || c == '_' || c == '-');
+    let glued_after = after.is_some_and(|c| c.is_alphanumeric() || c == '_')
+        || (after == Some('.') && after_next.is_some_and(|c| c.is_ascii_digit()));
+    if glued_before || glued_after {
+        return false;
+    }
+
+    // "version 10.123, 4.567" and similar
+    let preceding = text[..start].trim_end().to_lowercase();
+    !["version", "ver", "ver.", "v", "build", "release"].iter().any(|word| preceding.ends_with(word))
+}
+
+fn decimal_places(number: &str) -> usize {
+    number.split_once('.').map_or(0, |(_, fraction)| fraction.len())
+}
+
+fn extract_decimal(text: &str) -> Vec<ExtractedCoordinate> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = regex(&PATTERN, r"([-+]?\d{1,2}(?:\.\d+)?)\s*°?\s*([NSns])?\s*[,;/]?\s*([-+]?\d{1,3}(?:\.\d+)?)(?:\s*°)?(?:\s*([EWew])\b)?");
+
+    // A rejected candidate may swallow the start of a real one, so resume one character in
+    let mut found = Vec::new();
+    let mut position = 0;
+    while let Some(caps) = pattern.captures_at(text, position) {
+        let m = caps.get(0).expect("group 0 always matches");
+        match decimal_candidate(text, &caps) {
+            Some(coordinate) => {
+                position = m.end();
+                found.push(coordinate);
+            }
+            None => {
+                position = m.start() + text[m.start()..].chars().next().map_or(1, char::len_utf8);
+            }
+        }
+    }
+    found
+}
+
+fn decimal_candidate(text: &str, caps: &Captures) -> Option<ExtractedCoordinate> {
+    let m = caps.get(0)?;
+    let (lat_text, lon_text) = (&caps[1], &caps[3]);
+    let hemispheres = (caps.get(2), caps.get(4));
+    let min_places = decimal_places(lat_text).min(decimal_places(lon_text));
+
+    let mut lat: f64 = lat_text.parse().ok()?;
+    let mut lon: f64 = lon_text.parse().ok()?;
+    match hemispheres {
+        (Some(ns), Some(ew)) => {
+            if min_places < 1 || lat < 0.0 || lon < 0.0 {
+                return None;
+            }
+            if ns.as_str().eq_ignore_ascii_case("s") {
+                lat = -lat;
+            }
+            if ew.as_str().eq_ignore_ascii_case("w") {
+                lon = -lon;
+            }
+        }
+        (None, None) if min_places >= 3 => {
+            // Bare pairs need an explicit separator between the numbers
+            let between = &text[caps.get(1)?.end()..caps.get(3)?.start()];
+            if !between.contains([',', ';', '/']) && !lon_text.starts_with(['-', '+']) {
+                return None;
+            }
+        }
+        _ => return None,
+    }
+
+    if (lat == 0.0 && lon == 0.0) || !is_standalone(text, m.start(), m.end()) {
+        return None;
+    }
+    let precision = METRES_PER_DEGREE * 10f64.powi(-(min_places as i32));
+    coordinate(Point::new(lon, lat), CoordinateFormat::DecimalDegrees, m, precision)
+}
+
+/// One DMS or degrees-decimal-minutes component with its hemisphere letter
+fn dms_component(degrees: &str, minutes: Option<&str>, seconds: Option<&str>, hemisphere: &str) -> Option<(f64, char)> {
+    let degrees: f64 = degrees.parse().ok()?;
+    let minutes: f64 = minutes.map_or(Some(0.0), |m| m.parse().ok())?;
+    let seconds: f64 = seconds.map_or(Some(0.0), |s| s.parse().ok())?;
+    if minutes >= 60.0 || seconds >= 60.0 {
+        return None;
+    }
+    let hemisphere = hemisphere.chars().next()?.to_ascii_uppercase();
+    let value = degrees + minutes / 60.0 + seconds / 3600.0;
+    Some((if matches!(hemisphere, 'S' | 'W') { -value } else { value }, hemisphere))
+}
+
+fn extract_dms(text: &str) -> Vec<ExtractedCoordinate> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let component = r#"(\d{1,3})\s*[°º]\s*(?:(\d{1,2}(?:\.\d+)?)\s*['′’]\s*(?:(\d{1,2}(?:\.\d+)?)\s*(?:["″”]|''))?)?\s*([NSEWnsew])"#;
+    let pattern = regex(&PATTERN, &format!(r"{}\s*[,;/]?\s*{}", component, component));
+
+    pattern.captures_iter(text)
+        .filter_map(|caps| {
+            let m = caps.get(0)?;
+            let first = dms_component(&caps[1], caps.get(2).map(|c| c.as_str()), caps.get(3).map(|c| c.as_str()), &caps[4])?;
+            let second = dms_component(&caps[5], caps.get(6).map(|c| c.as_str()), caps.get(7).map(|c| c.as_str()), &caps[8])?;
+            let (lat, lon) = match (first.1, second.1) {
+                ('N' | 'S', 'E' | 'W') => (first.0, second.0),
+                ('E' | 'W', 'N' | 'S') => (second.0, first.0),
+                _ => return None,
+            };
+            let precision = if caps.get(3).is_some() { 31.0 } else if caps.get(2).is_some() { 1_850.0 } else { METRES_PER_DEGREE };
+            coordinate(Point::new(lon, lat), CoordinateFormat::DegreesMinutesSeconds, m, precision)
+        })
+        .collect()
+}
+
+fn extract_utm(text: &str) -> Vec<ExtractedCoordinate> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = regex(&PATTERN, r"\b(\d{1,2})\s?([C-HJ-NP-X])\s+(\d{6}(?:\.\d+)?)\s*(?:mE|E)?\s+(\d{1,7}(?:\.\d+)?)\s*(?:mN|N)?\b");
+
+    pattern.captures_iter(text)
+        .filter_map(|caps| {
+            let m = caps.get(0)?;
+            let zone: u8 = caps[1].parse().ok()?;
+            let band = caps[2].chars().next()?;
+            let point = utm_to_point(zone, band >= 'N', caps[3].parse().ok()?, caps[4].parse().ok()?)?;
+            coordinate(point, CoordinateFormat::Utm, m, 1.0)
+        })
+        .collect()
+}
+
+fn extract_mgrs(text: &str) -> Vec<ExtractedCoordinate> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = regex(&PATTERN, r"\b(\d{1,2})([C-HJ-NP-X])\s?([A-HJ-NP-Z])([A-HJ-NP-V])\s?(\d{1,5})\s?(\d{1,5})?\b");
+
+    pattern.captures_iter(text)
+        .filter_map(|caps| {
+            let m = caps.get(0)?;
+            let (easting, northing) = match caps.get(6) {
+                Some(northing) if northing.as_str().len() == caps[5].len() => (caps[5].to_string(), northing.as_str().to_string()),
+                Some(_) => return None,
+                None if caps[5].len() % 2 == 0 => {
+                    let (e, n) = caps[5].split_at(caps[5].len() / 2);
+                    (e.to_string(), n.to_string())
+                }
+                None => return None,
+            };
+            // Two-letter grid squares without digits are too ambiguous to report
+            if easting.is_empty() {
+                return None;
+            }
+            let point = mgrs_to_point(
+                caps[1].parse().ok()?,
+                caps[2].chars().next()?,
+                caps[3].chars().next()?,
+                caps[4].chars().next()?,
+                &easting,
+                &northing,
+            )?;
+            coordinate(point, CoordinateFormat::Mgrs, m, 10f64.powi(5 - easting.len() as i32))
+        })
+        .collect()
+}
+
+/// Geohashes look like ordinary words, so only labelled ones are extracted
+fn extract_geohashes(text: &str) -> Vec<ExtractedCoordinate> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = regex(&PATTERN, r"(?i)\bgeohash\b\s*[:=]?\s*([0-9b-hjkmnp-z]{4,12})\b");
+
+    pattern.captures_iter(text)
+        .filter_map(|caps| {
+            let m = caps.get(1)?;
+            let (point, lat_err, lon_err) = decode_geohash(&m.as_str().to_lowercase())?;
+            coordinate(point, CoordinateFormat::Geohash, m, lat_err.max(lon_err) * METRES_PER_DEGREE)
+        })
+        .collect()
+}
+
+fn extract_plus_codes(text: &str) -> Vec<ExtractedCoordinate> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = regex(&PATTERN, r"(?i)\b([2-9CFGHJMPQRVWX]{8}|[2-9CFGHJMPQRVWX]{2,6}0+)\+([2-9CFGHJMPQRVWX]{2,3})?(?:\s|$|[,.;)])");
+
+    pattern.captures_iter(text)
+        .filter_map(|caps| {
+            let code = format!("{}+{}", &caps[1], caps.get(2).map_or("", |c| c.as_str()));
+            let start = caps.get(1)?.start();
+            let end = start + code.len();
+            let (point, size) = decode_plus_code(&code.to_uppercase())?;
+            let valid = point.y().abs() <= 90.0 && point.x().abs() <= 180.0;
+            valid.then_some(ExtractedCoordinate {
+                point,
+                format: CoordinateFormat::PlusCode,
+                text: code,
+                start,
+                end,
+                precision_m: size * METRES_PER_DEGREE,
+            })
+        })
+        .collect()
+}
+
+/// Decode a geohash into its cell centre and half-sizes in degrees
+pub fn decode_geohash(hash: &str) -> Option<(Point, f64, f64)> {
+    let (mut lat, mut lon) = ((-90.0, 90.0), (-180.0, 180.0));
+    let mut even = true;
+    for c in hash.chars() {
+        let value = GEOHASH_ALPHABET.find(c)?;
+        for bit in (0..5).rev() {
+            let range: &mut (f64, f64) = if even { &mut lon } else { &mut lat };
+            let mid = (range.0 + range.1) / 2.0;
+            if value & (1 << bit) != 0 {
+                range.0 = mid;
+            } else {
+                range.1 = mid;
+            }
+            even = !even;
+        }
+    }
+    let point = Point::new((lon.0 + lon.1) / 2.0, (lat.0 + lat.1) / 2.0);
+    Some((point, (lat.1 - lat.0) / 2.0, (lon.1 - lon.0) / 2.0))
+}
+
+/// Decode a full Open Location Code into its area centre and height in degrees
+pub fn decode_plus_code(code: &str) -> Option<(Point, f64)> {
+    let (head, tail) = code.split_once('+')?;
+    if head.len() != 8 || tail.len() == 1 || tail.len() > 3 {
+        return None;
+    }
+    let digits: Vec<char> = head.trim_end_matches('0').chars().chain(tail.chars()).collect();
+    // Digits come in pairs up to ten, optionally followed by one grid refinement digit
+    if digits.len() < 2 || (digits.len() < 10 && !digits.len().is_multiple_of(2)) {
+        return None;
+    }
+    let value = |c: char| PLUS_CODE_ALPHABET.find(c).map(|v| v as f64);
+    // First digits encode 20° latitude and longitude steps from -90/-180
+    if value(digits[0])? >= 9.0 || value(digits[1])? >= 18.0 {
+        return None;
+    }
+
+    let (mut lat, mut lon) = (-90.0, -180.0);
+    let mut resolution = 20.0;
+    let pairs = digits.len().min(10);
+    for pair in digits[..pairs].chunks(2) {
+        lat += value(pair[0])? * resolution;
+        lon += value(pair[1])? * resolution;
+        resolution /= 20.0;
+    }
+    let (mut height, mut width) = (resolution * 20.0, resolution * 20.0);
+
+    // Eleventh digit refines a 4 x 5 grid
+    if let Some(c) = digits.get(10) {
+        let v = value(*c)?;
+        height /= 5.0;
+        width /= 4.0;
+        lat += (v / 4.0).floor() * height;
+        lon += (v % 4.0) * width;
+    }
+    Some((Point::new(lon + width / 2.0, lat + height / 2.0), height))
+}
+
+fn eccentricity_squared() -> f64 {
+    WGS84_F * (2.0 - WGS84_F)
+}
+
+/// Distance along the meridian from the equator to latitude `phi` in radians
+fn meridian_arc(phi: f64) -> f64 {
+    let e2 = eccentricity_squared();
+    let (e4, e6) = (e2 * e2, e2 * e2 * e2);
+    WGS84_A * ((1.0 - e2 / 4.0 - 3.0 * e4 / 64.0 - 5.0 * e6 / 256.0) * phi
+        - (3.0 * e2 / 8.0 + 3.0 * e4 / 32.0 + 45.0 * e6 / 1024.0) * (2.0 * phi).sin()
+        + (15.0 * e4 / 256.0 + 45.0 * e6 / 1024.0) * (4.0 * phi).sin()
+        - (35.0 * e6 / 3072.0) * (6.0 * phi).sin())
+}
+
+/// Convert UTM to longitude/latitude (Snyder's inverse series, sub-metre within a zone)
+pub fn utm_to_point(zone: u8, northern: bool, easting: f64, northing: f64) -> Option<Point> {
+    if !(1..=60).contains(&zone) || !(100_000.0..1_000_000.0).contains(&easting) || !(0.0..=10_000_000.0).contains(&northing) {
+        return None;
+    }
+    let e2 = eccentricity_squared();
+    let ep2 = e2 / (1.0 - e2);
+    let x = easting - 500_000.0;
+    let y = if northern { northing } else { northing - 10_000_000.0 };
+
+    let mu = y / UTM_K0 / (WGS84_A * (1.0 - e2 / 4.0 - 3.0 * e2 * e2 / 64.0 - 5.0 * e2 * e2 * e2 / 256.0));
+    let e1 = (1.0 - (1.0 - e2).sqrt()) / (1.0 + (1.0 - e2).sqrt());
+    let phi1 = mu
+        + (3.0 * e1 / 2.0 - 27.0 * e1.powi(3) / 32.0) * (2.0 * mu).sin()
+        + (21.0 * e1 * e1 / 16.0 - 55.0 * e1.powi(4) / 32.0) * (4.0 * mu).sin()
+        + (151.0 * e1.powi(3) / 96.0) * (6.0 * mu).sin()
+        + (1097.0 * e1.powi(4) / 512.0) * (8.0 * mu).sin();
+
+    let (sin, cos, tan) = (phi1.sin(), phi1.cos(), phi1.tan());
+    let c1 = ep2 * cos * cos;
+    let t1 = tan * tan;
+    let n1 = WGS84_A / (1.0 - e2 * sin * sin).sqrt();
+    let r1 = WGS84_A * (1.0 - e2) / (1.0 - e2 * sin * sin).powf(1.5);
+    let d = x / (n1 * UTM_K0);
+
+    let lat = phi1 - (n1 * tan / r1)
+        * (d * d / 2.0
+            - (5.0 + 3.0 * t1 + 10.0 * c1 - 4.0 * c1 * c1 - 9.0 * ep2) * d.powi(4) / 24.0
+            + (61.0 + 90.0 * t1 + 298.0 * c1 + 45.0 * t1 * t1 - 252.0 * ep2 - 3.0 * c1 * c1) * d.powi(6) / 720.0);
+    let lon = (d - (1.0 + 2.0 * t1 + c1) * d.powi(3) / 6.0
+        + (5.0 - 2.0 * c1 + 28.0 * t1 - 3.0 * c1 * c1 + 8.0 * ep2 + 24.0 * t1 * t1) * d.powi(5) / 120.0) / cos;
+
+    let central_meridian = (zone as f64 - 1.0) * 6.0 - 180.0 + 3.0;
+    Some(Point::new(central_meridian + lon.to_degrees(), lat.to_degrees()))
+}
+
+/// Convert an MGRS reference to the south-west corner of its grid square, shifted to the centre
+pub fn mgrs_to_point(zone: u8, band: char, column: char, row: char, easting: &str, northing: &str) -> Option<Point> {
+    const COLUMN_SETS: [&str; 3] = ["ABCDEFGH", "JKLMNPQR", "STUVWXYZ"];
+    const ROW_LETTERS: &str = "ABCDEFGHJKLMNPQRSTUV";
+
+    if !(1..=60).contains(&zone) {
+        return None;
+    }
+    let band_index = LATITUDE_BANDS.find(band)?;
+    let column_index = COLUMN_SETS[(zone as usize - 1) % 3].find(column)?;
+    let row_index = (ROW_LETTERS.find(row)? + if zone.is_multiple_of(2) { 15 } else { 0 }) % 20;
+
+    // Scale the digits to metres and move to the centre of the square they describe
+    let precision = 10f64.powi(5 - easting.len() as i32);
+    let e100k = (column_index + 1) as f64 * 100_000.0;
+    let n100k = row_index as f64 * 100_000.0;
+    let easting = e100k + easting.parse::<f64>().ok()? * precision + precision / 2.0;
+    let mut northing = n100k + northing.parse::<f64>().ok()? * precision + precision / 2.0;
+
+    // Row letters repeat every 2,000 km; step north until inside the latitude band
+    let band_latitude = (band_index as f64 - 10.0) * 8.0;
+    let band_northing = UTM_K0 * meridian_arc(band_latitude.to_radians()) + if band_latitude < 0.0 { 10_000_000.0 } else { 0.0 };
+    let band_northing = (band_northing / 100_000.0).floor() * 100_000.0;
+    while northing < band_northing {
+        northing += 2_000_000.0;
+    }
+
+    utm_to_point(zone, band >= 'N', easting, northing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(point: Point, lon: f64, lat: f64, tolerance: f64) {
+        assert!((point.x() - lon).abs() < tolerance && (point.y() - lat).abs() < tolerance, "{:?} vs ({}, {})", point, lon, lat);
+    }
+
+    #[test]
+    fn test_extract_all_formats() {
+        let text = "activity near 40.7128,-74.0060; also 40°42'46\"N 74°0'22\"W, grid 18T WL 83960 07523, \
+                    UTM 18T 583960 4507523, geohash dr5regw3p and plus code 87G7PX7V+4HH.";
+        let found = extract_coordinates(text);
+        let formats: Vec<CoordinateFormat> = found.iter().map(|c| c.format).collect();
+
+        assert_eq!(formats, vec![
+            CoordinateFormat::DecimalDegrees,
+            CoordinateFormat::DegreesMinutesSeconds,
+            CoordinateFormat::Mgrs,
+            CoordinateFormat::Utm,
+            CoordinateFormat::Geohash,
+            CoordinateFormat::PlusCode,
+        ]);
+        for coordinate in &found {
+            assert_near(coordinate.point, -74.006, 40.7128, 0.002);
+        }
+        assert_eq!(found[0].text, "40.7128,-74.0060");
+        assert_eq!(found[4].text, "dr5regw3p");
+
+        // Words after a pair are not part of it
+        let found = extract_coordinates("seen at 48.8584, 2.2945 and 40.7128 N, 74.0060 West later");
+        let texts: Vec<&str> = found.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(texts, ["48.8584, 2.2945"]);
+    }
+
+    #[test]
+    fn test_rejects_false_positives() {
+        for text in [
+            "upgraded to version 10.123, 4.567 today",
+            "build 1.2.3,4.5.6 shipped",
+            "server 192.168.1.100 and 10.0.0.1",
+            "ratio 1.5, 2.5 observed",
+            "value 91.12345, 10.12345 is out of range",
+            "the ABCD ticket and 12 WLAN hosts",
+        ] {
+            assert!(extract_coordinates(text).is_empty(), "{}", text);
+        }
+
+        let southern = extract_coordinates("camp at 33.8688 S, 151.2093 E");
+        assert_near(southern[0].point, 151.2093, -33.8688, 1e-9);
+    }
+}
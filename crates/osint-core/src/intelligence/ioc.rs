@@ -0,0 +1,571 @@
+//! Extraction of indicators of compromise from free text
+
+use crate::models::{EntityType, IndicatorType};
+use super::refang::refang;
+use serde::{Deserialize, Serialize};
+use regex::Regex;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::sync::OnceLock;
+
+const BASE58_ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const BECH32_ALPHABET: &str = "qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// Characters that end a sentence rather than a URL or path
+const TRAILING_PUNCTUATION: &[char] = &['.', ',', ';', ':', '!', '?', ')', ']', '}', '\'', '"'];
+
+/// Kind of indicator found in text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum IocKind {
+    Ipv4,
+    Ipv6,
+    Cidr,
+    Domain,
+    Url,
+    Email,
+    Md5,
+    Sha1,
+    Sha256,
+    Sha512,
+    Ssdeep,
+    Cve,
+    AttackTechnique,
+    AttackTactic,
+    RegistryKey,
+    FilePath,
+    Mutex,
+    Bitcoin,
+    Monero,
+    Ethereum,
+    Asn,
+    PhoneNumber,
+}
+
+impl IocKind {
+    /// Entity type created for a match of this kind
+    pub fn entity_type(&self) -> EntityType {
+        match self {
+            IocKind::Ipv4 | IocKind::Ipv6 => EntityType::IpAddress,
+            IocKind::Cidr => EntityType::NetworkRange,
+            IocKind::Domain => EntityType::Domain,
+            IocKind::Url => EntityType::Url,
+            IocKind::Email => EntityType::Email,
+            IocKind::Md5 | IocKind::Sha1 | IocKind::Sha256 | IocKind::Sha512 | IocKind::Ssdeep => EntityType::FileHash,
+            IocKind::Cve => EntityType::Vulnerability,
+            IocKind::AttackTechnique | IocKind::AttackTactic => EntityType::AttackPattern,
+            IocKind::RegistryKey => EntityType::RegistryKey,
+            IocKind::FilePath => EntityType::FilePath,
+            IocKind::Mutex => EntityType::Mutex,
+            IocKind::Bitcoin | IocKind::Monero | IocKind::Ethereum => EntityType::CryptocurrencyWallet,
+            IocKind::Asn => EntityType::AutonomousSystem,
+            IocKind::PhoneNumber => EntityType::PhoneNumber,
+        }
+    }
+
+    /// Indicator type for kinds that are tracked as threat indicators
+    pub fn indicator_type(&self) -> Option<IndicatorType> {
+        match self {
+            IocKind::Ipv4 | IocKind::Ipv6 => Some(IndicatorType::IpAddress),
+            IocKind::Cidr => Some(IndicatorType::NetworkRange),
+            IocKind::Domain => Some(IndicatorType::Domain),
+            IocKind::Url => Some(IndicatorType::Url),
+            IocKind::Email => Some(IndicatorType::Email),
+            IocKind::Md5 | IocKind::Sha1 | IocKind::Sha256 | IocKind::Sha512 | IocKind::Ssdeep => Some(IndicatorType::Hash),
+            IocKind::RegistryKey => Some(IndicatorType::Registry),
+            IocKind::FilePath => Some(IndicatorType::Filename),
+            IocKind::Mutex => Some(IndicatorType::Mutex),
+            IocKind::Bitcoin | IocKind::Monero | IocKind::Ethereum => Some(IndicatorType::CryptocurrencyAddress),
+            IocKind::Cve | IocKind::AttackTechnique | IocKind::AttackTactic | IocKind::Asn | IocKind::PhoneNumber => None,
+        }
+    }
+}
+
+/// Indicator found in text
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExtractedIoc {
+    pub kind: IocKind,
+    /// Value in canonical form
+    pub value: String,
+    /// Byte range of the match in the text as written
+    pub start: usize,
+    pub end: usize,
+    /// Whether the text wrote the indicator in defanged form
+    pub defanged: bool,
+}
+
+impl ExtractedIoc {
+    fn new(kind: IocKind, value: impl Into<String>, start: usize, end: usize) -> Self {
+        Self { kind, value: value.into(), start, end, defanged: false }
+    }
+}
+
+fn regex(cell: &'static OnceLock<Regex>, pattern: &str) -> &'static Regex {
+    cell.get_or_init(|| Regex::new(pattern).expect("IOC pattern is valid"))
+}
+
+/// Find every indicator in `text`, in order of appearance and without overlaps
+///
+/// Defanged indicators are refanged first. Containing notations win over the ones they
+/// contain, so a URL is not also reported as its domain and a file path not as a
+/// domain-like file name.
+pub fn extract_iocs(text: &str) -> Vec<ExtractedIoc> {
+    let refanged = refang(text);
+    let mut found = extract_refanged(&refanged.text);
+    if refanged.is_changed() {
+        for ioc in &mut found {
+            let (start, end) = refanged.original_span(ioc.start, ioc.end);
+            ioc.defanged = text[start..end] != refanged.text[ioc.start..ioc.end];
+            (ioc.start, ioc.end) = (start, end);
+        }
+    }
+    found
+}
+
+fn extract_refanged(text: &str) -> Vec<ExtractedIoc> {
+    let mut found: Vec<ExtractedIoc> = Vec::new();
+    let extractors: [fn(&str) -> Vec<ExtractedIoc>; 15] = [
+        extract_urls,
+        extract_emails,
+        extract_registry_keys,
+        extract_file_paths,
+        extract_mutexes,
+        extract_ipv6,
+        extract_ipv4,
+        extract_crypto_addresses,
+        extract_hashes,
+        extract_ssdeep,
+        extract_cves,
+        extract_attack_ids,
+        extract_asns,
+        extract_phone_numbers,
+        extract_domains,
+    ];
+
+    for extractor in extractors {
+        for candidate in extractor(text) {
+            if !found.iter().any(|f| candidate.start < f.end && f.start < candidate.end) {
+                found.push(candidate);
+            }
+        }
+    }
+
+    found.sort_by_key(|ioc| ioc.start);
+    found
+}
+
+/// Whether the match is not glued to surrounding word characters or the given separators
+fn is_standalone(text: &str, start: usize, end: usize, separators: &[char]) -> bool {
+    let glued = |c: char| c.is_alphanumeric() || c == '_' || separators.contains(&c);
+    let before = text[..start].chars().next_back();
+    let after = text[end..].chars().next();
+    let after_next = text[end..].chars().nth(1);
+
+    // A sentence may end right after the match
+    let glued_after = match after {
+        Some(c) if separators.contains(&c) => after_next.is_some_and(|n| n.is_alphanumeric()),
+        Some(c) => glued(c),
+        None => false,
+    };
+    !before.is_some_and(glued) && !glued_after
+}
+
+/// Shrink a match so it does not end in sentence punctuation or an unbalanced bracket
+fn trim_trailing(text: &str, start: usize, mut end: usize) -> usize {
+    while let Some(c) = text[start..end].chars().next_back() {
+        let unbalanced = c == ')' && text[start..end].matches('(').count() >= text[start..end].matches(')').count();
+        if !TRAILING_PUNCTUATION.contains(&c) || unbalanced {
+            break;
+        }
+        end -= c.len_utf8();
+    }
+    end
+}
+
+fn extract_urls(text: &str) -> Vec<ExtractedIoc> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = regex(&PATTERN, r#"(?i)\b(?:https?|ftp|sftp)://[^\s<>"'`{}|\\^]+"#);
+
+    pattern.find_iter(text)
+        .filter_map(|m| {
+            let end = trim_trailing(text, m.start(), m.end());
+            let value = &text[m.start()..end];
+            url::Url::parse(value).ok()?.host()?;
+            Some(ExtractedIoc::new(IocKind::Url, value, m.start(), end))
+        })
+        .collect()
+}
+
+fn extract_emails(text: &str) -> Vec<ExtractedIoc> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = regex(&PATTERN, r"(?i)\b[a-z0-9._%+-]+@(?:[a-z0-9](?:[a-z0-9-]{0,61}[a-z0-9])?\.)+[a-z]{2,63}\b");
+
+    pattern.find_iter(text)
+        .map(|m| ExtractedIoc::new(IocKind::Email, m.as_str().to_lowercase(), m.start(), m.end()))
+        .collect()
+}
+
+fn extract_domains(text: &str) -> Vec<ExtractedIoc> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = regex(&PATTERN, r"(?i)\b(?:[a-z0-9](?:[a-z0-9-]{0,61}[a-z0-9])?\.)+[a-z]{2,63}\b");
+
+    pattern.find_iter(text)
+        .filter(|m| is_standalone(text, m.start(), m.end(), &['.', '-', '@']))
+        .map(|m| ExtractedIoc::new(IocKind::Domain, m.as_str().to_lowercase(), m.start(), m.end()))
+        .collect()
+}
+
+/// IPv4 addresses, or CIDR blocks when followed by a valid prefix length
+fn extract_ipv4(text: &str) -> Vec<ExtractedIoc> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = regex(&PATTERN, r"\b(?:\d{1,3}\.){3}\d{1,3}(?:/(\d{1,2}))?");
+
+    pattern.captures_iter(text)
+        .filter_map(|caps| {
+            let m = caps.get(0)?;
+            let address_end = caps.get(1).map_or(m.end(), |prefix| prefix.start() - 1);
+            let address: Ipv4Addr = text[m.start()..address_end].parse().ok()?;
+            match caps.get(1).and_then(|prefix| prefix.as_str().parse::<u8>().ok()).filter(|p| *p <= 32) {
+                Some(prefix) if is_standalone(text, m.start(), m.end(), &['.', '/']) => {
+                    Some(ExtractedIoc::new(IocKind::Cidr, format!("{}/{}", address, prefix), m.start(), m.end()))
+                }
+                _ => is_standalone(text, m.start(), address_end, &['.'])
+                    .then(|| ExtractedIoc::new(IocKind::Ipv4, address.to_string(), m.start(), address_end)),
+            }
+        })
+        .collect()
+}
+
+/// IPv6 addresses in any compressed form, or CIDR blocks with a prefix length
+fn extract_ipv6(text: &str) -> Vec<ExtractedIoc> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = regex(&PATTERN, r"(?i)(?:[0-9a-f]{0,4}:){2,7}(?:(?:\d{1,3}\.){3}\d{1,3}|[0-9a-f]{0,4})(?:/(\d{1,3}))?");
+
+    pattern.captures_iter(text)
+        .filter_map(|caps| {
+            let m = caps.get(0)?;
+            let address_end = caps.get(1).map_or(m.end(), |prefix| prefix.start() - 1);
+            let candidate = &text[m.start()..address_end];
+            if !candidate.chars().any(|c| c.is_ascii_hexdigit()) {
+                return None;
+            }
+            let address: Ipv6Addr = candidate.parse().ok()?;
+            match caps.get(1).and_then(|prefix| prefix.as_str().parse::<u8>().ok()).filter(|p| *p <= 128) {
+                Some(prefix) => is_standalone(text, m.start(), m.end(), &[':', '.', '/'])
+                    .then(|| ExtractedIoc::new(IocKind::Cidr, format!("{}/{}", address, prefix), m.start(), m.end())),
+                None => is_standalone(text, m.start(), address_end, &[':', '.', '/'])
+                    .then(|| ExtractedIoc::new(IocKind::Ipv6, address.to_string(), m.start(), address_end)),
+            }
+        })
+        .collect()
+}
+
+fn extract_hashes(text: &str) -> Vec<ExtractedIoc> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = regex(&PATTERN, r"\b[0-9a-fA-F]{32,128}\b");
+
+    pattern.find_iter(text)
+        .filter_map(|m| {
+            let kind = match m.len() {
+                32 => IocKind::Md5,
+                40 => IocKind::Sha1,
+                64 => IocKind::Sha256,
+                128 => IocKind::Sha512,
+                _ => return None,
+            };
+            Some(ExtractedIoc::new(kind, m.as_str().to_lowercase(), m.start(), m.end()))
+        })
+        .collect()
+}
+
+/// ssdeep fuzzy hashes, `blocksize:hash:hash` with a block size of 3 * 2^n
+fn extract_ssdeep(text: &str) -> Vec<ExtractedIoc> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = regex(&PATTERN, r"\b(\d{1,10}):([0-9A-Za-z/+]{1,64}):([0-9A-Za-z/+]{1,64})");
+
+    pattern.captures_iter(text)
+        .filter_map(|caps| {
+            let m = caps.get(0)?;
+            let block_size: u64 = caps[1].parse().ok()?;
+            let valid_block = block_size.is_multiple_of(3) && (block_size / 3).is_power_of_two();
+            // Times and ratios are all digits
+            let has_letters = |chunk: &str| chunk.chars().any(|c| !c.is_ascii_digit());
+            (valid_block && has_letters(&caps[2]) && has_letters(&caps[3]) && is_standalone(text, m.start(), m.end(), &[':']))
+                .then(|| ExtractedIoc::new(IocKind::Ssdeep, m.as_str(), m.start(), m.end()))
+        })
+        .collect()
+}
+
+fn extract_cves(text: &str) -> Vec<ExtractedIoc> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = regex(&PATTERN, r"(?i)\bCVE-\d{4}-\d{4,7}\b");
+
+    pattern.find_iter(text)
+        .map(|m| ExtractedIoc::new(IocKind::Cve, m.as_str().to_uppercase(), m.start(), m.end()))
+        .collect()
+}
+
+/// ATT&CK technique (`T1059`, `T1059.001`) and tactic (`TA0002`) IDs
+fn extract_attack_ids(text: &str) -> Vec<ExtractedIoc> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = regex(&PATTERN, r"\b(?:TA\d{4}|T\d{4}(?:\.\d{3})?)\b");
+
+    pattern.find_iter(text)
+        .filter(|m| is_standalone(text, m.start(), m.end(), &['.']))
+        .map(|m| {
+            let kind = if m.as_str().starts_with("TA") { IocKind::AttackTactic } else { IocKind::AttackTechnique };
+            ExtractedIoc::new(kind, m.as_str(), m.start(), m.end())
+        })
+        .collect()
+}
+
+fn extract_registry_keys(text: &str) -> Vec<ExtractedIoc> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = regex(&PATTERN, r#"(?i)\b(?:HKEY_LOCAL_MACHINE|HKEY_CURRENT_USER|HKEY_CLASSES_ROOT|HKEY_USERS|HKEY_CURRENT_CONFIG|HKLM|HKCU|HKCR|HKU|HKCC)\\[^\s"'<>|]+"#);
+
+    pattern.find_iter(text)
+        .map(|m| {
+            let end = trim_trailing(text, m.start(), m.end());
+            ExtractedIoc::new(IocKind::RegistryKey, &text[m.start()..end], m.start(), end)
+        })
+        .collect()
+}
+
+/// Windows drive, UNC and environment-variable paths, and Unix paths under well-known roots
+fn extract_file_paths(text: &str) -> Vec<ExtractedIoc> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = regex(&PATTERN, concat!(
+        r#"(?:\b[A-Za-z]:|%[A-Za-z_]+%|\\\\[A-Za-z0-9._$-]+)(?:\\[^\\/:*?"<>|\s]+)+"#,
+        r#"|(?:^|[\s"'(=])(/(?:bin|boot|dev|etc|home|lib|lib64|opt|proc|root|run|sbin|srv|sys|tmp|usr|var|Library|Users|Applications|System|private)(?:/[^\s"'<>|;]+)+)"#,
+    ));
+
+    pattern.captures_iter(text)
+        .filter_map(|caps| {
+            let m = caps.get(1).or_else(|| caps.get(0))?;
+            let end = trim_trailing(text, m.start(), m.end());
+            Some(ExtractedIoc::new(IocKind::FilePath, &text[m.start()..end], m.start(), end))
+        })
+        .collect()
+}
+
+/// Namespaced mutex names, or ones explicitly labelled as a mutex
+fn extract_mutexes(text: &str) -> Vec<ExtractedIoc> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = regex(&PATTERN, concat!(
+        r"\b(?:Global|Local|Session\\\d+)\\[A-Za-z0-9_.{}-]{3,}",
+        r#"|(?i:\bmutex(?:\s+name)?)(?:\s*[:=]\s*["']?|\s+["'])([A-Za-z0-9_.{}\\-]{3,})"#,
+    ));
+
+    pattern.captures_iter(text)
+        .filter_map(|caps| {
+            let m = caps.get(1).or_else(|| caps.get(0))?;
+            let end = trim_trailing(text, m.start(), m.end());
+            Some(ExtractedIoc::new(IocKind::Mutex, &text[m.start()..end], m.start(), end))
+        })
+        .collect()
+}
+
+/// Bitcoin (checksum-validated), Monero and Ethereum addresses
+fn extract_crypto_addresses(text: &str) -> Vec<ExtractedIoc> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = regex(&PATTERN, concat!(
+        r"\b(?:(0x[0-9a-fA-F]{40})",
+        r"|([48][0-9AB][1-9A-HJ-NP-Za-km-z]{93}(?:[1-9A-HJ-NP-Za-km-z]{11})?)",
+        r"|([13][1-9A-HJ-NP-Za-km-z]{25,34})",
+        r"|((?i:bc1[02-9ac-hj-np-z]{11,71})))\b",
+    ));
+
+    pattern.captures_iter(text)
+        .filter_map(|caps| {
+            let m = caps.get(0)?;
+            let (kind, value) = if caps.get(1).is_some() {
+                (IocKind::Ethereum, m.as_str().to_lowercase())
+            } else if caps.get(2).is_some() {
+                (IocKind::Monero, m.as_str().to_string())
+            } else if caps.get(3).is_some() {
+                is_base58check_address(m.as_str()).then_some(())?;
+                (IocKind::Bitcoin, m.as_str().to_string())
+            } else {
+                is_bech32_address(m.as_str()).then_some(())?;
+                (IocKind::Bitcoin, m.as_str().to_lowercase())
+            };
+            Some(ExtractedIoc::new(kind, value, m.start(), m.end()))
+        })
+        .collect()
+}
+
+/// Autonomous system numbers such as `AS13335` or `ASN 13335`
+fn extract_asns(text: &str) -> Vec<ExtractedIoc> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = regex(&PATTERN, r"\b(?:AS|ASN|[Aa]sn)[\s-]?(\d{1,10})\b");
+
+    pattern.captures_iter(text)
+        .filter_map(|caps| {
+            let m = caps.get(0)?;
+            let number: u32 = caps[1].parse().ok().filter(|n| *n > 0)?;
+            Some(ExtractedIoc::new(IocKind::Asn, format!("AS{}", number), m.start(), m.end()))
+        })
+        .collect()
+}
+
+/// International numbers with a `+` prefix and North American formatted numbers
+fn extract_phone_numbers(text: &str) -> Vec<ExtractedIoc> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = regex(&PATTERN, concat!(
+        r"\+\d{1,3}[\s.-]?(?:\(\d{1,4}\)[\s.-]?)?\d{1,4}(?:[\s.-]?\d{2,4}){1,4}",
+        r"|\(\d{3}\)\s?\d{3}[\s.-]\d{4}",
+        r"|\b\d{3}-\d{3}-\d{4}",
+    ));
+
+    pattern.find_iter(text)
+        .filter_map(|m| {
+            let digits: String = m.as_str().chars().filter(char::is_ascii_digit).collect();
+            if !(7..=15).contains(&digits.len()) || !is_standalone(text, m.start(), m.end(), &['.', '-']) {
+                return None;
+            }
+            let value = if m.as_str().starts_with('+') { format!("+{}", digits) } else { digits };
+            Some(ExtractedIoc::new(IocKind::PhoneNumber, value, m.start(), m.end()))
+        })
+        .collect()
+}
+
+fn base58_decode(encoded: &str) -> Option<Vec<u8>> {
+    // Little-endian base 256 accumulator
+    let mut bytes: Vec<u8> = Vec::new();
+    for c in encoded.bytes() {
+        let mut carry = BASE58_ALPHABET.iter().position(|&a| a == c)? as u32;
+        for byte in bytes.iter_mut() {
+            carry += *byte as u32 * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    let leading_zeros = encoded.bytes().take_while(|&c| c == b'1').count();
+    bytes.resize(bytes.len() + leading_zeros, 0);
+    bytes.reverse();
+    Some(bytes)
+}
+
+/// P2PKH or P2SH address with a valid double SHA-256 checksum
+fn is_base58check_address(address: &str) -> bool {
+    use ring::digest::{digest, SHA256};
+    let Some(bytes) = base58_decode(address) else {
+        return false;
+    };
+    if bytes.len() != 25 || !matches!(bytes[0], 0x00 | 0x05) {
+        return false;
+    }
+    let checksum = digest(&SHA256, digest(&SHA256, &bytes[..21]).as_ref());
+    checksum.as_ref()[..4] == bytes[21..]
+}
+
+/// Segwit address with a valid bech32 or bech32m checksum
+fn is_bech32_address(address: &str) -> bool {
+    const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+    const BECH32M_CONSTANT: u32 = 0x2bc830a3;
+
+    // Mixed case is invalid
+    if address.chars().any(|c| c.is_ascii_lowercase()) && address.chars().any(|c| c.is_ascii_uppercase()) {
+        return false;
+    }
+    let address = address.to_lowercase();
+    let Some((hrp, data)) = address.rsplit_once('1') else {
+        return false;
+    };
+
+    let mut values: Vec<u8> = hrp.bytes().map(|b| b >> 5).collect();
+    values.push(0);
+    values.extend(hrp.bytes().map(|b| b & 31));
+    for c in data.chars() {
+        match BECH32_ALPHABET.find(c) {
+            Some(value) => values.push(value as u8),
+            None => return false,
+        }
+    }
+
+    let mut checksum: u32 = 1;
+    for value in values {
+        let top = checksum >> 25;
+        checksum = ((checksum & 0x1ffffff) << 5) ^ value as u32;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum == 1 || checksum == BECH32M_CONSTANT
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(text: &str) -> Vec<(IocKind, String)> {
+        extract_iocs(text).into_iter().map(|ioc| (ioc.kind, ioc.value)).collect()
+    }
+
+    #[test]
+    fn test_extract_network_indicators() {
+        let text = "Beacon to https://cdn.evil-example.com/gate.php?id=1, then 203.0.113.7 and \
+                    2001:DB8::1 inside 198.51.100.0/24 and 2001:db8::/32. Mail from Ops@Evil-Example.com \
+                    via AS13335. Call +1 202-555-0147.";
+        assert_eq!(kinds(text), vec![
+            (IocKind::Url, "https://cdn.evil-example.com/gate.php?id=1".to_string()),
+            (IocKind::Ipv4, "203.0.113.7".to_string()),
+            (IocKind::Ipv6, "2001:db8::1".to_string()),
+            (IocKind::Cidr, "198.51.100.0/24".to_string()),
+            (IocKind::Cidr, "2001:db8::/32".to_string()),
+            (IocKind::Email, "ops@evil-example.com".to_string()),
+            (IocKind::Asn, "AS13335".to_string()),
+            (IocKind::PhoneNumber, "+12025550147".to_string()),
+        ]);
+        assert_eq!(IocKind::Cidr.indicator_type(), Some(IndicatorType::NetworkRange));
+    }
+
+    #[test]
+    fn test_extract_host_and_threat_indicators() {
+        let text = "Dropped C:\\Users\\Public\\svc.exe and /tmp/.x/run.sh, set \
+                    HKCU\\Software\\Microsoft\\Windows\\CurrentVersion\\Run\\svc, created mutex Global\\xQz91_lock. \
+                    md5 D41D8CD98F00B204E9800998ECF8427E sha1 da39a3ee5e6b4b0d3255bfef95601890afd80709 \
+                    ssdeep 3:AXGBicFlgVNhBGcL6wCrFQEv:AXGHsNhxLsr2C. Exploits CVE-2021-44228 via T1190 and T1059.001 (TA0002).";
+        assert_eq!(kinds(text), vec![
+            (IocKind::FilePath, "C:\\Users\\Public\\svc.exe".to_string()),
+            (IocKind::FilePath, "/tmp/.x/run.sh".to_string()),
+            (IocKind::RegistryKey, "HKCU\\Software\\Microsoft\\Windows\\CurrentVersion\\Run\\svc".to_string()),
+            (IocKind::Mutex, "Global\\xQz91_lock".to_string()),
+            (IocKind::Md5, "d41d8cd98f00b204e9800998ecf8427e".to_string()),
+            (IocKind::Sha1, "da39a3ee5e6b4b0d3255bfef95601890afd80709".to_string()),
+            (IocKind::Ssdeep, "3:AXGBicFlgVNhBGcL6wCrFQEv:AXGHsNhxLsr2C".to_string()),
+            (IocKind::Cve, "CVE-2021-44228".to_string()),
+            (IocKind::AttackTechnique, "T1190".to_string()),
+            (IocKind::AttackTechnique, "T1059.001".to_string()),
+            (IocKind::AttackTactic, "TA0002".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_extract_cryptocurrency_addresses() {
+        let text = "Pay 1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2 or bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq, \
+                    refunds to 0x52908400098527886E0F7030069857D2E4169EE7. \
+                    Not an address: 1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN3";
+        assert_eq!(kinds(text), vec![
+            (IocKind::Bitcoin, "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2".to_string()),
+            (IocKind::Bitcoin, "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq".to_string()),
+            (IocKind::Ethereum, "0x52908400098527886e0f7030069857d2e4169ee7".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_rejects_look_alikes() {
+        for text in [
+            "build 1.2.3.4.5 released at 10:30:45",
+            "ratio 12:30:45 and MAC 00:1a:2b:3c:4d:5e",
+            "see std::net for details",
+            "order 4-15 items such as 2024-01-15",
+        ] {
+            assert!(extract_iocs(text).is_empty(), "{}: {:?}", text, extract_iocs(text));
+        }
+    }
+}
@@ -0,0 +1,241 @@
+//! Refanging of obfuscated indicators in reports and defanging for output
+//!
+//! Threat reports write `hxxp://evil[.]com` or `1.2.3[.]4` so indicators cannot be clicked or
+//! resolved. Extraction refangs text first and maps matches back to the text as written;
+//! anything we emit into documents is defanged again.
+
+use crate::models::IntelReport;
+use super::ioc::{extract_iocs, IocKind};
+use regex::Regex;
+use std::ops::Range;
+use std::sync::OnceLock;
+
+/// Text with defanged notation restored, able to map positions back to the original
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefangedText {
+    pub text: String,
+    /// Replaced byte ranges in the refanged and original text, in order
+    replacements: Vec<(Range<usize>, Range<usize>)>,
+}
+
+impl RefangedText {
+    /// Whether any obfuscation was found
+    pub fn is_changed(&self) -> bool {
+        !self.replacements.is_empty()
+    }
+
+    /// Byte range in the original text of a range in the refanged text
+    ///
+    /// A range touching part of a replacement covers all of the original notation.
+    pub fn original_span(&self, start: usize, end: usize) -> (usize, usize) {
+        (self.original_position(start, false), self.original_position(end, true))
+    }
+
+    fn original_position(&self, position: usize, is_end: bool) -> usize {
+        let mut shift = 0isize;
+        for (refanged, original) in &self.replacements {
+            if position < refanged.start || (is_end && position == refanged.start) {
+                break;
+            }
+            if position < refanged.end || (is_end && position == refanged.end) {
+                return if is_end { original.end } else { original.start };
+            }
+            shift = original.end as isize - refanged.end as isize;
+        }
+        (position as isize + shift) as usize
+    }
+}
+
+fn refang_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(concat!(
+            r"(?i)(?P<dot>[ \t]?[\[({](?:\.|dot)[\])}][ \t]?)",
+            r"|(?P<at>[ \t]?[\[({](?:@|at)[\])}][ \t]?)",
+            r"|(?P<colon>[\[({]:(?P<slashes>//)?[\])}])",
+            r"|\b(?P<scheme>h(?:xx|\*\*)p(?P<secure>s)?|fxp)\b",
+        ))
+        .expect("refang pattern is valid")
+    })
+}
+
+/// Restore `[.]`, `(dot)`, `[@]`, `[:]`, `hxxp` and similar obfuscations
+pub fn refang(text: &str) -> RefangedText {
+    let mut refanged = String::with_capacity(text.len());
+    let mut replacements = Vec::new();
+    let mut last = 0;
+
+    for caps in refang_pattern().captures_iter(text) {
+        let m = caps.get(0).expect("group 0 always matches");
+        let replacement = if caps.name("dot").is_some() {
+            "."
+        } else if caps.name("at").is_some() {
+            "@"
+        } else if caps.name("colon").is_some() {
+            if caps.name("slashes").is_some() { "://" } else { ":" }
+        } else if m.as_str().eq_ignore_ascii_case("fxp") {
+            "ftp"
+        } else if caps.name("secure").is_some() {
+            "https"
+        } else {
+            "http"
+        };
+
+        refanged.push_str(&text[last..m.start()]);
+        let start = refanged.len();
+        refanged.push_str(replacement);
+        replacements.push((start..refanged.len(), m.range()));
+        last = m.end();
+    }
+    refanged.push_str(&text[last..]);
+
+    RefangedText { text: refanged, replacements }
+}
+
+/// Defanged form of an indicator value; kinds that cannot be followed are returned as is
+pub fn defang_ioc(kind: IocKind, value: &str) -> String {
+    match kind {
+        IocKind::Domain | IocKind::Ipv4 => value.replace('.', "[.]"),
+        IocKind::Ipv6 => value.replace(':', "[:]"),
+        IocKind::Cidr if value.contains(':') => value.replace(':', "[:]"),
+        IocKind::Cidr => value.replace('.', "[.]"),
+        IocKind::Email => match value.rsplit_once('@') {
+            Some((local, domain)) => format!("{}[@]{}", local, domain.replace('.', "[.]")),
+            None => value.to_string(),
+        },
+        IocKind::Url => defang_url(value),
+        _ => value.to_string(),
+    }
+}
+
+/// `hxxps://evil[.]example[.]com/path`: the scheme and host are defanged, the path is not
+fn defang_url(value: &str) -> String {
+    let Some((scheme, rest)) = value.split_once("://") else {
+        return value.to_string();
+    };
+    let scheme = match scheme.to_ascii_lowercase().as_str() {
+        "http" => "hxxp".to_string(),
+        "https" => "hxxps".to_string(),
+        "ftp" => "fxp".to_string(),
+        _ => scheme.to_string(),
+    };
+    let authority_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let (authority, path) = rest.split_at(authority_end);
+    // Bracketed IPv6 hosts are left alone, their brackets would be ambiguous
+    let authority = if authority.contains('[') { authority.to_string() } else { authority.replace('.', "[.]") };
+    format!("{}://{}{}", scheme, authority, path)
+}
+
+/// Defang every network indicator in free text, leaving everything else untouched
+///
+/// Indicators that were already defanged are rewritten in the canonical defanged form.
+pub fn defang(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    for ioc in extract_iocs(text) {
+        let defanged = defang_ioc(ioc.kind, &ioc.value);
+        if defanged == ioc.value {
+            continue;
+        }
+        out.push_str(&text[last..ioc.start]);
+        out.push_str(&defanged);
+        last = ioc.end;
+    }
+    out.push_str(&text[last..]);
+    out
+}
+
+/// Defang the strings anywhere in a JSON value
+pub fn defang_json(value: &serde_json::Value) -> serde_json::Value {
+    map_json_strings(value, &defang)
+}
+
+/// Refang the strings anywhere in a JSON value
+pub fn refang_json(value: &serde_json::Value) -> serde_json::Value {
+    map_json_strings(value, &|s| refang(s).text)
+}
+
+fn map_json_strings(value: &serde_json::Value, f: &dyn Fn(&str) -> String) -> serde_json::Value {
+    use serde_json::Value;
+    match value {
+        Value::String(s) => Value::String(f(s)),
+        Value::Array(items) => Value::Array(items.iter().map(|item| map_json_strings(item, f)).collect()),
+        Value::Object(map) => Value::Object(map.iter().map(|(k, v)| (k.clone(), map_json_strings(v, f))).collect()),
+        other => other.clone(),
+    }
+}
+
+/// Copy of a report safe to publish, with indicators in its text and tags defanged
+pub fn defang_report(report: &IntelReport) -> IntelReport {
+    IntelReport {
+        title: defang(&report.title),
+        summary: defang(&report.summary),
+        content: defang(&report.content),
+        tags: report.tags.iter().map(|tag| defang(tag)).collect(),
+        ..report.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refang_maps_spans_back() {
+        let text = "C2 at hxxps[:]//evil[.]example(dot)com/x and 1.2.3[.]4, mail bob[@]evil.example";
+        let refanged = refang(text);
+        assert_eq!(refanged.text, "C2 at https://evil.example.com/x and 1.2.3.4, mail bob@evil.example");
+
+        let start = refanged.text.find("1.2.3.4").unwrap();
+        let (original_start, original_end) = refanged.original_span(start, start + "1.2.3.4".len());
+        assert_eq!(&text[original_start..original_end], "1.2.3[.]4");
+
+        let iocs = extract_iocs(text);
+        let url = iocs.iter().find(|ioc| ioc.kind == IocKind::Url).unwrap();
+        assert_eq!(url.value, "https://evil.example.com/x");
+        assert_eq!(&text[url.start..url.end], "hxxps[:]//evil[.]example(dot)com/x");
+        assert!(url.defanged);
+        assert!(iocs.iter().any(|ioc| ioc.kind == IocKind::Email && ioc.value == "bob@evil.example"));
+        assert!(!refang("plain text, nothing here.").is_changed());
+    }
+
+    #[test]
+    fn test_defang_round_trips() {
+        let text = "Beacon https://cdn.evil.example/gate.php?a=1.2 from 203.0.113.7 and 2001:db8::1, \
+                    reply to ops@evil.example; hash d41d8cd98f00b204e9800998ecf8427e.";
+        let defanged = defang(text);
+        assert_eq!(defanged, "Beacon hxxps://cdn[.]evil[.]example/gate.php?a=1.2 from 203[.]0[.]113[.]7 and 2001[:]db8[:][:]1, \
+                              reply to ops[@]evil[.]example; hash d41d8cd98f00b204e9800998ecf8427e.");
+        assert!(extract_iocs(&defanged).iter().all(|ioc| ioc.kind == IocKind::Md5 || ioc.defanged));
+        assert_eq!(refang(&defanged).text, text);
+        // Defanging is idempotent
+        assert_eq!(defang(&defanged), defanged);
+        // Escaped dots in paths and patterns are not obfuscation
+        assert!(!refang(r"C:\Users\.ssh and /evil\.example/").is_changed());
+    }
+
+    #[test]
+    fn test_published_reports_are_defanged() {
+        let report = IntelReport {
+            id: uuid::Uuid::new_v4(),
+            title: "Loader C2 on 203.0.113.7".to_string(),
+            summary: "Beacons to https://cdn.evil.example/gate.php".to_string(),
+            content: "Contact ops@evil.example for samples.".to_string(),
+            classification: crate::models::Classification::Unclassified,
+            analyst_id: uuid::Uuid::new_v4(),
+            session_id: None,
+            created_at: chrono::Utc::now(),
+            published_at: None,
+            tags: vec!["evil.example".to_string()],
+            entities_referenced: Vec::new(),
+            indicators_referenced: Vec::new(),
+        };
+
+        let markdown = report.to_markdown();
+        assert!(markdown.starts_with("# Loader C2 on 203[.]0[.]113[.]7\n"));
+        assert!(markdown.contains("hxxps://cdn[.]evil[.]example/gate.php"));
+        assert!(markdown.contains("ops[@]evil[.]example"));
+        assert!(markdown.contains("**Tags:** evil[.]example"));
+        assert!(!markdown.contains("203.0.113.7") && !markdown.contains("https://"));
+    }
+}
@@ -0,0 +1,512 @@
+//! Validation of extracted indicators to suppress false positives
+//!
+//! Matches are checked against real address parsing, the public suffix list and file
+//! extension heuristics, then against allow-lists of well-known and our own infrastructure.
+//! Benign matches are dropped or have their confidence reduced. A snapshot of the public
+//! suffix list ships in `data/`; refresh it from publicsuffix.org or load a newer list.
+
+use crate::{Result, Error};
+use super::ioc::{ExtractedIoc, IocKind};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+/// Snapshot of https://publicsuffix.org/list/public_suffix_list.dat
+const BUNDLED_SUFFIX_LIST: &str = include_str!("../../data/public_suffix_list.dat");
+
+/// Extensions that make a dotted name more likely a file than a domain
+const FILE_EXTENSIONS: &[&str] = &[
+    "exe", "dll", "sys", "scr", "bat", "cmd", "ps1", "psm1", "vbs", "js", "jse", "wsf", "hta", "lnk",
+    "jar", "msi", "apk", "dmg", "elf", "bin", "dat", "tmp", "log", "ini", "cfg", "conf",
+    "doc", "docx", "docm", "xls", "xlsx", "xlsm", "ppt", "pptx", "pdf", "rtf", "txt", "csv",
+    "json", "xml", "yml", "yaml", "html", "htm", "php", "asp", "aspx", "jsp", "py", "sh", "md",
+    "zip", "rar", "7z", "gz", "tar", "iso", "img", "png", "jpg", "jpeg", "gif", "bmp", "mov",
+];
+
+/// Widely used services that are rarely indicators themselves
+const DEFAULT_ALLOW_DOMAINS: &[&str] = &[
+    "google.com", "googleapis.com", "gstatic.com", "microsoft.com", "windows.com", "windowsupdate.com",
+    "office.com", "live.com", "apple.com", "icloud.com", "amazon.com", "amazonaws.com", "cloudflare.com",
+    "akamai.net", "facebook.com", "twitter.com", "linkedin.com", "youtube.com", "wikipedia.org",
+    "github.com", "mozilla.org", "w3.org", "schema.org", "digicert.com", "letsencrypt.org",
+];
+
+/// Range an IP address belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum IpScope {
+    Public,
+    /// RFC 1918 and IPv6 unique local addresses
+    Private,
+    Loopback,
+    LinkLocal,
+    /// RFC 5737 and RFC 3849 documentation ranges
+    Documentation,
+    /// RFC 6598 carrier-grade NAT space
+    SharedAddress,
+    Benchmarking,
+    Multicast,
+    Unspecified,
+    Reserved,
+}
+
+impl IpScope {
+    pub fn is_public(&self) -> bool {
+        *self == IpScope::Public
+    }
+
+    /// Tag recorded on non-public matches
+    pub fn tag(&self) -> &'static str {
+        match self {
+            IpScope::Public => "ip:public",
+            IpScope::Private => "ip:private",
+            IpScope::Loopback => "ip:loopback",
+            IpScope::LinkLocal => "ip:link_local",
+            IpScope::Documentation => "ip:documentation",
+            IpScope::SharedAddress => "ip:shared_address",
+            IpScope::Benchmarking => "ip:benchmarking",
+            IpScope::Multicast => "ip:multicast",
+            IpScope::Unspecified => "ip:unspecified",
+            IpScope::Reserved => "ip:reserved",
+        }
+    }
+}
+
+/// Classify an address by the special-purpose range it falls in
+pub fn ip_scope(ip: &IpAddr) -> IpScope {
+    match ip {
+        IpAddr::V4(v4) => ipv4_scope(v4),
+        IpAddr::V6(v6) => ipv6_scope(v6),
+    }
+}
+
+fn ipv4_scope(ip: &Ipv4Addr) -> IpScope {
+    let in_range = |network: [u8; 4], prefix: u8| prefix_matches(u32::from(*ip) as u128, u32::from(Ipv4Addr::from(network)) as u128, prefix, 32);
+    if ip.is_unspecified() {
+        IpScope::Unspecified
+    } else if in_range([10, 0, 0, 0], 8) || in_range([172, 16, 0, 0], 12) || in_range([192, 168, 0, 0], 16) {
+        IpScope::Private
+    } else if in_range([127, 0, 0, 0], 8) {
+        IpScope::Loopback
+    } else if in_range([169, 254, 0, 0], 16) {
+        IpScope::LinkLocal
+    } else if in_range([192, 0, 2, 0], 24) || in_range([198, 51, 100, 0], 24) || in_range([203, 0, 113, 0], 24) {
+        IpScope::Documentation
+    } else if in_range([100, 64, 0, 0], 10) {
+        IpScope::SharedAddress
+    } else if in_range([198, 18, 0, 0], 15) {
+        IpScope::Benchmarking
+    } else if in_range([224, 0, 0, 0], 4) {
+        IpScope::Multicast
+    } else if in_range([0, 0, 0, 0], 8) || in_range([192, 0, 0, 0], 24) || in_range([240, 0, 0, 0], 4) {
+        IpScope::Reserved
+    } else {
+        IpScope::Public
+    }
+}
+
+fn ipv6_scope(ip: &Ipv6Addr) -> IpScope {
+    if let Some(v4) = ip.to_ipv4_mapped() {
+        return ipv4_scope(&v4);
+    }
+    let in_range = |network: &str, prefix: u8| {
+        let network: Ipv6Addr = network.parse().expect("range constant is valid");
+        prefix_matches(u128::from(*ip), u128::from(network), prefix, 128)
+    };
+    if ip.is_unspecified() {
+        IpScope::Unspecified
+    } else if ip.is_loopback() {
+        IpScope::Loopback
+    } else if in_range("fc00::", 7) {
+        IpScope::Private
+    } else if in_range("fe80::", 10) {
+        IpScope::LinkLocal
+    } else if in_range("2001:db8::", 32) || in_range("3fff::", 20) {
+        IpScope::Documentation
+    } else if in_range("2001:2::", 48) {
+        IpScope::Benchmarking
+    } else if in_range("ff00::", 8) {
+        IpScope::Multicast
+    } else if in_range("100::", 64) || in_range("::", 96) {
+        IpScope::Reserved
+    } else {
+        IpScope::Public
+    }
+}
+
+fn prefix_matches(address: u128, network: u128, prefix: u8, bits: u8) -> bool {
+    if prefix == 0 {
+        return true;
+    }
+    let shift = (bits - prefix) as u32;
+    address >> shift == network >> shift
+}
+
+/// Parse `address` or `address/prefix` into a network address and prefix length
+fn parse_network(value: &str) -> Option<(IpAddr, u8)> {
+    let (address, prefix) = match value.trim().split_once('/') {
+        Some((address, prefix)) => (address.parse::<IpAddr>().ok()?, Some(prefix.parse::<u8>().ok()?)),
+        None => (value.trim().parse::<IpAddr>().ok()?, None),
+    };
+    let bits = if address.is_ipv4() { 32 } else { 128 };
+    let prefix = prefix.unwrap_or(bits);
+    (prefix <= bits).then_some((address, prefix))
+}
+
+fn network_contains(network: &(IpAddr, u8), ip: &IpAddr) -> bool {
+    match (network.0, ip) {
+        (IpAddr::V4(net), IpAddr::V4(ip)) => prefix_matches(u32::from(*ip) as u128, u32::from(net) as u128, network.1, 32),
+        (IpAddr::V6(net), IpAddr::V6(ip)) => prefix_matches(u128::from(*ip), u128::from(net), network.1, 128),
+        _ => false,
+    }
+}
+
+/// Rules of the public suffix list (publicsuffix.org format)
+#[derive(Debug, Clone, Default)]
+pub struct PublicSuffixList {
+    rules: HashSet<String>,
+    /// Parents of `*.` rules
+    wildcards: HashSet<String>,
+    /// `!` rules without the marker
+    exceptions: HashSet<String>,
+}
+
+impl PublicSuffixList {
+    /// The snapshot shipped with the crate, parsed once
+    pub fn bundled() -> Arc<Self> {
+        static BUNDLED: OnceLock<Arc<PublicSuffixList>> = OnceLock::new();
+        BUNDLED.get_or_init(|| Arc::new(Self::parse(BUNDLED_SUFFIX_LIST))).clone()
+    }
+
+    /// Load a list such as `public_suffix_list.dat`
+    pub fn load_file(path: impl AsRef<Path>) -> Result<Self> {
+        let content = std::fs::read_to_string(path.as_ref())?;
+        Ok(Self::parse(&content))
+    }
+
+    /// Parse list content; comments and blank lines are skipped, as is anything after a rule
+    pub fn parse(content: &str) -> Self {
+        let mut list = Self::default();
+        for line in content.lines() {
+            let Some(rule) = line.split_whitespace().next() else {
+                continue;
+            };
+            if rule.starts_with("//") {
+                continue;
+            }
+            let rule = rule.to_lowercase();
+            if let Some(exception) = rule.strip_prefix('!') {
+                list.exceptions.insert(exception.to_string());
+            } else if let Some(parent) = rule.strip_prefix("*.") {
+                list.wildcards.insert(parent.to_string());
+            } else {
+                list.rules.insert(rule);
+            }
+        }
+        list
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len() + self.wildcards.len() + self.exceptions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Longest listed suffix of a lowercase domain, or `None` when no rule covers its TLD
+    pub fn public_suffix<'a>(&self, domain: &'a str) -> Option<&'a str> {
+        let domain = domain.trim_end_matches('.');
+        let mut candidate = domain;
+        loop {
+            let parent = candidate.split_once('.').map(|(_, parent)| parent);
+            if self.exceptions.contains(candidate) {
+                return parent;
+            }
+            if self.rules.contains(candidate) || parent.is_some_and(|p| self.wildcards.contains(p)) {
+                return Some(candidate);
+            }
+            candidate = parent?;
+        }
+    }
+
+    /// The public suffix plus one label, the part a registrant controls
+    pub fn registrable_domain<'a>(&self, domain: &'a str) -> Option<&'a str> {
+        let domain = domain.trim_end_matches('.');
+        let suffix = self.public_suffix(domain)?;
+        let prefix = domain.strip_suffix(suffix)?.strip_suffix('.')?;
+        let start = prefix.rfind('.').map_or(0, |i| i + 1);
+        Some(&domain[start..])
+    }
+}
+
+/// Allow-lists and handling of benign matches
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationConfig {
+    /// Benign domains; their subdomains match too
+    pub allow_domains: Vec<String>,
+    /// Our own infrastructure as addresses or CIDR blocks
+    pub allow_networks: Vec<String>,
+    /// Drop allow-listed matches rather than down-weight them
+    pub drop_allowed: bool,
+    /// Drop private, loopback, documentation and other non-public addresses
+    pub drop_non_public_ips: bool,
+    /// Confidence multiplier for down-weighted matches
+    pub benign_weight: f32,
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        Self {
+            allow_domains: DEFAULT_ALLOW_DOMAINS.iter().map(|d| d.to_string()).collect(),
+            allow_networks: Vec::new(),
+            drop_allowed: false,
+            drop_non_public_ips: false,
+            benign_weight: 0.3,
+        }
+    }
+}
+
+/// Outcome of validating an extracted indicator
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Validation {
+    /// False for matches that should not become entities or indicators
+    pub keep: bool,
+    /// Confidence multiplier, below 1 for benign-looking matches
+    pub weight: f32,
+    /// Why the match was dropped or down-weighted, e.g. `ip:private` or `allowlisted`
+    pub tags: Vec<String>,
+}
+
+impl Validation {
+    fn accept() -> Self {
+        Self { keep: true, weight: 1.0, tags: Vec::new() }
+    }
+
+    fn reject(&mut self, tag: &str) {
+        self.keep = false;
+        self.tags.push(tag.to_string());
+    }
+
+    fn down_weight(&mut self, weight: f32, tag: &str) {
+        self.weight *= weight;
+        self.tags.push(tag.to_string());
+    }
+}
+
+/// Validation stage run on extracted indicators
+#[derive(Debug, Clone)]
+pub struct IocValidator {
+    config: ValidationConfig,
+    allow_domains: HashSet<String>,
+    allow_networks: Vec<(IpAddr, u8)>,
+    suffixes: Option<Arc<PublicSuffixList>>,
+}
+
+impl IocValidator {
+    pub fn new(config: ValidationConfig) -> Result<Self> {
+        let allow_networks = config.allow_networks.iter()
+            .map(|network| parse_network(network).ok_or_else(|| Error::Configuration(format!("Invalid allow-listed network: {}", network))))
+            .collect::<Result<Vec<_>>>()?;
+        let allow_domains = config.allow_domains.iter().map(|d| d.trim_end_matches('.').to_lowercase()).collect();
+        Ok(Self { config, allow_domains, allow_networks, suffixes: Some(PublicSuffixList::bundled()) })
+    }
+
+    /// Check domains against this list instead of the bundled snapshot
+    pub fn with_public_suffixes(mut self, suffixes: PublicSuffixList) -> Self {
+        self.suffixes = Some(Arc::new(suffixes));
+        self
+    }
+
+    /// Skip public suffix checks; file extensions then only down-weight matches
+    pub fn without_public_suffixes(mut self) -> Self {
+        self.suffixes = None;
+        self
+    }
+
+    pub fn config(&self) -> &ValidationConfig {
+        &self.config
+    }
+
+    /// Decide whether an extracted indicator is kept and how far it is trusted
+    pub fn validate(&self, ioc: &ExtractedIoc) -> Validation {
+        let mut validation = Validation::accept();
+        match ioc.kind {
+            IocKind::Ipv4 | IocKind::Ipv6 | IocKind::Cidr => match parse_network(&ioc.value) {
+                Some((ip, _)) => self.check_ip(&ip, &mut validation),
+                None => validation.reject("ip:invalid"),
+            },
+            IocKind::Domain => {
+                self.check_file_extension(&ioc.value, &mut validation);
+                self.check_domain(&ioc.value, &mut validation);
+            }
+            IocKind::Email => {
+                if let Some((_, domain)) = ioc.value.rsplit_once('@') {
+                    self.check_suffix(domain, &mut validation);
+                }
+            }
+            IocKind::Url => match url::Url::parse(&ioc.value).ok().and_then(|url| url.host().map(|h| h.to_owned())) {
+                Some(url::Host::Domain(domain)) => self.check_domain(&domain, &mut validation),
+                Some(url::Host::Ipv4(ip)) => self.check_ip(&IpAddr::V4(ip), &mut validation),
+                Some(url::Host::Ipv6(ip)) => self.check_ip(&IpAddr::V6(ip), &mut validation),
+                None => validation.reject("url:invalid"),
+            },
+            _ => {}
+        }
+        validation
+    }
+
+    fn allowed(&self, validation: &mut Validation) {
+        if self.config.drop_allowed {
+            validation.reject("allowlisted");
+        } else {
+            validation.down_weight(self.config.benign_weight, "allowlisted");
+        }
+    }
+
+    fn check_ip(&self, ip: &IpAddr, validation: &mut Validation) {
+        let scope = ip_scope(ip);
+        if !scope.is_public() {
+            if self.config.drop_non_public_ips {
+                validation.reject(scope.tag());
+            } else {
+                validation.down_weight(self.config.benign_weight, scope.tag());
+            }
+        }
+        if self.allow_networks.iter().any(|network| network_contains(network, ip)) {
+            self.allowed(validation);
+        }
+    }
+
+    fn check_domain(&self, domain: &str, validation: &mut Validation) {
+        let domain = domain.trim_end_matches('.').to_lowercase();
+        self.check_suffix(&domain, validation);
+        let allowed = self.allow_domains.iter().any(|allowed| {
+            domain == *allowed || domain.strip_suffix(allowed.as_str()).is_some_and(|prefix| prefix.ends_with('.'))
+        });
+        if allowed {
+            self.allowed(validation);
+        }
+    }
+
+    /// Reject domains whose TLD is not listed, or which are a public suffix themselves
+    fn check_suffix(&self, domain: &str, validation: &mut Validation) {
+        let Some(suffixes) = &self.suffixes else {
+            return;
+        };
+        let domain = domain.trim_end_matches('.').to_lowercase();
+        match suffixes.public_suffix(&domain) {
+            None => validation.reject("domain:unknown_suffix"),
+            Some(suffix) if suffix == domain => validation.reject("domain:public_suffix"),
+            Some(_) => {}
+        }
+    }
+
+    /// `report.pdf` is a file; `invoice.zip` may be either, as `zip` is also a TLD.
+    /// Without a suffix list there is no telling, so matches are only down-weighted.
+    fn check_file_extension(&self, domain: &str, validation: &mut Validation) {
+        let Some((_, extension)) = domain.rsplit_once('.') else {
+            return;
+        };
+        if !FILE_EXTENSIONS.contains(&extension) {
+            return;
+        }
+        let may_be_tld = self.suffixes.as_ref().is_none_or(|s| s.public_suffix(extension).is_some());
+        if may_be_tld {
+            validation.down_weight(self.config.benign_weight, "file_extension");
+        } else {
+            validation.reject("file_extension");
+        }
+    }
+}
+
+impl Default for IocValidator {
+    fn default() -> Self {
+        Self::new(ValidationConfig::default()).expect("default allow-lists are valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn suffixes() -> PublicSuffixList {
+        PublicSuffixList::load_file(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/psl/public_suffix_list.dat")).unwrap()
+    }
+
+    fn ioc(kind: IocKind, value: &str) -> ExtractedIoc {
+        ExtractedIoc { kind, value: value.to_string(), start: 0, end: value.len(), defanged: false }
+    }
+
+    #[test]
+    fn test_ip_scopes() {
+        let scope = |ip: &str| ip_scope(&ip.parse().unwrap());
+        assert_eq!(scope("192.168.1.100"), IpScope::Private);
+        assert_eq!(scope("172.31.255.1"), IpScope::Private);
+        assert_eq!(scope("172.32.0.1"), IpScope::Public);
+        assert_eq!(scope("127.0.0.1"), IpScope::Loopback);
+        assert_eq!(scope("203.0.113.7"), IpScope::Documentation);
+        assert_eq!(scope("100.100.1.1"), IpScope::SharedAddress);
+        assert_eq!(scope("8.8.8.8"), IpScope::Public);
+        assert_eq!(scope("fd12::1"), IpScope::Private);
+        assert_eq!(scope("2001:db8::1"), IpScope::Documentation);
+        assert_eq!(scope("::ffff:10.1.2.3"), IpScope::Private);
+        assert_eq!(scope("2606:4700::1111"), IpScope::Public);
+    }
+
+    #[test]
+    fn test_public_suffix_rules() {
+        let list = suffixes();
+        assert_eq!(list.public_suffix("evil.co.uk"), Some("co.uk"));
+        assert_eq!(list.registrable_domain("cdn.evil.co.uk"), Some("evil.co.uk"));
+        // Wildcard and exception rules
+        assert_eq!(list.public_suffix("a.b.kawasaki.jp"), Some("b.kawasaki.jp"));
+        assert_eq!(list.registrable_domain("www.city.kawasaki.jp"), Some("city.kawasaki.jp"));
+        assert_eq!(list.registrable_domain("user.github.io"), Some("user.github.io"));
+        assert_eq!(list.public_suffix("report.pdf"), None);
+    }
+
+    #[test]
+    fn test_validator_verdicts() {
+        let validator = IocValidator::new(ValidationConfig {
+            allow_networks: vec!["198.51.100.0/24".to_string()],
+            ..ValidationConfig::default()
+        }).unwrap().with_public_suffixes(suffixes());
+
+        assert!(!validator.validate(&ioc(IocKind::Domain, "report.pdf")).keep);
+        assert!(!validator.validate(&ioc(IocKind::Domain, "co.uk")).keep);
+        assert_eq!(validator.validate(&ioc(IocKind::Domain, "malware.evil.com")), Validation::accept());
+
+        let zip = validator.validate(&ioc(IocKind::Domain, "invoice.zip"));
+        assert!(zip.keep && zip.weight < 1.0);
+
+        let google = validator.validate(&ioc(IocKind::Url, "https://www.google.com/search?q=x"));
+        assert_eq!((google.keep, google.tags), (true, vec!["allowlisted".to_string()]));
+
+        let private = validator.validate(&ioc(IocKind::Ipv4, "192.168.1.100"));
+        assert_eq!((private.keep, private.weight, private.tags), (true, 0.3, vec!["ip:private".to_string()]));
+
+        let own = validator.validate(&ioc(IocKind::Ipv4, "198.51.100.20"));
+        assert_eq!(own.tags, vec!["ip:documentation", "allowlisted"]);
+
+        assert!(IocValidator::new(ValidationConfig { allow_networks: vec!["10.0.0.0/40".to_string()], ..Default::default() }).is_err());
+    }
+
+    #[test]
+    fn test_bundled_suffixes_by_default() {
+        let validator = IocValidator::default();
+        assert!(!validator.validate(&ioc(IocKind::Domain, "report.pdf")).keep);
+        assert!(!validator.validate(&ioc(IocKind::Domain, "payload.notatld")).keep);
+        let zip = validator.validate(&ioc(IocKind::Domain, "microsoft-update.zip"));
+        assert!(zip.keep && zip.weight < 1.0);
+        assert_eq!(PublicSuffixList::bundled().registrable_domain("cdn.evil.co.uk"), Some("evil.co.uk"));
+
+        // Without a list, extensions that may be TLDs are kept but down-weighted
+        let validator = IocValidator::default().without_public_suffixes();
+        for name in ["microsoft-update.zip", "report.pdf", "setup.sh"] {
+            let validation = validator.validate(&ioc(IocKind::Domain, name));
+            assert_eq!((validation.keep, validation.tags), (true, vec!["file_extension".to_string()]));
+        }
+    }
+}
@@ -9,6 +9,9 @@ pub mod threat_intel;
 pub mod geo_intel;
 pub mod network_intel;
 pub mod ml_analysis;
+pub mod enrichment;
+pub mod mitre;
+pub mod rules;
 pub mod models;
 pub mod error;
 
@@ -0,0 +1,427 @@
+//! MITRE ATT&CK knowledge base loaded from the Enterprise STIX bundle
+
+use crate::{Result, Error, models::*};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+use uuid::Uuid;
+
+const ATTACK_SOURCE: &str = "mitre-attack";
+const NAVIGATOR_VERSION: &str = "4.9.1";
+const LAYER_VERSION: &str = "4.5";
+
+/// ATT&CK tactic
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttackTactic {
+    pub id: String,
+    pub name: String,
+    /// Kill chain phase name used by techniques, e.g. `initial-access`
+    pub shortname: String,
+}
+
+/// ATT&CK technique or sub-technique
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttackTechnique {
+    pub id: String,
+    pub name: String,
+    /// Tactic IDs this technique belongs to
+    pub tactics: Vec<String>,
+    pub platforms: Vec<String>,
+    pub parent: Option<String>,
+    pub subtechniques: Vec<String>,
+    pub deprecated: bool,
+}
+
+impl AttackTechnique {
+    pub fn is_subtechnique(&self) -> bool {
+        self.parent.is_some()
+    }
+}
+
+/// Result of validating an indicator's ATT&CK references
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AttackValidation {
+    pub unknown_tactics: Vec<String>,
+    pub unknown_techniques: Vec<String>,
+    /// Tactic IDs added because a listed technique belongs to them
+    pub inferred_tactics: Vec<String>,
+}
+
+impl AttackValidation {
+    pub fn is_valid(&self) -> bool {
+        self.unknown_tactics.is_empty() && self.unknown_techniques.is_empty()
+    }
+}
+
+/// Technique observation counts across a set of indicators
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AttackCoverage {
+    /// Technique ID to the indicators it was observed on
+    pub techniques: BTreeMap<String, Vec<Uuid>>,
+    /// Tactic ID to the number of distinct techniques observed for it
+    pub tactics: BTreeMap<String, usize>,
+    /// Tactics without any observed technique
+    pub uncovered_tactics: Vec<String>,
+}
+
+impl AttackCoverage {
+    /// Number of indicators a technique was observed on
+    pub fn technique_count(&self, technique_id: &str) -> usize {
+        self.techniques.get(technique_id).map_or(0, Vec::len)
+    }
+}
+
+/// Minimal STIX bundle view
+#[derive(Debug, Deserialize)]
+struct StixBundle {
+    #[serde(default)]
+    objects: Vec<StixObject>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StixObject {
+    #[serde(rename = "type")]
+    object_type: String,
+    name: Option<String>,
+    #[serde(default)]
+    external_references: Vec<ExternalReference>,
+    #[serde(default)]
+    kill_chain_phases: Vec<KillChainPhase>,
+    #[serde(default)]
+    x_mitre_platforms: Vec<String>,
+    x_mitre_shortname: Option<String>,
+    #[serde(default)]
+    x_mitre_deprecated: bool,
+    #[serde(default)]
+    revoked: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExternalReference {
+    source_name: String,
+    external_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KillChainPhase {
+    kill_chain_name: String,
+    phase_name: String,
+}
+
+impl StixObject {
+    fn attack_id(&self) -> Option<String> {
+        self.external_references.iter()
+            .find(|r| r.source_name == ATTACK_SOURCE)
+            .and_then(|r| r.external_id.as_deref())
+            .map(|id| id.trim().to_uppercase())
+    }
+}
+
+/// Lookup of ATT&CK tactics and techniques
+#[derive(Debug, Clone, Default)]
+pub struct AttackKnowledgeBase {
+    tactics: BTreeMap<String, AttackTactic>,
+    techniques: BTreeMap<String, AttackTechnique>,
+    /// Lowercased names and shortnames to IDs
+    tactic_names: HashMap<String, String>,
+    technique_names: HashMap<String, String>,
+}
+
+impl AttackKnowledgeBase {
+    /// Load knowledge base from a STIX bundle file such as `enterprise-attack.json`
+    pub fn load_file(path: impl AsRef<Path>) -> Result<Self> {
+        let content = std::fs::read_to_string(path.as_ref())?;
+        Self::from_stix_json(&content)
+    }
+
+    /// Build knowledge base from STIX bundle JSON; revoked objects are skipped
+    pub fn from_stix_json(json: &str) -> Result<Self> {
+        let bundle: StixBundle = serde_json::from_str(json)
+            .map_err(|e| Error::Parsing(format!("Invalid ATT&CK STIX bundle: {}", e)))?;
+
+        let mut kb = Self::default();
+        let mut shortnames: HashMap<String, String> = HashMap::new();
+
+        for object in bundle.objects.iter().filter(|o| o.object_type == "x-mitre-tactic" && !o.revoked) {
+            let (Some(id), Some(name), Some(shortname)) = (object.attack_id(), &object.name, &object.x_mitre_shortname) else {
+                continue;
+            };
+            shortnames.insert(shortname.clone(), id.clone());
+            kb.tactic_names.insert(name.to_lowercase(), id.clone());
+            kb.tactic_names.insert(shortname.to_lowercase(), id.clone());
+            kb.tactics.insert(id.clone(), AttackTactic {
+                id,
+                name: name.clone(),
+                shortname: shortname.clone(),
+            });
+        }
+
+        for object in bundle.objects.iter().filter(|o| o.object_type == "attack-pattern" && !o.revoked) {
+            let (Some(id), Some(name)) = (object.attack_id(), &object.name) else {
+                continue;
+            };
+            let tactics = object.kill_chain_phases.iter()
+                .filter(|phase| phase.kill_chain_name == ATTACK_SOURCE)
+                .filter_map(|phase| shortnames.get(&phase.phase_name).cloned())
+                .collect();
+
+            kb.techniques.insert(id.clone(), AttackTechnique {
+                parent: id.split_once('.').map(|(parent, _)| parent.to_string()),
+                id,
+                name: name.clone(),
+                tactics,
+                platforms: object.x_mitre_platforms.clone(),
+                subtechniques: Vec::new(),
+                deprecated: object.x_mitre_deprecated,
+            });
+        }
+
+        let children: Vec<(String, String)> = kb.techniques.values()
+            .filter_map(|t| t.parent.clone().map(|parent| (parent, t.id.clone())))
+            .collect();
+        for (parent, child) in children {
+            match kb.techniques.get_mut(&parent) {
+                Some(technique) => technique.subtechniques.push(child),
+                None => tracing::warn!("ATT&CK sub-technique {} has no parent technique", child),
+            }
+        }
+
+        // Sub-technique names are only unique together with their parent ("Phishing: Spearphishing Link")
+        for technique in kb.techniques.values() {
+            let full_name = match technique.parent.as_ref().and_then(|p| kb.techniques.get(p)) {
+                Some(parent) => format!("{}: {}", parent.name, technique.name),
+                None => technique.name.clone(),
+            };
+            kb.technique_names.insert(full_name.to_lowercase(), technique.id.clone());
+            kb.technique_names.entry(technique.name.to_lowercase()).or_insert_with(|| technique.id.clone());
+        }
+
+        if kb.techniques.is_empty() {
+            return Err(Error::Parsing("STIX bundle contains no ATT&CK techniques".to_string()));
+        }
+
+        tracing::info!("Loaded ATT&CK knowledge base with {} tactics and {} techniques", kb.tactics.len(), kb.techniques.len());
+        Ok(kb)
+    }
+
+    /// All tactics ordered by ID
+    pub fn tactics(&self) -> impl Iterator<Item = &AttackTactic> {
+        self.tactics.values()
+    }
+
+    /// All techniques and sub-techniques ordered by ID
+    pub fn techniques(&self) -> impl Iterator<Item = &AttackTechnique> {
+        self.techniques.values()
+    }
+
+    /// Look up a tactic by ID, name or shortname
+    pub fn resolve_tactic(&self, reference: &str) -> Option<&AttackTactic> {
+        let reference = reference.trim();
+        self.tactics.get(&reference.to_uppercase())
+            .or_else(|| self.tactic_names.get(&reference.to_lowercase()).and_then(|id| self.tactics.get(id)))
+    }
+
+    /// Look up a technique by ID or name
+    pub fn resolve_technique(&self, reference: &str) -> Option<&AttackTechnique> {
+        let reference = reference.trim();
+        self.techniques.get(&reference.to_uppercase())
+            .or_else(|| self.technique_names.get(&reference.to_lowercase()).and_then(|id| self.techniques.get(id)))
+    }
+
+    /// Technique ID followed by the IDs of all its sub-techniques
+    pub fn expand_subtechniques(&self, reference: &str) -> Vec<String> {
+        match self.resolve_technique(reference) {
+            Some(technique) => std::iter::once(technique.id.clone())
+                .chain(technique.subtechniques.iter().cloned())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Tactic IDs a technique belongs to
+    pub fn tactics_for(&self, reference: &str) -> Vec<String> {
+        self.resolve_technique(reference)
+            .map(|t| t.tactics.clone())
+            .unwrap_or_default()
+    }
+
+    /// Replace tactic and technique names with IDs, drop unknown references and add implied tactics
+    pub fn normalize_indicator(&self, indicator: &mut ThreatIndicator) -> AttackValidation {
+        let mut validation = AttackValidation::default();
+
+        let mut techniques = BTreeSet::new();
+        for reference in &indicator.mitre_techniques {
+            match self.resolve_technique(reference) {
+                Some(technique) => {
+                    techniques.insert(technique.id.clone());
+                }
+                None => validation.unknown_techniques.push(reference.clone()),
+            }
+        }
+
+        let mut tactics = BTreeSet::new();
+        for reference in &indicator.mitre_tactics {
+            match self.resolve_tactic(reference) {
+                Some(tactic) => {
+                    tactics.insert(tactic.id.clone());
+                }
+                None => validation.unknown_tactics.push(reference.clone()),
+            }
+        }
+
+        for technique in &techniques {
+            for tactic in self.tactics_for(technique) {
+                if tactics.insert(tactic.clone()) {
+                    validation.inferred_tactics.push(tactic);
+                }
+            }
+        }
+
+        if !validation.is_valid() {
+            tracing::warn!(
+                "Dropped unknown ATT&CK references on {}: tactics {:?}, techniques {:?}",
+                indicator.value, validation.unknown_tactics, validation.unknown_techniques
+            );
+        }
+
+        indicator.mitre_techniques = techniques.into_iter().collect();
+        indicator.mitre_tactics = tactics.into_iter().collect();
+        validation
+    }
+
+    /// Which techniques and tactics are observed across the given indicators
+    pub fn coverage<'a>(&self, indicators: impl IntoIterator<Item = &'a ThreatIndicator>) -> AttackCoverage {
+        let mut coverage = AttackCoverage::default();
+
+        for indicator in indicators {
+            let ids: BTreeSet<&str> = indicator.mitre_techniques.iter()
+                .filter_map(|reference| self.resolve_technique(reference))
+                .map(|technique| technique.id.as_str())
+                .collect();
+            for id in ids {
+                coverage.techniques.entry(id.to_string()).or_default().push(indicator.id);
+            }
+        }
+
+        for technique_id in coverage.techniques.keys() {
+            for tactic in self.tactics_for(technique_id) {
+                *coverage.tactics.entry(tactic).or_default() += 1;
+            }
+        }
+        coverage.uncovered_tactics = self.tactics.keys()
+            .filter(|id| !coverage.tactics.contains_key(*id))
+            .cloned()
+            .collect();
+
+        coverage
+    }
+
+    /// Export coverage as an ATT&CK Navigator layer, scoring techniques by indicator count
+    pub fn navigator_layer(&self, coverage: &AttackCoverage, name: &str, description: &str) -> serde_json::Value {
+        let mut entries = Vec::new();
+        let mut max_score = 1;
+
+        for (technique_id, indicators) in &coverage.techniques {
+            let Some(technique) = self.techniques.get(technique_id) else {
+                continue;
+            };
+            max_score = max_score.max(indicators.len());
+            for tactic in technique.tactics.iter().filter_map(|id| self.tactics.get(id)) {
+                entries.push(serde_json::json!({
+                    "techniqueID": technique.id,
+                    "tactic": tactic.shortname,
+                    "score": indicators.len(),
+                    "comment": format!("Observed on {} indicator(s)", indicators.len()),
+                    "enabled": true,
+                    "showSubtechniques": technique.is_subtechnique(),
+                }));
+            }
+        }
+
+        serde_json::json!({
+            "name": name,
+            "versions": {
+                "navigator": NAVIGATOR_VERSION,
+                "layer": LAYER_VERSION,
+            },
+            "domain": "enterprise-attack",
+            "description": description,
+            "sorting": 3,
+            "hideDisabled": false,
+            "techniques": entries,
+            "gradient": {
+                "colors": ["#ffffff", "#ff6666"],
+                "minValue": 0,
+                "maxValue": max_score,
+            },
+            "legendItems": [],
+            "showTacticRowBackground": false,
+            "selectTechniquesAcrossTactics": true,
+            "selectSubtechniquesWithParent": false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BUNDLE: &str = include_str!("../tests/fixtures/mitre/enterprise-attack-mini.json");
+
+    fn indicator(techniques: &[&str], tactics: &[&str]) -> ThreatIndicator {
+        ThreatIndicator {
+            mitre_techniques: techniques.iter().map(|s| s.to_string()).collect(),
+            mitre_tactics: tactics.iter().map(|s| s.to_string()).collect(),
+            ..ThreatIndicator::new(IndicatorType::Domain, "evil.example.com", "test")
+        }
+    }
+
+    #[test]
+    fn test_load_and_resolve() {
+        let kb = AttackKnowledgeBase::from_stix_json(BUNDLE).unwrap();
+
+        assert_eq!(kb.tactics().count(), 5);
+        // Revoked T1064 is not loaded
+        assert!(kb.resolve_technique("T1064").is_none());
+        assert_eq!(kb.resolve_technique("t1059.001").unwrap().name, "PowerShell");
+        assert_eq!(kb.resolve_technique("Phishing: Spearphishing Link").unwrap().id, "T1566.002");
+        assert_eq!(kb.resolve_tactic("command-and-control").unwrap().id, "TA0011");
+        assert_eq!(kb.expand_subtechniques("Phishing"), vec!["T1566", "T1566.001", "T1566.002"]);
+        assert_eq!(kb.tactics_for("T1055"), vec!["TA0005", "TA0004"]);
+    }
+
+    #[test]
+    fn test_normalize_indicator() {
+        let kb = AttackKnowledgeBase::from_stix_json(BUNDLE).unwrap();
+        let mut ind = indicator(&["powershell", "T9999", "T1059.001"], &["Initial Access", "TA0042"]);
+
+        let validation = kb.normalize_indicator(&mut ind);
+
+        assert_eq!(ind.mitre_techniques, vec!["T1059.001"]);
+        assert_eq!(ind.mitre_tactics, vec!["TA0001", "TA0002"]);
+        assert_eq!(validation.unknown_techniques, vec!["T9999"]);
+        assert_eq!(validation.unknown_tactics, vec!["TA0042"]);
+        assert_eq!(validation.inferred_tactics, vec!["TA0002"]);
+    }
+
+    #[test]
+    fn test_coverage_and_navigator_layer() {
+        let kb = AttackKnowledgeBase::from_stix_json(BUNDLE).unwrap();
+        let indicators = vec![
+            indicator(&["T1566.002", "T1055"], &[]),
+            indicator(&["T1566.002"], &[]),
+        ];
+
+        let coverage = kb.coverage(&indicators);
+        assert_eq!(coverage.technique_count("T1566.002"), 2);
+        assert_eq!(coverage.tactics["TA0005"], 1);
+        assert_eq!(coverage.uncovered_tactics, vec!["TA0002", "TA0011"]);
+
+        let layer = kb.navigator_layer(&coverage, "Session", "Observed techniques");
+        let techniques = layer["techniques"].as_array().unwrap();
+        // T1055 appears once per tactic
+        assert_eq!(techniques.len(), 3);
+        assert_eq!(layer["gradient"]["maxValue"], 2);
+        assert!(techniques.iter().any(|t| t["techniqueID"] == "T1055" && t["tactic"] == "privilege-escalation"));
+    }
+}
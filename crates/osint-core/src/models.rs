@@ -28,6 +28,8 @@ pub struct IntelEntity {
 pub enum EntityType {
     // Network entities
     IpAddress,
+    NetworkRange,
+    AutonomousSystem,
     Domain,
     Url,
     Email,
@@ -37,6 +39,13 @@ pub enum EntityType {
     ThreatActor,
     Campaign,
     Vulnerability,
+    AttackPattern,
+
+    // Host artifacts
+    FileHash,
+    FilePath,
+    RegistryKey,
+    Mutex,
     
     // Geospatial entities
     Location,
@@ -52,6 +61,9 @@ pub enum EntityType {
     PhoneNumber,
     SocialMedia,
     Document,
+
+    // Financial entities
+    CryptocurrencyWallet,
     
     // Generic
     Unknown,
@@ -100,12 +112,62 @@ pub struct ThreatIndicator {
     pub context: Option<String>,
     pub mitre_tactics: Vec<String>,
     pub mitre_techniques: Vec<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Enrichment and source-specific attributes
+    #[serde(default)]
+    pub attributes: HashMap<String, serde_json::Value>,
+    /// Per-source observations of this indicator
+    #[serde(default)]
+    pub sightings: Vec<IndicatorSighting>,
+    #[serde(default)]
+    pub status: IndicatorStatus,
+    /// Analyst decision behind a revoked or false-positive status
+    #[serde(default)]
+    pub revocation: Option<IndicatorRevocation>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+/// Lifecycle state of a threat indicator
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Default)]
+pub enum IndicatorStatus {
+    #[default]
+    Active,
+    /// Past `valid_until` or the default TTL for its type
+    Expired,
+    /// Withdrawn by an analyst
+    Revoked,
+    /// Confirmed benign by an analyst
+    FalsePositive,
+}
+
+/// Analyst-driven revocation of an indicator
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndicatorRevocation {
+    pub reason: String,
+    pub analyst_id: Uuid,
+    pub revoked_at: DateTime<Utc>,
+}
+
+/// Observation of an indicator by a single source
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndicatorSighting {
+    pub source: String,
+    /// Reliability of the reporting source (0.0 - 1.0)
+    pub source_reliability: f32,
+    /// Confidence reported by the source
+    pub confidence: f32,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    /// Number of times the source reported the indicator
+    pub count: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum IndicatorType {
     Hash,
     IpAddress,
+    /// CIDR block such as `10.0.0.0/8`, kept apart from single hosts
+    NetworkRange,
     Domain,
     Url,
     Email,
@@ -115,6 +177,7 @@ pub enum IndicatorType {
     Certificate,
     Yara,
     Sigma,
+    CryptocurrencyAddress,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -143,6 +206,31 @@ pub enum ThreatSeverity {
     Info,
 }
 
+impl ThreatSeverity {
+    /// Lowercase name, as used in properties and rule levels
+    pub fn name(&self) -> &'static str {
+        match self {
+            ThreatSeverity::Critical => "critical",
+            ThreatSeverity::High => "high",
+            ThreatSeverity::Medium => "medium",
+            ThreatSeverity::Low => "low",
+            ThreatSeverity::Info => "info",
+        }
+    }
+
+    /// Parse a severity name, ignoring case; `informational` is accepted for Info
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "critical" => Some(ThreatSeverity::Critical),
+            "high" => Some(ThreatSeverity::High),
+            "medium" => Some(ThreatSeverity::Medium),
+            "low" => Some(ThreatSeverity::Low),
+            "info" | "informational" => Some(ThreatSeverity::Info),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum TrafficLightProtocol {
     Red,    // No sharing
@@ -242,6 +330,74 @@ impl Default for IntelEntity {
     }
 }
 
+impl Default for ThreatIndicator {
+    fn default() -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            indicator_type: IndicatorType::IpAddress,
+            value: String::new(),
+            threat_type: ThreatType::Malware,
+            severity: ThreatSeverity::Medium,
+            confidence: 0.5,
+            tlp: TrafficLightProtocol::Amber,
+            source: String::new(),
+            first_seen: Utc::now(),
+            last_seen: Utc::now(),
+            valid_until: None,
+            context: None,
+            mitre_tactics: Vec::new(),
+            mitre_techniques: Vec::new(),
+            tags: Vec::new(),
+            attributes: HashMap::new(),
+            sightings: Vec::new(),
+            status: IndicatorStatus::Active,
+            revocation: None,
+        }
+    }
+}
+
+impl ThreatIndicator {
+    /// Create new indicator with specified type and value
+    pub fn new(indicator_type: IndicatorType, value: impl Into<String>, source: impl Into<String>) -> Self {
+        Self {
+            indicator_type,
+            value: value.into(),
+            source: source.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Value in canonical form used to recognise the same indicator across sources
+    pub fn normalized_value(&self) -> String {
+        let value = self.value.trim();
+        match self.indicator_type {
+            IndicatorType::IpAddress => value.parse::<std::net::IpAddr>()
+                .map(|ip| ip.to_string())
+                .unwrap_or_else(|_| value.to_lowercase()),
+            IndicatorType::NetworkRange => match value.split_once('/') {
+                Some((ip, prefix)) => match (ip.parse::<std::net::IpAddr>(), prefix.parse::<u8>()) {
+                    (Ok(ip), Ok(prefix)) => format!("{}/{}", ip, prefix),
+                    _ => value.to_lowercase(),
+                },
+                None => value.to_lowercase(),
+            },
+            IndicatorType::Domain => value.trim_end_matches('.').to_lowercase(),
+            IndicatorType::Url => url::Url::parse(value)
+                .map(|url| url.to_string())
+                .unwrap_or_else(|_| value.to_string()),
+            IndicatorType::Hash | IndicatorType::Email | IndicatorType::Registry | IndicatorType::Certificate => {
+                value.to_lowercase()
+            }
+            _ => value.to_string(),
+        }
+    }
+
+    /// Key identifying the same indicator across sources and fetches
+    pub fn dedup_key(&self) -> (IndicatorType, String) {
+        (self.indicator_type.clone(), self.normalized_value())
+    }
+}
+
 impl IntelEntity {
     /// Create new entity with specified type and name
     pub fn new(entity_type: EntityType, name: impl Into<String>, source: impl Into<String>) -> Self {
@@ -273,6 +429,24 @@ impl IntelEntity {
     }
 }
 
+impl IntelReport {
+    /// Markdown document of the report for publishing, with indicators defanged
+    pub fn to_markdown(&self) -> String {
+        let report = crate::intelligence::defang_report(self);
+        let mut out = format!("# {}\n\n", report.title);
+        out.push_str(&format!("**Classification:** {:?}  \n", report.classification));
+        out.push_str(&format!("**Created:** {}  \n", report.created_at.format("%Y-%m-%d %H:%M:%S UTC")));
+        if let Some(published_at) = report.published_at {
+            out.push_str(&format!("**Published:** {}  \n", published_at.format("%Y-%m-%d %H:%M:%S UTC")));
+        }
+        if !report.tags.is_empty() {
+            out.push_str(&format!("**Tags:** {}  \n", report.tags.join(", ")));
+        }
+        out.push_str(&format!("\n## Summary\n\n{}\n\n{}\n", report.summary.trim(), report.content.trim()));
+        out
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -299,4 +473,14 @@ mod tests {
         assert_eq!(entity1.relationships.len(), 1);
         assert_eq!(entity1.relationships[0].target_entity_id, entity2_id);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_indicator_dedup_key_normalization() {
+        let upper = ThreatIndicator::new(IndicatorType::Domain, "Evil.Example.COM.", "misp");
+        let lower = ThreatIndicator::new(IndicatorType::Domain, "evil.example.com", "otx");
+        assert_eq!(upper.dedup_key(), lower.dedup_key());
+
+        let ipv6 = ThreatIndicator::new(IndicatorType::IpAddress, "2001:DB8:0:0:0:0:0:1", "misp");
+        assert_eq!(ipv6.normalized_value(), "2001:db8::1");
+    }
+}
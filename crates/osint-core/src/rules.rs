@@ -0,0 +1,172 @@
+//! YARA and Sigma detection rules as indicator content
+
+use crate::{Result, models::*};
+
+pub mod yara;
+pub mod sigma;
+
+pub use yara::{MetaValue, YaraRule, YaraRuleSet, YaraString, YaraStringKind};
+pub use sigma::{SigmaLogSource, SigmaRule};
+
+/// YARA metadata keys that commonly hold ATT&CK technique IDs
+const YARA_ATTACK_KEYS: [&str; 4] = ["mitre_attack", "attack", "technique", "mitre_technique"];
+/// YARA metadata keys that commonly name the malware family
+const YARA_FAMILY_KEYS: [&str; 3] = ["malware_family", "malware", "family"];
+
+/// Parse and validate the rule text of YARA and Sigma indicators, copying rule metadata into
+/// indicator fields. Other indicator types are left untouched.
+pub fn apply_rule_metadata(indicator: &mut ThreatIndicator) -> Result<()> {
+    match indicator.indicator_type {
+        IndicatorType::Yara => {
+            let set = YaraRuleSet::parse(&indicator.value)?;
+            apply_yara_metadata(indicator, &set);
+            Ok(())
+        }
+        IndicatorType::Sigma => {
+            let rules = SigmaRule::parse_all(&indicator.value)?;
+            for rule in &rules {
+                apply_sigma_metadata(indicator, rule);
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn apply_yara_metadata(indicator: &mut ThreatIndicator, set: &YaraRuleSet) {
+    let names: Vec<&str> = set.rules().iter().map(|r| r.name.as_str()).collect();
+    indicator.attributes.insert("rule_names".to_string(), serde_json::json!(names));
+
+    for rule in set.rules() {
+        for tag in &rule.tags {
+            push_unique(&mut indicator.tags, tag);
+        }
+        for key in YARA_FAMILY_KEYS {
+            if let Some(family) = rule.meta_str(key) {
+                push_unique(&mut indicator.tags, family);
+            }
+        }
+        for key in YARA_ATTACK_KEYS {
+            if let Some(value) = rule.meta_str(key) {
+                for technique in extract_technique_ids(value) {
+                    push_unique(&mut indicator.mitre_techniques, &technique);
+                }
+            }
+        }
+        if let Some(author) = rule.meta_str("author") {
+            set_attribute_once(indicator, "author", author);
+        }
+        for key in ["reference", "references", "url"] {
+            if let Some(reference) = rule.meta_str(key) {
+                push_reference(indicator, reference);
+            }
+        }
+        if indicator.context.is_none() {
+            indicator.context = rule.meta_str("description").map(str::to_string);
+        }
+    }
+}
+
+fn apply_sigma_metadata(indicator: &mut ThreatIndicator, rule: &SigmaRule) {
+    if let Some(id) = &rule.id {
+        set_attribute_once(indicator, "sigma_id", id);
+    }
+    if let Some(author) = &rule.author {
+        set_attribute_once(indicator, "author", author);
+    }
+    if let Some(status) = &rule.status {
+        set_attribute_once(indicator, "sigma_status", status);
+    }
+    indicator.attributes.entry("logsource".to_string())
+        .or_insert_with(|| serde_json::to_value(&rule.logsource).unwrap_or_default());
+    for reference in &rule.references {
+        push_reference(indicator, reference);
+    }
+
+    for technique in rule.attack_techniques() {
+        push_unique(&mut indicator.mitre_techniques, &technique);
+    }
+    for tactic in rule.attack_tactics() {
+        push_unique(&mut indicator.mitre_tactics, &tactic);
+    }
+    for tag in rule.other_tags() {
+        push_unique(&mut indicator.tags, &tag);
+    }
+
+    if let Some(severity) = rule.severity() {
+        // ThreatSeverity orders Critical first
+        if severity < indicator.severity {
+            indicator.severity = severity;
+        }
+    }
+    if indicator.context.is_none() {
+        indicator.context = Some(rule.description.clone().unwrap_or_else(|| rule.title.clone()));
+    }
+}
+
+fn push_unique(values: &mut Vec<String>, value: &str) {
+    let value = value.trim();
+    if !value.is_empty() && !values.iter().any(|v| v.eq_ignore_ascii_case(value)) {
+        values.push(value.to_string());
+    }
+}
+
+fn set_attribute_once(indicator: &mut ThreatIndicator, key: &str, value: &str) {
+    indicator.attributes.entry(key.to_string())
+        .or_insert_with(|| serde_json::Value::String(value.to_string()));
+}
+
+fn push_reference(indicator: &mut ThreatIndicator, reference: &str) {
+    let references = indicator.attributes.entry("references".to_string())
+        .or_insert_with(|| serde_json::json!([]));
+    if let Some(list) = references.as_array_mut() {
+        if !list.iter().any(|r| r.as_str() == Some(reference)) {
+            list.push(serde_json::Value::String(reference.to_string()));
+        }
+    }
+}
+
+/// Technique IDs such as `T1059` or `T1059.001` within free text
+fn extract_technique_ids(text: &str) -> Vec<String> {
+    text.split(|c: char| !(c.is_ascii_alphanumeric() || c == '.'))
+        .map(|word| word.trim_end_matches('.').to_uppercase())
+        .filter(|word| {
+            let (base, sub) = match word.split_once('.') {
+                Some((base, sub)) => (base, Some(sub)),
+                None => (word.as_str(), None),
+            };
+            base.len() == 5
+                && base.starts_with('T')
+                && base[1..].chars().all(|c| c.is_ascii_digit())
+                && sub.is_none_or(|s| s.len() == 3 && s.chars().all(|c| c.is_ascii_digit()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const YARA: &str = include_str!("../tests/fixtures/rules/dropper.yar");
+    const SIGMA: &str = include_str!("../tests/fixtures/rules/encoded_powershell.yml");
+
+    #[test]
+    fn test_rule_metadata_extraction() {
+        let mut yara = ThreatIndicator::new(IndicatorType::Yara, YARA, "test");
+        apply_rule_metadata(&mut yara).unwrap();
+        assert_eq!(yara.mitre_techniques, vec!["T1105", "T1059.001"]);
+        assert!(yara.tags.contains(&"SmokeLoader".to_string()));
+        assert_eq!(yara.attributes["author"], "Threat Research");
+        assert_eq!(yara.context.as_deref(), Some("Loader dropping a second stage from a hard-coded URL"));
+
+        let mut sigma = ThreatIndicator::new(IndicatorType::Sigma, SIGMA, "test");
+        apply_rule_metadata(&mut sigma).unwrap();
+        assert_eq!(sigma.severity, ThreatSeverity::High);
+        assert_eq!(sigma.mitre_tactics, vec!["TA0002", "TA0005"]);
+        assert_eq!(sigma.attributes["logsource"]["product"], "windows");
+        assert_eq!(sigma.attributes["references"][0], "https://example.org/reports/encoded-powershell");
+
+        let mut broken = ThreatIndicator::new(IndicatorType::Yara, "rule broken {", "test");
+        assert!(apply_rule_metadata(&mut broken).is_err());
+    }
+}
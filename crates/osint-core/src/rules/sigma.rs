@@ -0,0 +1,225 @@
+//! Sigma rule parsing and validation
+
+use crate::{Result, Error, models::ThreatSeverity};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// ATT&CK tactic names as used in Sigma `attack.*` tags
+const ATTACK_TACTICS: [(&str, &str); 14] = [
+    ("reconnaissance", "TA0043"),
+    ("resource_development", "TA0042"),
+    ("initial_access", "TA0001"),
+    ("execution", "TA0002"),
+    ("persistence", "TA0003"),
+    ("privilege_escalation", "TA0004"),
+    ("defense_evasion", "TA0005"),
+    ("credential_access", "TA0006"),
+    ("discovery", "TA0007"),
+    ("lateral_movement", "TA0008"),
+    ("collection", "TA0009"),
+    ("exfiltration", "TA0010"),
+    ("command_and_control", "TA0011"),
+    ("impact", "TA0040"),
+];
+
+/// Log source a Sigma rule applies to
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SigmaLogSource {
+    pub category: Option<String>,
+    pub product: Option<String>,
+    pub service: Option<String>,
+    pub definition: Option<String>,
+}
+
+/// Parsed Sigma rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SigmaRule {
+    pub title: String,
+    pub id: Option<String>,
+    pub status: Option<String>,
+    pub description: Option<String>,
+    pub author: Option<String>,
+    #[serde(default)]
+    pub references: Vec<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub logsource: SigmaLogSource,
+    /// Named selections plus the `condition` entry
+    pub detection: BTreeMap<String, serde_yaml::Value>,
+    #[serde(default)]
+    pub falsepositives: Vec<String>,
+    pub level: Option<String>,
+}
+
+impl SigmaRule {
+    /// Parse and validate a single Sigma rule
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut rules = Self::parse_all(source)?;
+        if rules.len() != 1 {
+            return Err(Error::Parsing(format!("Expected one Sigma rule, found {}", rules.len())));
+        }
+        Ok(rules.remove(0))
+    }
+
+    /// Parse and validate every rule of a multi-document YAML file
+    pub fn parse_all(source: &str) -> Result<Vec<Self>> {
+        let mut rules = Vec::new();
+        for document in serde_yaml::Deserializer::from_str(source) {
+            let value = serde_yaml::Value::deserialize(document)
+                .map_err(|e| Error::Parsing(format!("Invalid Sigma YAML: {}", e)))?;
+            if value.is_null() {
+                continue;
+            }
+            let rule: SigmaRule = serde_yaml::from_value(value)
+                .map_err(|e| Error::Parsing(format!("Invalid Sigma rule: {}", e)))?;
+            rule.validate()?;
+            rules.push(rule);
+        }
+
+        if rules.is_empty() {
+            return Err(Error::Parsing("Sigma source contains no rules".to_string()));
+        }
+        Ok(rules)
+    }
+
+    fn validate(&self) -> Result<()> {
+        let fail = |message: String| Err(Error::Parsing(format!("Sigma rule '{}': {}", self.title, message)));
+
+        if self.title.trim().is_empty() {
+            return Err(Error::Parsing("Sigma rule has an empty title".to_string()));
+        }
+        if let Some(id) = &self.id {
+            if uuid::Uuid::parse_str(id).is_err() {
+                return fail(format!("id {} is not a UUID", id));
+            }
+        }
+        if self.logsource == SigmaLogSource::default() {
+            return fail("logsource needs a category, product or service".to_string());
+        }
+        if let Some(level) = &self.level {
+            if !matches!(level.as_str(), "informational" | "low" | "medium" | "high" | "critical") {
+                return fail(format!("unknown level {}", level));
+            }
+        }
+
+        let conditions = self.conditions();
+        if conditions.is_empty() {
+            return fail("detection has no condition".to_string());
+        }
+        for condition in conditions {
+            // Aggregations after a pipe are deprecated and not validated
+            let expression = condition.split('|').next().unwrap_or_default();
+            for word in expression.split(|c: char| c.is_whitespace() || c == '(' || c == ')') {
+                if word.is_empty() || matches!(word, "and" | "or" | "not" | "of" | "them" | "all" | "1") {
+                    continue;
+                }
+                let known = match word.strip_suffix('*') {
+                    Some(prefix) => self.selections().any(|s| s.starts_with(prefix)),
+                    None => self.selections().any(|s| s == word),
+                };
+                if !known {
+                    return fail(format!("condition references unknown selection {}", word));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Condition expressions; Sigma allows a single string or a list
+    pub fn conditions(&self) -> Vec<String> {
+        match self.detection.get("condition") {
+            Some(serde_yaml::Value::String(condition)) => vec![condition.clone()],
+            Some(serde_yaml::Value::Sequence(items)) => items.iter()
+                .filter_map(|item| item.as_str().map(str::to_string))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Names of the detection selections
+    pub fn selections(&self) -> impl Iterator<Item = &str> {
+        self.detection.keys()
+            .map(String::as_str)
+            .filter(|key| *key != "condition" && *key != "timeframe")
+    }
+
+    /// Severity corresponding to the rule level
+    pub fn severity(&self) -> Option<ThreatSeverity> {
+        self.level.as_deref().and_then(ThreatSeverity::from_name)
+    }
+
+    /// ATT&CK technique IDs from `attack.tNNNN` tags
+    pub fn attack_techniques(&self) -> Vec<String> {
+        self.tags.iter()
+            .filter_map(|tag| tag.strip_prefix("attack."))
+            .filter(|id| id.starts_with('t') && id.get(1..5).is_some_and(|digits| digits.bytes().all(|b| b.is_ascii_digit())))
+            .map(|id| id.to_uppercase())
+            .collect()
+    }
+
+    /// ATT&CK tactic IDs from `attack.<tactic>` tags
+    pub fn attack_tactics(&self) -> Vec<String> {
+        self.tags.iter()
+            .filter_map(|tag| tag.strip_prefix("attack."))
+            .filter_map(|name| {
+                let name = name.replace('-', "_");
+                ATTACK_TACTICS.iter().find(|(tactic, _)| *tactic == name).map(|(_, id)| id.to_string())
+            })
+            .collect()
+    }
+
+    /// Tags that are not ATT&CK techniques or tactics, such as groups, software or CVEs
+    pub fn other_tags(&self) -> Vec<String> {
+        let techniques = self.attack_techniques();
+        self.tags.iter()
+            .filter(|tag| {
+                let Some(name) = tag.strip_prefix("attack.") else {
+                    return true;
+                };
+                !techniques.contains(&name.to_uppercase())
+                    && !ATTACK_TACTICS.iter().any(|(tactic, _)| *tactic == name.replace('-', "_"))
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULE: &str = include_str!("../../tests/fixtures/rules/encoded_powershell.yml");
+
+    #[test]
+    fn test_parse_sigma_rule() {
+        let rule = SigmaRule::parse(RULE).unwrap();
+
+        assert_eq!(rule.title, "Suspicious Encoded PowerShell Command Line");
+        assert_eq!(rule.logsource.category.as_deref(), Some("process_creation"));
+        assert_eq!(rule.severity(), Some(ThreatSeverity::High));
+        assert_eq!(rule.attack_techniques(), vec!["T1059.001", "T1027"]);
+        assert_eq!(rule.attack_tactics(), vec!["TA0002", "TA0005"]);
+        assert_eq!(rule.other_tags(), vec!["attack.g0016"]);
+        assert_eq!(rule.selections().collect::<Vec<_>>(), vec!["filter_admin", "selection_cli", "selection_img"]);
+    }
+
+    #[test]
+    fn test_invalid_sigma_rules() {
+        let unknown_selection = RULE.replace("all of selection_* and not filter_admin", "selection and not filter");
+        let no_logsource = "title: x\nlogsource: {}\ndetection:\n  sel:\n    a: b\n  condition: sel\n";
+        let bad_level = RULE.replace("level: high", "level: severe");
+
+        for source in [unknown_selection.as_str(), no_logsource, bad_level.as_str(), "just: text"] {
+            assert!(SigmaRule::parse(source).is_err(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_non_ascii_attack_tags() {
+        let mut rule = SigmaRule::parse(RULE).unwrap();
+        rule.tags = vec!["attack.t123é".to_string(), "attack.té".to_string(), "attack.t1027".to_string()];
+
+        assert_eq!(rule.attack_techniques(), vec!["T1027"]);
+        assert_eq!(rule.other_tags(), vec!["attack.t123é", "attack.té"]);
+    }
+}
@@ -0,0 +1,739 @@
+//! YARA rule parsing and validation
+//!
+//! Rules are checked for well-formed strings, known string references, imported modules and
+//! defined rule references, and their metadata is exposed for indicator enrichment. Matching
+//! rules against data is left to a YARA engine.
+
+use crate::{Result, Error};
+use serde::{Deserialize, Serialize};
+use regex::bytes::RegexBuilder;
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+/// Condition words that need no definition in the rule set
+const CONDITION_KEYWORDS: [&str; 25] = [
+    "and", "or", "not", "of", "them", "all", "any", "none", "at", "in", "for", "true", "false",
+    "filesize", "entrypoint", "contains", "icontains", "startswith", "istartswith", "endswith",
+    "iendswith", "iequals", "matches", "defined", "with",
+];
+
+/// Modifiers accepted after a string definition
+const STRING_MODIFIERS: [&str; 8] = ["nocase", "wide", "ascii", "fullword", "private", "xor", "base64", "base64wide"];
+
+/// Metadata value of a rule
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MetaValue {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+}
+
+impl MetaValue {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            MetaValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// Kind of pattern a string definition holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum YaraStringKind {
+    Text,
+    Hex,
+    Regex,
+}
+
+/// String definition of a rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct YaraString {
+    pub identifier: String,
+    pub kind: YaraStringKind,
+    pub source: String,
+    pub modifiers: Vec<String>,
+    pub private: bool,
+}
+
+/// Parsed YARA rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct YaraRule {
+    pub name: String,
+    pub tags: Vec<String>,
+    pub meta: BTreeMap<String, MetaValue>,
+    pub strings: Vec<YaraString>,
+    pub private: bool,
+    pub global: bool,
+    /// Condition source as written in the rule
+    pub condition: String,
+}
+
+impl YaraRule {
+    /// First metadata value with the given key
+    pub fn meta_str(&self, key: &str) -> Option<&str> {
+        self.meta.get(key).and_then(MetaValue::as_str)
+    }
+}
+
+/// Validated set of YARA rules
+#[derive(Debug, Clone, Default)]
+pub struct YaraRuleSet {
+    rules: Vec<YaraRule>,
+    imports: Vec<String>,
+}
+
+impl YaraRuleSet {
+    /// Parse and validate rule source
+    pub fn parse(source: &str) -> Result<Self> {
+        Parser::new(source).parse_rules()
+    }
+
+    /// Parse rules from a file
+    pub fn load_file(path: impl AsRef<Path>) -> Result<Self> {
+        Self::parse(&std::fs::read_to_string(path.as_ref())?)
+    }
+
+    pub fn rules(&self) -> &[YaraRule] {
+        &self.rules
+    }
+
+    /// Modules imported by the rules
+    pub fn imports(&self) -> &[String] {
+        &self.imports
+    }
+
+    /// Add the rules of another set, rejecting duplicate names
+    pub fn extend(&mut self, other: YaraRuleSet) -> Result<()> {
+        for rule in other.rules {
+            if self.rules.iter().any(|r| r.name == rule.name) {
+                return Err(Error::Parsing(format!("Duplicate YARA rule name {}", rule.name)));
+            }
+            self.rules.push(rule);
+        }
+        for import in other.imports {
+            if !self.imports.contains(&import) {
+                self.imports.push(import);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    /// `$a`, `#a`, `@a` or `!a`, normalized to the `$a` identifier
+    StringRef(String),
+    Number(i64),
+    Text(String),
+    Op(&'static str),
+}
+
+/// Recursive descent parser over rule source
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(src: &'a str) -> Self {
+        Self { src, pos: 0 }
+    }
+
+    fn error(&self, message: impl std::fmt::Display) -> Error {
+        let line = self.src[..self.pos.min(self.src.len())].matches('\n').count() + 1;
+        Error::Parsing(format!("YARA syntax error on line {}: {}", line, message))
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_trivia(&mut self) -> Result<()> {
+        loop {
+            let rest = self.rest();
+            if rest.starts_with("//") {
+                self.pos += rest.find('\n').unwrap_or(rest.len());
+            } else if rest.starts_with("/*") {
+                let end = rest.find("*/").ok_or_else(|| self.error("unterminated comment"))?;
+                self.pos += end + 2;
+            } else if self.peek().is_some_and(char::is_whitespace) {
+                self.bump();
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn eat(&mut self, literal: &str) -> Result<bool> {
+        self.skip_trivia()?;
+        if self.rest().starts_with(literal) {
+            self.pos += literal.len();
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn expect(&mut self, literal: &str) -> Result<()> {
+        if self.eat(literal)? {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected '{}'", literal)))
+        }
+    }
+
+    fn ident(&mut self) -> Result<String> {
+        self.skip_trivia()?;
+        let len = self.rest()
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(self.rest().len());
+        if len == 0 || self.rest().starts_with(|c: char| c.is_ascii_digit()) {
+            return Err(self.error("expected identifier"));
+        }
+        let ident = self.rest()[..len].to_string();
+        self.pos += len;
+        Ok(ident)
+    }
+
+
+    /// Consume a keyword only if it is followed by a non-identifier character
+    fn eat_keyword(&mut self, keyword: &str) -> Result<bool> {
+        self.skip_trivia()?;
+        let rest = self.rest();
+        if rest.starts_with(keyword)
+            && !rest[keyword.len()..].starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_')
+        {
+            self.pos += keyword.len();
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn quoted(&mut self) -> Result<String> {
+        Ok(String::from_utf8_lossy(&self.quoted_bytes()?).into_owned())
+    }
+
+    fn quoted_bytes(&mut self) -> Result<Vec<u8>> {
+        self.expect("\"")?;
+        let mut bytes = Vec::new();
+        loop {
+            match self.bump() {
+                None | Some('\n') => return Err(self.error("unterminated string")),
+                Some('"') => break,
+                Some('\\') => match self.bump() {
+                    Some('n') => bytes.push(b'\n'),
+                    Some('t') => bytes.push(b'\t'),
+                    Some('r') => bytes.push(b'\r'),
+                    Some('"') => bytes.push(b'"'),
+                    Some('\\') => bytes.push(b'\\'),
+                    Some('x') => {
+                        let hex: String = [self.bump(), self.bump()].iter().flatten().collect();
+                        let byte = u8::from_str_radix(&hex, 16)
+                            .map_err(|_| self.error(format!("invalid escape \\x{}", hex)))?;
+                        bytes.push(byte);
+                    }
+                    other => return Err(self.error(format!("invalid escape sequence \\{}", other.unwrap_or(' ')))),
+                },
+                Some(c) => {
+                    let mut buf = [0; 4];
+                    bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                }
+            }
+        }
+        Ok(bytes)
+    }
+
+    fn parse_rules(mut self) -> Result<YaraRuleSet> {
+        let mut set = YaraRuleSet::default();
+
+        loop {
+            self.skip_trivia()?;
+            if self.rest().is_empty() {
+                break;
+            }
+            if self.eat_keyword("import")? {
+                self.skip_trivia()?;
+                set.imports.push(self.quoted()?);
+                continue;
+            }
+            if self.eat_keyword("include")? {
+                return Err(self.error("include directives are not supported"));
+            }
+
+            let rule = self.parse_rule(&set)?;
+            if set.rules.iter().any(|r| r.name == rule.name) {
+                return Err(self.error(format!("duplicate rule name {}", rule.name)));
+            }
+            set.rules.push(rule);
+        }
+
+        if set.rules.is_empty() {
+            return Err(Error::Parsing("YARA source contains no rules".to_string()));
+        }
+        Ok(set)
+    }
+
+    /// Parse one rule; conditions may only reference rules already in `set`
+    fn parse_rule(&mut self, set: &YaraRuleSet) -> Result<YaraRule> {
+        let mut private = false;
+        let mut global = false;
+        loop {
+            if self.eat_keyword("private")? {
+                private = true;
+            } else if self.eat_keyword("global")? {
+                global = true;
+            } else {
+                break;
+            }
+        }
+        if !self.eat_keyword("rule")? {
+            return Err(self.error("expected 'rule'"));
+        }
+
+        let name = self.ident()?;
+        let mut tags = Vec::new();
+        if self.eat(":")? {
+            while !self.eat("{")? {
+                tags.push(self.ident()?);
+            }
+        } else {
+            self.expect("{")?;
+        }
+
+        let mut meta = BTreeMap::new();
+        let mut strings: Vec<YaraString> = Vec::new();
+
+        if self.eat_keyword("meta")? {
+            self.expect(":")?;
+            loop {
+                self.skip_trivia()?;
+                if self.rest().starts_with("strings") || self.rest().starts_with("condition") {
+                    break;
+                }
+                let key = self.ident()?;
+                self.expect("=")?;
+                self.skip_trivia()?;
+                let value = if self.peek() == Some('"') {
+                    MetaValue::String(self.quoted()?)
+                } else if self.eat_keyword("true")? {
+                    MetaValue::Boolean(true)
+                } else if self.eat_keyword("false")? {
+                    MetaValue::Boolean(false)
+                } else {
+                    let negative = self.eat("-")?;
+                    let n = self.number()?;
+                    MetaValue::Integer(if negative { -n } else { n })
+                };
+                // Repeated keys keep the first value
+                meta.entry(key).or_insert(value);
+            }
+        }
+
+        if self.eat_keyword("strings")? {
+            self.expect(":")?;
+            let mut anonymous = 0;
+            loop {
+                self.skip_trivia()?;
+                if !self.rest().starts_with('$') {
+                    break;
+                }
+                self.bump();
+                let identifier = match self.peek() {
+                    Some(c) if c.is_ascii_alphanumeric() || c == '_' => format!("${}", self.ident()?),
+                    _ => {
+                        anonymous += 1;
+                        format!("$__anonymous_{}", anonymous)
+                    }
+                };
+                if strings.iter().any(|s| s.identifier == identifier) {
+                    return Err(self.error(format!("duplicate string identifier {}", identifier)));
+                }
+                self.expect("=")?;
+                strings.push(self.parse_string(identifier)?);
+            }
+        }
+
+        if !self.eat_keyword("condition")? {
+            return Err(self.error(format!("rule {} has no condition", name)));
+        }
+        self.expect(":")?;
+        self.skip_trivia()?;
+
+        let start = self.pos;
+        let tokens = self.condition_tokens()?;
+        let condition = self.src[start..self.pos - 1].trim().to_string();
+        check_condition(&tokens, &strings, set).map_err(|e| self.error(format!("rule {}: {}", name, e)))?;
+
+        Ok(YaraRule {
+            name,
+            tags,
+            meta,
+            strings,
+            private,
+            global,
+            condition,
+        })
+    }
+
+    fn number(&mut self) -> Result<i64> {
+        self.skip_trivia()?;
+        let rest = self.rest();
+        let (value, len) = if let Some(hex) = rest.strip_prefix("0x") {
+            let len = hex.find(|c: char| !c.is_ascii_hexdigit()).unwrap_or(hex.len());
+            (i64::from_str_radix(&hex[..len], 16).ok(), len + 2)
+        } else {
+            let len = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+            (rest[..len].parse().ok(), len)
+        };
+        let mut value = value.ok_or_else(|| self.error("expected number"))?;
+        self.pos += len;
+        for (suffix, multiplier) in [("KB", 1024), ("MB", 1024 * 1024)] {
+            if self.rest().starts_with(suffix) {
+                value = value.checked_mul(multiplier)
+                    .ok_or_else(|| self.error(format!("number {}{} is too large", value, suffix)))?;
+                self.pos += suffix.len();
+                break;
+            }
+        }
+        Ok(value)
+    }
+
+    fn parse_string(&mut self, identifier: String) -> Result<YaraString> {
+        self.skip_trivia()?;
+        let start = self.pos;
+        let kind = match self.peek() {
+            Some('"') => {
+                if self.quoted_bytes()?.is_empty() {
+                    return Err(self.error(format!("empty string {}", identifier)));
+                }
+                YaraStringKind::Text
+            }
+            Some('{') => {
+                self.bump();
+                let end = self.rest().find('}').ok_or_else(|| self.error("unterminated hex string"))?;
+                let body = self.rest()[..end].to_string();
+                self.pos += end + 1;
+                check_hex(&body).map_err(|e| self.error(format!("{} in {}", e, identifier)))?;
+                YaraStringKind::Hex
+            }
+            Some('/') => {
+                self.bump();
+                let mut pattern = String::new();
+                loop {
+                    match self.bump() {
+                        None | Some('\n') => return Err(self.error("unterminated regular expression")),
+                        Some('\\') => {
+                            let next = self.bump().ok_or_else(|| self.error("unterminated regular expression"))?;
+                            if next != '/' {
+                                pattern.push('\\');
+                            }
+                            pattern.push(next);
+                        }
+                        Some('/') => break,
+                        Some(c) => pattern.push(c),
+                    }
+                }
+                while self.peek().is_some_and(|c| c == 'i' || c == 's') {
+                    self.bump();
+                }
+                RegexBuilder::new(&pattern)
+                    .unicode(false)
+                    .build()
+                    .map_err(|e| self.error(format!("invalid pattern for {}: {}", identifier, e)))?;
+                YaraStringKind::Regex
+            }
+            _ => return Err(self.error(format!("expected string value for {}", identifier))),
+        };
+        let source = self.src[start..self.pos].trim().to_string();
+
+        let mut modifiers = Vec::new();
+        loop {
+            self.skip_trivia()?;
+            let checkpoint = self.pos;
+            let Ok(modifier) = self.ident() else { break };
+            if !STRING_MODIFIERS.contains(&modifier.as_str()) {
+                self.pos = checkpoint;
+                break;
+            }
+            // Optional arguments such as xor(1-255) or a base64 alphabet
+            if matches!(modifier.as_str(), "xor" | "base64" | "base64wide") && self.eat("(")? {
+                let end = self.rest().find(')').ok_or_else(|| self.error("unterminated modifier arguments"))?;
+                self.pos += end + 1;
+            }
+            modifiers.push(modifier);
+        }
+
+        Ok(YaraString {
+            identifier,
+            kind,
+            source,
+            private: modifiers.iter().any(|m| m == "private"),
+            modifiers,
+        })
+    }
+
+    /// Tokenize the condition up to the closing brace of the rule
+    fn condition_tokens(&mut self) -> Result<Vec<Token>> {
+        let mut tokens = Vec::new();
+        loop {
+            self.skip_trivia()?;
+            let rest = self.rest();
+            let Some(c) = self.peek() else {
+                return Err(self.error("unterminated rule"));
+            };
+
+            if c == '}' {
+                self.bump();
+                return Ok(tokens);
+            }
+            if c == '"' {
+                tokens.push(Token::Text(self.quoted()?));
+                continue;
+            }
+            if c.is_ascii_digit() {
+                tokens.push(Token::Number(self.number()?));
+                continue;
+            }
+            if let Some(op) = ["..", "==", "!=", "<=", ">=", "<<", ">>", "<", ">", "(", ")", "[", "]", ",", ".", ":",
+                "-", "+", "*", "\\", "%", "&", "|", "^", "~"]
+                .into_iter()
+                .find(|op| rest.starts_with(*op))
+            {
+                self.pos += op.len();
+                tokens.push(Token::Op(op));
+                continue;
+            }
+            if matches!(c, '$' | '#' | '@' | '!') {
+                self.bump();
+                let name_len = self.rest()
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(self.rest().len());
+                let mut name = format!("${}", &self.rest()[..name_len]);
+                self.pos += name_len;
+                if c == '$' && self.rest().starts_with('*') {
+                    self.bump();
+                    name.push('*');
+                }
+                tokens.push(Token::StringRef(name));
+                continue;
+            }
+            if c.is_ascii_alphabetic() || c == '_' {
+                tokens.push(Token::Ident(self.ident()?));
+                continue;
+            }
+            return Err(self.error(format!("unexpected character '{}' in condition", c)));
+        }
+    }
+}
+
+/// Check the syntax of a hex string body
+fn check_hex(body: &str) -> std::result::Result<(), String> {
+    let chars: Vec<char> = body.chars().filter(|c| !c.is_whitespace()).collect();
+    let mut i = 0;
+    let mut bytes = 0;
+    let mut depth = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Err("unbalanced ')'".to_string()),
+            ')' => depth -= 1,
+            '|' => {}
+            '[' => {
+                let end = chars[i..].iter().position(|c| *c == ']').ok_or("unterminated jump")? + i;
+                let jump: String = chars[i + 1..end].iter().collect();
+                let (low, high) = jump.split_once('-').unwrap_or((&jump, &jump));
+                if !(low.is_empty() || low.parse::<u32>().is_ok()) || !(high.is_empty() || high.parse::<u32>().is_ok()) {
+                    return Err(format!("invalid jump [{}]", jump));
+                }
+                i = end;
+            }
+            hi if i + 1 < chars.len() => {
+                let nibble = |c: char| c == '?' || c.is_ascii_hexdigit();
+                if let Some(c) = [hi, chars[i + 1]].into_iter().find(|c| !nibble(*c)) {
+                    return Err(format!("invalid hex character '{}'", c));
+                }
+                bytes += 1;
+                i += 1;
+            }
+            c => return Err(format!("incomplete hex byte '{}'", c)),
+        }
+        i += 1;
+    }
+
+    if depth != 0 {
+        return Err("unbalanced '('".to_string());
+    }
+    if bytes == 0 {
+        return Err("empty hex string".to_string());
+    }
+    Ok(())
+}
+
+/// Whether the identifier is one of the `uintN`/`intN` read functions
+fn is_read_function(ident: &str) -> bool {
+    let name = ident.strip_prefix('u').unwrap_or(ident);
+    matches!(name.strip_suffix("be").unwrap_or(name), "int8" | "int16" | "int32")
+}
+
+/// Check that a condition only references defined strings, imported modules and earlier rules,
+/// and that every string of the rule is used
+fn check_condition(tokens: &[Token], strings: &[YaraString], set: &YaraRuleSet) -> std::result::Result<(), String> {
+    if tokens.is_empty() {
+        return Err("empty condition".to_string());
+    }
+
+    let mut referenced: HashSet<&str> = HashSet::new();
+    let mut loop_variables: Vec<&str> = Vec::new();
+    let mut brackets = Vec::new();
+
+    for (i, token) in tokens.iter().enumerate() {
+        let previous = i.checked_sub(1).map(|p| &tokens[p]);
+        match token {
+            Token::Op(open @ ("(" | "[")) => brackets.push(*open),
+            Token::Op(close @ (")" | "]")) => {
+                let expected = if *close == ")" { "(" } else { "[" };
+                if brackets.pop() != Some(expected) {
+                    return Err(format!("unbalanced '{}'", close));
+                }
+            }
+            Token::Op("%") => match previous {
+                Some(Token::Number(n)) if !(1..=100).contains(n) => {
+                    return Err(format!("percentage {} out of range", n));
+                }
+                _ => {}
+            },
+            // A bare `$` refers to the current string inside `for ... of` loops
+            Token::StringRef(id) if id == "$" => {}
+            Token::StringRef(id) => match id.strip_suffix('*') {
+                Some(prefix) => {
+                    let matching: Vec<&str> = strings.iter()
+                        .map(|s| s.identifier.as_str())
+                        .filter(|s| s.starts_with(prefix))
+                        .collect();
+                    if matching.is_empty() {
+                        return Err(format!("no strings match {}", id));
+                    }
+                    referenced.extend(matching);
+                }
+                None => match strings.iter().find(|s| &s.identifier == id) {
+                    Some(string) => {
+                        referenced.insert(&string.identifier);
+                    }
+                    None => return Err(format!("undefined string identifier {}", id)),
+                },
+            },
+            Token::Ident(ident) if ident == "them" => {
+                if strings.is_empty() {
+                    return Err("'them' used in a rule without strings".to_string());
+                }
+                referenced.extend(strings.iter().map(|s| s.identifier.as_str()));
+            }
+            Token::Ident(ident) if ident == "for" => {
+                // for <quantifier> <variable>, ... in (...) : (...)
+                let variables = tokens[i + 1..].iter()
+                    .skip(1)
+                    .take_while(|t| !matches!(t, Token::Ident(word) if word == "in" || word == "of"));
+                loop_variables.extend(variables.filter_map(|t| match t {
+                    Token::Ident(variable) => Some(variable.as_str()),
+                    _ => None,
+                }));
+            }
+            // Members of a module structure
+            Token::Ident(_) if previous == Some(&Token::Op(".")) => {}
+            Token::Ident(ident) if tokens.get(i + 1) == Some(&Token::Op(".")) && !set.imports.contains(ident) => {
+                return Err(format!("module {} is not imported", ident));
+            }
+            Token::Ident(_) if tokens.get(i + 1) == Some(&Token::Op(".")) => {}
+            Token::Ident(ident) => {
+                let known = CONDITION_KEYWORDS.contains(&ident.as_str())
+                    || is_read_function(ident)
+                    || loop_variables.contains(&ident.as_str())
+                    || set.rules.iter().any(|r| &r.name == ident);
+                if !known {
+                    return Err(format!("undefined identifier {}", ident));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(open) = brackets.pop() {
+        return Err(format!("unbalanced '{}'", open));
+    }
+    if let Some(unused) = strings.iter().find(|s| !referenced.contains(s.identifier.as_str())) {
+        return Err(format!("unreferenced string {}", unused.identifier));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: &str = include_str!("../../tests/fixtures/rules/dropper.yar");
+
+    #[test]
+    fn test_parse_rules() {
+        let set = YaraRuleSet::parse(RULES).unwrap();
+
+        assert_eq!(set.imports(), ["pe"]);
+        assert_eq!(set.rules().len(), 3);
+        assert!(set.rules()[0].private);
+        let loader = &set.rules()[1];
+        assert_eq!(loader.tags, vec!["dropper", "windows"]);
+        assert_eq!(loader.meta_str("malware_family"), Some("SmokeLoader"));
+        assert_eq!(loader.meta.get("score"), Some(&MetaValue::Integer(80)));
+        assert_eq!(loader.strings.len(), 4);
+        assert_eq!(loader.strings[0].modifiers, vec!["ascii", "wide"]);
+        assert_eq!(loader.strings[2].kind, YaraStringKind::Hex);
+        assert!(loader.condition.starts_with("IsPE and filesize < 1MB"));
+        assert_eq!(set.rules()[2].condition, "pe.number_of_sections > 8 and pe.is_dll()");
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let undefined = "rule a { strings: $a = \"x\" condition: $b }";
+        let unreferenced = "rule a { strings: $a = \"x\" $b = \"y\" condition: $a }";
+        let duplicate = "rule a { condition: true } rule a { condition: false }";
+        let bad_hex = "rule a { strings: $a = { 4D 5 } condition: $a }";
+        let unknown_rule = "rule a { condition: b }";
+        let later_rule = "rule a { condition: b } rule b { condition: true }";
+        let not_imported = "rule a { condition: pe.is_dll() }";
+        let unbalanced = "rule a { strings: $a = \"x\" condition: ($a }";
+        let percent = "rule a { strings: $a = \"ab\" condition: 150% of them }";
+        let overflow = "rule a { condition: filesize < 9223372036854775807MB }";
+
+        for source in [undefined, unreferenced, duplicate, bad_hex, unknown_rule, later_rule, not_imported, unbalanced, percent, overflow] {
+            assert!(matches!(YaraRuleSet::parse(source), Err(Error::Parsing(_))), "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_accepted_conditions() {
+        let sources = [
+            "rule a { strings: $a1 = \"ab\" $a2 = \"cd\" $b = \"ef\" condition: 2 of ($a*) and not $b }",
+            "rule a { condition: int8(0) == -1 and int16be(2) < -0x10 }",
+            "rule a { strings: $a = \"ab\" xor(1-255) condition: for any i in (1..#a) : (@a[i] > 10) }",
+            "rule a { strings: $a = \"ab\" $b = /c[d-f]+/i condition: for all of them : ( # > 1 or $ at 0 ) }",
+            "import \"math\" rule a { condition: math.entropy(0, filesize) >= 7.5 and filesize < 2KB }",
+        ];
+
+        for source in sources {
+            assert!(YaraRuleSet::parse(source).is_ok(), "{}", source);
+        }
+    }
+}
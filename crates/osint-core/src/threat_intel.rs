@@ -1,19 +1,53 @@
 //! Threat intelligence processing and analysis
 
 use crate::{Result, Error, models::*};
+use crate::enrichment::{EnrichmentEngine, EnrichmentOutcome};
+use crate::enrichment::virustotal::verdict_confidence;
+use crate::mitre::{AttackCoverage, AttackKnowledgeBase};
+use crate::rules::apply_rule_metadata;
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc, Duration};
 use uuid::Uuid;
 use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::Entry;
+use std::sync::Arc;
+use std::time::Instant;
+use futures::future::join_all;
 use reqwest::Client;
-use tokio::time::sleep;
+use tokio::time::{sleep, sleep_until, timeout};
+
+pub mod otx;
+pub mod feed;
+pub mod kill_chain;
+pub mod attribution;
+
+pub use otx::OtxSource;
+pub use feed::{FeedColumn, FeedConfig, FeedFormat, FeedLocation, FeedSource, IndicatorTypeSpec};
+pub use kill_chain::{CampaignStage, KillChainAnalysis, KillChainAnalyzer, KillChainPhase};
+pub use attribution::{ActorProfile, AttributionCandidate, AttributionEngine, AttributionEvidence, EvidenceKind};
 
 /// Threat intelligence engine for processing and correlating threat data
 pub struct ThreatIntelEngine {
     http_client: Client,
-    sources: HashMap<String, Box<dyn ThreatSource + Send + Sync>>,
+    sources: HashMap<String, RegisteredSource>,
     indicators: HashMap<Uuid, ThreatIndicator>,
+    indicator_keys: HashMap<(IndicatorType, String), Uuid>,
     correlation_rules: Vec<CorrelationRule>,
+    fetch_reports: HashMap<String, FetchReport>,
+    lifecycle: LifecyclePolicy,
+    attack: Option<Arc<AttackKnowledgeBase>>,
+    attribution: AttributionEngine,
+}
+
+/// Outcome of merging an incoming indicator into the store
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeOutcome {
+    /// First sighting of the indicator
+    New,
+    /// Indicator was known and the sighting added information
+    Updated,
+    /// Indicator was known and the sighting added nothing
+    Duplicate,
 }
 
 /// Trait for threat intelligence sources
@@ -41,6 +75,310 @@ pub enum ThreatSourceType {
     Internal,
 }
 
+impl ThreatSourceType {
+    /// Default reliability weight for indicators reported by this kind of source
+    pub fn reliability(&self) -> f32 {
+        match self {
+            ThreatSourceType::Government => 0.9,
+            ThreatSourceType::Commercial => 0.85,
+            ThreatSourceType::Internal => 0.8,
+            ThreatSourceType::Community => 0.7,
+            ThreatSourceType::OpenSource => 0.6,
+        }
+    }
+}
+
+/// Fetch policy applied to an individual threat source
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourcePolicy {
+    /// Minimum interval between the start of successive fetch attempts
+    pub min_interval: std::time::Duration,
+    /// Timeout for a single fetch attempt
+    pub timeout: std::time::Duration,
+    /// Retries allowed after the first attempt for retriable errors
+    pub max_retries: u32,
+    /// Backoff before the first retry, doubled on every subsequent retry
+    pub initial_backoff: std::time::Duration,
+    /// Upper bound for the retry backoff
+    pub max_backoff: std::time::Duration,
+    /// Consecutive failed fetches before the circuit opens
+    pub failure_threshold: u32,
+    /// How long an open circuit rejects fetches before allowing a trial fetch
+    pub reset_timeout: std::time::Duration,
+}
+
+impl Default for SourcePolicy {
+    fn default() -> Self {
+        Self {
+            min_interval: std::time::Duration::from_secs(1),
+            timeout: std::time::Duration::from_secs(60),
+            max_retries: 3,
+            initial_backoff: std::time::Duration::from_millis(500),
+            max_backoff: std::time::Duration::from_secs(30),
+            failure_threshold: 5,
+            reset_timeout: std::time::Duration::from_secs(300),
+        }
+    }
+}
+
+/// State of a per-source circuit breaker
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Fetches flow normally
+    Closed,
+    /// Fetches are rejected until the reset timeout elapses
+    Open,
+    /// A single trial fetch decides whether the circuit closes again
+    HalfOpen,
+}
+
+/// Circuit breaker tracking consecutive failures of a flapping source
+#[derive(Debug)]
+struct CircuitBreaker {
+    state: CircuitState,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    fn new() -> Self {
+        Self {
+            state: CircuitState::Closed,
+            consecutive_failures: 0,
+            opened_at: None,
+        }
+    }
+
+    /// Check whether a fetch may proceed, moving an expired open circuit to half-open
+    fn allow(&mut self, policy: &SourcePolicy, now: Instant) -> bool {
+        match self.state {
+            CircuitState::Closed | CircuitState::HalfOpen => true,
+            CircuitState::Open => {
+                let expired = self.opened_at
+                    .map(|opened| now.duration_since(opened) >= policy.reset_timeout)
+                    .unwrap_or(true);
+                if expired {
+                    self.state = CircuitState::HalfOpen;
+                }
+                expired
+            }
+        }
+    }
+
+    fn record_success(&mut self) {
+        self.state = CircuitState::Closed;
+        self.consecutive_failures = 0;
+        self.opened_at = None;
+    }
+
+    fn record_failure(&mut self, policy: &SourcePolicy, now: Instant) {
+        self.consecutive_failures += 1;
+        if self.state == CircuitState::HalfOpen || self.consecutive_failures >= policy.failure_threshold {
+            self.state = CircuitState::Open;
+            self.opened_at = Some(now);
+        }
+    }
+}
+
+/// Threat source registered with the engine together with its fetch state
+struct RegisteredSource {
+    source: Box<dyn ThreatSource + Send + Sync>,
+    policy: SourcePolicy,
+    breaker: CircuitBreaker,
+    last_attempt: Option<Instant>,
+}
+
+impl RegisteredSource {
+    /// Fetch from the source honouring rate limit, timeout, retry and circuit breaker policy
+    async fn fetch(&mut self, name: &str) -> (Vec<ThreatIndicator>, FetchReport) {
+        let started = Instant::now();
+        let mut report = FetchReport::new(name);
+        let indicators = self.fetch_with_retry(name, &mut report).await;
+
+        report.duration = started.elapsed();
+        report.circuit_state = self.breaker.state;
+        report.completed_at = Utc::now();
+        (indicators, report)
+    }
+
+    async fn fetch_with_retry(&mut self, name: &str, report: &mut FetchReport) -> Vec<ThreatIndicator> {
+        if !self.breaker.allow(&self.policy, Instant::now()) {
+            tracing::warn!("Circuit open for threat source {}, skipping fetch", name);
+            report.errors.push("circuit breaker open".to_string());
+            return Vec::new();
+        }
+
+        if !self.source.is_available().await {
+            tracing::warn!("Threat source {} is not available", name);
+            report.errors.push("source not available".to_string());
+            self.breaker.record_failure(&self.policy, Instant::now());
+            return Vec::new();
+        }
+
+        let mut backoff = self.policy.initial_backoff;
+        loop {
+            // Rate limiting
+            if let Some(last_attempt) = self.last_attempt {
+                sleep_until((last_attempt + self.policy.min_interval).into()).await;
+            }
+            self.last_attempt = Some(Instant::now());
+            report.attempts += 1;
+
+            let result = match timeout(self.policy.timeout, self.source.fetch_indicators()).await {
+                Ok(result) => result,
+                Err(_) => Err(Error::Timeout(format!(
+                    "{} did not respond within {:?}", name, self.policy.timeout
+                ))),
+            };
+
+            match result {
+                Ok(indicators) => {
+                    self.breaker.record_success();
+                    return indicators;
+                }
+                Err(e) => {
+                    report.errors.push(e.to_string());
+
+                    if e.is_retriable() && report.attempts <= self.policy.max_retries {
+                        tracing::warn!(
+                            "Fetch from {} failed (attempt {}), retrying in {:?}: {}",
+                            name, report.attempts, backoff, e
+                        );
+                        sleep(backoff).await;
+                        backoff = (backoff * 2).min(self.policy.max_backoff);
+                        continue;
+                    }
+
+                    tracing::error!("Failed to fetch indicators from {}: {}", name, e);
+                    self.breaker.record_failure(&self.policy, Instant::now());
+                    return Vec::new();
+                }
+            }
+        }
+    }
+}
+
+/// Outcome of fetching a single threat source
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FetchReport {
+    pub source: String,
+    /// Indicators returned by the source
+    pub fetched: usize,
+    /// Indicators not previously known to the engine
+    pub new: usize,
+    /// Known indicators whose content changed
+    pub updated: usize,
+    /// Known indicators returned again without changes
+    pub duplicates: usize,
+    /// Indicators failing validation, such as unparsable YARA or Sigma rules
+    pub rejected: usize,
+    /// Errors encountered, including those of attempts that were retried
+    pub errors: Vec<String>,
+    pub attempts: u32,
+    pub duration: std::time::Duration,
+    pub circuit_state: CircuitState,
+    pub completed_at: DateTime<Utc>,
+}
+
+impl FetchReport {
+    fn new(source: &str) -> Self {
+        Self {
+            source: source.to_string(),
+            fetched: 0,
+            new: 0,
+            updated: 0,
+            duplicates: 0,
+            rejected: 0,
+            errors: Vec::new(),
+            attempts: 0,
+            duration: std::time::Duration::ZERO,
+            circuit_state: CircuitState::Closed,
+            completed_at: Utc::now(),
+        }
+    }
+
+    /// Whether the source delivered indicators on this run
+    pub fn succeeded(&self) -> bool {
+        self.attempts > 0 && self.errors.len() < self.attempts as usize
+    }
+}
+
+/// Attribute recording when an analyst last reactivated an indicator
+pub const REACTIVATED_AT_ATTRIBUTE: &str = "reactivated_at";
+
+/// Expiry and aging policy for threat indicators
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LifecyclePolicy {
+    /// Days an indicator without `valid_until` stays active after its last sighting
+    pub ttl_days: HashMap<IndicatorType, u32>,
+    /// TTL for indicator types without an explicit entry
+    pub default_ttl_days: u32,
+    /// Confidence half-life as a fraction of the indicator's TTL
+    pub half_life_ratio: f32,
+}
+
+impl Default for LifecyclePolicy {
+    fn default() -> Self {
+        // Infrastructure is cheap to rotate, artefacts are not
+        let ttl_days = HashMap::from([
+            (IndicatorType::IpAddress, 30),
+            (IndicatorType::NetworkRange, 30),
+            (IndicatorType::Url, 60),
+            (IndicatorType::Domain, 90),
+            (IndicatorType::Email, 180),
+            (IndicatorType::Filename, 180),
+            (IndicatorType::Hash, 365),
+            (IndicatorType::Registry, 365),
+            (IndicatorType::Mutex, 365),
+            (IndicatorType::Certificate, 365),
+            (IndicatorType::CryptocurrencyAddress, 365),
+            (IndicatorType::Yara, 730),
+            (IndicatorType::Sigma, 730),
+        ]);
+
+        Self {
+            ttl_days,
+            default_ttl_days: 180,
+            half_life_ratio: 0.5,
+        }
+    }
+}
+
+impl LifecyclePolicy {
+    fn ttl(&self, indicator_type: &IndicatorType) -> Duration {
+        let days = self.ttl_days.get(indicator_type).copied().unwrap_or(self.default_ttl_days);
+        Duration::days(days as i64)
+    }
+
+    /// Time at which the indicator expires; reactivation restarts the TTL like a sighting
+    pub fn expires_at(&self, indicator: &ThreatIndicator) -> DateTime<Utc> {
+        indicator.valid_until.unwrap_or_else(|| {
+            let reactivated_at = indicator.attributes.get(REACTIVATED_AT_ATTRIBUTE)
+                .and_then(|v| serde_json::from_value::<DateTime<Utc>>(v.clone()).ok());
+            let active_since = reactivated_at.map_or(indicator.last_seen, |t| t.max(indicator.last_seen));
+            active_since + self.ttl(&indicator.indicator_type)
+        })
+    }
+
+    /// Lifecycle status at the given time, applying expiry to active indicators
+    pub fn status_at(&self, indicator: &ThreatIndicator, now: DateTime<Utc>) -> IndicatorStatus {
+        match indicator.status {
+            IndicatorStatus::Active if self.expires_at(indicator) <= now => IndicatorStatus::Expired,
+            ref status => status.clone(),
+        }
+    }
+
+    /// Confidence decayed by time since the indicator was last seen
+    pub fn decayed_confidence(&self, indicator: &ThreatIndicator, now: DateTime<Utc>) -> f32 {
+        let age_days = (now - indicator.last_seen).num_seconds().max(0) as f32 / 86_400.0;
+        let half_life_days = self.ttl(&indicator.indicator_type).num_days() as f32 * self.half_life_ratio;
+        if half_life_days <= 0.0 {
+            return indicator.confidence;
+        }
+        indicator.confidence * 0.5_f32.powf(age_days / half_life_days)
+    }
+}
+
 /// Correlation rule for threat indicators
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CorrelationRule {
@@ -106,6 +444,9 @@ pub struct CorrelationResult {
     pub correlation_score: f32,
     pub created_at: DateTime<Utc>,
     pub actions_taken: Vec<CorrelationAction>,
+    /// Candidate actors for attribution rules, best first
+    #[serde(default)]
+    pub attributions: Vec<AttributionCandidate>,
 }
 
 impl ThreatIntelEngine {
@@ -121,43 +462,340 @@ impl ThreatIntelEngine {
             http_client,
             sources: HashMap::new(),
             indicators: HashMap::new(),
+            indicator_keys: HashMap::new(),
             correlation_rules: Vec::new(),
+            fetch_reports: HashMap::new(),
+            lifecycle: LifecyclePolicy::default(),
+            attack: None,
+            attribution: AttributionEngine::new(),
         }
     }
 
-    /// Add threat intelligence source
+    /// Add threat intelligence source with the default fetch policy
     pub fn add_source(&mut self, name: String, source: Box<dyn ThreatSource + Send + Sync>) {
-        self.sources.insert(name, source);
+        self.add_source_with_policy(name, source, SourcePolicy::default());
     }
 
-    /// Fetch indicators from all sources
-    pub async fn fetch_all_indicators(&mut self) -> Result<usize> {
-        let mut total_fetched = 0;
+    /// Add threat intelligence source with a custom fetch policy
+    pub fn add_source_with_policy(&mut self, name: String, source: Box<dyn ThreatSource + Send + Sync>, policy: SourcePolicy) {
+        self.sources.insert(name, RegisteredSource {
+            source,
+            policy,
+            breaker: CircuitBreaker::new(),
+            last_attempt: None,
+        });
+    }
 
-        for (name, source) in &self.sources {
-            if !source.is_available().await {
-                tracing::warn!("Threat source {} is not available", name);
-                continue;
+    /// Fetch indicators from all sources concurrently
+    pub async fn fetch_all_indicators(&mut self) -> Result<Vec<FetchReport>> {
+        let fetches = self.sources.iter_mut()
+            .map(|(name, registered)| async move {
+                let reliability = registered.source.source_type().reliability();
+                let (indicators, report) = registered.fetch(name).await;
+                (name.clone(), reliability, indicators, report)
+            });
+        let results = join_all(fetches).await;
+
+        let mut reports = Vec::with_capacity(results.len());
+        for (name, reliability, indicators, mut report) in results {
+            report.fetched = indicators.len();
+            for mut indicator in indicators {
+                if let Err(e) = self.prepare_indicator(&mut indicator) {
+                    tracing::warn!("Rejected indicator from {}: {}", name, e);
+                    report.rejected += 1;
+                    continue;
+                }
+                match self.merge_indicator(indicator, reliability) {
+                    MergeOutcome::New => report.new += 1,
+                    MergeOutcome::Updated => report.updated += 1,
+                    MergeOutcome::Duplicate => report.duplicates += 1,
+                }
             }
 
-            match source.fetch_indicators().await {
-                Ok(indicators) => {
-                    for indicator in indicators {
-                        self.indicators.insert(indicator.id, indicator);
-                        total_fetched += 1;
-                    }
-                    tracing::info!("Fetched {} indicators from {}", total_fetched, name);
+            if report.succeeded() {
+                tracing::info!(
+                    "Fetched {} indicators from {} ({} new, {} updated, {} duplicates) in {:?}",
+                    report.fetched, name, report.new, report.updated, report.duplicates, report.duration
+                );
+            }
+
+            self.fetch_reports.insert(name, report.clone());
+            reports.push(report);
+        }
+
+        Ok(reports)
+    }
+
+    /// Add a single indicator, merging it with any known indicator of the same type and value
+    pub fn add_indicator(&mut self, mut indicator: ThreatIndicator) -> Result<Uuid> {
+        self.prepare_indicator(&mut indicator)?;
+        let key = indicator.dedup_key();
+        self.merge_indicator(indicator, 1.0);
+        Ok(self.indicator_keys[&key])
+    }
+
+    /// Validate rule content and normalize ATT&CK references before an indicator is stored
+    fn prepare_indicator(&self, indicator: &mut ThreatIndicator) -> Result<()> {
+        apply_rule_metadata(indicator)?;
+        if let Some(attack) = &self.attack {
+            attack.normalize_indicator(indicator);
+        }
+        Ok(())
+    }
+
+    /// Get indicator by ID
+    pub fn get_indicator(&self, id: &Uuid) -> Option<&ThreatIndicator> {
+        self.indicators.get(id)
+    }
+
+    /// Find indicator by type and value
+    pub fn find_indicator(&self, indicator_type: &IndicatorType, value: &str) -> Option<&ThreatIndicator> {
+        let probe = ThreatIndicator::new(indicator_type.clone(), value, "");
+        self.indicator_keys.get(&probe.dedup_key())
+            .and_then(|id| self.indicators.get(id))
+    }
+
+    /// Merge an incoming indicator into the store, deduplicating on type and normalized value
+    fn merge_indicator(&mut self, mut incoming: ThreatIndicator, reliability: f32) -> MergeOutcome {
+        let key = incoming.dedup_key();
+
+        let existing_id = match self.indicator_keys.get(&key) {
+            Some(id) => *id,
+            None => {
+                incoming.value = key.1.clone();
+                if incoming.sightings.is_empty() {
+                    incoming.sightings.push(sighting_from(&incoming, reliability));
                 }
-                Err(e) => {
-                    tracing::error!("Failed to fetch indicators from {}: {}", name, e);
+                incoming.confidence = verdict_confidence(corroborated_confidence(&incoming.sightings), &mut incoming.attributes);
+                self.indicator_keys.insert(key, incoming.id);
+                self.indicators.insert(incoming.id, incoming);
+                return MergeOutcome::New;
+            }
+        };
+
+        let existing = self.indicators.get_mut(&existing_id)
+            .expect("indicator key index out of sync");
+        let mut changed = false;
+
+        match existing.sightings.iter_mut().find(|s| s.source == incoming.source) {
+            Some(sighting) => {
+                sighting.count += 1;
+                sighting.source_reliability = reliability;
+                if incoming.first_seen < sighting.first_seen {
+                    sighting.first_seen = incoming.first_seen;
+                    changed = true;
+                }
+                if incoming.last_seen > sighting.last_seen {
+                    sighting.last_seen = incoming.last_seen;
+                    changed = true;
                 }
+                if incoming.confidence != sighting.confidence {
+                    sighting.confidence = incoming.confidence;
+                    changed = true;
+                }
+            }
+            None => {
+                existing.sightings.push(sighting_from(&incoming, reliability));
+                changed = true;
             }
+        }
 
-            // Rate limiting
-            sleep(std::time::Duration::from_secs(1)).await;
+        if incoming.first_seen < existing.first_seen {
+            existing.first_seen = incoming.first_seen;
+            changed = true;
+        }
+        if incoming.last_seen > existing.last_seen {
+            existing.last_seen = incoming.last_seen;
+            changed = true;
+        }
+        // Lower ordinal means more severe
+        if incoming.severity < existing.severity {
+            existing.severity = incoming.severity.clone();
+            changed = true;
+        }
+        if let Some(valid_until) = incoming.valid_until {
+            if existing.valid_until.is_some_and(|current| valid_until > current) {
+                existing.valid_until = Some(valid_until);
+                changed = true;
+            }
+        }
+        if existing.context.is_none() && incoming.context.is_some() {
+            existing.context = incoming.context.take();
+            changed = true;
+        }
+        for tactic in incoming.mitre_tactics {
+            if !existing.mitre_tactics.contains(&tactic) {
+                existing.mitre_tactics.push(tactic);
+                changed = true;
+            }
+        }
+        for technique in incoming.mitre_techniques {
+            if !existing.mitre_techniques.contains(&technique) {
+                existing.mitre_techniques.push(technique);
+                changed = true;
+            }
+        }
+        for tag in incoming.tags {
+            if !existing.tags.contains(&tag) {
+                existing.tags.push(tag);
+                changed = true;
+            }
+        }
+        for (key, value) in incoming.attributes {
+            if let Entry::Vacant(slot) = existing.attributes.entry(key) {
+                slot.insert(value);
+                changed = true;
+            }
         }
 
-        Ok(total_fetched)
+        // Enrichment verdicts stay applied on top of the corroborated confidence
+        existing.confidence = verdict_confidence(corroborated_confidence(&existing.sightings), &mut existing.attributes);
+
+        // Fresh sightings revive expired indicators; analyst decisions stand
+        if existing.status == IndicatorStatus::Expired && self.lifecycle.expires_at(existing) > Utc::now() {
+            existing.status = IndicatorStatus::Active;
+            changed = true;
+        }
+
+        if changed {
+            MergeOutcome::Updated
+        } else {
+            MergeOutcome::Duplicate
+        }
+    }
+
+    /// Enrich all active indicators, returning outcomes per indicator ID
+    pub async fn enrich_indicators(&mut self, enrichment: &EnrichmentEngine) -> HashMap<Uuid, Vec<EnrichmentOutcome>> {
+        let now = Utc::now();
+        let mut results = HashMap::new();
+
+        for indicator in self.indicators.values_mut() {
+            if self.lifecycle.status_at(indicator, now) != IndicatorStatus::Active {
+                continue;
+            }
+            let outcomes = enrichment.enrich_indicator(indicator).await;
+            if !outcomes.is_empty() {
+                results.insert(indicator.id, outcomes);
+            }
+        }
+
+        results
+    }
+
+    /// Set indicator expiry and aging policy
+    pub fn set_lifecycle_policy(&mut self, policy: LifecyclePolicy) {
+        self.lifecycle = policy;
+    }
+
+    /// Mark active indicators past their expiry as expired, returning how many changed
+    pub fn expire_indicators(&mut self) -> usize {
+        let now = Utc::now();
+        let mut expired = 0;
+
+        for indicator in self.indicators.values_mut() {
+            if indicator.status == IndicatorStatus::Active && self.lifecycle.expires_at(indicator) <= now {
+                indicator.status = IndicatorStatus::Expired;
+                expired += 1;
+            }
+        }
+
+        if expired > 0 {
+            tracing::info!("Expired {} threat indicators", expired);
+        }
+        expired
+    }
+
+    /// Revoke an indicator so it no longer matches searches or correlations
+    pub fn revoke_indicator(&mut self, id: &Uuid, reason: String, analyst_id: Uuid) -> Result<()> {
+        self.set_analyst_status(id, IndicatorStatus::Revoked, reason, analyst_id)
+    }
+
+    /// Mark an indicator as a confirmed false positive
+    pub fn mark_false_positive(&mut self, id: &Uuid, reason: String, analyst_id: Uuid) -> Result<()> {
+        self.set_analyst_status(id, IndicatorStatus::FalsePositive, reason, analyst_id)
+    }
+
+    /// Return a revoked, false-positive or expired indicator to active use
+    pub fn reactivate_indicator(&mut self, id: &Uuid) -> Result<()> {
+        let indicator = self.indicators.get_mut(id)
+            .ok_or_else(|| Error::NotFound(format!("Indicator {} not found", id)))?;
+        indicator.status = IndicatorStatus::Active;
+        indicator.revocation = None;
+
+        // Restart the TTL without recording a sighting, so decay and activity are unaffected
+        let now = Utc::now();
+        if self.lifecycle.expires_at(indicator) <= now {
+            if indicator.valid_until.is_some() {
+                indicator.valid_until = Some(now + self.lifecycle.ttl(&indicator.indicator_type));
+            } else {
+                indicator.attributes.insert(REACTIVATED_AT_ATTRIBUTE.to_string(), serde_json::json!(now));
+            }
+        }
+        Ok(())
+    }
+
+    fn set_analyst_status(&mut self, id: &Uuid, status: IndicatorStatus, reason: String, analyst_id: Uuid) -> Result<()> {
+        let indicator = self.indicators.get_mut(id)
+            .ok_or_else(|| Error::NotFound(format!("Indicator {} not found", id)))?;
+        tracing::info!("Indicator {} marked {:?} by {}: {}", indicator.value, status, analyst_id, reason);
+        indicator.status = status;
+        indicator.revocation = Some(IndicatorRevocation {
+            reason,
+            analyst_id,
+            revoked_at: Utc::now(),
+        });
+        Ok(())
+    }
+
+    /// Whether the indicator is currently active
+    pub fn is_active(&self, indicator: &ThreatIndicator) -> bool {
+        self.lifecycle.status_at(indicator, Utc::now()) == IndicatorStatus::Active
+    }
+
+    /// Indicator confidence after aging since last sighting
+    pub fn effective_confidence(&self, indicator: &ThreatIndicator) -> f32 {
+        self.lifecycle.decayed_confidence(indicator, Utc::now())
+    }
+
+    /// Validate and normalize ATT&CK references of ingested indicators against a knowledge base
+    pub fn set_attack_knowledge_base(&mut self, attack: Arc<AttackKnowledgeBase>) {
+        self.attack = Some(attack);
+    }
+
+    /// ATT&CK techniques observed across active indicators
+    pub fn attack_coverage(&self) -> Option<AttackCoverage> {
+        let attack = self.attack.as_ref()?;
+        Some(attack.coverage(self.indicators.values().filter(|i| self.is_active(i))))
+    }
+
+    /// Reconstruct the kill chain timeline for the given indicators
+    pub fn kill_chain(&self, indicator_ids: &[Uuid]) -> KillChainAnalysis {
+        KillChainAnalyzer::new().analyze(indicator_ids.iter().filter_map(|id| self.indicators.get(id)))
+    }
+
+    /// Reconstruct the kill chain timeline for the indicators of a correlation
+    pub fn correlation_kill_chain(&self, correlation: &CorrelationResult) -> KillChainAnalysis {
+        self.kill_chain(&correlation.matched_indicators)
+    }
+
+    /// Register or replace a threat actor profile used by attribution rules
+    pub fn add_actor_profile(&mut self, profile: ActorProfile) {
+        self.attribution.add_profile(profile);
+    }
+
+    /// Replace the attribution engine and its actor profiles
+    pub fn set_attribution_engine(&mut self, attribution: AttributionEngine) {
+        self.attribution = attribution;
+    }
+
+    /// Rank known actors against the given indicators
+    pub fn attribute_indicators(&self, indicator_ids: &[Uuid]) -> Vec<AttributionCandidate> {
+        self.attribution.attribute(indicator_ids.iter().filter_map(|id| self.indicators.get(id)))
+    }
+
+    /// Latest fetch report for every source that has been fetched
+    pub fn fetch_reports(&self) -> &HashMap<String, FetchReport> {
+        &self.fetch_reports
     }
 
     /// Add correlation rule
@@ -179,12 +817,18 @@ impl ThreatIntelEngine {
                 let correlation_score = self.calculate_correlation_score(rule, &matched_indicators);
                 
                 if correlation_score >= 0.7 {
+                    let attributions = if rule.rule_type == CorrelationType::Attribution {
+                        self.attribution.attribute(matched_indicators.iter().filter_map(|id| self.indicators.get(id)))
+                    } else {
+                        Vec::new()
+                    };
                     let result = CorrelationResult {
                         rule_id: rule.id,
                         matched_indicators: matched_indicators.into_iter().collect(),
                         correlation_score,
                         created_at: Utc::now(),
                         actions_taken: rule.actions.clone(),
+                        attributions,
                     };
                     results.push(result);
                 }
@@ -198,7 +842,7 @@ impl ThreatIntelEngine {
     async fn find_matching_indicators(&self, rule: &CorrelationRule) -> Result<HashSet<Uuid>> {
         let mut matched = HashSet::new();
 
-        for indicator in self.indicators.values() {
+        for indicator in self.indicators.values().filter(|i| self.is_active(i)) {
             let mut condition_matches = 0;
             let mut total_weight = 0.0;
             let mut matched_weight = 0.0;
@@ -295,7 +939,7 @@ impl ThreatIntelEngine {
 
         for indicator_id in matched_indicators {
             if let Some(indicator) = self.indicators.get(indicator_id) {
-                total_confidence += indicator.confidence;
+                total_confidence += self.effective_confidence(indicator);
                 valid_indicators += 1;
             }
         }
@@ -313,14 +957,22 @@ impl ThreatIntelEngine {
         let mut threat_types = HashMap::new();
         let mut severities = HashMap::new();
         let mut sources = HashMap::new();
+        let mut statuses = HashMap::new();
         let mut recent_count = 0;
+        let mut raw_count = 0;
+        let now = Utc::now();
 
         let one_day_ago = Utc::now() - Duration::days(1);
 
         for indicator in self.indicators.values() {
             *threat_types.entry(indicator.threat_type.clone()).or_insert(0) += 1;
             *severities.entry(indicator.severity.clone()).or_insert(0) += 1;
-            *sources.entry(indicator.source.clone()).or_insert(0) += 1;
+            *statuses.entry(self.lifecycle.status_at(indicator, now)).or_insert(0) += 1;
+
+            for sighting in &indicator.sightings {
+                *sources.entry(sighting.source.clone()).or_insert(0) += 1;
+                raw_count += sighting.count as usize;
+            }
 
             if indicator.first_seen >= one_day_ago {
                 recent_count += 1;
@@ -329,17 +981,28 @@ impl ThreatIntelEngine {
 
         ThreatStatistics {
             total_indicators: self.indicators.len(),
+            raw_indicators: raw_count,
             recent_indicators: recent_count,
             threat_types,
             severities,
             sources,
+            statuses,
+            fetch_reports: self.fetch_reports.clone(),
         }
     }
 
     /// Search indicators by criteria
     pub fn search_indicators(&self, query: &ThreatQuery) -> Vec<&ThreatIndicator> {
+        let now = Utc::now();
         self.indicators.values()
             .filter(|indicator| query.matches(indicator))
+            .filter(|indicator| {
+                query.include_inactive || self.lifecycle.status_at(indicator, now) == IndicatorStatus::Active
+            })
+            .filter(|indicator| {
+                query.min_confidence
+                    .is_none_or(|min| self.lifecycle.decayed_confidence(indicator, now) >= min)
+            })
             .collect()
     }
 }
@@ -353,6 +1016,9 @@ pub struct ThreatQuery {
     pub value_pattern: Option<String>,
     pub date_range: Option<(DateTime<Utc>, DateTime<Utc>)>,
     pub min_confidence: Option<f32>,
+    /// Also return expired, revoked and false-positive indicators
+    #[serde(default)]
+    pub include_inactive: bool,
 }
 
 impl ThreatQuery {
@@ -369,8 +1035,11 @@ impl ThreatQuery {
             }
         }
 
+        // Merged indicators match any source that reported them
         if let Some(sources) = &self.sources {
-            if !sources.contains(&indicator.source) {
+            let reported = sources.contains(&indicator.source)
+                || indicator.sightings.iter().any(|s| sources.contains(&s.source));
+            if !reported {
                 return false;
             }
         }
@@ -400,11 +1069,37 @@ impl ThreatQuery {
 /// Threat intelligence statistics
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ThreatStatistics {
+    /// Unique indicators after deduplication
     pub total_indicators: usize,
+    /// Indicator reports received from all sources before deduplication
+    pub raw_indicators: usize,
     pub recent_indicators: usize,
     pub threat_types: HashMap<ThreatType, usize>,
     pub severities: HashMap<ThreatSeverity, usize>,
     pub sources: HashMap<String, usize>,
+    pub statuses: HashMap<IndicatorStatus, usize>,
+    /// Latest fetch report per source
+    pub fetch_reports: HashMap<String, FetchReport>,
+}
+
+/// Build the sighting recorded for an incoming indicator
+fn sighting_from(indicator: &ThreatIndicator, reliability: f32) -> IndicatorSighting {
+    IndicatorSighting {
+        source: indicator.source.clone(),
+        source_reliability: reliability,
+        confidence: indicator.confidence,
+        first_seen: indicator.first_seen,
+        last_seen: indicator.last_seen,
+        count: 1,
+    }
+}
+
+/// Combine sightings into a single confidence, treating each source as independent evidence
+fn corroborated_confidence(sightings: &[IndicatorSighting]) -> f32 {
+    let disbelief: f32 = sightings.iter()
+        .map(|s| 1.0 - (s.confidence * s.source_reliability).clamp(0.0, 1.0))
+        .product();
+    (1.0 - disbelief).clamp(0.0, 1.0)
 }
 
 /// MISP threat intelligence source implementation
@@ -456,6 +1151,74 @@ impl Default for ThreatIntelEngine {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Source that fails a configurable number of times before succeeding
+    struct FlakySource {
+        failures: AtomicU32,
+        retriable: bool,
+        delay: std::time::Duration,
+        indicators: Vec<ThreatIndicator>,
+    }
+
+    impl FlakySource {
+        fn new(failures: u32, retriable: bool, indicators: Vec<ThreatIndicator>) -> Self {
+            Self {
+                failures: AtomicU32::new(failures),
+                retriable,
+                delay: std::time::Duration::ZERO,
+                indicators,
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ThreatSource for FlakySource {
+        async fn fetch_indicators(&self) -> Result<Vec<ThreatIndicator>> {
+            sleep(self.delay).await;
+            if self.failures.load(Ordering::SeqCst) > 0 {
+                self.failures.fetch_sub(1, Ordering::SeqCst);
+                return if self.retriable {
+                    Err(Error::Internal("temporary failure".to_string()))
+                } else {
+                    Err(Error::Parsing("malformed feed".to_string()))
+                };
+            }
+            Ok(self.indicators.clone())
+        }
+
+        fn name(&self) -> &str {
+            "flaky"
+        }
+
+        fn source_type(&self) -> ThreatSourceType {
+            ThreatSourceType::Internal
+        }
+
+        async fn is_available(&self) -> bool {
+            true
+        }
+    }
+
+    fn fast_policy() -> SourcePolicy {
+        SourcePolicy {
+            min_interval: std::time::Duration::ZERO,
+            timeout: std::time::Duration::from_millis(200),
+            max_retries: 3,
+            initial_backoff: std::time::Duration::from_millis(1),
+            max_backoff: std::time::Duration::from_millis(5),
+            failure_threshold: 2,
+            reset_timeout: std::time::Duration::from_secs(60),
+        }
+    }
+
+    fn test_indicator(value: &str) -> ThreatIndicator {
+        ThreatIndicator {
+            severity: ThreatSeverity::High,
+            confidence: 0.9,
+            ..ThreatIndicator::new(IndicatorType::IpAddress, value, "test")
+        }
+    }
 
     #[test]
     fn test_threat_intel_engine_creation() {
@@ -482,6 +1245,11 @@ mod tests {
             context: None,
             mitre_tactics: vec!["initial-access".to_string()],
             mitre_techniques: vec!["T1566".to_string()],
+            tags: Vec::new(),
+            attributes: HashMap::new(),
+            sightings: Vec::new(),
+            status: IndicatorStatus::Active,
+            revocation: None,
         };
 
         let condition = CorrelationCondition {
@@ -493,4 +1261,213 @@ mod tests {
 
         assert!(engine.evaluate_condition(&condition, &indicator));
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_fetch_retries_retriable_errors() {
+        let mut engine = ThreatIntelEngine::new();
+        let source = FlakySource::new(2, true, vec![test_indicator("1.1.1.1"), test_indicator("2.2.2.2")]);
+        engine.add_source_with_policy("flaky".to_string(), Box::new(source), fast_policy());
+
+        let reports = engine.fetch_all_indicators().await.unwrap();
+        assert_eq!(reports.len(), 1);
+
+        let report = &reports[0];
+        assert!(report.succeeded());
+        assert_eq!(report.attempts, 3);
+        assert_eq!(report.errors.len(), 2);
+        assert_eq!(report.fetched, 2);
+        assert_eq!(report.new, 2);
+        assert_eq!(report.circuit_state, CircuitState::Closed);
+        assert_eq!(engine.get_threat_stats().fetch_reports["flaky"].new, 2);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_does_not_retry_permanent_errors() {
+        let mut engine = ThreatIntelEngine::new();
+        let source = FlakySource::new(1, false, vec![test_indicator("1.1.1.1")]);
+        engine.add_source_with_policy("broken".to_string(), Box::new(source), fast_policy());
+
+        let report = engine.fetch_all_indicators().await.unwrap().remove(0);
+        assert!(!report.succeeded());
+        assert_eq!(report.attempts, 1);
+        assert_eq!(report.fetched, 0);
+        assert_eq!(engine.indicators.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_opens_after_repeated_failures() {
+        let mut engine = ThreatIntelEngine::new();
+        let source = FlakySource::new(u32::MAX, false, Vec::new());
+        engine.add_source_with_policy("flapping".to_string(), Box::new(source), fast_policy());
+
+        engine.fetch_all_indicators().await.unwrap();
+        let report = engine.fetch_all_indicators().await.unwrap().remove(0);
+        assert_eq!(report.circuit_state, CircuitState::Open);
+
+        // Open circuit rejects the next fetch without contacting the source
+        let report = engine.fetch_all_indicators().await.unwrap().remove(0);
+        assert_eq!(report.attempts, 0);
+        assert_eq!(report.circuit_state, CircuitState::Open);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_timeout_and_duplicate_accounting() {
+        let mut engine = ThreatIntelEngine::new();
+        let mut slow = FlakySource::new(0, true, vec![test_indicator("3.3.3.3")]);
+        slow.delay = std::time::Duration::from_secs(5);
+        let policy = SourcePolicy { max_retries: 0, ..fast_policy() };
+        engine.add_source_with_policy("slow".to_string(), Box::new(slow), policy);
+
+        let indicators = vec![test_indicator("1.1.1.1"), test_indicator("2.2.2.2")];
+        engine.add_source_with_policy("steady".to_string(), Box::new(FlakySource::new(0, true, indicators)), fast_policy());
+
+        let reports = engine.fetch_all_indicators().await.unwrap();
+        let slow_report = reports.iter().find(|r| r.source == "slow").unwrap();
+        assert!(slow_report.errors[0].contains("timed out"));
+
+        let reports = engine.fetch_all_indicators().await.unwrap();
+        let steady_report = reports.iter().find(|r| r.source == "steady").unwrap();
+        assert_eq!(steady_report.new, 0);
+        assert_eq!(steady_report.duplicates, 2);
+    }
+
+    #[test]
+    fn test_indicator_deduplication_across_sources() {
+        let mut engine = ThreatIntelEngine::new();
+        let earlier = Utc::now() - Duration::days(3);
+
+        let misp = ThreatIndicator {
+            confidence: 0.6,
+            first_seen: earlier,
+            last_seen: earlier,
+            ..ThreatIndicator::new(IndicatorType::Domain, "Evil.Example.com", "misp")
+        };
+        let otx = ThreatIndicator {
+            confidence: 0.6,
+            severity: ThreatSeverity::Critical,
+            ..ThreatIndicator::new(IndicatorType::Domain, "evil.example.com", "otx")
+        };
+
+        let id = engine.add_indicator(misp).unwrap();
+        assert_eq!(engine.merge_indicator(otx.clone(), 1.0), MergeOutcome::Updated);
+        assert_eq!(engine.merge_indicator(otx, 1.0), MergeOutcome::Duplicate);
+
+        let merged = engine.get_indicator(&id).unwrap();
+        assert_eq!(merged.sightings.len(), 2);
+        assert_eq!(merged.first_seen, earlier);
+        assert_eq!(merged.severity, ThreatSeverity::Critical);
+        assert!((merged.confidence - 0.84).abs() < 1e-5);
+
+        let stats = engine.get_threat_stats();
+        assert_eq!(stats.total_indicators, 1);
+        assert_eq!(stats.raw_indicators, 3);
+        assert_eq!(stats.sources["otx"], 1);
+
+        let by_source = |source: &str| engine.search_indicators(&ThreatQuery { sources: Some(vec![source.to_string()]), ..query_all() }).len();
+        assert_eq!(by_source("otx"), 1);
+        assert_eq!(by_source("misp"), 1);
+        assert_eq!(by_source("abuse.ch"), 0);
+    }
+
+    fn query_all() -> ThreatQuery {
+        ThreatQuery {
+            threat_types: None,
+            severities: None,
+            sources: None,
+            value_pattern: None,
+            date_range: None,
+            min_confidence: None,
+            include_inactive: false,
+        }
+    }
+
+    #[test]
+    fn test_expired_and_revoked_indicators_excluded_from_search() {
+        let mut engine = ThreatIntelEngine::new();
+        let expired = ThreatIndicator {
+            valid_until: Some(Utc::now() - Duration::hours(1)),
+            ..ThreatIndicator::new(IndicatorType::Hash, "d41d8cd98f00b204e9800998ecf8427e", "test")
+        };
+        let stale_ip = ThreatIndicator {
+            last_seen: Utc::now() - Duration::days(45),
+            ..ThreatIndicator::new(IndicatorType::IpAddress, "203.0.113.7", "test")
+        };
+        let revoked_id = engine.add_indicator(test_indicator("198.51.100.1")).unwrap();
+        let active_id = engine.add_indicator(test_indicator("198.51.100.2")).unwrap();
+        engine.add_indicator(expired).unwrap();
+        engine.add_indicator(stale_ip).unwrap();
+
+        engine.revoke_indicator(&revoked_id, "sinkholed".to_string(), Uuid::new_v4()).unwrap();
+
+        let results = engine.search_indicators(&query_all());
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, active_id);
+
+        let all = engine.search_indicators(&ThreatQuery { include_inactive: true, ..query_all() });
+        assert_eq!(all.len(), 4);
+
+        assert_eq!(engine.expire_indicators(), 2);
+        assert_eq!(engine.get_threat_stats().statuses[&IndicatorStatus::Revoked], 1);
+    }
+
+    #[test]
+    fn test_confidence_decay_and_reactivation() {
+        let mut engine = ThreatIntelEngine::new();
+        // Default IP TTL is 30 days, so confidence halves after 15
+        let aged = ThreatIndicator {
+            confidence: 0.8,
+            first_seen: Utc::now() - Duration::days(40),
+            last_seen: Utc::now() - Duration::days(15),
+            ..ThreatIndicator::new(IndicatorType::IpAddress, "192.0.2.10", "test")
+        };
+        let id = engine.add_indicator(aged).unwrap();
+        let indicator = engine.get_indicator(&id).unwrap();
+        assert!((engine.effective_confidence(indicator) - 0.4).abs() < 0.01);
+
+        engine.set_lifecycle_policy(LifecyclePolicy {
+            ttl_days: HashMap::from([(IndicatorType::IpAddress, 10)]),
+            ..LifecyclePolicy::default()
+        });
+        assert_eq!(engine.expire_indicators(), 1);
+
+        // A fresh sighting revives the expired indicator
+        engine.add_indicator(ThreatIndicator::new(IndicatorType::IpAddress, "192.0.2.10", "otx")).unwrap();
+        assert_eq!(engine.get_indicator(&id).unwrap().status, IndicatorStatus::Active);
+
+        engine.mark_false_positive(&id, "CDN edge node".to_string(), Uuid::new_v4()).unwrap();
+        engine.add_indicator(ThreatIndicator::new(IndicatorType::IpAddress, "192.0.2.10", "misp")).unwrap();
+        assert_eq!(engine.get_indicator(&id).unwrap().status, IndicatorStatus::FalsePositive);
+    }
+
+    #[test]
+    fn test_reactivation_keeps_last_seen() {
+        let mut engine = ThreatIntelEngine::new();
+        let last_seen = Utc::now() - Duration::days(45);
+        let stale = ThreatIndicator {
+            confidence: 0.8,
+            first_seen: last_seen,
+            last_seen,
+            ..ThreatIndicator::new(IndicatorType::IpAddress, "192.0.2.20", "test")
+        };
+        let id = engine.add_indicator(stale).unwrap();
+        assert_eq!(engine.expire_indicators(), 1);
+
+        engine.reactivate_indicator(&id).unwrap();
+        let indicator = engine.get_indicator(&id).unwrap();
+        assert!(engine.is_active(indicator));
+        assert_eq!(indicator.last_seen, last_seen);
+        assert!(engine.effective_confidence(indicator) < 0.3);
+        assert_eq!(engine.expire_indicators(), 0);
+
+        // An explicit validity period is extended instead
+        let bounded = ThreatIndicator {
+            valid_until: Some(Utc::now() - Duration::days(1)),
+            ..ThreatIndicator::new(IndicatorType::Domain, "expired.example.com", "test")
+        };
+        let id = engine.add_indicator(bounded).unwrap();
+        engine.reactivate_indicator(&id).unwrap();
+        let indicator = engine.get_indicator(&id).unwrap();
+        assert!(engine.is_active(indicator));
+        assert!(indicator.valid_until.unwrap() > Utc::now() + Duration::days(89));
+    }
+}
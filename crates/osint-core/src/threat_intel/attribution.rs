@@ -0,0 +1,528 @@
+//! Threat actor attribution scoring against known actor profiles
+
+use crate::models::*;
+use serde::{Deserialize, Serialize};
+use chrono::{Timelike, Utc};
+use std::collections::{BTreeSet, HashMap};
+use std::net::IpAddr;
+use uuid::Uuid;
+
+/// Weights of the evidence categories in the final score
+const TECHNIQUE_WEIGHT: f32 = 0.35;
+const INFRASTRUCTURE_WEIGHT: f32 = 0.3;
+const TOOL_WEIGHT: f32 = 0.2;
+const TARGETING_WEIGHT: f32 = 0.1;
+const TIMEZONE_WEIGHT: f32 = 0.05;
+
+/// Minimum timestamps before activity hours are considered evidence
+const MIN_TIMESTAMPS: usize = 3;
+
+/// Known behaviour of a threat actor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActorProfile {
+    pub id: Uuid,
+    pub name: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// ATT&CK technique IDs
+    #[serde(default)]
+    pub techniques: Vec<String>,
+    /// IP addresses, CIDR ranges and domains; domains also match their subdomains
+    #[serde(default)]
+    pub infrastructure: Vec<String>,
+    /// Malware families and tools
+    #[serde(default)]
+    pub tools: Vec<String>,
+    #[serde(default)]
+    pub target_sectors: Vec<String>,
+    #[serde(default)]
+    pub target_countries: Vec<String>,
+    /// UTC offsets in hours the actor is believed to operate from
+    #[serde(default)]
+    pub utc_offsets: Vec<i32>,
+}
+
+impl ActorProfile {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            aliases: Vec::new(),
+            techniques: Vec::new(),
+            infrastructure: Vec::new(),
+            tools: Vec::new(),
+            target_sectors: Vec::new(),
+            target_countries: Vec::new(),
+            utc_offsets: Vec::new(),
+        }
+    }
+
+    /// Build profile from a `ThreatActor` entity whose attributes hold the profile fields
+    pub fn from_entity(entity: &IntelEntity) -> Option<Self> {
+        if entity.entity_type != EntityType::ThreatActor {
+            return None;
+        }
+
+        let list = |key: &str| -> Vec<String> {
+            entity.attributes.get(key)
+                .and_then(|v| serde_json::from_value(v.clone()).ok())
+                .unwrap_or_default()
+        };
+
+        Some(Self {
+            id: entity.id,
+            name: entity.name.clone(),
+            aliases: list("aliases"),
+            techniques: list("techniques"),
+            infrastructure: list("infrastructure"),
+            tools: list("tools"),
+            target_sectors: list("target_sectors"),
+            target_countries: list("target_countries"),
+            utc_offsets: entity.attributes.get("utc_offsets")
+                .and_then(|v| serde_json::from_value(v.clone()).ok())
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Convert profile to a `ThreatActor` entity
+    pub fn to_entity(&self, source: &str) -> IntelEntity {
+        let mut attributes = HashMap::new();
+        attributes.insert("aliases".to_string(), serde_json::json!(self.aliases));
+        attributes.insert("techniques".to_string(), serde_json::json!(self.techniques));
+        attributes.insert("infrastructure".to_string(), serde_json::json!(self.infrastructure));
+        attributes.insert("tools".to_string(), serde_json::json!(self.tools));
+        attributes.insert("target_sectors".to_string(), serde_json::json!(self.target_sectors));
+        attributes.insert("target_countries".to_string(), serde_json::json!(self.target_countries));
+        attributes.insert("utc_offsets".to_string(), serde_json::json!(self.utc_offsets));
+
+        let now = Utc::now();
+        IntelEntity {
+            id: self.id,
+            entity_type: EntityType::ThreatActor,
+            name: self.name.clone(),
+            description: None,
+            confidence: 1.0,
+            source: source.to_string(),
+            created_at: now,
+            updated_at: now,
+            tags: self.aliases.clone(),
+            attributes,
+            location: None,
+            relationships: Vec::new(),
+        }
+    }
+
+    /// Whether a name refers to this actor
+    pub fn is_named(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name) || self.aliases.iter().any(|a| a.eq_ignore_ascii_case(name))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum EvidenceKind {
+    Technique,
+    Infrastructure,
+    Tool,
+    Targeting,
+    TimeZone,
+}
+
+/// Single explainable reason supporting an attribution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttributionEvidence {
+    pub kind: EvidenceKind,
+    pub description: String,
+    pub indicators: Vec<Uuid>,
+}
+
+/// Ranked attribution candidate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttributionCandidate {
+    pub actor_id: Uuid,
+    pub actor_name: String,
+    /// Weighted score between 0.0 and 1.0
+    pub score: f32,
+    pub technique_score: f32,
+    pub infrastructure_score: f32,
+    pub tool_score: f32,
+    pub targeting_score: f32,
+    pub timezone_score: f32,
+    pub evidence: Vec<AttributionEvidence>,
+}
+
+/// Scores indicator sets against actor profiles
+#[derive(Debug, Clone)]
+pub struct AttributionEngine {
+    profiles: Vec<ActorProfile>,
+    min_score: f32,
+}
+
+impl AttributionEngine {
+    pub fn new() -> Self {
+        Self {
+            profiles: Vec::new(),
+            min_score: 0.1,
+        }
+    }
+
+    /// Drop candidates scoring below this threshold
+    pub fn with_min_score(mut self, min_score: f32) -> Self {
+        self.min_score = min_score;
+        self
+    }
+
+    /// Add or replace an actor profile
+    pub fn add_profile(&mut self, profile: ActorProfile) {
+        self.profiles.retain(|p| p.id != profile.id);
+        self.profiles.push(profile);
+    }
+
+    pub fn profiles(&self) -> &[ActorProfile] {
+        &self.profiles
+    }
+
+    /// Find profile by name or alias
+    pub fn find_profile(&self, name: &str) -> Option<&ActorProfile> {
+        self.profiles.iter().find(|p| p.is_named(name))
+    }
+
+    /// Rank actors by how well they explain the given indicators
+    pub fn attribute<'a>(&self, indicators: impl IntoIterator<Item = &'a ThreatIndicator>) -> Vec<AttributionCandidate> {
+        let indicators: Vec<&ThreatIndicator> = indicators.into_iter().collect();
+        if indicators.is_empty() {
+            return Vec::new();
+        }
+
+        let mut candidates: Vec<AttributionCandidate> = self.profiles.iter()
+            .map(|profile| score_profile(profile, &indicators))
+            .filter(|candidate| candidate.score >= self.min_score)
+            .collect();
+
+        candidates.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+        candidates
+    }
+}
+
+impl Default for AttributionEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn score_profile(profile: &ActorProfile, indicators: &[&ThreatIndicator]) -> AttributionCandidate {
+    let mut evidence = Vec::new();
+    let technique_score = score_techniques(profile, indicators, &mut evidence);
+    let infrastructure_score = score_infrastructure(profile, indicators, &mut evidence);
+    let tool_score = score_tools(profile, indicators, &mut evidence);
+    let targeting_score = score_targeting(profile, indicators, &mut evidence);
+    let timezone_score = score_timezone(profile, indicators, &mut evidence);
+
+    AttributionCandidate {
+        actor_id: profile.id,
+        actor_name: profile.name.clone(),
+        score: technique_score * TECHNIQUE_WEIGHT
+            + infrastructure_score * INFRASTRUCTURE_WEIGHT
+            + tool_score * TOOL_WEIGHT
+            + targeting_score * TARGETING_WEIGHT
+            + timezone_score * TIMEZONE_WEIGHT,
+        technique_score,
+        infrastructure_score,
+        tool_score,
+        targeting_score,
+        timezone_score,
+        evidence,
+    }
+}
+
+/// Share of observed techniques the actor is known to use; a shared parent technique counts half
+fn score_techniques(profile: &ActorProfile, indicators: &[&ThreatIndicator], evidence: &mut Vec<AttributionEvidence>) -> f32 {
+    let known: BTreeSet<String> = profile.techniques.iter().map(|t| t.trim().to_uppercase()).collect();
+    let parent = |id: &str| id.split('.').next().unwrap_or(id).to_string();
+    let known_parents: BTreeSet<String> = known.iter().map(|t| parent(t)).collect();
+
+    let mut observed: HashMap<String, Vec<Uuid>> = HashMap::new();
+    for indicator in indicators {
+        for technique in &indicator.mitre_techniques {
+            observed.entry(technique.trim().to_uppercase()).or_default().push(indicator.id);
+        }
+    }
+    if observed.is_empty() || known.is_empty() {
+        return 0.0;
+    }
+
+    let mut matched = 0.0;
+    let mut techniques: Vec<_> = observed.into_iter().collect();
+    techniques.sort();
+    let total = techniques.len() as f32;
+
+    for (technique, ids) in techniques {
+        if known.contains(&technique) {
+            matched += 1.0;
+            evidence.push(AttributionEvidence {
+                kind: EvidenceKind::Technique,
+                description: format!("{} uses {}", profile.name, technique),
+                indicators: ids,
+            });
+        } else if known_parents.contains(&parent(&technique)) {
+            matched += 0.5;
+            evidence.push(AttributionEvidence {
+                kind: EvidenceKind::Technique,
+                description: format!("{} uses techniques related to {}", profile.name, technique),
+                indicators: ids,
+            });
+        }
+    }
+
+    matched / total
+}
+
+/// Shared infrastructure is strong evidence; two matching indicators saturate the score
+fn score_infrastructure(profile: &ActorProfile, indicators: &[&ThreatIndicator], evidence: &mut Vec<AttributionEvidence>) -> f32 {
+    let mut matches = 0;
+
+    for indicator in indicators {
+        let Some(host) = indicator_host(indicator) else {
+            continue;
+        };
+        if let Some(entry) = profile.infrastructure.iter().find(|entry| infrastructure_matches(entry, &host)) {
+            matches += 1;
+            evidence.push(AttributionEvidence {
+                kind: EvidenceKind::Infrastructure,
+                description: format!("{} overlaps known {} infrastructure {}", indicator.value, profile.name, entry),
+                indicators: vec![indicator.id],
+            });
+        }
+    }
+
+    (matches as f32 * 0.5).min(1.0)
+}
+
+/// Known tools named in indicator tags, context or attributes
+fn score_tools(profile: &ActorProfile, indicators: &[&ThreatIndicator], evidence: &mut Vec<AttributionEvidence>) -> f32 {
+    if profile.tools.is_empty() {
+        return 0.0;
+    }
+
+    let mut found = 0;
+    for tool in &profile.tools {
+        let needle = tool.to_lowercase();
+        let ids: Vec<Uuid> = indicators.iter()
+            .filter(|indicator| indicator_mentions(indicator, &needle))
+            .map(|indicator| indicator.id)
+            .collect();
+        if !ids.is_empty() {
+            found += 1;
+            evidence.push(AttributionEvidence {
+                kind: EvidenceKind::Tool,
+                description: format!("{} is part of the {} toolset", tool, profile.name),
+                indicators: ids,
+            });
+        }
+    }
+
+    // One known tool is meaningful, two are near conclusive
+    (found as f32 * 0.6).min(1.0)
+}
+
+/// Targeted sectors or countries named in indicator tags or attributes
+fn score_targeting(profile: &ActorProfile, indicators: &[&ThreatIndicator], evidence: &mut Vec<AttributionEvidence>) -> f32 {
+    let targets: Vec<&String> = profile.target_sectors.iter().chain(&profile.target_countries).collect();
+    if targets.is_empty() {
+        return 0.0;
+    }
+
+    let mut found = 0;
+    for target in targets {
+        let needle = target.to_lowercase();
+        let ids: Vec<Uuid> = indicators.iter()
+            .filter(|indicator| indicator_mentions(indicator, &needle))
+            .map(|indicator| indicator.id)
+            .collect();
+        if !ids.is_empty() {
+            found += 1;
+            evidence.push(AttributionEvidence {
+                kind: EvidenceKind::Targeting,
+                description: format!("{} is known to target {}", profile.name, target),
+                indicators: ids,
+            });
+        }
+    }
+
+    (found as f32 * 0.5).min(1.0)
+}
+
+/// Share of activity falling into working hours of the actor's time zones
+fn score_timezone(profile: &ActorProfile, indicators: &[&ThreatIndicator], evidence: &mut Vec<AttributionEvidence>) -> f32 {
+    if profile.utc_offsets.is_empty() || indicators.len() < MIN_TIMESTAMPS {
+        return 0.0;
+    }
+
+    let mut best = (0.0, 0);
+    for offset in &profile.utc_offsets {
+        let in_hours = indicators.iter()
+            .filter(|indicator| {
+                let local_hour = (indicator.first_seen.hour() as i32 + offset).rem_euclid(24);
+                (8..18).contains(&local_hour)
+            })
+            .count();
+        let share = in_hours as f32 / indicators.len() as f32;
+        if share > best.0 {
+            best = (share, *offset);
+        }
+    }
+
+    if best.0 >= 0.5 {
+        evidence.push(AttributionEvidence {
+            kind: EvidenceKind::TimeZone,
+            description: format!("{:.0}% of activity falls into working hours at UTC{:+}", best.0 * 100.0, best.1),
+            indicators: Vec::new(),
+        });
+        best.0
+    } else {
+        0.0
+    }
+}
+
+fn indicator_mentions(indicator: &ThreatIndicator, needle: &str) -> bool {
+    indicator.tags.iter().any(|tag| tag.to_lowercase() == needle)
+        || indicator.context.as_deref().is_some_and(|c| contains_words(&c.to_lowercase(), needle))
+        || indicator.attributes.values().any(|value| match value {
+            serde_json::Value::String(s) => s.to_lowercase() == needle,
+            serde_json::Value::Array(items) => items.iter()
+                .any(|item| item.as_str().is_some_and(|s| s.to_lowercase() == needle)),
+            _ => false,
+        })
+}
+
+/// Whether `needle` occurs in `text` as whole words, so short names do not match inside others
+fn contains_words(text: &str, needle: &str) -> bool {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    !needle.is_empty() && text.match_indices(needle).any(|(start, matched)| {
+        !text[..start].chars().next_back().is_some_and(is_word)
+            && !text[start + matched.len()..].chars().next().is_some_and(is_word)
+    })
+}
+
+/// Host part of network indicators
+fn indicator_host(indicator: &ThreatIndicator) -> Option<String> {
+    match indicator.indicator_type {
+        IndicatorType::IpAddress | IndicatorType::Domain => Some(indicator.normalized_value()),
+        IndicatorType::Url => url::Url::parse(&indicator.value)
+            .ok()
+            .and_then(|url| url.host_str().map(|h| h.trim_matches(|c| c == '[' || c == ']').to_lowercase())),
+        IndicatorType::Email => indicator.value.rsplit_once('@').map(|(_, domain)| domain.to_lowercase()),
+        _ => None,
+    }
+}
+
+fn infrastructure_matches(entry: &str, host: &str) -> bool {
+    let entry = entry.trim().trim_start_matches("*.").to_lowercase();
+
+    if let Some((network, prefix)) = entry.split_once('/') {
+        return match (network.parse::<IpAddr>(), prefix.parse::<u32>(), host.parse::<IpAddr>()) {
+            (Ok(network), Ok(prefix), Ok(ip)) => in_cidr(network, prefix, ip),
+            _ => false,
+        };
+    }
+
+    match (entry.parse::<IpAddr>(), host.parse::<IpAddr>()) {
+        (Ok(a), Ok(b)) => a == b,
+        (Err(_), Err(_)) => host == entry || host.ends_with(&format!(".{}", entry)),
+        _ => false,
+    }
+}
+
+fn in_cidr(network: IpAddr, prefix: u32, ip: IpAddr) -> bool {
+    match (network, ip) {
+        (IpAddr::V4(net), IpAddr::V4(ip)) if prefix <= 32 => {
+            let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+            u32::from(net) & mask == u32::from(ip) & mask
+        }
+        (IpAddr::V6(net), IpAddr::V6(ip)) if prefix <= 128 => {
+            let mask = u128::MAX.checked_shl(128 - prefix).unwrap_or(0);
+            u128::from(net) & mask == u128::from(ip) & mask
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn profiles() -> AttributionEngine {
+        let mut apt28 = ActorProfile::new("APT28");
+        apt28.aliases = vec!["Fancy Bear".to_string()];
+        apt28.techniques = vec!["T1566.002".to_string(), "T1071.001".to_string(), "T1055".to_string()];
+        apt28.infrastructure = vec!["198.51.100.0/24".to_string(), "update-checker.example".to_string()];
+        apt28.tools = vec!["X-Agent".to_string()];
+        apt28.target_sectors = vec!["government".to_string()];
+        apt28.utc_offsets = vec![3];
+
+        let mut fin7 = ActorProfile::new("FIN7");
+        fin7.techniques = vec!["T1566.001".to_string(), "T1059.001".to_string()];
+        fin7.tools = vec!["Carbanak".to_string()];
+        fin7.target_sectors = vec!["retail".to_string()];
+
+        let mut engine = AttributionEngine::new();
+        engine.add_profile(apt28);
+        engine.add_profile(fin7);
+        engine
+    }
+
+    fn indicator(indicator_type: IndicatorType, value: &str, techniques: &[&str], tags: &[&str]) -> ThreatIndicator {
+        ThreatIndicator {
+            mitre_techniques: techniques.iter().map(|s| s.to_string()).collect(),
+            tags: tags.iter().map(|s| s.to_string()).collect(),
+            first_seen: Utc.with_ymd_and_hms(2024, 3, 4, 9, 0, 0).unwrap(),
+            ..ThreatIndicator::new(indicator_type, value, "test")
+        }
+    }
+
+    #[test]
+    fn test_ranked_candidates_with_evidence() {
+        let engine = profiles();
+        let indicators = vec![
+            indicator(IndicatorType::IpAddress, "198.51.100.77", &["T1071.001"], &["X-Agent"]),
+            indicator(IndicatorType::Url, "https://cdn.update-checker.example/a.php", &["T1566.002"], &["government"]),
+            indicator(IndicatorType::Domain, "unrelated.example.org", &["T1566.001"], &[]),
+        ];
+
+        let candidates = engine.attribute(&indicators);
+
+        assert_eq!(candidates[0].actor_name, "APT28");
+        assert!(candidates[0].score > candidates[1].score);
+        assert_eq!(candidates[0].infrastructure_score, 1.0);
+        assert!(candidates[0].evidence.iter().any(|e| e.kind == EvidenceKind::Tool));
+        assert!(candidates[0].evidence.iter().any(|e| e.kind == EvidenceKind::TimeZone));
+        assert_eq!(candidates[1].actor_name, "FIN7");
+        assert!(candidates[1].evidence.iter().all(|e| e.kind == EvidenceKind::Technique));
+    }
+
+    #[test]
+    fn test_profile_entity_roundtrip() {
+        let engine = profiles();
+        let profile = engine.find_profile("fancy bear").unwrap();
+
+        let entity = profile.to_entity("analyst");
+        let restored = ActorProfile::from_entity(&entity).unwrap();
+
+        assert_eq!(restored.name, "APT28");
+        assert_eq!(restored.infrastructure, profile.infrastructure);
+        assert_eq!(restored.utc_offsets, vec![3]);
+        assert!(infrastructure_matches("2001:db8::/32", "2001:db8:1::5"));
+        assert!(!infrastructure_matches("example.com", "notexample.com"));
+    }
+
+    #[test]
+    fn test_context_mentions_match_whole_words() {
+        let mut indicator = indicator(IndicatorType::Domain, "panel.example", &[], &[]);
+
+        indicator.context = Some("Admin panel of the PonyExpress courier".to_string());
+        assert!(!indicator_mentions(&indicator, "pony"));
+        indicator.context = Some("Pony stealer panel, drops X-Agent.".to_string());
+        assert!(indicator_mentions(&indicator, "pony"));
+        assert!(indicator_mentions(&indicator, "x-agent"));
+    }
+}
@@ -0,0 +1,654 @@
+//! Generic CSV and plaintext blocklist feeds
+
+use super::{ThreatSource, ThreatSourceType};
+use crate::{Result, Error, models::*};
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::{Client, StatusCode};
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// Where a feed is read from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum FeedLocation {
+    Url(String),
+    File(PathBuf),
+}
+
+/// Layout of the feed content
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum FeedFormat {
+    /// One indicator per line; anything after the first whitespace is ignored
+    PlainText,
+    Csv {
+        delimiter: u8,
+        has_header: bool,
+    },
+}
+
+/// Column reference by zero-based position or header name
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum FeedColumn {
+    Index(usize),
+    Name(String),
+}
+
+/// How the indicator type of each row is determined
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum IndicatorTypeSpec {
+    /// Every row has the same type
+    Fixed(IndicatorType),
+    /// Infer the type from the value
+    Infer,
+    /// Read the type from a column, falling back to inference for unknown names
+    Column(FeedColumn),
+}
+
+/// Feed configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeedConfig {
+    pub name: String,
+    pub location: FeedLocation,
+    pub format: FeedFormat,
+    pub source_type: ThreatSourceType,
+    /// Column holding the indicator value (ignored for plaintext feeds)
+    pub value_column: FeedColumn,
+    pub indicator_type: IndicatorTypeSpec,
+    /// Column holding the first-seen date
+    pub date_column: Option<FeedColumn>,
+    /// chrono format of the date column; RFC 3339 and common formats are tried when unset
+    pub date_format: Option<String>,
+    /// Lines starting with this prefix are skipped
+    pub comment_prefix: Option<String>,
+    pub threat_type: ThreatType,
+    pub severity: ThreatSeverity,
+    pub tlp: TrafficLightProtocol,
+    pub confidence: f32,
+}
+
+impl FeedConfig {
+    /// Configuration for a newline-delimited list with type inference
+    pub fn plain_text(name: impl Into<String>, location: FeedLocation) -> Self {
+        Self {
+            name: name.into(),
+            location,
+            format: FeedFormat::PlainText,
+            source_type: ThreatSourceType::OpenSource,
+            value_column: FeedColumn::Index(0),
+            indicator_type: IndicatorTypeSpec::Infer,
+            date_column: None,
+            date_format: None,
+            comment_prefix: Some("#".to_string()),
+            threat_type: ThreatType::Malware,
+            severity: ThreatSeverity::Medium,
+            tlp: TrafficLightProtocol::White,
+            confidence: 0.5,
+        }
+    }
+
+    /// Configuration for a comma separated feed with a header row
+    pub fn csv(name: impl Into<String>, location: FeedLocation, value_column: FeedColumn) -> Self {
+        Self {
+            format: FeedFormat::Csv { delimiter: b',', has_header: true },
+            value_column,
+            ..Self::plain_text(name, location)
+        }
+    }
+}
+
+/// Validators remembered between fetches
+#[derive(Debug, Default)]
+struct FeedState {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    file_modified: Option<SystemTime>,
+    /// Indicators of the last content, re-emitted while the feed is unchanged
+    indicators: Vec<ThreatIndicator>,
+}
+
+/// Changed feed content with the validators to remember once it parsed
+struct FeedContent {
+    body: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    file_modified: Option<SystemTime>,
+}
+
+/// Threat source reading CSV or plaintext blocklists from a URL or local file
+pub struct FeedSource {
+    config: FeedConfig,
+    client: Client,
+    state: Mutex<FeedState>,
+}
+
+impl FeedSource {
+    /// Create feed source from configuration
+    pub fn new(config: FeedConfig) -> Self {
+        let client = Client::builder()
+            .user_agent("OSINT-Platform/1.0")
+            .build()
+            .expect("Failed to create HTTP client");
+
+        Self {
+            config,
+            client,
+            state: Mutex::new(FeedState::default()),
+        }
+    }
+
+    /// Get feed configuration
+    pub fn config(&self) -> &FeedConfig {
+        &self.config
+    }
+
+    /// Parse feed content into indicators
+    pub fn parse(&self, content: &str) -> Result<Vec<ThreatIndicator>> {
+        let lines: Vec<&str> = content.lines()
+            .filter(|line| !line.trim().is_empty() && !self.is_comment(line))
+            .collect();
+
+        match &self.config.format {
+            FeedFormat::PlainText => Ok(lines.iter()
+                .filter_map(|line| line.split_whitespace().next())
+                .filter_map(|value| self.build_indicator(value, None, None))
+                .collect()),
+            FeedFormat::Csv { delimiter, has_header } => self.parse_csv(&lines.join("\n"), *delimiter, *has_header),
+        }
+    }
+
+    fn is_comment(&self, line: &str) -> bool {
+        self.config.comment_prefix.as_deref()
+            .is_some_and(|prefix| !prefix.is_empty() && line.trim_start().starts_with(prefix))
+    }
+
+    fn parse_csv(&self, content: &str, delimiter: u8, has_header: bool) -> Result<Vec<ThreatIndicator>> {
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .has_headers(has_header)
+            .flexible(true)
+            .trim(csv::Trim::All)
+            .from_reader(content.as_bytes());
+
+        let headers = if has_header {
+            Some(reader.headers()
+                .map_err(|e| Error::Parsing(format!("Invalid header in feed {}: {}", self.config.name, e)))?
+                .clone())
+        } else {
+            None
+        };
+
+        let resolve = |column: &FeedColumn| -> Result<usize> {
+            match column {
+                FeedColumn::Index(index) => Ok(*index),
+                FeedColumn::Name(name) => headers.as_ref()
+                    .and_then(|h| h.iter().position(|field| field.eq_ignore_ascii_case(name)))
+                    .ok_or_else(|| Error::Configuration(format!("Feed {} has no column {}", self.config.name, name))),
+            }
+        };
+
+        let value_index = resolve(&self.config.value_column)?;
+        let date_index = self.config.date_column.as_ref().map(&resolve).transpose()?;
+        let type_index = match &self.config.indicator_type {
+            IndicatorTypeSpec::Column(column) => Some(resolve(column)?),
+            _ => None,
+        };
+
+        let mut indicators = Vec::new();
+        for (row, record) in reader.records().enumerate() {
+            let record = match record {
+                Ok(record) => record,
+                Err(e) => {
+                    tracing::debug!("Skipping malformed row {} in feed {}: {}", row + 1, self.config.name, e);
+                    continue;
+                }
+            };
+            let Some(value) = record.get(value_index) else {
+                continue;
+            };
+            let type_name = type_index.and_then(|i| record.get(i));
+            let first_seen = date_index
+                .and_then(|i| record.get(i))
+                .and_then(|date| parse_feed_date(date, self.config.date_format.as_deref()));
+
+            if let Some(indicator) = self.build_indicator(value, type_name, first_seen) {
+                indicators.push(indicator);
+            }
+        }
+
+        Ok(indicators)
+    }
+
+    fn build_indicator(
+        &self,
+        value: &str,
+        type_name: Option<&str>,
+        first_seen: Option<DateTime<Utc>>,
+    ) -> Option<ThreatIndicator> {
+        let value = value.trim().trim_matches('"');
+        if value.is_empty() {
+            return None;
+        }
+
+        let indicator_type = match &self.config.indicator_type {
+            IndicatorTypeSpec::Fixed(indicator_type) => Some(indicator_type.clone()),
+            IndicatorTypeSpec::Infer => infer_indicator_type(value),
+            IndicatorTypeSpec::Column(_) => type_name
+                .and_then(map_type_name)
+                .or_else(|| infer_indicator_type(value)),
+        };
+        let Some(indicator_type) = indicator_type else {
+            tracing::debug!("Skipping unrecognised value {:?} in feed {}", value, self.config.name);
+            return None;
+        };
+
+        // Blocklists mix hosts, ip:port and CIDR entries, whatever the column says;
+        // single-host CIDR entries are hosts
+        let (indicator_type, value) = match indicator_type {
+            IndicatorType::IpAddress | IndicatorType::NetworkRange => classify_address(value)
+                .unwrap_or((indicator_type, value.to_string())),
+            _ => (indicator_type, value.to_string()),
+        };
+
+        let now = Utc::now();
+        let first_seen = first_seen.unwrap_or(now);
+        Some(ThreatIndicator {
+            threat_type: self.config.threat_type.clone(),
+            severity: self.config.severity.clone(),
+            confidence: self.config.confidence,
+            tlp: self.config.tlp.clone(),
+            first_seen,
+            last_seen: now.max(first_seen),
+            context: Some(self.config.name.clone()),
+            ..ThreatIndicator::new(indicator_type, value, self.config.name.as_str())
+        })
+    }
+
+    async fn fetch_url(&self, url: &str) -> Result<Option<FeedContent>> {
+        let mut request = self.client.get(url);
+        {
+            let state = self.state.lock().expect("Feed state lock poisoned");
+            if let Some(etag) = &state.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &state.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        let response = request.send().await?;
+        if response.status() == StatusCode::NOT_MODIFIED {
+            tracing::debug!("Feed {} not modified since last fetch", self.config.name);
+            return Ok(None);
+        }
+
+        let response = response.error_for_status()?;
+        let header = |name| response.headers()
+            .get(name)
+            .and_then(|v: &reqwest::header::HeaderValue| v.to_str().ok())
+            .map(str::to_string);
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+        let body = response.text().await?;
+
+        Ok(Some(FeedContent { body, etag, last_modified, file_modified: None }))
+    }
+
+    async fn read_file(&self, path: &PathBuf) -> Result<Option<FeedContent>> {
+        let modified = tokio::fs::metadata(path).await?.modified().ok();
+        if modified.is_some() && self.state.lock().expect("Feed state lock poisoned").file_modified == modified {
+            tracing::debug!("Feed file {} unchanged since last read", path.display());
+            return Ok(None);
+        }
+
+        let body = tokio::fs::read_to_string(path).await?;
+        Ok(Some(FeedContent { body, etag: None, last_modified: None, file_modified: modified }))
+    }
+}
+
+#[async_trait::async_trait]
+impl ThreatSource for FeedSource {
+    /// Fetch feed; when the content is unchanged the previous indicators are returned as seen now,
+    /// so a source that still lists them keeps them from expiring
+    async fn fetch_indicators(&self) -> Result<Vec<ThreatIndicator>> {
+        let content = match &self.config.location {
+            FeedLocation::Url(url) => self.fetch_url(url).await?,
+            FeedLocation::File(path) => self.read_file(path).await?,
+        };
+
+        let mut state = match content {
+            Some(content) => {
+                let indicators = self.parse(&content.body)?;
+                tracing::debug!("Parsed {} indicators from feed {}", indicators.len(), self.config.name);
+                // Validators are only stored once the content parsed, so a broken body is fetched again
+                let mut state = self.state.lock().expect("Feed state lock poisoned");
+                state.etag = content.etag;
+                state.last_modified = content.last_modified;
+                state.file_modified = content.file_modified;
+                state.indicators = indicators;
+                return Ok(state.indicators.clone());
+            }
+            None => self.state.lock().expect("Feed state lock poisoned"),
+        };
+
+        let now = Utc::now();
+        for indicator in &mut state.indicators {
+            indicator.last_seen = indicator.last_seen.max(now);
+        }
+        Ok(state.indicators.clone())
+    }
+
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn source_type(&self) -> ThreatSourceType {
+        self.config.source_type.clone()
+    }
+
+    /// URL feeds are always tried; many servers reject HEAD, so the GET reports failures
+    async fn is_available(&self) -> bool {
+        match &self.config.location {
+            FeedLocation::Url(_) => true,
+            FeedLocation::File(path) => tokio::fs::metadata(path).await.is_ok(),
+        }
+    }
+}
+
+/// Infer indicator type from the shape of a value
+pub fn infer_indicator_type(value: &str) -> Option<IndicatorType> {
+    let value = value.trim();
+    if let Some((indicator_type, _)) = classify_address(value) {
+        return Some(indicator_type);
+    }
+    if value.contains("://") {
+        return Some(IndicatorType::Url);
+    }
+    if matches!(value.len(), 32 | 40 | 64 | 128) && value.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Some(IndicatorType::Hash);
+    }
+    if let Some((local, domain)) = value.split_once('@') {
+        if !local.is_empty() && is_domain(domain) {
+            return Some(IndicatorType::Email);
+        }
+    }
+    if is_domain(value) {
+        return Some(IndicatorType::Domain);
+    }
+    None
+}
+
+/// Host address or network range of an `ip`, `ip:port` or CIDR value, in canonical form
+fn classify_address(value: &str) -> Option<(IndicatorType, String)> {
+    if let Ok(ip) = value.parse::<IpAddr>() {
+        return Some((IndicatorType::IpAddress, ip.to_string()));
+    }
+    if let Ok(socket) = value.parse::<SocketAddr>() {
+        return Some((IndicatorType::IpAddress, socket.ip().to_string()));
+    }
+    let (ip, prefix) = value.split_once('/')?;
+    let ip = ip.parse::<IpAddr>().ok()?;
+    let prefix = prefix.parse::<u8>().ok()?;
+    let host_prefix = if ip.is_ipv4() { 32 } else { 128 };
+    match prefix.cmp(&host_prefix) {
+        std::cmp::Ordering::Equal => Some((IndicatorType::IpAddress, ip.to_string())),
+        std::cmp::Ordering::Less => Some((IndicatorType::NetworkRange, format!("{}/{}", ip, prefix))),
+        std::cmp::Ordering::Greater => None,
+    }
+}
+
+fn is_domain(value: &str) -> bool {
+    let value = value.trim_end_matches('.');
+    let labels: Vec<&str> = value.split('.').collect();
+    labels.len() >= 2
+        && value.len() <= 253
+        && labels.iter().all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+        && labels.last().is_some_and(|tld| tld.chars().all(|c| c.is_ascii_alphabetic()) || tld.starts_with("xn--"))
+}
+
+/// Map indicator type names used by common feeds
+fn map_type_name(name: &str) -> Option<IndicatorType> {
+    match name.trim().to_lowercase().as_str() {
+        "ip" | "ipv4" | "ipv6" | "ip:port" | "ip_address" | "ipaddress" => Some(IndicatorType::IpAddress),
+        "cidr" | "netblock" | "ip_range" | "network" => Some(IndicatorType::NetworkRange),
+        "domain" | "hostname" | "fqdn" => Some(IndicatorType::Domain),
+        "url" | "uri" => Some(IndicatorType::Url),
+        "email" | "email_address" => Some(IndicatorType::Email),
+        "md5" | "sha1" | "sha256" | "sha512" | "hash" | "md5_hash" | "sha256_hash" => Some(IndicatorType::Hash),
+        "filename" | "file_name" => Some(IndicatorType::Filename),
+        "mutex" => Some(IndicatorType::Mutex),
+        _ => None,
+    }
+}
+
+fn parse_feed_date(value: &str, format: Option<&str>) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    if let Some(format) = format {
+        return NaiveDateTime::parse_from_str(value, format)
+            .map(|naive| naive.and_utc())
+            .or_else(|_| NaiveDate::parse_from_str(value, format)
+                .map(|date| date.and_hms_opt(0, 0, 0).expect("midnight is valid").and_utc()))
+            .ok();
+    }
+
+    DateTime::parse_from_rfc3339(value)
+        .map(|dt| dt.with_timezone(&Utc))
+        .ok()
+        .or_else(|| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").ok().map(|naive| naive.and_utc()))
+        .or_else(|| NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .map(|naive| naive.and_utc()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::threat_intel::{SourcePolicy, ThreatIntelEngine};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    use wiremock::matchers::{header, method, path};
+
+    const BLOCKLIST: &str = include_str!("../../tests/fixtures/feeds/blocklist.txt");
+    const URLHAUS: &str = include_str!("../../tests/fixtures/feeds/urlhaus.csv");
+
+    #[test]
+    fn test_plaintext_inference() {
+        let feed = FeedSource::new(FeedConfig::plain_text("blocklist", FeedLocation::File("unused".into())));
+        let indicators = feed.parse(BLOCKLIST).unwrap();
+
+        let types: Vec<_> = indicators.iter().map(|i| i.indicator_type.clone()).collect();
+        assert_eq!(types, vec![
+            IndicatorType::IpAddress,
+            IndicatorType::NetworkRange,
+            IndicatorType::Domain,
+            IndicatorType::Url,
+            IndicatorType::Hash,
+            IndicatorType::Email,
+        ]);
+        assert_eq!(indicators[1].value, "198.51.100.0/24");
+        assert_eq!(indicators[0].value, "203.0.113.7");
+        assert!(indicators.iter().all(|i| i.context.as_deref() == Some("blocklist")));
+
+        assert_eq!(infer_indicator_type("192.0.2.5:8080"), Some(IndicatorType::IpAddress));
+        assert_eq!(infer_indicator_type("[2001:db8::1]:443"), Some(IndicatorType::IpAddress));
+        assert_eq!(infer_indicator_type("10.0.0.0/40"), None);
+        let hosts = feed.parse("192.0.2.5:8080\n192.0.2.6/32\n10.0.0.0/8\n").unwrap();
+        let parsed: Vec<_> = hosts.iter().map(|i| (i.indicator_type.clone(), i.value.as_str())).collect();
+        assert_eq!(parsed, vec![
+            (IndicatorType::IpAddress, "192.0.2.5"),
+            (IndicatorType::IpAddress, "192.0.2.6"),
+            (IndicatorType::NetworkRange, "10.0.0.0/8"),
+        ]);
+    }
+
+    #[test]
+    fn test_csv_column_mapping() {
+        // URLhaus style: commented header, no header row, quoted fields
+        let mut config = FeedConfig::csv("urlhaus", FeedLocation::File("unused".into()), FeedColumn::Index(2));
+        config.format = FeedFormat::Csv { delimiter: b',', has_header: false };
+        config.indicator_type = IndicatorTypeSpec::Fixed(IndicatorType::Url);
+        config.date_column = Some(FeedColumn::Index(1));
+        config.date_format = Some("%Y-%m-%d %H:%M:%S".to_string());
+        config.threat_type = ThreatType::Delivery;
+        config.severity = ThreatSeverity::High;
+        config.tlp = TrafficLightProtocol::Green;
+
+        let indicators = FeedSource::new(config).parse(URLHAUS).unwrap();
+
+        assert_eq!(indicators.len(), 3);
+        assert_eq!(indicators[0].value, "http://198.51.100.23/bins/mozi.m");
+        assert_eq!(indicators[0].first_seen.to_rfc3339(), "2024-03-01T10:15:02+00:00");
+        assert_eq!(indicators[0].threat_type, ThreatType::Delivery);
+        assert_eq!(indicators[0].severity, ThreatSeverity::High);
+        assert_eq!(indicators[0].tlp, TrafficLightProtocol::Green);
+    }
+
+    #[test]
+    fn test_csv_named_columns() {
+        let content = "indicator,kind,added\nevil.example.com,hostname,2024-02-01\n44d88612fea8a8f36de82e1278abb02f,md5,2024-02-02\nnot a value,unknown,2024-02-03\n";
+        let mut config = FeedConfig::csv("internal", FeedLocation::File("unused".into()), FeedColumn::Name("indicator".into()));
+        config.indicator_type = IndicatorTypeSpec::Column(FeedColumn::Name("kind".into()));
+        config.date_column = Some(FeedColumn::Name("added".into()));
+
+        let indicators = FeedSource::new(config).parse(content).unwrap();
+
+        assert_eq!(indicators.len(), 2);
+        assert_eq!(indicators[0].indicator_type, IndicatorType::Domain);
+        assert_eq!(indicators[1].indicator_type, IndicatorType::Hash);
+        assert_eq!(indicators[1].first_seen.to_rfc3339(), "2024-02-02T00:00:00+00:00");
+
+        let mut missing = FeedConfig::csv("internal", FeedLocation::File("unused".into()), FeedColumn::Name("ioc".into()));
+        missing.indicator_type = IndicatorTypeSpec::Infer;
+        assert!(matches!(FeedSource::new(missing).parse(content), Err(Error::Configuration(_))));
+    }
+
+    #[tokio::test]
+    async fn test_conditional_get() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/blocklist.txt"))
+            .and(header("If-None-Match", "\"v1\""))
+            .respond_with(ResponseTemplate::new(304))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/blocklist.txt"))
+            .respond_with(ResponseTemplate::new(200)
+                .insert_header("ETag", "\"v1\"")
+                .set_body_string(BLOCKLIST))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let url = format!("{}/blocklist.txt", server.uri());
+        let feed = FeedSource::new(FeedConfig::plain_text("blocklist", FeedLocation::Url(url)));
+
+        let first = feed.fetch_indicators().await.unwrap();
+        let second = feed.fetch_indicators().await.unwrap();
+        assert_eq!(first.len(), 6);
+        // A 304 re-emits the listed indicators as seen again
+        assert_eq!(second.len(), 6);
+        assert!(second[0].last_seen >= first[0].last_seen);
+    }
+
+    #[tokio::test]
+    async fn test_validators_kept_only_for_parsed_content() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(header("If-None-Match", "\"v1\""))
+            .respond_with(ResponseTemplate::new(304))
+            .expect(0)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200)
+                .insert_header("ETag", "\"v1\"")
+                .set_body_string("value\nevil.example\n"))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200)
+                .insert_header("ETag", "\"v2\"")
+                .set_body_string("ioc\nevil.example\n"))
+            .mount(&server)
+            .await;
+
+        let url = format!("{}/feed.csv", server.uri());
+        let feed = FeedSource::new(FeedConfig::csv("internal", FeedLocation::Url(url), FeedColumn::Name("ioc".into())));
+
+        assert!(matches!(feed.fetch_indicators().await, Err(Error::Configuration(_))));
+        assert_eq!(feed.fetch_indicators().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_not_modified_feed_does_not_expire() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/blocklist.txt"))
+            .and(header("If-None-Match", "\"v1\""))
+            .respond_with(ResponseTemplate::new(304))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/blocklist.txt"))
+            .respond_with(ResponseTemplate::new(200)
+                .insert_header("ETag", "\"v1\"")
+                .set_body_string(BLOCKLIST))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("HEAD"))
+            .respond_with(ResponseTemplate::new(405))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let url = format!("{}/blocklist.txt", server.uri());
+        let feed = FeedSource::new(FeedConfig::plain_text("blocklist", FeedLocation::Url(url)));
+        let policy = SourcePolicy { min_interval: std::time::Duration::ZERO, ..SourcePolicy::default() };
+        let mut engine = ThreatIntelEngine::new();
+        engine.add_source_with_policy("blocklist".to_string(), Box::new(feed), policy);
+        engine.fetch_all_indicators().await.unwrap();
+
+        // Age the first fetch past the 30 day IP TTL
+        for indicator in engine.indicators.values_mut() {
+            indicator.last_seen -= chrono::Duration::days(40);
+            for sighting in &mut indicator.sightings {
+                sighting.last_seen -= chrono::Duration::days(40);
+            }
+        }
+
+        let reports = engine.fetch_all_indicators().await.unwrap();
+        assert_eq!(reports[0].updated, 6);
+        assert_eq!(engine.expire_indicators(), 0);
+        let ip = engine.find_indicator(&IndicatorType::IpAddress, "203.0.113.7").unwrap();
+        assert!(engine.is_active(ip));
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_file_is_not_reparsed() {
+        let path = std::env::temp_dir().join(format!("osint-feed-{}.txt", uuid::Uuid::new_v4()));
+        std::fs::write(&path, BLOCKLIST).unwrap();
+
+        let feed = FeedSource::new(FeedConfig::plain_text("local", FeedLocation::File(path.clone())));
+        let first = feed.fetch_indicators().await;
+        let second = feed.fetch_indicators().await;
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(first.unwrap().len(), 6);
+        assert_eq!(second.unwrap().len(), 6);
+    }
+}
@@ -0,0 +1,302 @@
+//! Kill chain timeline reconstruction for sets of indicators
+
+use crate::models::*;
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{BTreeMap, BTreeSet};
+use uuid::Uuid;
+
+/// Cyber kill chain phases in attack order
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum KillChainPhase {
+    Reconnaissance,
+    Weaponization,
+    Delivery,
+    Exploitation,
+    Installation,
+    CommandControl,
+    ActionsOnObjectives,
+}
+
+impl KillChainPhase {
+    pub const ALL: [KillChainPhase; 7] = [
+        KillChainPhase::Reconnaissance,
+        KillChainPhase::Weaponization,
+        KillChainPhase::Delivery,
+        KillChainPhase::Exploitation,
+        KillChainPhase::Installation,
+        KillChainPhase::CommandControl,
+        KillChainPhase::ActionsOnObjectives,
+    ];
+
+    /// Phase a threat type belongs to
+    pub fn from_threat_type(threat_type: &ThreatType) -> Self {
+        match threat_type {
+            ThreatType::Reconnaissance => KillChainPhase::Reconnaissance,
+            ThreatType::Weaponization => KillChainPhase::Weaponization,
+            ThreatType::Delivery | ThreatType::Phishing => KillChainPhase::Delivery,
+            ThreatType::Exploitation => KillChainPhase::Exploitation,
+            ThreatType::Installation | ThreatType::Persistence | ThreatType::Malware => KillChainPhase::Installation,
+            ThreatType::CommandControl | ThreatType::Botnet => KillChainPhase::CommandControl,
+            ThreatType::LateralMovement | ThreatType::DataExfiltration | ThreatType::Impact => KillChainPhase::ActionsOnObjectives,
+        }
+    }
+
+    /// Phase an ATT&CK Enterprise tactic ID belongs to
+    pub fn from_attack_tactic(tactic_id: &str) -> Option<Self> {
+        match tactic_id.trim().to_uppercase().as_str() {
+            "TA0043" => Some(KillChainPhase::Reconnaissance),
+            "TA0042" => Some(KillChainPhase::Weaponization),
+            "TA0001" => Some(KillChainPhase::Delivery),
+            "TA0002" => Some(KillChainPhase::Exploitation),
+            "TA0003" | "TA0004" | "TA0005" => Some(KillChainPhase::Installation),
+            "TA0011" => Some(KillChainPhase::CommandControl),
+            "TA0006" | "TA0007" | "TA0008" | "TA0009" | "TA0010" | "TA0040" => Some(KillChainPhase::ActionsOnObjectives),
+            _ => None,
+        }
+    }
+}
+
+/// Overall progress of a campaign
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CampaignStage {
+    /// Only reconnaissance or weaponization observed
+    Preparation,
+    /// Delivery or exploitation observed
+    Intrusion,
+    /// Foothold with installation or command and control
+    Established,
+    /// Adversary acting on objectives
+    Operational,
+}
+
+impl CampaignStage {
+    fn from_phase(phase: KillChainPhase) -> Self {
+        match phase {
+            KillChainPhase::Reconnaissance | KillChainPhase::Weaponization => CampaignStage::Preparation,
+            KillChainPhase::Delivery | KillChainPhase::Exploitation => CampaignStage::Intrusion,
+            KillChainPhase::Installation | KillChainPhase::CommandControl => CampaignStage::Established,
+            KillChainPhase::ActionsOnObjectives => CampaignStage::Operational,
+        }
+    }
+}
+
+/// Single indicator placed on the timeline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineEvent {
+    pub indicator_id: Uuid,
+    pub value: String,
+    pub indicator_type: IndicatorType,
+    pub phase: KillChainPhase,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Indicators observed for one phase
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhaseObservation {
+    pub phase: KillChainPhase,
+    pub indicators: Vec<Uuid>,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+/// A later phase observed before an earlier one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutOfOrderProgression {
+    pub earlier_phase: KillChainPhase,
+    pub later_phase: KillChainPhase,
+    pub earlier_phase_first_seen: DateTime<Utc>,
+    pub later_phase_first_seen: DateTime<Utc>,
+}
+
+/// Phase-ordered reconstruction of a campaign
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KillChainAnalysis {
+    /// Observed phases in kill chain order
+    pub phases: Vec<PhaseObservation>,
+    /// Events in chronological order
+    pub events: Vec<TimelineEvent>,
+    /// Phases not observed at all
+    pub missing_phases: Vec<KillChainPhase>,
+    /// Unobserved phases between the earliest and furthest observed phase
+    pub gaps: Vec<KillChainPhase>,
+    pub out_of_order: Vec<OutOfOrderProgression>,
+    pub furthest_phase: Option<KillChainPhase>,
+    pub stage: Option<CampaignStage>,
+    /// Share of phases observed up to the furthest phase
+    pub completeness: f32,
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+}
+
+/// Builds kill chain timelines from indicators
+#[derive(Debug, Clone)]
+pub struct KillChainAnalyzer {
+    /// Slack allowed before a later phase preceding an earlier one is flagged
+    tolerance: Duration,
+}
+
+impl KillChainAnalyzer {
+    pub fn new() -> Self {
+        Self {
+            tolerance: Duration::hours(1),
+        }
+    }
+
+    /// Set clock skew tolerated between phases
+    pub fn with_tolerance(mut self, tolerance: Duration) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Phases an indicator contributes to, from its threat type and ATT&CK tactics
+    pub fn phases_for(indicator: &ThreatIndicator) -> BTreeSet<KillChainPhase> {
+        let mut phases: BTreeSet<KillChainPhase> = indicator.mitre_tactics.iter()
+            .filter_map(|tactic| KillChainPhase::from_attack_tactic(tactic))
+            .collect();
+        phases.insert(KillChainPhase::from_threat_type(&indicator.threat_type));
+        phases
+    }
+
+    /// Reconstruct the kill chain for a set of indicators
+    pub fn analyze<'a>(&self, indicators: impl IntoIterator<Item = &'a ThreatIndicator>) -> KillChainAnalysis {
+        let mut events = Vec::new();
+        let mut observed: BTreeMap<KillChainPhase, PhaseObservation> = BTreeMap::new();
+
+        for indicator in indicators {
+            for phase in Self::phases_for(indicator) {
+                events.push(TimelineEvent {
+                    indicator_id: indicator.id,
+                    value: indicator.value.clone(),
+                    indicator_type: indicator.indicator_type.clone(),
+                    phase,
+                    timestamp: indicator.first_seen,
+                });
+
+                let observation = observed.entry(phase).or_insert_with(|| PhaseObservation {
+                    phase,
+                    indicators: Vec::new(),
+                    first_seen: indicator.first_seen,
+                    last_seen: indicator.last_seen,
+                });
+                observation.indicators.push(indicator.id);
+                observation.first_seen = observation.first_seen.min(indicator.first_seen);
+                observation.last_seen = observation.last_seen.max(indicator.last_seen);
+            }
+        }
+
+        events.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then(a.phase.cmp(&b.phase)));
+
+        let missing_phases: Vec<KillChainPhase> = KillChainPhase::ALL.iter()
+            .filter(|phase| !observed.contains_key(phase))
+            .copied()
+            .collect();
+
+        let earliest_phase = observed.keys().next().copied();
+        let furthest_phase = observed.keys().next_back().copied();
+        let gaps = match (earliest_phase, furthest_phase) {
+            (Some(first), Some(last)) => missing_phases.iter()
+                .filter(|phase| **phase > first && **phase < last)
+                .copied()
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        let mut out_of_order = Vec::new();
+        let phases: Vec<PhaseObservation> = observed.into_values().collect();
+        for (i, earlier) in phases.iter().enumerate() {
+            for later in &phases[i + 1..] {
+                if later.first_seen + self.tolerance < earlier.first_seen {
+                    out_of_order.push(OutOfOrderProgression {
+                        earlier_phase: earlier.phase,
+                        later_phase: later.phase,
+                        earlier_phase_first_seen: earlier.first_seen,
+                        later_phase_first_seen: later.first_seen,
+                    });
+                }
+            }
+        }
+
+        let completeness = match furthest_phase {
+            Some(last) => {
+                let expected = KillChainPhase::ALL.iter().filter(|phase| **phase <= last).count();
+                phases.len() as f32 / expected as f32
+            }
+            None => 0.0,
+        };
+
+        KillChainAnalysis {
+            start: phases.iter().map(|p| p.first_seen).min(),
+            end: phases.iter().map(|p| p.last_seen).max(),
+            stage: furthest_phase.map(CampaignStage::from_phase),
+            phases,
+            events,
+            missing_phases,
+            gaps,
+            out_of_order,
+            furthest_phase,
+            completeness,
+        }
+    }
+}
+
+impl Default for KillChainAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn indicator(value: &str, threat_type: ThreatType, hours_ago: i64) -> ThreatIndicator {
+        let seen = Utc::now() - Duration::hours(hours_ago);
+        ThreatIndicator {
+            threat_type,
+            first_seen: seen,
+            last_seen: seen,
+            ..ThreatIndicator::new(IndicatorType::Domain, value, "test")
+        }
+    }
+
+    #[test]
+    fn test_ordered_campaign() {
+        let indicators = vec![
+            indicator("c2.example.com", ThreatType::CommandControl, 10),
+            indicator("phish.example.com", ThreatType::Phishing, 48),
+            indicator("scan.example.com", ThreatType::Reconnaissance, 72),
+        ];
+
+        let analysis = KillChainAnalyzer::new().analyze(&indicators);
+
+        assert_eq!(analysis.events[0].value, "scan.example.com");
+        assert_eq!(analysis.furthest_phase, Some(KillChainPhase::CommandControl));
+        assert_eq!(analysis.stage, Some(CampaignStage::Established));
+        assert_eq!(analysis.gaps, vec![
+            KillChainPhase::Weaponization,
+            KillChainPhase::Exploitation,
+            KillChainPhase::Installation,
+        ]);
+        assert!(analysis.missing_phases.contains(&KillChainPhase::ActionsOnObjectives));
+        assert!(analysis.out_of_order.is_empty());
+        assert!((analysis.completeness - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_out_of_order_and_tactic_phases() {
+        let mut exfil = indicator("drop.example.com", ThreatType::DataExfiltration, 30);
+        exfil.mitre_tactics = vec!["TA0011".to_string()];
+        let indicators = vec![exfil, indicator("lure.example.com", ThreatType::Delivery, 5)];
+
+        let analysis = KillChainAnalyzer::new().analyze(&indicators);
+
+        assert_eq!(analysis.phases.len(), 3);
+        assert_eq!(analysis.stage, Some(CampaignStage::Operational));
+        assert_eq!(analysis.out_of_order.len(), 2);
+        assert_eq!(analysis.out_of_order[0].earlier_phase, KillChainPhase::Delivery);
+
+        let lenient = KillChainAnalyzer::new().with_tolerance(Duration::days(2)).analyze(&indicators);
+        assert!(lenient.out_of_order.is_empty());
+    }
+}
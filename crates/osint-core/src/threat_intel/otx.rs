@@ -0,0 +1,523 @@
+//! AlienVault OTX pulse source
+
+use super::{ThreatSource, ThreatSourceType};
+use crate::{Result, Error, models::*};
+use serde::Deserialize;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use reqwest::{Client, StatusCode};
+use std::sync::Mutex;
+
+const DEFAULT_BASE_URL: &str = "https://otx.alienvault.com";
+const API_KEY_HEADER: &str = "X-OTX-API-KEY";
+const OTX_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// Threat source pulling subscribed pulses from AlienVault OTX
+pub struct OtxSource {
+    base_url: String,
+    api_key: String,
+    client: Client,
+    page_size: usize,
+    max_pages: usize,
+    state: Mutex<FetchState>,
+}
+
+/// Incremental fetch position carried between runs
+#[derive(Debug, Default)]
+struct FetchState {
+    /// Pulses modified after this time are fetched on the next run
+    modified_since: Option<DateTime<Utc>>,
+    /// Page to continue from when the last run stopped at `max_pages`
+    resume_url: Option<String>,
+    /// Newest modification seen by an unfinished run
+    pending_modified: Option<DateTime<Utc>>,
+}
+
+/// Page of subscribed pulses
+#[derive(Debug, Deserialize)]
+struct PulsePage {
+    #[serde(default)]
+    results: Vec<Pulse>,
+    next: Option<String>,
+}
+
+/// OTX pulse with its indicators
+#[derive(Debug, Deserialize)]
+struct Pulse {
+    name: String,
+    created: Option<String>,
+    modified: Option<String>,
+    #[serde(rename = "TLP")]
+    tlp: Option<String>,
+    adversary: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    malware_families: Vec<NamedRef>,
+    #[serde(default)]
+    attack_ids: Vec<NamedRef>,
+    #[serde(default)]
+    indicators: Vec<PulseIndicator>,
+}
+
+/// OTX references are either bare identifiers or objects, depending on the endpoint
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum NamedRef {
+    Id(String),
+    Object {
+        id: String,
+        display_name: Option<String>,
+    },
+}
+
+impl NamedRef {
+    fn id(&self) -> &str {
+        match self {
+            NamedRef::Id(id) => id,
+            NamedRef::Object { id, .. } => id,
+        }
+    }
+
+    fn display_name(&self) -> &str {
+        match self {
+            NamedRef::Id(id) => id,
+            NamedRef::Object { id, display_name } => display_name.as_deref().unwrap_or(id),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PulseIndicator {
+    indicator: String,
+    #[serde(rename = "type")]
+    indicator_type: String,
+    created: Option<String>,
+    expiration: Option<String>,
+    is_active: Option<serde_json::Value>,
+    role: Option<String>,
+}
+
+impl OtxSource {
+    /// Create source for the public OTX service
+    pub fn new(api_key: String) -> Self {
+        Self::with_base_url(DEFAULT_BASE_URL.to_string(), api_key)
+    }
+
+    /// Create source for an OTX-compatible API at a custom location
+    pub fn with_base_url(base_url: String, api_key: String) -> Self {
+        let client = Client::builder()
+            .user_agent("OSINT-Platform/1.0")
+            .build()
+            .expect("Failed to create HTTP client");
+
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            client,
+            page_size: 50,
+            max_pages: 100,
+            state: Mutex::new(FetchState::default()),
+        }
+    }
+
+    /// Set number of pulses requested per page
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// Limit pages followed in a single fetch
+    pub fn with_max_pages(mut self, max_pages: usize) -> Self {
+        self.max_pages = max_pages.max(1);
+        self
+    }
+
+    /// Only fetch pulses modified after the given time on the next run
+    ///
+    /// Discards the position of an unfinished paginated fetch.
+    pub fn set_modified_since(&self, since: Option<DateTime<Utc>>) {
+        *self.state() = FetchState { modified_since: since, ..FetchState::default() };
+    }
+
+    /// Modification time the next incremental fetch starts from
+    pub fn modified_since(&self) -> Option<DateTime<Utc>> {
+        self.state().modified_since
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, FetchState> {
+        self.state.lock().expect("OTX state lock poisoned")
+    }
+
+    fn first_page_url(&self) -> String {
+        let mut url = format!(
+            "{}/api/v1/pulses/subscribed?limit={}&page=1",
+            self.base_url, self.page_size
+        );
+        if let Some(since) = self.modified_since() {
+            url.push_str(&format!("&modified_since={}", since.format(OTX_TIME_FORMAT)));
+        }
+        url
+    }
+
+    /// Ensure pagination links stay on the configured OTX host
+    fn check_next_url(&self, next: &str) -> Result<()> {
+        let base = url::Url::parse(&self.base_url)
+            .map_err(|e| Error::Configuration(format!("Invalid OTX base URL: {}", e)))?;
+        let next_url = url::Url::parse(next)
+            .map_err(|e| Error::Parsing(format!("Invalid OTX pagination link: {}", e)))?;
+
+        if base.host_str() != next_url.host_str() || base.port_or_known_default() != next_url.port_or_known_default() {
+            return Err(Error::ThreatIntel(format!("OTX pagination link points to foreign host: {}", next)));
+        }
+        Ok(())
+    }
+
+    async fn fetch_page(&self, url: &str) -> Result<PulsePage> {
+        let response = self.client
+            .get(url)
+            .header(API_KEY_HEADER, &self.api_key)
+            .send()
+            .await?;
+
+        match response.status() {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                return Err(Error::Authentication("OTX rejected the API key".to_string()));
+            }
+            _ => {}
+        }
+
+        let body = response.error_for_status()?.text().await?;
+        serde_json::from_str(&body)
+            .map_err(|e| Error::Parsing(format!("Invalid OTX pulse page: {}", e)))
+    }
+}
+
+#[async_trait::async_trait]
+impl ThreatSource for OtxSource {
+    async fn fetch_indicators(&self) -> Result<Vec<ThreatIndicator>> {
+        let mut indicators = Vec::new();
+        let (resume_url, mut latest_modified) = {
+            let state = self.state();
+            (state.resume_url.clone(), state.modified_since.max(state.pending_modified))
+        };
+        let mut next_url = Some(resume_url.unwrap_or_else(|| self.first_page_url()));
+        let mut pages = 0;
+        let mut remaining = None;
+
+        while let Some(url) = next_url.take() {
+            let page = self.fetch_page(&url).await?;
+            pages += 1;
+
+            for pulse in &page.results {
+                if let Some(modified) = pulse.modified.as_deref().and_then(parse_otx_time) {
+                    latest_modified = latest_modified.max(Some(modified));
+                }
+                indicators.extend(indicators_from_pulse(pulse));
+            }
+
+            match page.next {
+                Some(next) if pages < self.max_pages => {
+                    self.check_next_url(&next)?;
+                    next_url = Some(next);
+                }
+                Some(next) => {
+                    self.check_next_url(&next)?;
+                    tracing::warn!("OTX fetch stopped after {} pages, remaining pulses follow next run", pages);
+                    remaining = Some(next);
+                }
+                None => {}
+            }
+        }
+
+        // Advance the incremental window only after every page was retrieved
+        let mut state = self.state();
+        match remaining {
+            Some(next) => {
+                state.resume_url = Some(next);
+                state.pending_modified = latest_modified;
+            }
+            None => *state = FetchState { modified_since: latest_modified, ..FetchState::default() },
+        }
+        drop(state);
+
+        tracing::debug!("Fetched {} OTX indicators from {} pages", indicators.len(), pages);
+        Ok(indicators)
+    }
+
+    fn name(&self) -> &str {
+        "OTX"
+    }
+
+    fn source_type(&self) -> ThreatSourceType {
+        ThreatSourceType::Community
+    }
+
+    async fn is_available(&self) -> bool {
+        let url = format!("{}/api/v1/users/me", self.base_url);
+        match self.client.get(&url).header(API_KEY_HEADER, &self.api_key).send().await {
+            Ok(response) => response.status().is_success(),
+            Err(e) => {
+                tracing::debug!("OTX availability check failed: {}", e);
+                false
+            }
+        }
+    }
+}
+
+/// Convert all supported indicators of a pulse
+fn indicators_from_pulse(pulse: &Pulse) -> Vec<ThreatIndicator> {
+    let mut tags: Vec<String> = Vec::new();
+    let mut add_tag = |tag: &str| {
+        let tag = tag.trim();
+        if !tag.is_empty() && !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.to_string());
+        }
+    };
+    if let Some(adversary) = &pulse.adversary {
+        add_tag(adversary);
+    }
+    for family in &pulse.malware_families {
+        add_tag(family.display_name());
+    }
+    for tag in &pulse.tags {
+        add_tag(tag);
+    }
+
+    let (mitre_tactics, mitre_techniques): (Vec<String>, Vec<String>) = pulse.attack_ids.iter()
+        .map(|attack| attack.id().trim().to_uppercase())
+        .filter(|id| id.starts_with('T'))
+        .partition(|id| id.starts_with("TA"));
+
+    let tlp = map_tlp(pulse.tlp.as_deref());
+    let has_attribution = pulse.adversary.as_deref().is_some_and(|a| !a.trim().is_empty())
+        || !pulse.malware_families.is_empty();
+    let created = pulse.created.as_deref().and_then(parse_otx_time);
+    let modified = pulse.modified.as_deref().and_then(parse_otx_time);
+
+    pulse.indicators.iter()
+        .filter_map(|raw| {
+            let Some(indicator_type) = map_indicator_type(&raw.indicator_type) else {
+                tracing::debug!("Skipping unsupported OTX indicator type {}", raw.indicator_type);
+                return None;
+            };
+
+            let first_seen = raw.created.as_deref().and_then(parse_otx_time)
+                .or(created)
+                .unwrap_or_else(Utc::now);
+            let last_seen = modified.unwrap_or(first_seen).max(first_seen);
+            let inactive = raw.is_active.as_ref()
+                .is_some_and(|v| v.as_i64() == Some(0) || v.as_bool() == Some(false));
+
+            Some(ThreatIndicator {
+                threat_type: map_threat_type(raw.role.as_deref(), &tags),
+                severity: if has_attribution { ThreatSeverity::High } else { ThreatSeverity::Medium },
+                confidence: 0.7,
+                tlp: tlp.clone(),
+                first_seen,
+                last_seen,
+                valid_until: raw.expiration.as_deref().and_then(parse_otx_time),
+                context: Some(pulse.name.clone()),
+                mitre_tactics: mitre_tactics.clone(),
+                mitre_techniques: mitre_techniques.clone(),
+                tags: tags.clone(),
+                status: if inactive { IndicatorStatus::Expired } else { IndicatorStatus::Active },
+                ..ThreatIndicator::new(indicator_type, raw.indicator.trim(), "OTX")
+            })
+        })
+        .collect()
+}
+
+fn map_indicator_type(otx_type: &str) -> Option<IndicatorType> {
+    match otx_type {
+        "IPv4" | "IPv6" => Some(IndicatorType::IpAddress),
+        "CIDR" => Some(IndicatorType::NetworkRange),
+        "domain" | "hostname" => Some(IndicatorType::Domain),
+        "URL" | "URI" => Some(IndicatorType::Url),
+        "email" => Some(IndicatorType::Email),
+        "Mutex" => Some(IndicatorType::Mutex),
+        "FilePath" => Some(IndicatorType::Filename),
+        "SSLCert" => Some(IndicatorType::Certificate),
+        "YARA" => Some(IndicatorType::Yara),
+        t if t.starts_with("FileHash-") => Some(IndicatorType::Hash),
+        _ => None,
+    }
+}
+
+fn map_threat_type(role: Option<&str>, tags: &[String]) -> ThreatType {
+    match role {
+        Some("command_and_control") => return ThreatType::CommandControl,
+        Some("phishing") => return ThreatType::Phishing,
+        Some("malware_hosting") => return ThreatType::Delivery,
+        Some("scanning_host") | Some("scanning") | Some("bruteforce") => return ThreatType::Reconnaissance,
+        Some("exploit_source") => return ThreatType::Exploitation,
+        _ => {}
+    }
+
+    let has_tag = |needle: &str| tags.iter().any(|t| t.to_lowercase().contains(needle));
+    if has_tag("phishing") {
+        ThreatType::Phishing
+    } else if has_tag("botnet") {
+        ThreatType::Botnet
+    } else if has_tag("c2") || has_tag("c&c") || has_tag("command and control") {
+        ThreatType::CommandControl
+    } else if has_tag("ransomware") {
+        ThreatType::Impact
+    } else {
+        ThreatType::Malware
+    }
+}
+
+fn map_tlp(tlp: Option<&str>) -> TrafficLightProtocol {
+    match tlp.map(|t| t.to_lowercase()).as_deref() {
+        Some("red") => TrafficLightProtocol::Red,
+        Some("amber") | Some("amber+strict") => TrafficLightProtocol::Amber,
+        Some("green") => TrafficLightProtocol::Green,
+        _ => TrafficLightProtocol::White,
+    }
+}
+
+/// Parse OTX timestamps, which usually omit the timezone and are UTC
+fn parse_otx_time(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|dt| dt.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f")
+                .ok()
+                .map(|naive| naive.and_utc())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    use wiremock::matchers::{header, method, path, query_param, query_param_is_missing};
+
+    const PAGE_1: &str = include_str!("../../tests/fixtures/otx/subscribed_page1.json");
+    const PAGE_2: &str = include_str!("../../tests/fixtures/otx/subscribed_page2.json");
+
+    fn fixture(body: &str, base_url: &str) -> String {
+        body.replace("{{BASE_URL}}", base_url)
+    }
+
+    #[test]
+    fn test_pulse_mapping() {
+        let page: PulsePage = serde_json::from_str(&fixture(PAGE_1, "http://localhost")).unwrap();
+        let indicators: Vec<_> = page.results.iter().flat_map(indicators_from_pulse).collect();
+
+        // CVE indicators have no matching IndicatorType and are skipped
+        assert_eq!(indicators.len(), 4);
+
+        let domain = &indicators[0];
+        assert_eq!(domain.indicator_type, IndicatorType::Domain);
+        assert_eq!(domain.threat_type, ThreatType::Phishing);
+        assert_eq!(domain.context.as_deref(), Some("APT28 Credential Phishing Infrastructure"));
+        assert_eq!(domain.tlp, TrafficLightProtocol::Green);
+        assert_eq!(domain.mitre_techniques, vec!["T1566.002"]);
+        assert_eq!(domain.mitre_tactics, vec!["TA0001"]);
+        assert!(domain.tags.contains(&"APT28".to_string()));
+
+        let ip = &indicators[1];
+        assert_eq!(ip.threat_type, ThreatType::CommandControl);
+        assert!(ip.valid_until.is_some());
+
+        let hash = &indicators[2];
+        assert_eq!(hash.indicator_type, IndicatorType::Hash);
+        assert_eq!(hash.tlp, TrafficLightProtocol::White);
+        assert!(hash.tags.contains(&"QakBot".to_string()));
+        assert_eq!(hash.mitre_techniques, vec!["T1055"]);
+
+        assert_eq!(indicators[3].status, IndicatorStatus::Expired);
+    }
+
+    #[tokio::test]
+    async fn test_incremental_paginated_fetch() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/api/v1/pulses/subscribed"))
+            .and(query_param("page", "1"))
+            .and(query_param_is_missing("modified_since"))
+            .and(header(API_KEY_HEADER, "test-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(fixture(PAGE_1, &server.uri())))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/pulses/subscribed"))
+            .and(query_param("page", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(fixture(PAGE_2, &server.uri())))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/pulses/subscribed"))
+            .and(query_param("modified_since", "2024-01-16T08:30:00"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"results": [], "next": null}"#))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let source = OtxSource::with_base_url(server.uri(), "test-key".to_string()).with_page_size(2);
+
+        let indicators = source.fetch_indicators().await.unwrap();
+        assert_eq!(indicators.len(), 6);
+        assert!(indicators.iter().any(|i| i.indicator_type == IndicatorType::Mutex));
+        assert_eq!(
+            source.modified_since(),
+            parse_otx_time("2024-01-16T08:30:00")
+        );
+
+        let indicators = source.fetch_indicators().await.unwrap();
+        assert!(indicators.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_max_pages_resumes_next_run() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/api/v1/pulses/subscribed"))
+            .and(query_param("page", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(fixture(PAGE_1, &server.uri())))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/pulses/subscribed"))
+            .and(query_param("page", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(fixture(PAGE_2, &server.uri())))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let source = OtxSource::with_base_url(server.uri(), "test-key".to_string())
+            .with_page_size(2)
+            .with_max_pages(1);
+
+        let first = source.fetch_indicators().await.unwrap();
+        assert_eq!(first.len(), 4);
+        // The window stays put until the remaining page was fetched
+        assert_eq!(source.modified_since(), None);
+
+        let second = source.fetch_indicators().await.unwrap();
+        assert_eq!(second.len(), 2);
+        assert_eq!(source.modified_since(), parse_otx_time("2024-01-16T08:30:00"));
+    }
+
+    #[tokio::test]
+    async fn test_rejected_api_key() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(403))
+            .mount(&server)
+            .await;
+
+        let source = OtxSource::with_base_url(server.uri(), "bad-key".to_string());
+        let err = source.fetch_indicators().await.unwrap_err();
+        assert!(matches!(err, Error::Authentication(_)));
+        assert!(!err.is_retriable());
+        assert!(!source.is_available().await);
+    }
+}